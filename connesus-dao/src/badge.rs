@@ -0,0 +1,102 @@
+use crate::*;
+
+// Contract minting supporter badges, see `Policy::badge_contract_id`.
+#[ext_contract(ext_badge)]
+pub trait BadgeContract {
+    fn nft_mint(&mut self, token_id: String, receiver_id: AccountId);
+}
+
+#[ext_contract(ext_badge_callbacks)]
+pub trait BadgeCallbacks {
+    fn on_badge_minted(&mut self, receiver_id: AccountId);
+}
+
+impl Contract {
+    // Mints a badge for donation with given index in the donation feed, when it is large enough.
+    // The donation doesn't depend on the mint.
+    pub(crate) fn internal_mint_badge(&self, donor: &AccountId, index: u64, amount: Balance) {
+        let badge_contract_id = match &self.policy.badge_contract_id {
+            Some(badge_contract_id) if amount >= self.policy.badge_threshold.0 => badge_contract_id,
+            _ => return,
+        };
+        ext_badge::nft_mint(format!("donation-{}", index), donor.clone(), badge_contract_id, 0, GAS_FOR_NFT_MINT).then(
+            ext_badge_callbacks::on_badge_minted(
+                donor.clone(),
+                &env::current_account_id(),
+                0,
+                GAS_FOR_EXECUTION_CALLBACK,
+            ),
+        );
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    #[private]
+    pub fn on_badge_minted(&mut self, receiver_id: AccountId) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        env::log(format!("ERR_BADGE_MINT_FAILED: {}", receiver_id).as_bytes());
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, get_logs, testing_env_with_promise_results, VMContextBuilder};
+
+    use crate::test_utils::*;
+
+    fn setup_contract_with_badge() -> (VMContextBuilder, Contract) {
+        setup_contract_with_policy(Policy {
+            badge_contract_id: Some("badge.near".to_string()),
+            badge_threshold: U128(100),
+            ..test_policy()
+        })
+    }
+
+    fn mints() -> Vec<String> {
+        created_receipts().into_iter().filter(|receipt| receipt.contains("nft_mint")).collect()
+    }
+
+    #[test]
+    fn test_badge_above_threshold() {
+        let (mut context, mut contract) = setup_contract_with_badge();
+        let msg = "{\"purpose\":\"OpenDonate\"}";
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 99, msg), 0);
+        assert!(mints().is_empty());
+
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, msg), 0);
+        let mints = mints();
+        assert_eq!(mints.len(), 1);
+        assert!(mints[0].contains("badge.near"));
+        assert!(mints[0].contains("\\\"token_id\\\":\\\"donation-1\\\""));
+        assert!(mints[0].contains("\\\"receiver_id\\\":\\\"bob\\\""));
+        assert_eq!(contract.get_total_open_donations(None), U128(199));
+    }
+
+    #[test]
+    fn test_no_badge_for_anonymous_donation() {
+        let (mut context, mut contract) = setup_contract_with_badge();
+        let msg = "{\"purpose\":\"OpenDonate\",\"anonymous\":true}";
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, msg), 0);
+        assert!(mints().is_empty());
+    }
+
+    #[test]
+    fn test_no_badge_contract() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, "{\"purpose\":\"OpenDonate\"}"), 0);
+        assert!(mints().is_empty());
+    }
+
+    #[test]
+    fn test_badge_mint_failed() {
+        let (mut context, mut contract) = setup_contract_with_badge();
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_badge_minted(accounts(1).to_string());
+        assert_eq!(get_logs(), vec!["ERR_BADGE_MINT_FAILED: bob"]);
+    }
+}
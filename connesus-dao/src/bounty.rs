@@ -1,9 +1,70 @@
 use crate::*;
 
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum BountyStatus {
+    Open,
+    // Work was approved, no more claims are accepted.
+    Completed,
+    // Creator took back the escrow.
+    Cancelled,
+}
+
+// Token a bounty is paid in.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum BountyToken {
+    // $NEAR attached to `create_bounty_near`, kept apart from `locked_near`.
+    Near,
+    FungibleToken(AccountId),
+}
+
 // Bounty information.
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Bounty {
+    pub description: String,
+    // Account that funded the bounty.
+    pub creator: AccountId,
+    pub token: BountyToken,
+    // Paid for completing the bounty.
+    pub amount: Balance,
+    // Longest time a claimant can take to complete the bounty, in nanoseconds.
+    pub max_deadline: U64,
+    // Number of completions left to pay.
+    pub times: u32,
+    // Number of completions paid, or being paid.
+    pub completions: u32,
+    // Time after which the bounty can't be claimed and the creator can cancel it.
+    pub expires_at: Option<U64>,
+    // Accounts approving the work besides the creator and the owner.
+    pub reviewers: Vec<AccountId>,
+    // Stages each completion is paid in, none to pay it at once.
+    pub milestones: Vec<BountyMilestone>,
+    // Skills the bounty asks for, like "rust" or "design".
+    pub tags: Vec<String>,
+    // Only applicants accepted by the creator can claim the bounty, see `bounty_apply`.
+    pub requires_application: bool,
+    // Time reviewers have to approve or reject submitted work, in nanoseconds. Once it passes the work
+    // can be paid out with `bounty_finalize_claim`. Reviewers have no time limit if not set.
+    pub review_period: Option<U64>,
+    // Accounts allowed to claim the bounty, anyone can if not set.
+    pub claimant_whitelist: Option<Vec<AccountId>>,
+    pub status: BountyStatus,
+}
+
+// Part of the bounty amount paid once given stage of the work is approved.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyMilestone {
+    pub amount: U128,
+    pub description: String,
+}
+
+// Bounty as it was stored before claims, with its payout preset per claimer.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyV1 {
     pub description: String,
     pub token: OldAccountId,
     pub total: Balance,
@@ -13,124 +74,2521 @@ pub struct Bounty {
     pub claimer: HashMap<AccountId, Balance>,
 }
 
+impl BountyV1 {
+    pub fn is_expired(&self) -> bool {
+        env::block_timestamp() >= self.start_time.0 + self.duration.0
+    }
+
+    // Claimers keep their preset payouts until the bounty expires, see `bounty_claim_legacy`.
+    pub fn has_legacy_claims(&self) -> bool {
+        !self.claimer.is_empty() && !self.is_expired()
+    }
+}
+
+impl From<BountyV1> for Bounty {
+    fn from(b: BountyV1) -> Self {
+        // What wasn't claimed yet is paid out once. It's only upgraded once claimers can't claim their
+        // preset payouts anymore, until then they are shown as the accounts allowed to claim it.
+        // Creator is assigned once the bounty is used, see `assign_legacy_creator`.
+        let claimant_whitelist = if b.claimer.is_empty() {
+            None
+        } else {
+            Some(b.claimer.keys().cloned().collect())
+        };
+        Self {
+            description: b.description,
+            creator: String::new(),
+            token: BountyToken::FungibleToken(b.token),
+            amount: b.rest,
+            max_deadline: b.duration,
+            times: if b.rest > 0 { 1 } else { 0 },
+            completions: 0,
+            expires_at: Some(U64(b.start_time.0 + b.duration.0)),
+            reviewers: vec![],
+            milestones: vec![],
+            tags: vec![],
+            requires_application: false,
+            review_period: None,
+            claimant_whitelist,
+            status: if b.rest > 0 { BountyStatus::Open } else { BountyStatus::Completed },
+        }
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub enum VersionedBounty {
-    Default(Bounty),
+    Default(BountyV1),
+    V2(Bounty),
 }
 
 impl From<VersionedBounty> for Bounty {
     fn from(v: VersionedBounty) -> Self {
         match v {
-            VersionedBounty::Default(b) => b,
+            VersionedBounty::Default(b) => b.into(),
+            VersionedBounty::V2(b) => b,
         }
     }
 }
 
+// Pitch of `applicant` for bounty that requires applications.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyApplication {
+    pub applicant: AccountId,
+    pub message: String,
+    pub submission_time: U64,
+    // Set once the creator accepted the applicant, who can claim the bounty then.
+    pub accepted: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum BountyClaimStatus {
+    InProgress,
+    // Claimant submitted the work, waiting for approval.
+    Submitted,
+    // Work was approved, waiting for the payout.
+    Approved,
+    // Work was rejected, claimant can only give up or dispute it.
+    Rejected,
+    // Rejection is put to the vote of the DAO, see `bounty_dispute`.
+    Disputed,
+}
+
+// Work on a bounty by `claimant`, to be completed within `deadline` from `start_time`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyClaim {
+    pub claimant: AccountId,
+    pub start_time: U64,
+    pub deadline: U64,
+    // $NEAR attached to the claim.
+    pub bond: U128,
+    pub status: BountyClaimStatus,
+    // Description of the submitted work.
+    pub description: Option<String>,
+    // Number of milestones of the bounty paid to the claimant, they are paid in order.
+    pub paid_milestones: u32,
+    // Time the work was last submitted.
+    pub submission_time: Option<U64>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum BountyPayoutStatus {
+    Pending,
+    Paid,
+    // Transfer failed, it can be sent again with `retry_bounty_payout`.
+    Failed,
+}
+
+// Transfer of a split payout to one of its recipients.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyPayoutLeg {
+    pub recipient: AccountId,
+    pub amount: U128,
+    pub status: BountyPayoutStatus,
+}
+
+// Reward for the work of `claimant` paid to several recipients, see `bounty_approve_split`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountySplitPayout {
+    pub claimant: AccountId,
+    pub legs: Vec<BountyPayoutLeg>,
+}
 
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub struct BountyInput {
     pub description: String,
     pub token: OldAccountId,
-    pub start_time: U64,
-    pub duration: U64,
-    pub claimer: HashMap<AccountId, Balance>,
+    // Paid for each completion.
+    pub amount: U128,
+    // Number of times the bounty can be completed.
+    pub times: u32,
+    pub max_deadline: U64,
+    #[serde(default)]
+    pub expires_at: Option<U64>,
+    #[serde(default)]
+    pub reviewers: Vec<AccountId>,
+    // Amounts of the milestones must add up to `amount`.
+    #[serde(default)]
+    pub milestones: Vec<BountyMilestone>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub requires_application: bool,
+    #[serde(default)]
+    pub review_period: Option<U64>,
+    #[serde(default)]
+    pub claimant_whitelist: Option<Vec<AccountId>>,
 }
 
-impl From<BountyInput> for Bounty {
+// Bounty paid in $NEAR, `BountyInput` without the token.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyNearInput {
+    pub description: String,
+    // Paid for each completion.
+    pub amount: U128,
+    // Number of times the bounty can be completed.
+    pub times: u32,
+    pub max_deadline: U64,
+    #[serde(default)]
+    pub expires_at: Option<U64>,
+    #[serde(default)]
+    pub reviewers: Vec<AccountId>,
+    // Amounts of the milestones must add up to `amount`.
+    #[serde(default)]
+    pub milestones: Vec<BountyMilestone>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub requires_application: bool,
+    #[serde(default)]
+    pub review_period: Option<U64>,
+    #[serde(default)]
+    pub claimant_whitelist: Option<Vec<AccountId>>,
+}
+
+// Number of bounties with a tag, see `get_bounty_tags`. Bounties are closed once completed or cancelled.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyTagCount {
+    pub open: u32,
+    pub closed: u32,
+}
+
+impl From<BountyInput> for BountyNearInput {
     fn from(input: BountyInput) -> Self {
-        assert!(input.duration.0 > 1000000000 * 60 * 2, "DURATION_MIN_ERROR");
-        let mut total = 0u128;
-        for (_, value) in input.claimer.clone().into_iter() {
-            total += value
-        };
+        Self {
+            description: input.description,
+            amount: input.amount,
+            times: input.times,
+            max_deadline: input.max_deadline,
+            expires_at: input.expires_at,
+            reviewers: input.reviewers,
+            milestones: input.milestones,
+            tags: input.tags,
+            requires_application: input.requires_application,
+            review_period: input.review_period,
+            claimant_whitelist: input.claimant_whitelist,
+        }
+    }
+}
+
+impl BountyInput {
+    // Returns amount of tokens reserved for all completions of the bounty, or the error code if input is not valid.
+    pub fn validate(&self) -> Result<Balance, &'static str> {
+        BountyNearInput::from(self.clone()).validate()
+    }
+}
 
-        let BountyInput {
+impl BountyNearInput {
+    // Returns amount of tokens reserved for all completions of the bounty, or the error code if input is not valid.
+    pub fn validate(&self) -> Result<Balance, &'static str> {
+        if self.max_deadline.0 <= 1000000000 * 60 * 2 {
+            return Err("DURATION_MIN_ERROR");
+        }
+        if self.amount.0 == 0 {
+            return Err("ERR_INVALID_AMOUNT");
+        }
+        if self.times == 0 {
+            return Err("ERR_INVALID_TIMES");
+        }
+        if self.expires_at.is_some_and(|expires_at| expires_at.0 <= env::block_timestamp()) {
+            return Err("ERR_INVALID_EXPIRATION");
+        }
+        validate_reviewers(&self.reviewers)?;
+        if self.review_period.is_some_and(|review_period| review_period.0 == 0) {
+            return Err("ERR_INVALID_REVIEW_PERIOD");
+        }
+        if let Some(whitelist) = &self.claimant_whitelist {
+            validate_whitelist(whitelist)?;
+        }
+        if self.milestones.len() > MAX_BOUNTY_MILESTONES {
+            return Err("ERR_TOO_MANY_MILESTONES");
+        }
+        if !self.milestones.is_empty() {
+            let mut total: Balance = 0;
+            for milestone in self.milestones.iter() {
+                if milestone.amount.0 == 0 {
+                    return Err("ERR_INVALID_AMOUNT");
+                }
+                total = total.checked_add(milestone.amount.0).ok_or("ERR_INVALID_AMOUNT")?;
+            }
+            if total != self.amount.0 {
+                return Err("ERR_MILESTONES_SUM");
+            }
+        }
+        if self.tags.len() > MAX_BOUNTY_TAGS {
+            return Err("ERR_TOO_MANY_TAGS");
+        }
+        if !self.tags.iter().enumerate().all(|(index, tag)| {
+            !tag.is_empty() && tag.len() <= MAX_TAG_LENGTH && !self.tags[..index].contains(tag)
+        }) {
+            return Err("ERR_INVALID_TAG");
+        }
+        self.amount.0.checked_mul(self.times as Balance).ok_or("ERR_INVALID_AMOUNT")
+    }
+}
+
+fn validate_reviewers(reviewers: &[AccountId]) -> Result<(), &'static str> {
+    if reviewers.len() > MAX_BOUNTY_REVIEWERS {
+        return Err("ERR_TOO_MANY_REVIEWERS");
+    }
+    if reviewers.iter().any(|reviewer| !env::is_valid_account_id(reviewer.as_bytes())) {
+        return Err("ERR_INVALID_REVIEWER");
+    }
+    Ok(())
+}
+
+fn validate_whitelist(whitelist: &[AccountId]) -> Result<(), &'static str> {
+    if whitelist.len() > MAX_BOUNTY_WHITELIST {
+        return Err("ERR_WHITELIST_TOO_LONG");
+    }
+    if whitelist.iter().any(|account_id| !env::is_valid_account_id(account_id.as_bytes())) {
+        return Err("ERR_INVALID_WHITELIST_ACCOUNT");
+    }
+    Ok(())
+}
+
+#[ext_contract(ext_bounties)]
+pub trait BountyCallbacks {
+    fn on_bounty_paid(&mut self, id: u64, claimant: AccountId);
+    fn on_bounty_refunded(&mut self, id: u64);
+    fn on_bounty_payout_leg(&mut self, id: u64, recipient: AccountId);
+    fn on_bounty_legacy_claimed(&mut self, id: u64, claimer: AccountId, amount: U128);
+}
+
+impl Bounty {
+    pub fn new(creator: &AccountId, token: BountyToken, input: BountyNearInput) -> Self {
+        input.validate().unwrap_or_else(|err| env::panic(err.as_bytes()));
+        let BountyNearInput {
             description,
-            token,
-            start_time,
-            duration,
-            claimer,
+            amount,
+            times,
+            max_deadline,
+            expires_at,
+            reviewers,
+            milestones,
+            tags,
+            requires_application,
+            review_period,
+            claimant_whitelist,
         } = input;
-
         Self {
             description,
+            creator: creator.clone(),
             token,
-            total,
-            rest: total,
-            start_time,
-            duration,
-            claimer
+            amount: amount.0,
+            max_deadline,
+            times,
+            completions: 0,
+            expires_at,
+            reviewers,
+            milestones,
+            tags,
+            requires_application,
+            review_period,
+            claimant_whitelist,
+            status: BountyStatus::Open,
         }
     }
-}
 
+    // Bounties of the first version could only be created by the owner.
+    pub fn assign_legacy_creator(&mut self, owner_id: &AccountId) {
+        if self.creator.is_empty() {
+            self.creator = owner_id.clone();
+        }
+    }
 
+    pub fn is_reviewer(&self, account_id: &AccountId) -> bool {
+        account_id == &self.creator || self.reviewers.contains(account_id)
+    }
 
-impl Bounty {
-    pub fn claim(&mut self, account_id: &AccountId) -> Self {
-        let expired_time = self.start_time.0 + self.duration.0;
-        assert!(env::block_timestamp() < expired_time, "BOUNTY_DID_NOT_EXPIRED");
-        let balance_option = self.claimer.remove(account_id);
-        assert!(balance_option.is_some(), "ERR_INVALID_CLAIMER");
-        let balance_claimed = balance_option.unwrap_or(0);
-        self.rest -= balance_claimed;
-        ext_fungible_token::ft_transfer(
-            account_id.to_string(),
-            balance_claimed.into(),
-            None,
-            &self.token,
-            ONE_YOCTO_NEAR,
-            GAS_FOR_FT_TRANSFER
-        );
-        self.clone()
-    }
-
-    pub fn withdraw_the_rest(&mut self, receiver_id: &AccountId) {
-        let expired_time = self.start_time.0 + self.duration.0;
-        assert!(env::block_timestamp() > expired_time, "BOUNTY_DID_NOT_EXPIRED");
-        let rest_balance = self.rest.clone();
-        self.rest = 0;
-        ext_fungible_token::ft_transfer(
-            receiver_id.to_string(),
-            rest_balance.into(),
-            None,
-            &self.token,
-            ONE_YOCTO_NEAR,
-            GAS_FOR_FT_TRANSFER
-        );
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| env::block_timestamp() >= expires_at.0)
+    }
+
+    pub fn assert_open(&self) {
+        match self.status {
+            BountyStatus::Open => {},
+            BountyStatus::Completed => env::panic(b"ERR_BOUNTY_COMPLETED"),
+            BountyStatus::Cancelled => env::panic(b"ERR_BOUNTY_CANCELLED"),
+        }
+    }
+
+    // Tokens kept for the completions left.
+    pub fn escrow(&self) -> Balance {
+        self.amount * self.times as Balance
     }
 }
 
 impl Contract {
-    pub fn create_bounty(&mut self, bounty_input: BountyInput) -> u64 {
-        let bounty = Bounty::from(bounty_input);
+    pub fn create_bounty(&mut self, creator: &AccountId, bounty_input: BountyInput) -> u64 {
+        let token = BountyToken::FungibleToken(bounty_input.token.clone());
+        self.internal_add_bounty(Bounty::new(creator, token, bounty_input.into()))
+    }
+
+    // Claims are reviewed by the creator and reviewers of the bounty, and the roles allowed to,
+    // which is the owner by default.
+    fn assert_bounty_reviewer(&self, bounty: &Bounty) {
+        let account_id = env::predecessor_account_id();
+        assert!(
+            bounty.is_reviewer(&account_id)
+                || self.has_permission(&account_id, BOUNTY_PERMISSION_LABEL, PermissionAction::ReviewBounty),
+            "ERR_NOT_ALLOWED"
+        );
+    }
+
+    fn internal_add_bounty(&mut self, bounty: Bounty) -> u64 {
+        let creator = &bounty.creator.clone();
+        self.internal_add_bounty_escrow(&bounty.token, bounty.escrow());
         let id = self.last_bounty_id;
-        self.bounties
-            .insert(&id, &VersionedBounty::Default(bounty.into()));
+        DaoEvent::BountyCreate(vec![BountyData {
+            bounty_id: id,
+            actor: creator.clone(),
+            token: bounty.token.clone(),
+            amount: U128(bounty.escrow()),
+            claimant: None,
+        }])
+        .emit();
+        let mut ids = self.bounties_by_creator.get(creator).unwrap_or_default();
+        ids.push(id);
+        self.bounties_by_creator.insert(creator, &ids);
+        for tag in bounty.tags.iter() {
+            let mut ids = self.bounties_by_tag.get(tag).unwrap_or_default();
+            ids.push(id);
+            self.bounties_by_tag.insert(tag, &ids);
+            let mut count = self.bounty_tags.get(tag).unwrap_or_default();
+            count.open += 1;
+            self.bounty_tags.insert(tag, &count);
+        }
+        self.bounties.insert(&id, &VersionedBounty::V2(bounty));
         self.last_bounty_id += 1;
         id
     }
+
+    pub(crate) fn internal_get_bounty(&self, id: u64) -> Option<Bounty> {
+        self.bounties.get(&id).map(|versioned_bounty| {
+            let mut bounty: Bounty = versioned_bounty.into();
+            bounty.assign_legacy_creator(&self.owner_id);
+            bounty
+        })
+    }
+
+    // Returns bounty with given id, writing it in the current version if it was stored in an older one.
+    // Escrow of older bounties wasn't tracked, it's added when they are upgraded.
+    pub(crate) fn internal_upgrade_bounty(&mut self, id: u64) -> Bounty {
+        let versioned_bounty = self.bounties.get(&id).expect("ERR_NO_BOUNTY");
+        if let VersionedBounty::Default(bounty) = &versioned_bounty {
+            assert!(!bounty.has_legacy_claims(), "ERR_BOUNTY_HAS_LEGACY_CLAIMS");
+        }
+        let upgraded = matches!(versioned_bounty, VersionedBounty::Default(_));
+        let mut bounty: Bounty = versioned_bounty.into();
+        if upgraded {
+            bounty.assign_legacy_creator(&self.owner_id);
+            self.internal_add_bounty_escrow(&bounty.token, bounty.escrow());
+            self.bounties.insert(&id, &VersionedBounty::V2(bounty.clone()));
+        }
+        bounty
+    }
+
+    pub(crate) fn internal_bounty_escrow(&self, token_id: &AccountId) -> Balance {
+        self.bounty_escrow.get(token_id).unwrap_or(0)
+    }
+
+    // Adds `amount` of given token to the reward of open bounty, split evenly between the completions left.
+    // Existing claims are paid the new reward, the last milestone takes the increase.
+    // Returns the error code to refund the transfer.
+    pub(crate) fn internal_top_up_bounty(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        id: u64,
+        amount: Balance,
+    ) -> Result<(), &'static str> {
+        if self.bounties.get(&id).is_none() {
+            return Err("ERR_NO_BOUNTY");
+        }
+        let mut bounty = self.internal_upgrade_bounty(id);
+        if &bounty.creator != account_id {
+            return Err("ERR_NOT_ALLOWED");
+        }
+        match bounty.status {
+            BountyStatus::Open if bounty.is_expired() => return Err("ERR_BOUNTY_EXPIRED"),
+            BountyStatus::Open => {},
+            BountyStatus::Completed => return Err("ERR_BOUNTY_COMPLETED"),
+            BountyStatus::Cancelled => return Err("ERR_BOUNTY_CANCELLED"),
+        }
+        if bounty.token != BountyToken::FungibleToken(token_id.clone()) {
+            return Err("ERR_WRONG_BOUNTY_TOKEN");
+        }
+        // Payout in flight is put back with the reward it was sent with if it fails.
+        if self.internal_bounty_claims(id).iter().any(|claim| claim.status == BountyClaimStatus::Approved) {
+            return Err("ERR_BOUNTY_PAYOUT_IN_PROGRESS");
+        }
+        if amount % bounty.times as Balance != 0 {
+            return Err("ERR_TOP_UP_NOT_DIVISIBLE");
+        }
+        let increase = amount / bounty.times as Balance;
+        bounty.amount = bounty.amount.checked_add(increase).ok_or("ERR_INVALID_AMOUNT")?;
+        if let Some(milestone) = bounty.milestones.last_mut() {
+            milestone.amount = U128(milestone.amount.0 + increase);
+        }
+        self.internal_add_bounty_escrow(&bounty.token, amount);
+        DaoEvent::BountyTopUp(vec![BountyData {
+            bounty_id: id,
+            actor: account_id.clone(),
+            token: bounty.token.clone(),
+            amount: U128(amount),
+            claimant: None,
+        }])
+        .emit();
+        self.bounties.insert(&id, &VersionedBounty::V2(bounty));
+        Ok(())
+    }
+
+    // Sends `amount` of the bounty token to `receiver_id`.
+    fn internal_transfer_bounty_token(token: &BountyToken, receiver_id: AccountId, amount: Balance) -> Promise {
+        match token {
+            BountyToken::Near => Promise::new(receiver_id).transfer(amount),
+            BountyToken::FungibleToken(token_id) => ext_fungible_token::ft_transfer(
+                receiver_id,
+                U128(amount),
+                None,
+                token_id,
+                ONE_YOCTO_NEAR,
+                GAS_FOR_FT_TRANSFER,
+            ),
+        }
+    }
+
+    // Moves the bounty in the counts of its tags once it's closed, or back once it's open again.
+    fn internal_count_bounty_tags(&mut self, bounty: &Bounty, closed: bool) {
+        for tag in bounty.tags.iter() {
+            let mut count = self.bounty_tags.get(tag).unwrap_or_default();
+            if closed {
+                count.open -= 1;
+                count.closed += 1;
+            } else {
+                count.open += 1;
+                count.closed -= 1;
+            }
+            self.bounty_tags.insert(tag, &count);
+        }
+    }
+
+    fn internal_add_bounty_escrow(&mut self, token: &BountyToken, amount: Balance) {
+        match token {
+            BountyToken::Near => {
+                self.near_bounty_escrow = self.near_bounty_escrow.checked_add(amount).expect("ERR_BOUNTY_ESCROW_OVERFLOW");
+            },
+            BountyToken::FungibleToken(token_id) => {
+                let escrow =
+                    self.internal_bounty_escrow(token_id).checked_add(amount).expect("ERR_BOUNTY_ESCROW_OVERFLOW");
+                self.bounty_escrow.insert(token_id, &escrow);
+            },
+        }
+    }
+
+    fn internal_take_bounty_escrow(&mut self, token: &BountyToken, amount: Balance) {
+        match token {
+            BountyToken::Near => {
+                self.near_bounty_escrow = self.near_bounty_escrow.checked_sub(amount).expect("ERR_BOUNTY_ESCROW_UNDERFLOW");
+            },
+            BountyToken::FungibleToken(token_id) => {
+                let escrow =
+                    self.internal_bounty_escrow(token_id).checked_sub(amount).expect("ERR_BOUNTY_ESCROW_UNDERFLOW");
+                self.bounty_escrow.insert(token_id, &escrow);
+            },
+        }
+    }
+
+    pub(crate) fn internal_bounty_applications(&self, id: u64) -> Vec<BountyApplication> {
+        self.bounty_applications.get(&id).unwrap_or_default()
+    }
+
+    pub(crate) fn internal_bounty_claims(&self, id: u64) -> Vec<BountyClaim> {
+        self.bounty_claims.get(&id).unwrap_or_default()
+    }
+
+    // Position of the claim of `claimant` in the claims of the bounty.
+    fn internal_find_claim(claims: &[BountyClaim], claimant: &AccountId) -> usize {
+        claims.iter().position(|claim| &claim.claimant == claimant).expect("ERR_NO_BOUNTY_CLAIM")
+    }
+
+    fn internal_get_claim(&self, id: u64, claimant: &AccountId) -> BountyClaim {
+        let claims = self.internal_bounty_claims(id);
+        let index = Self::internal_find_claim(&claims, claimant);
+        claims[index].clone()
+    }
+
+    // Removes claim of `claimant`, unlocking its bond.
+    fn internal_remove_claim(&mut self, id: u64, claimant: &AccountId) -> BountyClaim {
+        let mut claims = self.internal_bounty_claims(id);
+        let index = Self::internal_find_claim(&claims, claimant);
+        let claim = claims.remove(index);
+        self.bounty_claims.insert(&id, &claims);
+        let mut ids = self.bounty_claims_by_account.get(claimant).unwrap_or_default();
+        ids.retain(|claimed_id| claimed_id != &id);
+        if ids.is_empty() {
+            self.bounty_claims_by_account.remove(claimant);
+        } else {
+            self.bounty_claims_by_account.insert(claimant, &ids);
+        }
+        self.locked_near = self.locked_near.checked_sub(claim.bond.0).expect("ERR_LOCKED_NEAR_UNDERFLOW");
+        claim
+    }
+
+    // Pays `amount` of the escrow to `claimant`, whose claim is marked approved.
+    // Bounty is completed once more if `completes`.
+    fn internal_pay_claim(
+        &mut self,
+        id: u64,
+        bounty: Bounty,
+        claims: Vec<BountyClaim>,
+        claimant: AccountId,
+        amount: Balance,
+        completes: bool,
+    ) -> Promise {
+        let token = self.internal_take_payout(id, bounty, claims, &claimant, amount, completes);
+        Self::internal_transfer_bounty_token(&token, claimant.clone(), amount).then(ext_bounties::on_bounty_paid(
+            id,
+            claimant,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+
+    // Takes `amount` paid for the approved work of `claimant` out of the escrow, returns the token it's paid in.
+    fn internal_take_payout(
+        &mut self,
+        id: u64,
+        mut bounty: Bounty,
+        claims: Vec<BountyClaim>,
+        claimant: &AccountId,
+        amount: Balance,
+        completes: bool,
+    ) -> BountyToken {
+        self.bounty_claims.insert(&id, &claims);
+        if completes {
+            bounty.times -= 1;
+            bounty.completions += 1;
+            if bounty.times == 0 {
+                bounty.status = BountyStatus::Completed;
+                self.internal_count_bounty_tags(&bounty, true);
+            }
+        }
+        let token = bounty.token.clone();
+        self.internal_take_bounty_escrow(&token, amount);
+        self.bounties.insert(&id, &VersionedBounty::V2(bounty));
+        DaoEvent::BountyApprove(vec![BountyData {
+            bounty_id: id,
+            actor: env::predecessor_account_id(),
+            token: token.clone(),
+            amount: U128(amount),
+            claimant: Some(claimant.clone()),
+        }])
+        .emit();
+        token
+    }
+
+    // Sends the part of the split payout of given bounty owed to `recipient`.
+    fn internal_pay_leg(token: &BountyToken, id: u64, recipient: AccountId, amount: Balance) -> Promise {
+        Self::internal_transfer_bounty_token(token, recipient.clone(), amount).then(ext_bounties::on_bounty_payout_leg(
+            id,
+            recipient,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+
+    // Cancels the bounty, sending the escrow left back to its creator.
+    // Escrow of bounty funded by the DAO itself, see `ProposalKind::AddBounty`, just goes back to the treasury.
+    pub(crate) fn internal_refund_bounty(&mut self, id: u64, mut bounty: Bounty) -> PromiseOrValue<()> {
+        bounty.status = BountyStatus::Cancelled;
+        self.internal_count_bounty_tags(&bounty, true);
+        let (creator, token, escrow) = (bounty.creator.clone(), bounty.token.clone(), bounty.escrow());
+        self.internal_take_bounty_escrow(&token, escrow);
+        self.bounties.insert(&id, &VersionedBounty::V2(bounty));
+        DaoEvent::BountyCancel(vec![BountyData {
+            bounty_id: id,
+            actor: env::predecessor_account_id(),
+            token: token.clone(),
+            amount: U128(escrow),
+            claimant: None,
+        }])
+        .emit();
+        if creator == env::current_account_id() {
+            return PromiseOrValue::Value(());
+        }
+        Self::internal_transfer_bounty_token(&token, creator, escrow)
+            .then(ext_bounties::on_bounty_refunded(id, &env::current_account_id(), 0, GAS_FOR_EXECUTION_CALLBACK))
+            .into()
+    }
+
+    // Approves the work of the claim with given index without a reviewer, paying the whole bounty
+    // or its next milestone.
+    fn internal_approve_claim(&mut self, id: u64, bounty: Bounty, mut claims: Vec<BountyClaim>, index: usize) -> Promise {
+        let claim = &mut claims[index];
+        claim.status = BountyClaimStatus::Approved;
+        let (amount, completes) = if bounty.milestones.is_empty() {
+            (bounty.amount, true)
+        } else {
+            claim.paid_milestones += 1;
+            let paid_milestones = claim.paid_milestones as usize;
+            (bounty.milestones[paid_milestones - 1].amount.0, paid_milestones == bounty.milestones.len())
+        };
+        let claimant = claim.claimant.clone();
+        self.internal_pay_claim(id, bounty, claims, claimant, amount, completes)
+    }
+
+    // Pays the disputed work of `claimant` after the DAO approved it, the next milestone if the bounty has them.
+    // Returns false if the bounty isn't open anymore, the claim is rejected again then.
+    pub(crate) fn internal_resolve_bounty_dispute(&mut self, id: u64, claimant: &AccountId) -> bool {
+        let bounty = self.internal_upgrade_bounty(id);
+        let mut claims = self.internal_bounty_claims(id);
+        let index = Self::internal_find_claim(&claims, claimant);
+        if bounty.status != BountyStatus::Open {
+            claims[index].status = BountyClaimStatus::Rejected;
+            self.bounty_claims.insert(&id, &claims);
+            return false;
+        }
+        self.internal_approve_claim(id, bounty, claims, index);
+        true
+    }
+
+    // Upholds the rejection once the dispute of given proposal kind didn't pass. The claim is closed,
+    // its bond is kept by the DAO if `forfeit_bond` and returned otherwise.
+    // Claims with paid milestones stay rejected, they can't be closed.
+    pub(crate) fn internal_close_bounty_dispute(&mut self, kind: &ProposalKind, forfeit_bond: bool) {
+        let (id, claimant) = match kind {
+            ProposalKind::ResolveBountyDispute { bounty_id, claimant } => (*bounty_id, claimant),
+            _ => return,
+        };
+        let mut claims = self.internal_bounty_claims(id);
+        let index = Self::internal_find_claim(&claims, claimant);
+        if claims[index].paid_milestones > 0 {
+            claims[index].status = BountyClaimStatus::Rejected;
+            self.bounty_claims.insert(&id, &claims);
+            return;
+        }
+        let claim = self.internal_remove_claim(id, claimant);
+        if !forfeit_bond {
+            Promise::new(claimant.clone()).transfer(claim.bond.0);
+        }
+    }
+
+    pub(crate) fn internal_bounty_output_status(&self, id: u64, bounty: &Bounty) -> BountyOutputStatus {
+        match bounty.status {
+            BountyStatus::Completed => BountyOutputStatus::Done,
+            BountyStatus::Cancelled => BountyOutputStatus::Cancelled,
+            BountyStatus::Open if !self.internal_bounty_claims(id).is_empty() => BountyOutputStatus::Claimed,
+            BountyStatus::Open if bounty.is_expired() => BountyOutputStatus::Expired,
+            BountyStatus::Open => BountyOutputStatus::Open,
+        }
+    }
 }
 
 #[near_bindgen]
 impl Contract {
-    pub fn with_draw_bounty_rest(&self, bounty_id: u64) {
+    // Creates bounty paid in $NEAR, the attached deposit must cover all of its completions.
+    #[payable]
+    pub fn create_bounty_near(&mut self, input: BountyNearInput) -> u64 {
+        self.assert_not_paused();
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        let total = input.validate().unwrap_or_else(|err| env::panic(err.as_bytes()));
+        assert_eq!(total, env::attached_deposit(), "ERR_NOT_DEPOSIT_ENOUGH_NEAR");
+        self.internal_add_bounty(Bounty::new(&env::predecessor_account_id(), BountyToken::Near, input))
+    }
+
+    // Claims bounty with given id, to complete it within `deadline` nanoseconds.
+    // `Policy::bounty_bond` must be attached, and the claimant registered with `storage_deposit`
+    // to pay for the index of its claims.
+    #[payable]
+    pub fn bounty_claim(&mut self, id: u64, deadline: U64) {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        bounty.assert_open();
+        assert!(!bounty.is_expired(), "ERR_BOUNTY_EXPIRED");
+        assert!(deadline.0 > 0 && deadline.0 <= bounty.max_deadline.0, "ERR_BOUNTY_WRONG_DEADLINE");
+        let bond = env::attached_deposit();
+        assert_eq!(bond, self.policy.bounty_bond.0, "ERR_BOUNTY_WRONG_BOND");
+        let claimant = env::predecessor_account_id();
+        assert!(self.is_registered(&claimant), "ERR_NOT_REGISTERED");
+        if let Some(whitelist) = &bounty.claimant_whitelist {
+            assert!(whitelist.contains(&claimant), "ERR_NOT_WHITELISTED");
+        }
+        if bounty.requires_application {
+            assert!(
+                self.internal_bounty_applications(id)
+                    .iter()
+                    .any(|application| application.applicant == claimant && application.accepted),
+                "ERR_BOUNTY_APPLICANT_NOT_ACCEPTED"
+            );
+        }
+        let mut claims = self.internal_bounty_claims(id);
+        assert!(claims.iter().all(|claim| claim.claimant != claimant), "ERR_BOUNTY_ALREADY_CLAIMED");
+        assert!(claims.len() < MAX_BOUNTY_CLAIMS, "ERR_BOUNTY_ALL_CLAIMED");
+        let mut ids = self.bounty_claims_by_account.get(&claimant).unwrap_or_default();
+        assert!(ids.len() < self.policy.max_bounty_claims_per_account as usize, "ERR_TOO_MANY_BOUNTY_CLAIMS");
+        ids.push(id);
+        self.bounty_claims_by_account.insert(&claimant, &ids);
+        DaoEvent::BountyClaim(vec![BountyData {
+            bounty_id: id,
+            actor: claimant.clone(),
+            token: bounty.token.clone(),
+            amount: U128(bounty.amount),
+            claimant: None,
+        }])
+        .emit();
+        claims.push(BountyClaim {
+            claimant,
+            start_time: U64(env::block_timestamp()),
+            deadline,
+            bond: U128(bond),
+            status: BountyClaimStatus::InProgress,
+            description: None,
+            paid_milestones: 0,
+            submission_time: None,
+        });
+        self.bounty_claims.insert(&id, &claims);
+        self.locked_near = self.locked_near.checked_add(bond).expect("ERR_LOCKED_NEAR_OVERFLOW");
+    }
+
+    // Applies for bounty with given id that requires applications, at most `MAX_BOUNTY_APPLICATIONS` per bounty.
+    pub fn bounty_apply(&mut self, id: u64, message: String) {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        bounty.assert_open();
+        assert!(!bounty.is_expired(), "ERR_BOUNTY_EXPIRED");
+        assert!(bounty.requires_application, "ERR_BOUNTY_NO_APPLICATIONS");
+        assert!(
+            !message.is_empty() && message.len() <= MAX_BOUNTY_APPLICATION_LENGTH,
+            "ERR_INVALID_APPLICATION_MESSAGE"
+        );
+        let applicant = env::predecessor_account_id();
+        let mut applications = self.internal_bounty_applications(id);
+        assert!(
+            applications.iter().all(|application| application.applicant != applicant),
+            "ERR_BOUNTY_ALREADY_APPLIED"
+        );
+        assert!(applications.len() < MAX_BOUNTY_APPLICATIONS, "ERR_BOUNTY_TOO_MANY_APPLICATIONS");
+        applications.push(BountyApplication {
+            applicant,
+            message,
+            submission_time: U64(env::block_timestamp()),
+            accepted: false,
+        });
+        self.bounty_applications.insert(&id, &applications);
+    }
+
+    // Lets applicant claim bounty with given id, only the creator of the bounty can accept applicants.
+    pub fn bounty_accept_applicant(&mut self, id: u64, account_id: AccountId) {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        assert_eq!(bounty.creator, env::predecessor_account_id(), "ERR_NOT_ALLOWED");
+        bounty.assert_open();
+        let mut applications = self.internal_bounty_applications(id);
+        let application = applications
+            .iter_mut()
+            .find(|application| application.applicant == account_id)
+            .expect("ERR_NO_BOUNTY_APPLICATION");
+        application.accepted = true;
+        self.bounty_applications.insert(&id, &applications);
+    }
+
+    // Submits the work on bounty with given id, before the deadline of the claim.
+    pub fn bounty_done(&mut self, id: u64, description: String) {
+        self.assert_not_paused();
+        let claimant = env::predecessor_account_id();
+        let mut claims = self.internal_bounty_claims(id);
+        let index = Self::internal_find_claim(&claims, &claimant);
+        let claim = &mut claims[index];
+        assert_eq!(claim.status, BountyClaimStatus::InProgress, "ERR_BOUNTY_ALREADY_SUBMITTED");
+        assert!(env::block_timestamp() <= claim.start_time.0 + claim.deadline.0, "ERR_BOUNTY_CLAIM_EXPIRED");
+        claim.status = BountyClaimStatus::Submitted;
+        claim.description = Some(description);
+        claim.submission_time = Some(U64(env::block_timestamp()));
+        self.bounty_claims.insert(&id, &claims);
+        let bounty = self.internal_upgrade_bounty(id);
+        DaoEvent::BountyDone(vec![BountyData {
+            bounty_id: id,
+            actor: claimant,
+            token: bounty.token,
+            amount: U128(bounty.amount),
+            claimant: None,
+        }])
+        .emit();
+    }
+
+    // Replaces the reviewers of bounty with given id, only its creator can while the bounty is open.
+    pub fn set_bounty_reviewers(&mut self, id: u64, reviewers: Vec<AccountId>) {
+        self.assert_not_paused();
+        let mut bounty = self.internal_upgrade_bounty(id);
+        assert_eq!(bounty.creator, env::predecessor_account_id(), "ERR_NOT_ALLOWED");
+        bounty.assert_open();
+        validate_reviewers(&reviewers).unwrap_or_else(|err| env::panic(err.as_bytes()));
+        bounty.reviewers = reviewers;
+        self.bounties.insert(&id, &VersionedBounty::V2(bounty));
+    }
+
+    // Adds and removes accounts allowed to claim bounty with given id, only its creator can while the bounty is open.
+    // Bounty without a whitelist gets one, existing claims stay.
+    pub fn update_bounty_whitelist(&mut self, id: u64, add: Vec<AccountId>, remove: Vec<AccountId>) {
+        self.assert_not_paused();
+        let mut bounty = self.internal_upgrade_bounty(id);
+        assert_eq!(bounty.creator, env::predecessor_account_id(), "ERR_NOT_ALLOWED");
+        bounty.assert_open();
+        let mut whitelist = bounty.claimant_whitelist.take().unwrap_or_default();
+        whitelist.retain(|account_id| !remove.contains(account_id));
+        for account_id in add {
+            if !whitelist.contains(&account_id) {
+                whitelist.push(account_id);
+            }
+        }
+        validate_whitelist(&whitelist).unwrap_or_else(|err| env::panic(err.as_bytes()));
+        bounty.claimant_whitelist = Some(whitelist);
+        self.bounties.insert(&id, &VersionedBounty::V2(bounty));
+    }
+
+    // Approves the work of `claimant`, paying them the bounty.
+    // The creator of the bounty, its reviewers or the roles allowed to review bounties can approve.
+    pub fn bounty_approve(&mut self, id: u64, claimant: AccountId) -> Promise {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        self.assert_bounty_reviewer(&bounty);
+        bounty.assert_open();
+        assert!(bounty.milestones.is_empty(), "ERR_BOUNTY_HAS_MILESTONES");
+        let mut claims = self.internal_bounty_claims(id);
+        let index = Self::internal_find_claim(&claims, &claimant);
+        assert_eq!(claims[index].status, BountyClaimStatus::Submitted, "ERR_BOUNTY_NOT_SUBMITTED");
+        claims[index].status = BountyClaimStatus::Approved;
+        let amount = bounty.amount;
+        self.internal_pay_claim(id, bounty, claims, claimant, amount, true)
+    }
+
+    // Approves submitted work done together by `recipients`, splitting the bounty between them. Amounts must
+    // add up to the bounty, the first recipient with a submitted claim is the one whose claim is approved.
+    // Each recipient gets its own transfer, the claim is closed once all of them succeed.
+    pub fn bounty_approve_split(&mut self, id: u64, recipients: Vec<(AccountId, U128)>) {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        self.assert_bounty_reviewer(&bounty);
+        bounty.assert_open();
+        assert!(bounty.milestones.is_empty(), "ERR_BOUNTY_HAS_MILESTONES");
+        assert!(self.bounty_payouts.get(&id).is_none(), "ERR_BOUNTY_PAYOUT_IN_PROGRESS");
+        assert!(
+            !recipients.is_empty() && recipients.len() <= MAX_BOUNTY_SPLIT_RECIPIENTS,
+            "ERR_INVALID_RECIPIENTS"
+        );
+        let mut total: Balance = 0;
+        for (index, (recipient, amount)) in recipients.iter().enumerate() {
+            assert!(env::is_valid_account_id(recipient.as_bytes()), "ERR_INVALID_RECIPIENT");
+            assert!(recipients[..index].iter().all(|(other, _)| other != recipient), "ERR_DUPLICATE_RECIPIENT");
+            assert!(amount.0 > 0, "ERR_INVALID_AMOUNT");
+            total = total.checked_add(amount.0).expect("ERR_SPLIT_SUM_MISMATCH");
+        }
+        assert_eq!(total, bounty.amount, "ERR_SPLIT_SUM_MISMATCH");
+        let mut claims = self.internal_bounty_claims(id);
+        let index = claims
+            .iter()
+            .position(|claim| {
+                claim.status == BountyClaimStatus::Submitted
+                    && recipients.iter().any(|(recipient, _)| recipient == &claim.claimant)
+            })
+            .expect("ERR_BOUNTY_NOT_SUBMITTED");
+        claims[index].status = BountyClaimStatus::Approved;
+        let claimant = claims[index].claimant.clone();
+        let token = self.internal_take_payout(id, bounty, claims, &claimant, total, true);
+        let legs = recipients
+            .into_iter()
+            .map(|(recipient, amount)| {
+                Self::internal_pay_leg(&token, id, recipient.clone(), amount.0);
+                BountyPayoutLeg { recipient, amount, status: BountyPayoutStatus::Pending }
+            })
+            .collect();
+        self.bounty_payouts.insert(&id, &BountySplitPayout { claimant, legs });
+    }
+
+    // Sends the failed transfer of the split payout of given bounty to `recipient` again. Anyone can call it.
+    pub fn retry_bounty_payout(&mut self, id: u64, recipient: AccountId) -> Promise {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        let mut payout = self.bounty_payouts.get(&id).expect("ERR_NO_BOUNTY_PAYOUT");
+        let leg = payout.legs.iter_mut().find(|leg| leg.recipient == recipient).expect("ERR_NO_BOUNTY_PAYOUT");
+        assert_eq!(leg.status, BountyPayoutStatus::Failed, "ERR_BOUNTY_PAYOUT_NOT_FAILED");
+        leg.status = BountyPayoutStatus::Pending;
+        let amount = leg.amount.0;
+        self.internal_take_bounty_escrow(&bounty.token, amount);
+        self.bounty_payouts.insert(&id, &payout);
+        Self::internal_pay_leg(&bounty.token, id, recipient, amount)
+    }
+
+    // Pays out the work of `claimant` as if approved, once it was submitted longer than the review period
+    // of the bounty ago and no reviewer rejected it. Anyone can call it.
+    pub fn bounty_finalize_claim(&mut self, id: u64, claimant: AccountId) -> Promise {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        bounty.assert_open();
+        let review_period = bounty.review_period.expect("ERR_BOUNTY_NO_REVIEW_PERIOD");
+        let claims = self.internal_bounty_claims(id);
+        let index = Self::internal_find_claim(&claims, &claimant);
+        let claim = &claims[index];
+        assert_eq!(claim.status, BountyClaimStatus::Submitted, "ERR_BOUNTY_NOT_SUBMITTED");
+        let submission_time = claim.submission_time.expect("ERR_BOUNTY_NOT_SUBMITTED");
+        assert!(env::block_timestamp() > submission_time.0 + review_period.0, "ERR_BOUNTY_REVIEW_PERIOD_NOT_OVER");
+        self.internal_approve_claim(id, bounty, claims, index)
+    }
+
+    // Approves milestone with given index of the work of `claimant`, paying its part of the bounty.
+    // Milestones are approved in order, the last one completes the claim.
+    pub fn bounty_approve_milestone(&mut self, id: u64, claimant: AccountId, index: u32) -> Promise {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        self.assert_bounty_reviewer(&bounty);
+        bounty.assert_open();
+        let milestone = bounty.milestones.get(index as usize).expect("ERR_NO_MILESTONE").clone();
+        let mut claims = self.internal_bounty_claims(id);
+        let claim_index = Self::internal_find_claim(&claims, &claimant);
+        let claim = &mut claims[claim_index];
+        assert!(
+            matches!(claim.status, BountyClaimStatus::InProgress | BountyClaimStatus::Submitted),
+            "ERR_BOUNTY_CLAIM_NOT_ACTIVE"
+        );
+        assert_eq!(claim.paid_milestones, index, "ERR_MILESTONE_OUT_OF_ORDER");
+        claim.paid_milestones += 1;
+        // Claim waits for the payout the same way as when the whole work is approved.
+        claim.status = BountyClaimStatus::Approved;
+        let completes = claim.paid_milestones as usize == bounty.milestones.len();
+        self.internal_pay_claim(id, bounty, claims, claimant, milestone.amount.0, completes)
+    }
+
+    // Gives up the claim on bounty with given id. The bond is refunded within `Policy::bounty_forgiveness_period`
+    // of the claim, and kept by the DAO after it.
+    pub fn bounty_giveup(&mut self, id: u64) -> Option<Promise> {
+        self.assert_not_paused();
+        let claimant = env::predecessor_account_id();
+        let claim = self.internal_get_claim(id, &claimant);
+        assert_ne!(claim.status, BountyClaimStatus::Approved, "ERR_BOUNTY_CLAIM_APPROVED");
+        assert_ne!(claim.status, BountyClaimStatus::Disputed, "ERR_BOUNTY_CLAIM_DISPUTED");
+        // Paid milestones can't be taken back, the claim stays for the reviewers to pay the rest.
+        assert_eq!(claim.paid_milestones, 0, "ERR_MILESTONES_PAID");
+        self.internal_remove_claim(id, &claimant);
+        let bounty = self.internal_upgrade_bounty(id);
+        DaoEvent::BountyGiveup(vec![BountyData {
+            bounty_id: id,
+            actor: claimant.clone(),
+            token: bounty.token,
+            amount: U128(bounty.amount),
+            claimant: None,
+        }])
+        .emit();
+        if env::block_timestamp() <= claim.start_time.0 + self.policy.bounty_forgiveness_period.0 {
+            Some(Promise::new(claimant).transfer(claim.bond.0))
+        } else {
+            None
+        }
+    }
+
+    // Removes claim of `claimant` whose deadline passed without the work submitted or with it rejected,
+    // the DAO keeps the bond. Anyone can call it.
+    pub fn bounty_expire_claim(&mut self, id: u64, claimant: AccountId) {
+        self.assert_not_paused();
+        let claim = self.internal_get_claim(id, &claimant);
+        assert!(
+            matches!(claim.status, BountyClaimStatus::InProgress | BountyClaimStatus::Rejected),
+            "ERR_BOUNTY_ALREADY_SUBMITTED"
+        );
+        assert!(env::block_timestamp() > claim.start_time.0 + claim.deadline.0, "ERR_BOUNTY_CLAIM_NOT_EXPIRED");
+        assert_eq!(claim.paid_milestones, 0, "ERR_MILESTONES_PAID");
+        self.internal_remove_claim(id, &claimant);
+    }
+
+    // Rewrites bounties stored in older versions in given id range, returns how many were upgraded.
+    // Old bounties are upgraded when used as well, this lets the owner migrate them in batches.
+    pub fn upgrade_bounties(&mut self, from_id: u64, limit: u64) -> u64 {
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        let mut upgraded = 0;
+        for id in from_id..std::cmp::min(self.last_bounty_id, from_id.saturating_add(limit)) {
+            match self.bounties.get(&id) {
+                Some(VersionedBounty::Default(bounty)) if !bounty.has_legacy_claims() => {
+                    self.internal_upgrade_bounty(id);
+                    upgraded += 1;
+                },
+                _ => {},
+            }
+        }
+        upgraded
+    }
+
+    // Pays the caller their preset part of a bounty stored in the first version, as its `claim_bounty`
+    // did, until the bounty expires. The bounty stays in that version meanwhile.
+    pub fn bounty_claim_legacy(&mut self, id: u64) -> Promise {
+        self.assert_not_paused();
         let account_id = env::predecessor_account_id();
+        let mut bounty = match self.bounties.get(&id).expect("ERR_NO_BOUNTY") {
+            VersionedBounty::Default(bounty) => bounty,
+            VersionedBounty::V2(_) => env::panic(b"ERR_NOT_LEGACY_BOUNTY"),
+        };
+        assert!(!bounty.is_expired(), "ERR_BOUNTY_EXPIRED");
+        let amount = bounty.claimer.remove(&account_id).expect("ERR_INVALID_CLAIMER");
+        bounty.rest = bounty.rest.saturating_sub(amount);
+        let token = bounty.token.clone();
+        self.bounties.insert(&id, &VersionedBounty::Default(bounty));
+        ext_fungible_token::ft_transfer(account_id.clone(), U128(amount), None, &token, ONE_YOCTO_NEAR, GAS_FOR_FT_TRANSFER)
+            .then(ext_bounties::on_bounty_legacy_claimed(
+                id,
+                account_id,
+                U128(amount),
+                &env::current_account_id(),
+                0,
+                GAS_FOR_EXECUTION_CALLBACK,
+            ))
+    }
+
+    // Cancels bounty that was never claimed, returning all of its escrow to the creator.
+    // Only the creator of the bounty or the owner can cancel it.
+    pub fn cancel_bounty(&mut self, id: u64) -> PromiseOrValue<()> {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        let account_id = env::predecessor_account_id();
+        assert!(account_id == bounty.creator || account_id == self.owner_id, "ERR_NOT_ALLOWED");
+        bounty.assert_open();
+        assert!(bounty.completions == 0 && self.internal_bounty_claims(id).is_empty(), "ERR_BOUNTY_HAS_CLAIMS");
+        self.internal_refund_bounty(id, bounty)
+    }
+
+    // Rejects the work of `claimant`, the claim stays until they give up or it expires.
+    pub fn bounty_reject(&mut self, id: u64, claimant: AccountId) {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        self.assert_bounty_reviewer(&bounty);
+        let mut claims = self.internal_bounty_claims(id);
+        let index = Self::internal_find_claim(&claims, &claimant);
+        assert_eq!(claims[index].status, BountyClaimStatus::Submitted, "ERR_BOUNTY_NOT_SUBMITTED");
+        claims[index].status = BountyClaimStatus::Rejected;
+        self.bounty_claims.insert(&id, &claims);
+    }
+
+    // Disputes the rejection of the work of the caller on bounty with given id, returns id of the
+    // `ResolveBountyDispute` proposal created for it. The DAO pays the work out if it approves the proposal.
+    // Otherwise the claim is closed, with its bond kept by the DAO if the proposal is rejected or vetoed.
+    pub fn bounty_dispute(&mut self, id: u64) -> u64 {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        bounty.assert_open();
+        let claimant = env::predecessor_account_id();
+        let mut claims = self.internal_bounty_claims(id);
+        let index = Self::internal_find_claim(&claims, &claimant);
+        assert_eq!(claims[index].status, BountyClaimStatus::Rejected, "ERR_BOUNTY_NOT_REJECTED");
+        claims[index].status = BountyClaimStatus::Disputed;
+        self.bounty_claims.insert(&id, &claims);
+        self.internal_add_proposal(
+            ProposalInput {
+                description: format!("Dispute of the rejected work of {} on bounty {}", claimant, id),
+                description_hash: None,
+                kind: ProposalKind::ResolveBountyDispute { bounty_id: id, claimant },
+                duration: None,
+                options: HashMap::default(),
+                target: None,
+                deadline: None,
+                milestones: vec![],
+                beneficiary: None,
+                accepted_tokens: vec![],
+                tags: vec![],
+            },
+            0,
+        )
+    }
+
+    // Returns the escrow of expired bounty to its creator, once there are no claims left.
+    pub fn cancel_expired_bounty(&mut self, id: u64) -> PromiseOrValue<()> {
+        self.assert_not_paused();
+        let bounty = self.internal_upgrade_bounty(id);
+        assert_eq!(bounty.creator, env::predecessor_account_id(), "ERR_NOT_ALLOWED");
+        bounty.assert_open();
+        assert!(bounty.is_expired(), "ERR_BOUNTY_NOT_EXPIRED");
+        assert!(self.internal_bounty_claims(id).is_empty(), "ERR_BOUNTY_HAS_CLAIMS");
+        self.internal_refund_bounty(id, bounty)
+    }
+
+    // Opens the bounty again if the escrow couldn't be returned.
+    #[private]
+    pub fn on_bounty_refunded(&mut self, id: u64) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        let mut bounty = self.internal_upgrade_bounty(id);
+        bounty.status = BountyStatus::Open;
+        self.internal_count_bounty_tags(&bounty, false);
+        self.internal_add_bounty_escrow(&bounty.token, bounty.escrow());
+        self.bounties.insert(&id, &VersionedBounty::V2(bounty));
+        env::log(b"ERR_BOUNTY_REFUND_FAILED");
+    }
+
+    // Records the result of the transfer to `recipient` of a split payout, a failed one stays in the escrow
+    // until it's retried. Closes the claim and returns its bond once every recipient is paid.
+    #[private]
+    pub fn on_bounty_payout_leg(&mut self, id: u64, recipient: AccountId) {
+        let mut payout = self.bounty_payouts.get(&id).expect("ERR_NO_BOUNTY_PAYOUT");
+        let leg = payout.legs.iter_mut().find(|leg| leg.recipient == recipient).expect("ERR_NO_BOUNTY_PAYOUT");
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            leg.status = BountyPayoutStatus::Paid;
+        } else {
+            leg.status = BountyPayoutStatus::Failed;
+            let amount = leg.amount.0;
+            let token = self.internal_upgrade_bounty(id).token;
+            self.internal_add_bounty_escrow(&token, amount);
+            env::log(format!("ERR_BOUNTY_PAYOUT_FAILED: {}", recipient).as_bytes());
+        }
+        if payout.legs.iter().all(|leg| leg.status == BountyPayoutStatus::Paid) {
+            self.bounty_payouts.remove(&id);
+            let claim = self.internal_remove_claim(id, &payout.claimant);
+            Promise::new(payout.claimant).transfer(claim.bond.0);
+        } else {
+            self.bounty_payouts.insert(&id, &payout);
+        }
+    }
+
+    // Gives the preset payout back to the claimer if the transfer of `bounty_claim_legacy` didn't go through.
+    #[private]
+    pub fn on_bounty_legacy_claimed(&mut self, id: u64, claimer: AccountId, amount: U128) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        // A bounty upgraded in the meantime has expired; its payout then stays in the treasury.
+        if let Some(VersionedBounty::Default(mut bounty)) = self.bounties.get(&id) {
+            bounty.claimer.insert(claimer, amount.0);
+            bounty.rest += amount.0;
+            self.bounties.insert(&id, &VersionedBounty::Default(bounty));
+        }
+        env::log(b"ERR_BOUNTY_CLAIM_FAILED");
+    }
+
+    // Closes the claim and returns its bond once paid, or puts the work back to approve if the transfer failed.
+    // Claim paid for a milestone that isn't the last one goes on.
+    #[private]
+    pub fn on_bounty_paid(&mut self, id: u64, claimant: AccountId) {
+        let mut bounty = self.internal_upgrade_bounty(id);
+        let mut claims = self.internal_bounty_claims(id);
+        let index = Self::internal_find_claim(&claims, &claimant);
+        let paid_milestones = claims[index].paid_milestones as usize;
+        let completes = bounty.milestones.is_empty() || paid_milestones == bounty.milestones.len();
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            if completes {
+                let claim = self.internal_remove_claim(id, &claimant);
+                Promise::new(claimant).transfer(claim.bond.0);
+            } else {
+                claims[index].status = BountyClaimStatus::InProgress;
+                self.bounty_claims.insert(&id, &claims);
+            }
+            return;
+        }
+        let amount = if bounty.milestones.is_empty() {
+            bounty.amount
+        } else {
+            claims[index].paid_milestones -= 1;
+            bounty.milestones[paid_milestones - 1].amount.0
+        };
+        claims[index].status = BountyClaimStatus::Submitted;
+        self.bounty_claims.insert(&id, &claims);
+        if completes {
+            if bounty.status == BountyStatus::Completed {
+                self.internal_count_bounty_tags(&bounty, false);
+            }
+            bounty.times += 1;
+            bounty.completions -= 1;
+            bounty.status = BountyStatus::Open;
+        }
+        self.internal_add_bounty_escrow(&bounty.token, amount);
+        self.bounties.insert(&id, &VersionedBounty::V2(bounty));
+        env::log(format!("ERR_BOUNTY_PAYOUT_FAILED: {}", claimant).as_bytes());
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, get_logs, testing_env_with_promise_results, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
+
+    const MAX_DEADLINE: u64 = 1_000_000_000 * 60 * 60;
+
+    const EXPIRES_AT: u64 = MAX_DEADLINE * 10;
+
+    fn bounty_msg(amount: Balance, times: u32) -> String {
+        format!(
+            "{{\"purpose\":{{\"CreateBounty\":{{\"description\":\"d\",\"token\":\"{}\",\"amount\":\"{}\",\"times\":{},\"max_deadline\":\"{}\",\"expires_at\":\"{}\"}}}}}}",
+            token(),
+            amount,
+            times,
+            MAX_DEADLINE,
+            EXPIRES_AT
+        )
+    }
+
+    fn create_bounty_times(context: &mut VMContextBuilder, contract: &mut Contract, amount: Balance, times: u32) -> u64 {
+        let msg = bounty_msg(amount, times);
+        assert_eq!(transfer_call(context, contract, owner(), amount * times as Balance, &msg), 0);
+        contract.get_last_bounty_id() - 1
+    }
+
+    fn create_bounty(context: &mut VMContextBuilder, contract: &mut Contract, amount: Balance) -> u64 {
+        create_bounty_times(context, contract, amount, 1)
+    }
+
+    fn claim(context: &mut VMContextBuilder, contract: &mut Contract, id: u64, claimant: ValidAccountId) {
+        register(contract, &claimant);
+        testing_env!(context
+            .predecessor_account_id(claimant)
+            .attached_deposit(contract.policy.bounty_bond.0)
+            .build());
+        contract.bounty_claim(id, U64(MAX_DEADLINE));
+        testing_env!(context.attached_deposit(0).build());
+    }
+
+    fn done(context: &mut VMContextBuilder, contract: &mut Contract, id: u64, claimant: ValidAccountId) {
+        testing_env!(context.predecessor_account_id(claimant).build());
+        contract.bounty_done(id, "work".to_string());
+    }
+
+    fn approve(context: &mut VMContextBuilder, contract: &mut Contract, id: u64, claimant: ValidAccountId) {
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.bounty_approve(id, claimant.to_string());
+    }
+
+    fn paid(context: &mut VMContextBuilder, contract: &mut Contract, id: u64, claimant: ValidAccountId, result: PromiseResult) {
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), result);
+        contract.on_bounty_paid(id, claimant.to_string());
+    }
+
+    fn get_bounty(contract: &Contract, id: u64) -> Bounty {
+        contract.internal_get_bounty(id).unwrap()
+    }
+
+    #[test]
+    fn test_bounty_claim() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        claim(&mut context, &mut contract, id, accounts(2));
+        assert_eq!(contract.locked_near, 2 * DEFAULT_BOUNTY_BOND);
+        let claims = contract.internal_bounty_claims(id);
+        assert_eq!(claims.len(), 2);
+        assert_eq!(claims[0].claimant, accounts(1).to_string());
+        assert_eq!(claims[0].deadline, U64(MAX_DEADLINE));
+        assert_eq!(claims[1].bond, U128(DEFAULT_BOUNTY_BOND));
+    }
+
+    #[test]
+    fn test_bounty_events() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty_times(&mut context, &mut contract, 100, 2);
+        assert_eq!(
+            get_logs(),
+            vec![
+                "EVENT_JSON:{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"bounty_create\",\
+                 \"data\":[{\"bounty_id\":0,\"actor\":\"alice\",\"token\":{\"FungibleToken\":\"token.near\"},\"amount\":\"200\"}]}"
+            ]
+        );
+        register(&mut contract, &accounts(1));
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(contract.policy.bounty_bond.0)
+            .build());
+        contract.bounty_claim(id, U64(MAX_DEADLINE));
+        assert_eq!(
+            get_logs(),
+            vec![
+                "EVENT_JSON:{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"bounty_claim\",\
+                 \"data\":[{\"bounty_id\":0,\"actor\":\"bob\",\"token\":{\"FungibleToken\":\"token.near\"},\"amount\":\"100\"}]}"
+            ]
+        );
+        done(&mut context, &mut contract, id, accounts(1));
+        approve(&mut context, &mut contract, id, accounts(1));
         assert_eq!(
-            account_id,
-            self.owner_id,
-            "ONLY_OWNER"
+            get_logs(),
+            vec![
+                "EVENT_JSON:{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"bounty_approve\",\
+                 \"data\":[{\"bounty_id\":0,\"actor\":\"alice\",\"token\":{\"FungibleToken\":\"token.near\"},\"amount\":\"100\",\
+                 \"claimant\":\"bob\"}]}"
+            ]
         );
-        let mut bounty: Bounty = self.bounties.get(&bounty_id).expect("BOUNTY_NOT_FOUND").into();
-        bounty.withdraw_the_rest(&account_id);
     }
 
-    pub fn claim_bounty(&mut self,  bounty_id: u64) {
-        let account_id = env::predecessor_account_id();
-        let mut bounty: Bounty = self.bounties.get(&bounty_id).expect("BOUNTY_NOT_FOUND").into();
-        let new_bounty = bounty.claim(&account_id);
-        self.bounties.insert(&bounty_id, &VersionedBounty::Default(new_bounty.into()));
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_WRONG_DEADLINE")]
+    fn test_bounty_claim_beyond_max_deadline() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(DEFAULT_BOUNTY_BOND).build());
+        contract.bounty_claim(id, U64(MAX_DEADLINE + 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_ALREADY_CLAIMED")]
+    fn test_bounty_claim_twice() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        claim(&mut context, &mut contract, id, accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_WRONG_BOND")]
+    fn test_bounty_claim_without_bond() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.bounty_claim(id, U64(MAX_DEADLINE));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NO_BOUNTY")]
+    fn test_bounty_claim_no_bounty() {
+        let (mut context, mut contract) = setup_contract();
+        claim(&mut context, &mut contract, 0, accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_COMPLETED")]
+    fn test_bounty_claim_completed() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        let mut bounty: Bounty = contract.bounties.get(&id).unwrap().into();
+        bounty.status = BountyStatus::Completed;
+        contract.bounties.insert(&id, &VersionedBounty::V2(bounty));
+        claim(&mut context, &mut contract, id, accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_ALL_CLAIMED")]
+    fn test_bounty_claim_limit() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        for i in 0..=MAX_BOUNTY_CLAIMS {
+            claim(&mut context, &mut contract, id, format!("claimant{}.near", i).try_into().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_bounty_claims_per_account() {
+        let (mut context, mut contract) = setup_contract();
+        let ids: Vec<u64> = (0..4).map(|_| create_bounty(&mut context, &mut contract, 100)).collect();
+        for id in ids[..3].iter() {
+            claim(&mut context, &mut contract, *id, accounts(1));
+        }
+        assert_eq!(contract.get_bounty_claim_allowance(accounts(1).to_string()), 0);
+        assert_eq!(contract.get_bounty_claim_allowance(accounts(2).to_string()), DEFAULT_MAX_BOUNTY_CLAIMS_PER_ACCOUNT);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.bounty_giveup(ids[0]);
+        assert_eq!(contract.get_bounty_claim_allowance(accounts(1).to_string()), 1);
+        claim(&mut context, &mut contract, ids[3], accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOO_MANY_BOUNTY_CLAIMS")]
+    fn test_bounty_claims_per_account_limit() {
+        let (mut context, mut contract) = setup_contract();
+        for _ in 0..=DEFAULT_MAX_BOUNTY_CLAIMS_PER_ACCOUNT {
+            let id = create_bounty(&mut context, &mut contract, 100);
+            claim(&mut context, &mut contract, id, accounts(1));
+        }
+    }
+
+    #[test]
+    fn test_bounty_approve() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        let claims = contract.internal_bounty_claims(id);
+        assert_eq!(claims[0].status, BountyClaimStatus::Submitted);
+        assert_eq!(claims[0].description, Some("work".to_string()));
+
+        approve(&mut context, &mut contract, id, accounts(1));
+        let receipts = created_receipts();
+        assert!(receipts[0].contains("ft_transfer"));
+        assert!(receipts[0].contains("\\\"amount\\\":\\\"100\\\""));
+        assert_eq!(get_bounty(&contract, id).status, BountyStatus::Completed);
+        paid(&mut context, &mut contract, id, accounts(1), PromiseResult::Successful(vec![]));
+        assert!(contract.internal_bounty_claims(id).is_empty());
+        assert_eq!(contract.locked_near, 0);
+        assert!(created_receipts()[0].contains(&DEFAULT_BOUNTY_BOND.to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_bounty_approve_not_creator() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.bounty_approve(id, accounts(1).to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_NOT_SUBMITTED")]
+    fn test_bounty_approve_not_submitted() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        approve(&mut context, &mut contract, id, accounts(1));
+    }
+
+    #[test]
+    fn test_bounty_payout_failed() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        approve(&mut context, &mut contract, id, accounts(1));
+        paid(&mut context, &mut contract, id, accounts(1), PromiseResult::Failed);
+        assert_eq!(get_logs(), vec!["ERR_BOUNTY_PAYOUT_FAILED: bob"]);
+        assert_eq!(contract.internal_bounty_claims(id)[0].status, BountyClaimStatus::Submitted);
+        let bounty = get_bounty(&contract, id);
+        assert_eq!(bounty.status, BountyStatus::Open);
+        assert_eq!(bounty.times, 1);
+        assert_eq!(contract.locked_near, DEFAULT_BOUNTY_BOND);
+        // The work can be approved again.
+        approve(&mut context, &mut contract, id, accounts(1));
+    }
+
+    #[test]
+    fn test_bounty_repeats() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty_times(&mut context, &mut contract, 100, 2);
+        for (claimant, times) in [(accounts(1), 1), (accounts(2), 0)] {
+            claim(&mut context, &mut contract, id, claimant.clone());
+            done(&mut context, &mut contract, id, claimant.clone());
+            approve(&mut context, &mut contract, id, claimant.clone());
+            paid(&mut context, &mut contract, id, claimant, PromiseResult::Successful(vec![]));
+            assert_eq!(get_bounty(&contract, id).times, times);
+        }
+        assert_eq!(get_bounty(&contract, id).status, BountyStatus::Completed);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_COMPLETED")]
+    fn test_bounty_claim_after_repeats() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty_times(&mut context, &mut contract, 100, 1);
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        approve(&mut context, &mut contract, id, accounts(1));
+        claim(&mut context, &mut contract, id, accounts(2));
+    }
+
+    #[test]
+    fn test_bounty_escrow_covers_repeats() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = bounty_msg(100, 3);
+        assert_eq!(transfer_call(&mut context, &mut contract, owner(), 200, &msg), 200);
+        assert_eq!(transfer_call(&mut context, &mut contract, owner(), 100, &bounty_msg(100, 0)), 100);
+        assert_eq!(contract.get_last_bounty_id(), 0);
+        assert_eq!(transfer_call(&mut context, &mut contract, owner(), 300, &msg), 0);
+        assert_eq!(get_bounty(&contract, 0).times, 3);
+    }
+
+    #[test]
+    fn test_bounty_funded_with_other_token() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = bounty_msg(100, 1);
+        accept_token(&mut context, &mut contract, accounts(3));
+        assert_eq!(transfer_call_from(&mut context, &mut contract, accounts(3), owner(), 100, &msg), 100);
+        assert_eq!(get_logs(), vec!["ERR_WRONG_BOUNTY_TOKEN"]);
+        assert_eq!(contract.get_last_bounty_id(), 0);
+        assert_eq!(contract.get_bounty_escrow(None), U128(0));
+    }
+
+    #[test]
+    fn test_bounty_funding_amount() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = bounty_msg(100, 2);
+        assert_eq!(transfer_call(&mut context, &mut contract, owner(), 150, &msg), 150);
+        assert_eq!(get_logs(), vec!["ERR_NOT_DEPOSIT_ENOUGH_TOKEN"]);
+        assert_eq!(contract.get_last_bounty_id(), 0);
+        // The excess is sent back.
+        assert_eq!(transfer_call(&mut context, &mut contract, owner(), 250, &msg), 50);
+        assert_eq!(contract.get_bounty_escrow(None), U128(200));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_CLAIM_EXPIRED")]
+    fn test_bounty_done_after_deadline() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(MAX_DEADLINE + 1).build());
+        contract.bounty_done(id, "work".to_string());
+    }
+
+    #[test]
+    fn test_bounty_giveup_forgiveness_period() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(DEFAULT_BOUNTY_FORGIVENESS_PERIOD)
+            .build());
+        assert!(contract.bounty_giveup(id).is_some());
+        assert!(created_receipts()[0].contains(&DEFAULT_BOUNTY_BOND.to_string()));
+        assert!(contract.internal_bounty_claims(id).is_empty());
+        assert_eq!(contract.locked_near, 0);
+    }
+
+    #[test]
+    fn test_bounty_giveup_after_forgiveness_period() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(DEFAULT_BOUNTY_FORGIVENESS_PERIOD + 1)
+            .build());
+        assert!(contract.bounty_giveup(id).is_none());
+        assert!(created_receipts().is_empty());
+        assert_eq!(contract.locked_near, 0);
+    }
+
+    #[test]
+    fn test_bounty_expire_claim() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        for i in 0..MAX_BOUNTY_CLAIMS {
+            claim(&mut context, &mut contract, id, format!("claimant{}.near", i).try_into().unwrap());
+        }
+        // Anyone can expire the claim after its deadline, freeing the slot.
+        testing_env!(context.predecessor_account_id(accounts(3)).block_timestamp(MAX_DEADLINE + 1).build());
+        contract.bounty_expire_claim(id, "claimant0.near".to_string());
+        assert!(created_receipts().is_empty());
+        assert_eq!(contract.locked_near, (MAX_BOUNTY_CLAIMS as Balance - 1) * DEFAULT_BOUNTY_BOND);
+        claim(&mut context, &mut contract, id, accounts(1));
+        assert_eq!(contract.internal_bounty_claims(id).len(), MAX_BOUNTY_CLAIMS);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_CLAIM_NOT_EXPIRED")]
+    fn test_bounty_expire_claim_before_deadline() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(3)).block_timestamp(MAX_DEADLINE).build());
+        contract.bounty_expire_claim(id, accounts(1).to_string());
+    }
+
+    fn cancel_expired(context: &mut VMContextBuilder, contract: &mut Contract, id: u64) {
+        testing_env!(context.predecessor_account_id(owner()).block_timestamp(EXPIRES_AT).build());
+        contract.cancel_expired_bounty(id);
+    }
+
+    #[test]
+    fn test_cancel_expired_bounty() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty_times(&mut context, &mut contract, 100, 3);
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        approve(&mut context, &mut contract, id, accounts(1));
+        paid(&mut context, &mut contract, id, accounts(1), PromiseResult::Successful(vec![]));
+        cancel_expired(&mut context, &mut contract, id);
+        // Only the completions left are refunded.
+        let receipts = created_receipts();
+        assert!(receipts[0].contains("\\\"receiver_id\\\":\\\"alice\\\""));
+        assert!(receipts[0].contains("\\\"amount\\\":\\\"200\\\""));
+        assert_eq!(get_bounty(&contract, id).status, BountyStatus::Cancelled);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_HAS_CLAIMS")]
+    fn test_cancel_expired_bounty_with_claim() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        cancel_expired(&mut context, &mut contract, id);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_NOT_EXPIRED")]
+    fn test_cancel_bounty_not_expired() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        testing_env!(context.predecessor_account_id(owner()).block_timestamp(EXPIRES_AT - 1).build());
+        contract.cancel_expired_bounty(id);
+    }
+
+    #[test]
+    fn test_cancel_expired_bounty_refund_failed() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        cancel_expired(&mut context, &mut contract, id);
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_bounty_refunded(id);
+        assert_eq!(get_logs(), vec!["ERR_BOUNTY_REFUND_FAILED"]);
+        assert_eq!(get_bounty(&contract, id).status, BountyStatus::Open);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_EXPIRED")]
+    fn test_bounty_claim_expired() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        testing_env!(context.block_timestamp(EXPIRES_AT).build());
+        claim(&mut context, &mut contract, id, accounts(1));
+    }
+
+    #[test]
+    fn test_cancel_bounty() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty_times(&mut context, &mut contract, 100, 2);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.cancel_bounty(id);
+        let receipts = created_receipts();
+        assert!(receipts[0].contains("token.near"));
+        assert!(receipts[0].contains("\\\"receiver_id\\\":\\\"alice\\\""));
+        assert!(receipts[0].contains("\\\"amount\\\":\\\"200\\\""));
+        assert_eq!(get_bounty(&contract, id).status, BountyStatus::Cancelled);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_HAS_CLAIMS")]
+    fn test_cancel_bounty_after_claim() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.cancel_bounty(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_HAS_CLAIMS")]
+    fn test_cancel_bounty_after_completion() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty_times(&mut context, &mut contract, 100, 2);
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        approve(&mut context, &mut contract, id, accounts(1));
+        paid(&mut context, &mut contract, id, accounts(1), PromiseResult::Successful(vec![]));
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.cancel_bounty(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_cancel_bounty_not_creator() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.cancel_bounty(id);
+    }
+
+    #[test]
+    fn test_bounty_escrow() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty_times(&mut context, &mut contract, 100, 3);
+        create_bounty(&mut context, &mut contract, 50);
+        assert_eq!(contract.get_bounty_escrow(None), U128(350));
+
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        approve(&mut context, &mut contract, id, accounts(1));
+        assert_eq!(contract.get_bounty_escrow(None), U128(250));
+        // Failed payout goes back to the escrow.
+        paid(&mut context, &mut contract, id, accounts(1), PromiseResult::Failed);
+        assert_eq!(contract.get_bounty_escrow(None), U128(350));
+        approve(&mut context, &mut contract, id, accounts(1));
+        paid(&mut context, &mut contract, id, accounts(1), PromiseResult::Successful(vec![]));
+        assert_eq!(contract.get_bounty_escrow(None), U128(250));
+
+        cancel_expired(&mut context, &mut contract, id);
+        assert_eq!(contract.get_bounty_escrow(None), U128(50));
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_bounty_refunded(id);
+        assert_eq!(contract.get_bounty_escrow(None), U128(250));
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.cancel_bounty(id + 1);
+        assert_eq!(contract.get_bounty_escrow(None), U128(200));
+        assert_eq!(contract.get_bounty_escrow(Some(accounts(3))), U128(0));
+    }
+
+    // Bounty of the first version, 30 of it was claimed already.
+    fn bounty_v1() -> BountyV1 {
+        BountyV1 {
+            description: "old".to_string(),
+            token: token().to_string(),
+            total: 100,
+            rest: 70,
+            start_time: U64(5),
+            duration: U64(MAX_DEADLINE),
+            claimer: HashMap::new(),
+        }
+    }
+
+    // Stores V1 bounty under given id the way the old contract did.
+    fn store_raw_v1(contract: &mut Contract, id: u64, bounty: BountyV1) {
+        let mut key = StorageKeys::Bounties.try_to_vec().unwrap();
+        key.extend(id.try_to_vec().unwrap());
+        let value = VersionedBounty::Default(bounty.clone()).try_to_vec().unwrap();
+        // Old layout is variant 0 followed by the fields of the bounty.
+        assert_eq!(value[0], 0);
+        assert_eq!(value[1..], bounty.try_to_vec().unwrap()[..]);
+        env::storage_write(&key, &value);
+        contract.last_bounty_id = std::cmp::max(contract.last_bounty_id, id + 1);
+    }
+
+    #[test]
+    fn test_read_v1_bounty() {
+        let (mut context, mut contract) = setup_contract();
+        store_raw_v1(&mut contract, 0, bounty_v1());
+        let output = contract.get_bounty(0).unwrap();
+        assert_eq!(output.bounty.description, "old");
+        assert_eq!(output.bounty.creator, owner().to_string());
+        assert_eq!(output.bounty.amount, U128(70));
+        assert_eq!(output.bounty.times, 1);
+        assert_eq!(output.bounty.expires_at, Some(U64(MAX_DEADLINE + 5)));
+        assert_eq!(output.status, BountyOutputStatus::Open);
+        assert_eq!(contract.get_bounty_escrow(None), U128(0));
+
+        // Using it writes it back as V2, with its escrow.
+        claim(&mut context, &mut contract, 0, accounts(1));
+        assert!(matches!(contract.bounties.get(&0), Some(VersionedBounty::V2(_))));
+        assert_eq!(contract.get_bounty_escrow(None), U128(70));
+        assert_eq!(contract.get_bounty(0).unwrap().status, BountyOutputStatus::Claimed);
+    }
+
+    #[test]
+    fn test_upgrade_bounties() {
+        let (mut context, mut contract) = setup_contract();
+        store_raw_v1(&mut contract, 0, bounty_v1());
+        store_raw_v1(&mut contract, 1, bounty_v1());
+        create_bounty(&mut context, &mut contract, 100);
+        store_raw_v1(&mut contract, 3, bounty_v1());
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        assert_eq!(contract.upgrade_bounties(0, 2), 2);
+        assert_eq!(contract.upgrade_bounties(2, 10), 1);
+        assert_eq!(contract.upgrade_bounties(0, 10), 0);
+        for id in 0..4 {
+            assert!(matches!(contract.bounties.get(&id), Some(VersionedBounty::V2(_))));
+        }
+        assert_eq!(contract.get_bounty_escrow(None), U128(310));
+    }
+
+    #[test]
+    fn test_v1_bounty_claimers_keep_payouts() {
+        let (mut context, mut contract) = setup_contract();
+        let mut bounty = bounty_v1();
+        bounty.claimer.insert(accounts(1).to_string(), 30);
+        bounty.claimer.insert(accounts(2).to_string(), 40);
+        store_raw_v1(&mut contract, 0, bounty);
+        let output = contract.get_bounty(0).unwrap();
+        let mut whitelist = output.bounty.claimant_whitelist.unwrap();
+        whitelist.sort();
+        assert_eq!(whitelist, vec![accounts(1).to_string(), accounts(2).to_string()]);
+        // It isn't upgraded while claimers can claim.
+        testing_env!(context.predecessor_account_id(owner()).build());
+        assert_eq!(contract.upgrade_bounties(0, 10), 0);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.bounty_claim_legacy(0);
+        let receipts = created_receipts();
+        assert!(receipts[0].contains("ft_transfer") && receipts[0].contains("\\\"amount\\\":\\\"30\\\""));
+        assert_eq!(contract.get_bounty(0).unwrap().bounty.amount, U128(40));
+        // Failed transfer gives the payout back.
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_bounty_legacy_claimed(0, accounts(1).to_string(), U128(30));
+        assert_eq!(contract.get_bounty(0).unwrap().bounty.amount, U128(70));
+
+        // Once expired, it's upgraded with what wasn't claimed.
+        testing_env!(context.block_timestamp(MAX_DEADLINE + 5).predecessor_account_id(owner()).build());
+        assert_eq!(contract.upgrade_bounties(0, 10), 1);
+        assert_eq!(contract.get_bounty_escrow(None), U128(70));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_HAS_LEGACY_CLAIMS")]
+    fn test_claim_v1_bounty_with_claimers() {
+        let (mut context, mut contract) = setup_contract();
+        let mut bounty = bounty_v1();
+        bounty.claimer.insert(accounts(1).to_string(), 70);
+        store_raw_v1(&mut contract, 0, bounty);
+        claim(&mut context, &mut contract, 0, accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_CLAIMER")]
+    fn test_claim_legacy_not_claimer() {
+        let (mut context, mut contract) = setup_contract();
+        let mut bounty = bounty_v1();
+        bounty.claimer.insert(accounts(1).to_string(), 70);
+        store_raw_v1(&mut contract, 0, bounty);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.bounty_claim_legacy(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY_OWNER")]
+    fn test_upgrade_bounties_only_owner() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.upgrade_bounties(0, 10);
+    }
+
+    fn create_reviewed_bounty(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        let msg = format!(
+            "{{\"purpose\":{{\"CreateBounty\":{{\"description\":\"d\",\"token\":\"{}\",\"amount\":\"100\",\"times\":1,\"max_deadline\":\"{}\",\"reviewers\":[\"{}\"]}}}}}}",
+            token(),
+            MAX_DEADLINE,
+            accounts(3)
+        );
+        assert_eq!(transfer_call(context, contract, owner(), 100, &msg), 0);
+        let id = contract.get_last_bounty_id() - 1;
+        claim(context, contract, id, accounts(1));
+        done(context, contract, id, accounts(1));
+        id
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_bounty_reviewer_approves() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_reviewed_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.bounty_approve(id, accounts(1).to_string());
+        assert_eq!(contract.internal_bounty_claims(id)[0].status, BountyClaimStatus::Approved);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_bounty_approve_not_reviewer() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_reviewed_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.bounty_approve(id, accounts(1).to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_bounty_reviewer_removed() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_reviewed_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.set_bounty_reviewers(id, vec![accounts(2).to_string()]);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.bounty_reject(id, accounts(1).to_string());
+    }
+
+    #[test]
+    fn test_bounty_reject() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_reviewed_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.bounty_reject(id, accounts(1).to_string());
+        assert_eq!(contract.internal_bounty_claims(id)[0].status, BountyClaimStatus::Rejected);
+        // Rejected claim can be expired like one without the work submitted.
+        testing_env!(context.block_timestamp(MAX_DEADLINE + 1).build());
+        contract.bounty_expire_claim(id, accounts(1).to_string());
+        assert!(contract.internal_bounty_claims(id).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_set_bounty_reviewers_not_creator() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_reviewed_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.set_bounty_reviewers(id, vec![accounts(3).to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PAUSED")]
+    fn test_set_bounty_reviewers_while_paused() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_reviewed_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.pause();
+        contract.set_bounty_reviewers(id, vec![accounts(3).to_string()]);
+    }
+
+    fn create_milestone_bounty(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        amounts: &[u32],
+        amount: Balance,
+    ) -> Balance {
+        let milestones = amounts
+            .iter()
+            .map(|amount| format!("{{\"amount\":\"{}\",\"description\":\"m\"}}", amount))
+            .collect::<Vec<_>>()
+            .join(",");
+        let msg = format!(
+            "{{\"purpose\":{{\"CreateBounty\":{{\"description\":\"d\",\"token\":\"{}\",\"amount\":\"{}\",\"times\":1,\"max_deadline\":\"{}\",\"milestones\":[{}]}}}}}}",
+            token(),
+            amount,
+            MAX_DEADLINE,
+            milestones
+        );
+        transfer_call(context, contract, owner(), amount, &msg)
+    }
+
+    fn approve_milestone(context: &mut VMContextBuilder, contract: &mut Contract, id: u64, index: u32) {
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.bounty_approve_milestone(id, accounts(1).to_string(), index);
+    }
+
+    #[test]
+    fn test_bounty_milestones_sum() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(create_milestone_bounty(&mut context, &mut contract, &[30, 60], 100), 100);
+        assert_eq!(create_milestone_bounty(&mut context, &mut contract, &[0, 100], 100), 100);
+        assert_eq!(contract.get_last_bounty_id(), 0);
+        assert_eq!(create_milestone_bounty(&mut context, &mut contract, &[30, 70], 100), 0);
+        assert_eq!(get_bounty(&contract, 0).milestones.len(), 2);
+    }
+
+    #[test]
+    fn test_bounty_milestone_payouts() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(create_milestone_bounty(&mut context, &mut contract, &[30, 70], 100), 0);
+        claim(&mut context, &mut contract, 0, accounts(1));
+        approve_milestone(&mut context, &mut contract, 0, 0);
+        assert!(created_receipts()[0].contains("\\\"amount\\\":\\\"30\\\""));
+        assert_eq!(contract.get_bounty_escrow(None), U128(70));
+        paid(&mut context, &mut contract, 0, accounts(1), PromiseResult::Successful(vec![]));
+        let claim = &contract.internal_bounty_claims(0)[0];
+        assert_eq!(claim.status, BountyClaimStatus::InProgress);
+        assert_eq!(claim.paid_milestones, 1);
+        assert_eq!(get_bounty(&contract, 0).times, 1);
+
+        // Failed payout can be approved again.
+        approve_milestone(&mut context, &mut contract, 0, 1);
+        paid(&mut context, &mut contract, 0, accounts(1), PromiseResult::Failed);
+        assert_eq!(contract.internal_bounty_claims(0)[0].paid_milestones, 1);
+        assert_eq!(contract.get_bounty_escrow(None), U128(70));
+        assert_eq!(get_bounty(&contract, 0).status, BountyStatus::Open);
+        approve_milestone(&mut context, &mut contract, 0, 1);
+        assert!(created_receipts()[0].contains("\\\"amount\\\":\\\"70\\\""));
+        paid(&mut context, &mut contract, 0, accounts(1), PromiseResult::Successful(vec![]));
+        assert!(contract.internal_bounty_claims(0).is_empty());
+        assert_eq!(contract.get_bounty_escrow(None), U128(0));
+        assert_eq!(get_bounty(&contract, 0).status, BountyStatus::Completed);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MILESTONE_OUT_OF_ORDER")]
+    fn test_bounty_milestone_out_of_order() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(create_milestone_bounty(&mut context, &mut contract, &[30, 70], 100), 0);
+        claim(&mut context, &mut contract, 0, accounts(1));
+        approve_milestone(&mut context, &mut contract, 0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MILESTONES_PAID")]
+    fn test_bounty_giveup_after_milestone() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(create_milestone_bounty(&mut context, &mut contract, &[30, 70], 100), 0);
+        claim(&mut context, &mut contract, 0, accounts(1));
+        approve_milestone(&mut context, &mut contract, 0, 0);
+        paid(&mut context, &mut contract, 0, accounts(1), PromiseResult::Successful(vec![]));
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.bounty_giveup(0);
+    }
+
+    // Rejected work of bob on a reviewed bounty, disputed once charlie delegated to vote on it.
+    fn dispute(context: &mut VMContextBuilder, contract: &mut Contract) -> (u64, u64) {
+        let id = create_reviewed_bounty(context, contract);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.bounty_reject(id, accounts(1).to_string());
+        delegate(context, contract, accounts(2), 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let proposal_id = contract.bounty_dispute(id);
+        assert_eq!(contract.internal_bounty_claims(id)[0].status, BountyClaimStatus::Disputed);
+        (id, proposal_id)
+    }
+
+    #[test]
+    fn test_bounty_dispute_approved() {
+        let (mut context, mut contract) = setup_contract();
+        let (id, proposal_id) = dispute(&mut context, &mut contract);
+        let locked_near = contract.locked_near;
+        let proposal = get_proposal(&contract, proposal_id);
+        assert_eq!(proposal.proposer, accounts(1).to_string());
+        assert!(proposal.kind == ProposalKind::ResolveBountyDispute { bounty_id: id, claimant: accounts(1).to_string() });
+
+        act(&mut context, &mut contract, accounts(2), proposal_id, Action::VoteApprove);
+        assert_eq!(get_proposal(&contract, proposal_id).status, ProposalStatus::Executed);
+        assert!(created_receipts().iter().any(|receipt| receipt.contains("ft_transfer")));
+        assert_eq!(contract.internal_bounty_claims(id)[0].status, BountyClaimStatus::Approved);
+        assert_eq!(get_bounty(&contract, id).status, BountyStatus::Completed);
+        paid(&mut context, &mut contract, id, accounts(1), PromiseResult::Successful(vec![]));
+        assert!(contract.internal_bounty_claims(id).is_empty());
+        assert_eq!(contract.locked_near, locked_near - DEFAULT_BOUNTY_BOND);
+    }
+
+    #[test]
+    fn test_bounty_dispute_rejected() {
+        let (mut context, mut contract) = setup_contract();
+        let (id, proposal_id) = dispute(&mut context, &mut contract);
+        let locked_near = contract.locked_near;
+        act(&mut context, &mut contract, accounts(2), proposal_id, Action::VoteReject);
+        assert_eq!(get_proposal(&contract, proposal_id).status, ProposalStatus::Rejected);
+        // The DAO keeps the bond of the claim.
+        assert!(contract.internal_bounty_claims(id).is_empty());
+        assert_eq!(contract.locked_near, locked_near - DEFAULT_BOUNTY_BOND);
+        assert!(created_receipts().is_empty());
+        assert_eq!(get_bounty(&contract, id).times, 1);
+    }
+
+    #[test]
+    fn test_bounty_dispute_expired() {
+        let (mut context, mut contract) = setup_contract();
+        let (id, proposal_id) = dispute(&mut context, &mut contract);
+        testing_env!(context.block_timestamp(DEFAULT_PROPOSAL_PERIOD + 1).build());
+        contract.finalize_proposal(proposal_id);
+        assert_eq!(get_proposal(&contract, proposal_id).status, ProposalStatus::Expired);
+        // Without quorum the bond goes back to the claimant.
+        assert!(contract.internal_bounty_claims(id).is_empty());
+        let receipts = created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert!(receipts[0].contains("bob") && receipts[0].contains("Transfer"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_NOT_REJECTED")]
+    fn test_bounty_dispute_not_rejected() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_reviewed_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.bounty_dispute(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_CLAIM_DISPUTED")]
+    fn test_bounty_giveup_disputed() {
+        let (mut context, mut contract) = setup_contract();
+        dispute(&mut context, &mut contract);
+        contract.bounty_giveup(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_DISPUTE_PROPOSAL")]
+    fn test_add_bounty_dispute_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        add_proposal(&mut context, &mut contract, ProposalKind::ResolveBountyDispute {
+            bounty_id: 0,
+            claimant: accounts(1).to_string(),
+        });
+    }
+
+    fn create_near_bounty(context: &mut VMContextBuilder, contract: &mut Contract, deposit: Balance) -> u64 {
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(deposit).build());
+        let id = contract.create_bounty_near(BountyNearInput {
+            description: "d".to_string(),
+            amount: U128(100),
+            times: 2,
+            max_deadline: U64(MAX_DEADLINE),
+            expires_at: None,
+            reviewers: vec![],
+            milestones: vec![],
+            tags: vec![],
+            requires_application: false,
+            review_period: None,
+            claimant_whitelist: None,
+        });
+        testing_env!(context.attached_deposit(0).build());
+        id
+    }
+
+    #[test]
+    fn test_near_bounty() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_near_bounty(&mut context, &mut contract, 200);
+        assert_eq!(get_bounty(&contract, id).token, BountyToken::Near);
+        assert_eq!(contract.get_near_bounty_escrow(), U128(200));
+        assert_eq!(contract.get_bounty_escrow(None), U128(0));
+        assert_eq!(contract.locked_near, 0);
+
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        approve(&mut context, &mut contract, id, accounts(1));
+        let receipts = created_receipts();
+        assert!(receipts[0].contains("bob") && receipts[0].contains("Transfer(TransferAction { deposit: 100 })"));
+        assert!(!receipts[0].contains("ft_transfer"));
+        assert_eq!(contract.get_near_bounty_escrow(), U128(100));
+        // Failed payout puts the $NEAR back to the escrow.
+        paid(&mut context, &mut contract, id, accounts(1), PromiseResult::Failed);
+        assert_eq!(contract.get_near_bounty_escrow(), U128(200));
+    }
+
+    #[test]
+    fn test_cancel_near_bounty() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_near_bounty(&mut context, &mut contract, 200);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.cancel_bounty(id);
+        let receipts = created_receipts();
+        assert!(receipts[0].contains("alice") && receipts[0].contains("Transfer(TransferAction { deposit: 200 })"));
+        assert_eq!(contract.get_near_bounty_escrow(), U128(0));
+        assert_eq!(get_bounty(&contract, id).status, BountyStatus::Cancelled);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_DEPOSIT_ENOUGH_NEAR")]
+    fn test_near_bounty_wrong_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        create_near_bounty(&mut context, &mut contract, 100);
+    }
+
+    fn top_up(context: &mut VMContextBuilder, contract: &mut Contract, token_id: ValidAccountId, id: u64, amount: Balance) -> Balance {
+        let msg = format!("{{\"purpose\":{{\"TopUpBounty\":{{\"bounty_id\":{}}}}}}}", id);
+        transfer_call_from(context, contract, token_id, owner(), amount, &msg)
+    }
+
+    #[test]
+    fn test_bounty_top_up() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty_times(&mut context, &mut contract, 100, 2);
+        claim(&mut context, &mut contract, id, accounts(1));
+        assert_eq!(top_up(&mut context, &mut contract, token(), id, 50), 0);
+        let output = contract.get_bounty(id).unwrap();
+        assert_eq!(output.bounty.amount, U128(125));
+        assert_eq!(contract.get_bounty_escrow(None), U128(250));
+        assert!(get_logs()[0].contains("\"event\":\"bounty_top_up\""));
+
+        // Claim made before the top up is paid the new reward.
+        done(&mut context, &mut contract, id, accounts(1));
+        approve(&mut context, &mut contract, id, accounts(1));
+        assert!(created_receipts()[0].contains("\\\"amount\\\":\\\"125\\\""));
+        // Top up while the payout is in flight is refunded.
+        assert_eq!(top_up(&mut context, &mut contract, token(), id, 50), 50);
+    }
+
+    #[test]
+    fn test_bounty_top_up_refunds() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty_times(&mut context, &mut contract, 100, 2);
+        accept_token(&mut context, &mut contract, accounts(3));
+        assert_eq!(top_up(&mut context, &mut contract, accounts(3), id, 50), 50);
+        assert_eq!(get_logs(), vec!["ERR_WRONG_BOUNTY_TOKEN"]);
+        assert_eq!(top_up(&mut context, &mut contract, token(), id, 51), 51);
+        assert_eq!(top_up(&mut context, &mut contract, token(), id + 1, 50), 50);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.cancel_bounty(id);
+        assert_eq!(top_up(&mut context, &mut contract, token(), id, 50), 50);
+        assert_eq!(get_logs(), vec!["ERR_BOUNTY_CANCELLED"]);
+        assert_eq!(contract.get_bounty(id).unwrap().bounty.amount, U128(100));
+    }
+
+    #[test]
+    fn test_bounty_top_up_transfer_options() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty_times(&mut context, &mut contract, 100, 2);
+        let msg = format!("{{\"purpose\":{{\"TopUpBounty\":{{\"bounty_id\":{}}}}},\"anonymous\":true}}", id);
+        assert_eq!(transfer_call_from(&mut context, &mut contract, token(), owner(), 50, &msg), 50);
+        assert_eq!(get_logs(), vec!["ERR_ANONYMOUS_ONLY_FOR_DONATIONS"]);
+
+        // Top up keeps no records, so no storage registration is needed.
+        contract.storage_deposits.remove(&owner().to_string());
+        testing_env!(context.predecessor_account_id(token()).build());
+        let msg = format!("{{\"purpose\":{{\"TopUpBounty\":{{\"bounty_id\":{}}}}}}}", id);
+        match contract.ft_on_transfer(owner(), U128(50), msg) {
+            PromiseOrValue::Value(refund) => assert_eq!(refund, U128(0)),
+            PromiseOrValue::Promise(_) => panic!("unexpected promise"),
+        }
+        assert_eq!(contract.get_bounty(id).unwrap().bounty.amount, U128(125));
+    }
+
+    fn create_application_bounty(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        let msg = format!(
+            "{{\"purpose\":{{\"CreateBounty\":{{\"description\":\"d\",\"token\":\"{}\",\"amount\":\"100\",\"times\":1,\"max_deadline\":\"{}\",\"requires_application\":true}}}}}}",
+            token(),
+            MAX_DEADLINE
+        );
+        assert_eq!(transfer_call(context, contract, owner(), 100, &msg), 0);
+        contract.get_last_bounty_id() - 1
+    }
+
+    fn apply(context: &mut VMContextBuilder, contract: &mut Contract, id: u64, applicant: ValidAccountId) {
+        testing_env!(context.predecessor_account_id(applicant).build());
+        contract.bounty_apply(id, "pitch".to_string());
+    }
+
+    #[test]
+    fn test_bounty_applications() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_application_bounty(&mut context, &mut contract);
+        apply(&mut context, &mut contract, id, accounts(1));
+        apply(&mut context, &mut contract, id, accounts(2));
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.bounty_accept_applicant(id, accounts(1).to_string());
+        let applications = contract.get_bounty_applications(id, 0, 10);
+        assert_eq!(applications.len(), 2);
+        assert!(applications[0].accepted && !applications[1].accepted);
+        assert_eq!(applications[1].message, "pitch");
+        assert_eq!(contract.get_bounty_applications(id, 1, 10)[0].applicant, accounts(2).to_string());
+        claim(&mut context, &mut contract, id, accounts(1));
+        assert_eq!(contract.internal_bounty_claims(id).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_APPLICANT_NOT_ACCEPTED")]
+    fn test_bounty_claim_without_acceptance() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_application_bounty(&mut context, &mut contract);
+        apply(&mut context, &mut contract, id, accounts(1));
+        claim(&mut context, &mut contract, id, accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_TOO_MANY_APPLICATIONS")]
+    fn test_bounty_applications_limit() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_application_bounty(&mut context, &mut contract);
+        for i in 0..=MAX_BOUNTY_APPLICATIONS {
+            apply(&mut context, &mut contract, id, format!("applicant{}.near", i).try_into().unwrap());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_bounty_accept_applicant_not_creator() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_application_bounty(&mut context, &mut contract);
+        apply(&mut context, &mut contract, id, accounts(1));
+        contract.bounty_accept_applicant(id, accounts(1).to_string());
+    }
+
+    const REVIEW_PERIOD: u64 = 1_000;
+
+    // Bounty with its work submitted by bob at time 0, reviewed by danny.
+    fn create_review_period_bounty(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        let msg = format!(
+            "{{\"purpose\":{{\"CreateBounty\":{{\"description\":\"d\",\"token\":\"{}\",\"amount\":\"100\",\"times\":1,\"max_deadline\":\"{}\",\"reviewers\":[\"{}\"],\"review_period\":\"{}\"}}}}}}",
+            token(),
+            MAX_DEADLINE,
+            accounts(3),
+            REVIEW_PERIOD
+        );
+        assert_eq!(transfer_call(context, contract, owner(), 100, &msg), 0);
+        let id = contract.get_last_bounty_id() - 1;
+        claim(context, contract, id, accounts(1));
+        testing_env!(context.block_timestamp(0).build());
+        done(context, contract, id, accounts(1));
+        id
+    }
+
+    #[test]
+    fn test_bounty_finalize_claim() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_review_period_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(2)).block_timestamp(REVIEW_PERIOD + 1).build());
+        contract.bounty_finalize_claim(id, accounts(1).to_string());
+        assert!(created_receipts()[0].contains("ft_transfer"));
+        assert_eq!(contract.internal_bounty_claims(id)[0].status, BountyClaimStatus::Approved);
+        assert_eq!(get_bounty(&contract, id).status, BountyStatus::Completed);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_REVIEW_PERIOD_NOT_OVER")]
+    fn test_bounty_finalize_claim_in_review_period() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_review_period_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(2)).block_timestamp(REVIEW_PERIOD).build());
+        contract.bounty_finalize_claim(id, accounts(1).to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_NOT_SUBMITTED")]
+    fn test_bounty_finalize_rejected_claim() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_review_period_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(3)).block_timestamp(REVIEW_PERIOD).build());
+        contract.bounty_reject(id, accounts(1).to_string());
+        testing_env!(context.predecessor_account_id(accounts(2)).block_timestamp(REVIEW_PERIOD + 1).build());
+        contract.bounty_finalize_claim(id, accounts(1).to_string());
+    }
+
+    fn create_whitelisted_bounty(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        let msg = format!(
+            "{{\"purpose\":{{\"CreateBounty\":{{\"description\":\"d\",\"token\":\"{}\",\"amount\":\"100\",\"times\":2,\"max_deadline\":\"{}\",\"claimant_whitelist\":[\"{}\"]}}}}}}",
+            token(),
+            MAX_DEADLINE,
+            accounts(1)
+        );
+        assert_eq!(transfer_call(context, contract, owner(), 200, &msg), 0);
+        contract.get_last_bounty_id() - 1
+    }
+
+    #[test]
+    fn test_bounty_whitelist() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_whitelisted_bounty(&mut context, &mut contract);
+        claim(&mut context, &mut contract, id, accounts(1));
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.update_bounty_whitelist(id, vec![accounts(2).to_string()], vec![accounts(1).to_string()]);
+        let whitelist = contract.get_bounty(id).unwrap().bounty.claimant_whitelist;
+        assert_eq!(whitelist, Some(vec![accounts(2).to_string()]));
+        claim(&mut context, &mut contract, id, accounts(2));
+        // Removed account keeps the claim it made.
+        assert_eq!(contract.internal_bounty_claims(id).len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_WHITELISTED")]
+    fn test_bounty_claim_not_whitelisted() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_whitelisted_bounty(&mut context, &mut contract);
+        claim(&mut context, &mut contract, id, accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_WHITELISTED")]
+    fn test_bounty_claim_removed_from_whitelist() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_whitelisted_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.update_bounty_whitelist(id, vec![], vec![accounts(1).to_string()]);
+        claim(&mut context, &mut contract, id, accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_update_bounty_whitelist_not_creator() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_whitelisted_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.update_bounty_whitelist(id, vec![accounts(1).to_string()], vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PAUSED")]
+    fn test_update_bounty_whitelist_while_paused() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_whitelisted_bounty(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.pause();
+        contract.update_bounty_whitelist(id, vec![accounts(1).to_string()], vec![]);
+    }
+
+    fn split(context: &mut VMContextBuilder, contract: &mut Contract, id: u64, amounts: Vec<(ValidAccountId, u128)>) {
+        testing_env!(context.predecessor_account_id(owner()).build());
+        let recipients = amounts.into_iter().map(|(recipient, amount)| (recipient.to_string(), U128(amount))).collect();
+        contract.bounty_approve_split(id, recipients);
+    }
+
+    fn leg_paid(context: &mut VMContextBuilder, contract: &mut Contract, id: u64, recipient: ValidAccountId, result: PromiseResult) {
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), result);
+        contract.on_bounty_payout_leg(id, recipient.to_string());
+    }
+
+    #[test]
+    fn test_bounty_split_retry() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        split(&mut context, &mut contract, id, vec![(accounts(1), 60), (accounts(2), 40)]);
+        let receipts: Vec<String> = created_receipts().into_iter().filter(|r| r.contains("ft_transfer")).collect();
+        assert_eq!(receipts.len(), 2);
+        assert!(receipts[0].contains("\\\"receiver_id\\\":\\\"bob\\\"") && receipts[0].contains("\\\"amount\\\":\\\"60\\\""));
+        assert!(receipts[1].contains("\\\"receiver_id\\\":\\\"charlie\\\"") && receipts[1].contains("\\\"amount\\\":\\\"40\\\""));
+        assert_eq!(contract.get_bounty_escrow(None), U128(0));
+
+        leg_paid(&mut context, &mut contract, id, accounts(1), PromiseResult::Successful(vec![]));
+        leg_paid(&mut context, &mut contract, id, accounts(2), PromiseResult::Failed);
+        assert_eq!(get_logs(), vec!["ERR_BOUNTY_PAYOUT_FAILED: charlie"]);
+        let legs = contract.get_bounty_payout(id).unwrap().legs;
+        assert_eq!(legs[0].status, BountyPayoutStatus::Paid);
+        assert_eq!(legs[1].status, BountyPayoutStatus::Failed);
+        assert_eq!(contract.get_bounty_escrow(None), U128(40));
+        assert_eq!(contract.locked_near, DEFAULT_BOUNTY_BOND);
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.retry_bounty_payout(id, accounts(2).to_string());
+        assert_eq!(contract.get_bounty_escrow(None), U128(0));
+        leg_paid(&mut context, &mut contract, id, accounts(2), PromiseResult::Successful(vec![]));
+        assert!(contract.get_bounty_payout(id).is_none());
+        assert!(contract.internal_bounty_claims(id).is_empty());
+        assert_eq!(contract.locked_near, 0);
+        assert_eq!(get_bounty(&contract, id).status, BountyStatus::Completed);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOUNTY_PAYOUT_NOT_FAILED")]
+    fn test_bounty_split_retry_pending() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        split(&mut context, &mut contract, id, vec![(accounts(1), 60), (accounts(2), 40)]);
+        contract.retry_bounty_payout(id, accounts(2).to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_SPLIT_SUM_MISMATCH")]
+    fn test_bounty_split_sum_mismatch() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        split(&mut context, &mut contract, id, vec![(accounts(1), 60), (accounts(2), 30)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DUPLICATE_RECIPIENT")]
+    fn test_bounty_split_duplicate_recipient() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        claim(&mut context, &mut contract, id, accounts(1));
+        done(&mut context, &mut contract, id, accounts(1));
+        split(&mut context, &mut contract, id, vec![(accounts(1), 50), (accounts(1), 50)]);
+    }
+
+    fn allow_council_review(context: &mut VMContextBuilder, contract: &mut Contract, member: ValidAccountId) {
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.add_council_member(member);
+        contract.set_permission(
+            BOUNTY_PERMISSION_LABEL.to_string(),
+            PermissionAction::ReviewBounty,
+            vec![Role::Owner, Role::Council],
+        );
+    }
+
+    #[test]
+    fn test_council_approves_bounty() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        allow_council_review(&mut context, &mut contract, accounts(1));
+        claim(&mut context, &mut contract, id, accounts(2));
+        done(&mut context, &mut contract, id, accounts(2));
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.bounty_approve(id, accounts(2).to_string());
+        assert_eq!(contract.internal_bounty_claims(id)[0].status, BountyClaimStatus::Approved);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_random_account_approves_bounty() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_bounty(&mut context, &mut contract, 100);
+        allow_council_review(&mut context, &mut contract, accounts(1));
+        claim(&mut context, &mut contract, id, accounts(2));
+        done(&mut context, &mut contract, id, accounts(2));
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.bounty_approve(id, accounts(2).to_string());
+    }
+}
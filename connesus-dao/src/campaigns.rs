@@ -0,0 +1,460 @@
+use crate::*;
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum CampaignStatus {
+    Active,
+    // Goal was raised, no more donations are accepted.
+    Completed,
+    // Deadline passed before the goal was raised.
+    Expired,
+}
+
+// Fundraising drive of the DAO, run separately from the proposals.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Campaign {
+    pub id: u64,
+    pub title: String,
+    // Token the campaign raises.
+    pub token: AccountId,
+    pub goal: U128,
+    pub deadline: U64,
+    pub raised: U128,
+    pub status: CampaignStatus,
+    // Part of `raised` drawn from the matching pools.
+    pub matched: U128,
+    // Donations refunded to their donors since the campaign expired.
+    pub refunded: U128,
+    // Set once the raised tokens, or the matched part of an expired campaign, were withdrawn.
+    pub withdrawn: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum VersionedCampaign {
+    Default(Campaign),
+}
+
+impl From<VersionedCampaign> for Campaign {
+    fn from(v: VersionedCampaign) -> Self {
+        match v {
+            VersionedCampaign::Default(c) => c,
+        }
+    }
+}
+
+impl Campaign {
+    // Stored status, unless the deadline passed while the campaign was active.
+    pub fn get_status(&self) -> CampaignStatus {
+        if self.status == CampaignStatus::Active && env::block_timestamp() >= self.deadline.0 {
+            CampaignStatus::Expired
+        } else {
+            self.status.clone()
+        }
+    }
+
+    // Part of `amount` that fits until the goal.
+    pub fn acceptable_donation(&self, amount: Balance) -> Balance {
+        std::cmp::min(amount, self.goal.0.saturating_sub(self.raised.0))
+    }
+
+    // What `withdraw_campaign` sends: all that was raised once completed, only the matched part once
+    // expired, as the donations go back to the donors then.
+    pub fn withdrawable(&self) -> Balance {
+        if self.withdrawn {
+            return 0;
+        }
+        match self.get_status() {
+            CampaignStatus::Active => 0,
+            CampaignStatus::Completed => self.raised.0,
+            CampaignStatus::Expired => self.matched.0,
+        }
+    }
+
+    fn donate(&mut self, amount: Balance) {
+        self.raised = U128(self.raised.0 + amount);
+        if self.raised.0 >= self.goal.0 {
+            self.status = CampaignStatus::Completed;
+        }
+    }
+}
+
+impl Contract {
+    // Adds donation to given campaign, returns the accepted part or the error code to refund it all.
+    pub(crate) fn internal_campaign_donate(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        campaign_id: u64,
+        amount: Balance,
+        anonymous: bool,
+    ) -> Result<Balance, &'static str> {
+        let mut campaign: Campaign = self.campaigns.get(&campaign_id).ok_or("ERR_NO_CAMPAIGN")?.into();
+        match campaign.get_status() {
+            CampaignStatus::Active => {},
+            CampaignStatus::Completed => return Err("ERR_CAMPAIGN_GOAL_REACHED"),
+            CampaignStatus::Expired => return Err("ERR_CAMPAIGN_DEADLINE_PASSED"),
+        }
+        if &campaign.token != token_id {
+            return Err("ERR_TOKEN_NOT_ACCEPTED");
+        }
+        let accepted = campaign.acceptable_donation(amount);
+        campaign.donate(accepted);
+        let purpose = DonationPurpose::Campaign(campaign_id);
+        let matched = self.internal_match_donation(token_id, &purpose, accepted, campaign.acceptable_donation(Balance::MAX));
+        campaign.donate(matched);
+        campaign.matched = U128(campaign.matched.0 + matched);
+        self.campaigns.insert(&campaign_id, &VersionedCampaign::Default(campaign));
+        let total = self.campaign_donation_totals.get(token_id).unwrap_or_default();
+        self.campaign_donation_totals.insert(token_id, &(total + accepted + matched));
+        self.internal_record_donation(
+            account_id,
+            token_id,
+            accepted,
+            purpose,
+            anonymous,
+            None,
+        );
+        Ok(accepted)
+    }
+
+    // Donations of given account to given campaign that weren't refunded yet, as index of the donation
+    // record and the amount.
+    pub(crate) fn internal_campaign_refund_parts(&self, account_id: &AccountId, campaign_id: u64) -> Vec<(u64, U128)> {
+        self.donation_records_by_account
+            .get(account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|index| {
+                let record = self.donation_records.get(index).expect("ERR_NO_DONATION_RECORD");
+                if record.purpose != DonationPurpose::Campaign(campaign_id) {
+                    return None;
+                }
+                let amount = record.amount.0 - self.refunded_donations.get(&index).unwrap_or_default();
+                if amount > 0 {
+                    Some((index, U128(amount)))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // Takes `amount` of given token out of the campaign totals, or puts it back when `restore` is set.
+    fn internal_update_campaign_total(&mut self, token_id: &AccountId, amount: Balance, restore: bool) {
+        let total = self.campaign_donation_totals.get(token_id).unwrap_or_default();
+        let total = if restore {
+            total + amount
+        } else {
+            total.checked_sub(amount).expect("ERR_CAMPAIGN_TOTAL_UNDERFLOW")
+        };
+        if total == 0 {
+            self.campaign_donation_totals.remove(token_id);
+        } else {
+            self.campaign_donation_totals.insert(token_id, &total);
+        }
+    }
+
+    // Marks the refunded parts of the donation records, or takes the marks back when `restore` is set.
+    fn internal_apply_campaign_refund(&mut self, campaign: &mut Campaign, parts: &[(u64, U128)], restore: bool) {
+        let mut total = 0;
+        for (index, amount) in parts {
+            let refunded = self.refunded_donations.get(index).unwrap_or_default();
+            let refunded = if restore { refunded - amount.0 } else { refunded + amount.0 };
+            if refunded == 0 {
+                self.refunded_donations.remove(index);
+            } else {
+                self.refunded_donations.insert(index, &refunded);
+            }
+            total += amount.0;
+        }
+        campaign.refunded = U128(if restore { campaign.refunded.0 - total } else { campaign.refunded.0 + total });
+        self.internal_update_campaign_total(&campaign.token, total, restore);
+    }
+}
+
+#[ext_contract(ext_campaigns)]
+pub trait CampaignCallbacks {
+    fn on_campaign_withdrawn(&mut self, id: u64, receiver_id: AccountId, amount: U128);
+    fn on_campaign_refunded(&mut self, id: u64, parts: Vec<(u64, U128)>);
+}
+
+#[near_bindgen]
+impl Contract {
+    // Starts a campaign raising `goal` of given token, the DAO token by default, until `deadline`.
+    // Donations are made with `TransferPurpose::CampaignDonate`.
+    pub fn create_campaign(
+        &mut self,
+        title: String,
+        goal: U128,
+        deadline: U64,
+        token_id: Option<ValidAccountId>,
+    ) -> u64 {
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        assert!(!title.is_empty(), "ERR_EMPTY_TITLE");
+        assert!(goal.0 > 0, "ERR_INVALID_AMOUNT");
+        assert!(deadline.0 > env::block_timestamp(), "ERR_INVALID_DEADLINE");
+        let id = self.last_campaign_id;
+        let campaign = Campaign {
+            id,
+            title,
+            token: token_id.map_or_else(|| self.token_account.clone(), |token_id| token_id.into()),
+            goal,
+            deadline,
+            raised: U128(0),
+            status: CampaignStatus::Active,
+            matched: U128(0),
+            refunded: U128(0),
+            withdrawn: false,
+        };
+        self.campaigns.insert(&id, &VersionedCampaign::Default(campaign));
+        self.last_campaign_id += 1;
+        id
+    }
+
+    // Sends what given campaign raised to `receiver_id` once it's completed. Of an expired campaign only
+    // the part matched from the pools is sent, its donors take back their donations with
+    // `refund_campaign_donation`.
+    pub fn withdraw_campaign(&mut self, id: u64, receiver_id: ValidAccountId) -> Promise {
+        self.assert_not_paused();
+        self.assert_owner_or_dao();
+        let mut campaign: Campaign = self.campaigns.get(&id).expect("ERR_NO_CAMPAIGN").into();
+        let amount = campaign.withdrawable();
+        assert!(amount > 0, "ERR_NOTHING_TO_WITHDRAW");
+        campaign.withdrawn = true;
+        self.internal_update_campaign_total(&campaign.token, amount, false);
+        let token_id = campaign.token.clone();
+        self.campaigns.insert(&id, &VersionedCampaign::Default(campaign));
+        ext_fungible_token::ft_transfer(
+            receiver_id.to_string(),
+            U128(amount),
+            None,
+            &token_id,
+            ONE_YOCTO_NEAR,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_campaigns::on_campaign_withdrawn(
+            id,
+            receiver_id.into(),
+            U128(amount),
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+
+    // Sends the donations of the caller to given campaign back to them, once it expired before its goal.
+    pub fn refund_campaign_donation(&mut self, id: u64) -> Promise {
+        self.assert_not_paused();
+        let account_id = env::predecessor_account_id();
+        let mut campaign: Campaign = self.campaigns.get(&id).expect("ERR_NO_CAMPAIGN").into();
+        assert_eq!(campaign.get_status(), CampaignStatus::Expired, "ERR_CAMPAIGN_NOT_EXPIRED");
+        let parts = self.internal_campaign_refund_parts(&account_id, id);
+        let amount: Balance = parts.iter().map(|(_, part)| part.0).sum();
+        assert!(amount > 0, "ERR_NOTHING_TO_REFUND");
+        self.internal_apply_campaign_refund(&mut campaign, &parts, false);
+        let token_id = campaign.token.clone();
+        self.campaigns.insert(&id, &VersionedCampaign::Default(campaign));
+        ext_fungible_token::ft_transfer(
+            account_id,
+            U128(amount),
+            None,
+            &token_id,
+            ONE_YOCTO_NEAR,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_campaigns::on_campaign_refunded(
+            id,
+            parts,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+
+    // Emits the withdrawal once sent, lets the campaign be withdrawn again otherwise.
+    #[private]
+    pub fn on_campaign_withdrawn(&mut self, id: u64, receiver_id: AccountId, amount: U128) {
+        let mut campaign: Campaign = self.campaigns.get(&id).expect("ERR_NO_CAMPAIGN").into();
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            DaoEvent::DonationWithdraw(vec![DonationWithdrawData {
+                receiver_id,
+                token: campaign.token,
+                amount,
+                memo: campaign.title,
+            }])
+            .emit();
+            return;
+        }
+        campaign.withdrawn = false;
+        self.internal_update_campaign_total(&campaign.token, amount.0, true);
+        self.campaigns.insert(&id, &VersionedCampaign::Default(campaign));
+        env::log(b"ERR_CAMPAIGN_WITHDRAW_FAILED");
+    }
+
+    // Puts the refunded donations back if the transfer failed.
+    #[private]
+    pub fn on_campaign_refunded(&mut self, id: u64, parts: Vec<(u64, U128)>) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        let mut campaign: Campaign = self.campaigns.get(&id).expect("ERR_NO_CAMPAIGN").into();
+        self.internal_apply_campaign_refund(&mut campaign, &parts, true);
+        self.campaigns.insert(&id, &VersionedCampaign::Default(campaign));
+        env::log(b"ERR_CAMPAIGN_REFUND_FAILED");
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, get_logs, testing_env_with_promise_results, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
+
+    fn campaign_donate(context: &mut VMContextBuilder, contract: &mut Contract, id: u64, amount: Balance) -> Balance {
+        let msg = format!("{{\"purpose\":{{\"CampaignDonate\":{{\"campaign_id\":{}}}}}}}", id);
+        transfer_call(context, contract, accounts(1), amount, &msg)
+    }
+
+    fn create_campaign(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        testing_env!(context.predecessor_account_id(owner()).block_timestamp(0).build());
+        contract.create_campaign("drive".to_string(), U128(100), U64(1_000), None)
+    }
+
+    #[test]
+    fn test_campaign_goal() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_campaign(&mut context, &mut contract);
+        assert_eq!(campaign_donate(&mut context, &mut contract, id, 60), 0);
+        // Only what is left until the goal is accepted.
+        assert_eq!(campaign_donate(&mut context, &mut contract, id, 70), 30);
+        let output = contract.get_campaign(id).unwrap();
+        assert_eq!(output.campaign.raised, U128(100));
+        assert_eq!(output.campaign.status, CampaignStatus::Completed);
+        assert_eq!(output.progress_bps, 10_000);
+        assert_eq!(campaign_donate(&mut context, &mut contract, id, 10), 10);
+
+        let records = contract.get_donation_records(0, 10);
+        assert_eq!(records[1].amount, U128(40));
+        assert_eq!(records[1].purpose, DonationPurpose::Campaign(id));
+    }
+
+    #[test]
+    fn test_campaign_deadline() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_campaign(&mut context, &mut contract);
+        testing_env!(context.block_timestamp(999).build());
+        assert_eq!(campaign_donate(&mut context, &mut contract, id, 25), 0);
+        testing_env!(context.block_timestamp(1_000).build());
+        assert_eq!(campaign_donate(&mut context, &mut contract, id, 25), 25);
+        let output = contract.get_campaign(id).unwrap();
+        assert_eq!(output.campaign.raised, U128(25));
+        assert_eq!(output.campaign.status, CampaignStatus::Expired);
+        assert_eq!(output.progress_bps, 2_500);
+    }
+
+    #[test]
+    fn test_campaign_refunds() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_campaign(&mut context, &mut contract);
+        assert_eq!(campaign_donate(&mut context, &mut contract, id + 1, 10), 10);
+        let msg = format!("{{\"purpose\":{{\"CampaignDonate\":{{\"campaign_id\":{}}}}}}}", id);
+        let other_token = accounts(3);
+        assert_eq!(transfer_call_from(&mut context, &mut contract, other_token, accounts(1), 10, &msg), 10);
+        assert_eq!(contract.get_campaign(id).unwrap().campaign.raised, U128(0));
+    }
+
+    #[test]
+    fn test_withdraw_completed_campaign() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_campaign(&mut context, &mut contract);
+        campaign_donate(&mut context, &mut contract, id, 100);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_campaign(id, accounts(3));
+        let receipts = created_receipts();
+        assert!(receipts[0].contains("ft_transfer") && receipts[0].contains(&accounts(3).to_string()));
+        assert_eq!(contract.campaign_donation_totals.get(&token().to_string()), None);
+        assert!(contract.get_campaign(id).unwrap().campaign.withdrawn);
+
+        // Failed transfer lets it be withdrawn again.
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_campaign_withdrawn(id, accounts(3).to_string(), U128(100));
+        assert_eq!(get_logs(), vec!["ERR_CAMPAIGN_WITHDRAW_FAILED"]);
+        assert_eq!(contract.campaign_donation_totals.get(&token().to_string()), Some(100));
+        assert_eq!(contract.get_campaign(id).unwrap().campaign.withdrawable(), 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOTHING_TO_WITHDRAW")]
+    fn test_withdraw_active_campaign() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_campaign(&mut context, &mut contract);
+        campaign_donate(&mut context, &mut contract, id, 60);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_campaign(id, accounts(3));
+    }
+
+    #[test]
+    fn test_refund_expired_campaign() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_campaign(&mut context, &mut contract);
+        campaign_donate(&mut context, &mut contract, id, 25);
+        campaign_donate(&mut context, &mut contract, id, 15);
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(1_000).build());
+        contract.refund_campaign_donation(id);
+        let receipts = created_receipts();
+        assert!(receipts[0].contains("ft_transfer") && receipts[0].contains("\\\"amount\\\":\\\"40\\\""));
+        assert_eq!(contract.get_campaign(id).unwrap().campaign.refunded, U128(40));
+        assert_eq!(contract.campaign_donation_totals.get(&token().to_string()), None);
+        // Nothing matched, so there is nothing for the owner.
+        assert_eq!(contract.get_campaign(id).unwrap().campaign.withdrawable(), 0);
+
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_campaign_refunded(id, vec![(0, U128(25)), (1, U128(15))]);
+        assert_eq!(get_logs(), vec!["ERR_CAMPAIGN_REFUND_FAILED"]);
+        assert_eq!(contract.get_campaign(id).unwrap().campaign.refunded, U128(0));
+        assert_eq!(contract.campaign_donation_totals.get(&token().to_string()), Some(40));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CAMPAIGN_NOT_EXPIRED")]
+    fn test_refund_active_campaign() {
+        let (mut context, mut contract) = setup_contract();
+        let id = create_campaign(&mut context, &mut contract);
+        campaign_donate(&mut context, &mut contract, id, 25);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.refund_campaign_donation(id);
+    }
+
+    #[test]
+    fn test_get_campaigns() {
+        let (mut context, mut contract) = setup_contract();
+        create_campaign(&mut context, &mut contract);
+        let id = create_campaign(&mut context, &mut contract);
+        assert_eq!(contract.get_last_campaign_id(), 2);
+        let campaigns = contract.get_campaigns(1, 10);
+        assert_eq!(campaigns.len(), 1);
+        assert_eq!(campaigns[0].campaign.id, id);
+        assert!(contract.get_campaign(2).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY_OWNER")]
+    fn test_create_campaign_not_owner() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.create_campaign("drive".to_string(), U128(100), U64(1_000), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_DEADLINE")]
+    fn test_create_campaign_past_deadline() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.block_timestamp(1_000).build());
+        contract.create_campaign("drive".to_string(), U128(100), U64(1_000), None);
+    }
+}
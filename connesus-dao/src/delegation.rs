@@ -1,30 +1,334 @@
 use crate::*;
 
+// Undelegated tokens waiting for the unbonding period to pass.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingWithdrawal {
+    pub amount: U128,
+    pub requested_at: U64,
+    pub available_at: U64,
+}
+
+// Change of the delegation of an account, `balance` is its delegation after it.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DelegationChange {
+    pub timestamp: U64,
+    pub delta: I128,
+    pub balance: U128,
+}
+
+// Delegation of `funder` that can't be undelegated until `unlocks_at`, weighing `bonus` more in votes meanwhile.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DelegationLock {
+    pub funder: AccountId,
+    pub amount: U128,
+    pub bonus: U128,
+    pub unlocks_at: U64,
+}
+
+// Extra voting weight of `amount` locked for `duration`, from nothing up to `amount` for `MAX_LOCK_DURATION`.
+pub fn lock_bonus(amount: Balance, duration: u64) -> Balance {
+    let duration = duration as Balance;
+    let max_duration = MAX_LOCK_DURATION as Balance;
+    amount / max_duration * duration + amount % max_duration * duration / max_duration
+}
+
 impl Contract {
     pub fn get_user_weight(&self, account_id: &AccountId) -> Balance {
-        self.delegations.get(account_id).unwrap_or_default()
+        self.get_token_delegation(&self.token_account, account_id)
     }
 
-    pub fn internal_delegate(&mut self, account_id: &AccountId, amount: U128) {
-        let prev_amount = self
-            .delegations
-            .get(&account_id.to_string())
+    // Delegation of given account in given token. Delegations of the DAO token from before they were
+    // kept per token are read from `delegations` until they change.
+    pub fn get_token_delegation(&self, token_id: &AccountId, account_id: &AccountId) -> Balance {
+        match self.token_delegations.get(&(token_id.clone(), account_id.clone())) {
+            Some(amount) => amount,
+            None if token_id == &self.token_account => self.delegations.get(account_id).unwrap_or_default(),
+            None => 0,
+        }
+    }
+
+    // Returns true if given account registered or delegated the DAO token, even if nothing is left.
+    pub(crate) fn has_delegation_entry(&self, account_id: &AccountId) -> bool {
+        self.token_delegations.contains_key(&(self.token_account.clone(), account_id.clone()))
+            || self.delegations.contains_key(account_id)
+    }
+
+    // Moves delegation of the DAO token out of `delegations` on its first change, removes empty entries.
+    fn internal_set_token_delegation(&mut self, token_id: &AccountId, account_id: &AccountId, amount: Balance) {
+        if token_id == &self.token_account {
+            self.delegations.remove(account_id);
+        }
+        let key = (token_id.clone(), account_id.clone());
+        if amount == 0 {
+            self.token_delegations.remove(&key);
+        } else {
+            self.token_delegations.insert(&key, &amount);
+        }
+    }
+
+    // Total delegation in given token.
+    pub fn get_token_delegation_total(&self, token_id: &AccountId) -> Balance {
+        if token_id == &self.token_account {
+            self.total_delegation_amount
+        } else {
+            self.token_delegation_totals.get(token_id).unwrap_or_default()
+        }
+    }
+
+    // Keeps given account's delegation of a token other than the DAO token, it doesn't vote.
+    pub fn internal_delegate_token(&mut self, token_id: &AccountId, account_id: &AccountId, amount: Balance) {
+        let new_amount = self
+            .get_token_delegation(token_id, account_id)
+            .checked_add(amount)
+            .expect("ERR_DELEGATION_OVERFLOW");
+        self.internal_set_token_delegation(token_id, account_id, new_amount);
+        let total = self.get_token_delegation_total(token_id);
+        self.token_delegation_totals.insert(token_id, &(total + amount));
+        let mut delegators = self.internal_token_delegators(token_id);
+        delegators.insert(account_id);
+        self.token_delegators.insert(token_id, &delegators);
+    }
+
+    pub fn internal_undelegate_token(&mut self, token_id: &AccountId, account_id: &AccountId, amount: Balance) {
+        let new_amount = self
+            .get_token_delegation(token_id, account_id)
+            .checked_sub(amount)
+            .expect("ERR_NOT_ACCOUNT_NOT_ENOUGH_BALANCE");
+        self.internal_set_token_delegation(token_id, account_id, new_amount);
+        match self.get_token_delegation_total(token_id).saturating_sub(amount) {
+            0 => self.token_delegation_totals.remove(token_id),
+            total => self.token_delegation_totals.insert(token_id, &total),
+        };
+        if new_amount == 0 {
+            let mut delegators = self.internal_token_delegators(token_id);
+            delegators.remove(account_id);
+            self.token_delegators.insert(token_id, &delegators);
+        }
+    }
+
+    fn internal_token_delegators(&self, token_id: &AccountId) -> UnorderedSet<AccountId> {
+        self.token_delegators.get(token_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKeys::TokenDelegatorSet { token_id: token_id.clone() })
+        })
+    }
+
+    // Makes given token the DAO token, its delegations start to vote. Nothing is delegated in the old
+    // one at this point, see `ProposalKind::ChangeStakingToken`.
+    pub(crate) fn internal_change_staking_token(&mut self, token_id: &AccountId) {
+        let total = self.token_delegation_totals.remove(token_id).unwrap_or_default();
+        self.token_account = token_id.clone();
+        self.total_delegation_amount = total;
+        // Held for the delegators like any delegation of the DAO token.
+        self.locked_amount = self.locked_amount.checked_add(total).expect("ERR_LOCKED_AMOUNT_OVERFLOW");
+        if let Some(delegators) = self.token_delegators.remove(token_id) {
+            self.delegators = delegators;
+        }
+        self.reward_checkpoint = self.reward_per_token;
+    }
+
+    // Delegation of given account with the bonus of its locks that didn't expire yet, used for votes.
+    pub fn get_voting_weight(&self, account_id: &AccountId) -> Balance {
+        let now = env::block_timestamp();
+        let bonus: Balance = self
+            .delegation_locks
+            .get(account_id)
+            .unwrap_or_default()
+            .iter()
+            .filter(|lock| lock.unlocks_at.0 > now)
+            .map(|lock| lock.bonus.0)
+            .sum();
+        self.get_user_weight(account_id) + bonus
+    }
+
+    // Locks `amount` that `funder` just delegated to `delegate` for `duration`.
+    pub fn internal_lock_delegation(&mut self, funder: &AccountId, delegate: &AccountId, amount: Balance, duration: u64) {
+        let mut locks = self.delegation_locks.get(delegate).unwrap_or_default();
+        let bonus = lock_bonus(amount, duration);
+        locks.push(DelegationLock {
+            funder: funder.clone(),
+            amount: U128(amount),
+            bonus: U128(bonus),
+            unlocks_at: U64(env::block_timestamp() + duration),
+        });
+        self.delegation_locks.insert(delegate, &locks);
+        self.total_delegation_bonus += bonus;
+    }
+
+    // Drops expired locks of given account, with their bonus.
+    pub fn internal_release_expired_locks(&mut self, account_id: &AccountId) {
+        let now = env::block_timestamp();
+        let (expired, locks): (Vec<DelegationLock>, Vec<DelegationLock>) = self
+            .delegation_locks
+            .get(account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .partition(|lock| lock.unlocks_at.0 <= now);
+        if expired.is_empty() {
+            return;
+        }
+        let bonus: Balance = expired.iter().map(|lock| lock.bonus.0).sum();
+        self.total_delegation_bonus = self.total_delegation_bonus.saturating_sub(bonus);
+        if locks.is_empty() {
+            self.delegation_locks.remove(account_id);
+        } else {
+            self.delegation_locks.insert(account_id, &locks);
+        }
+    }
+
+    // Part of what `funder` delegated to `delegate` that is still locked.
+    pub fn get_locked_delegation(&self, funder: &AccountId, delegate: &AccountId) -> Balance {
+        let now = env::block_timestamp();
+        self.delegation_locks
+            .get(delegate)
+            .unwrap_or_default()
+            .iter()
+            .filter(|lock| &lock.funder == funder && lock.unlocks_at.0 > now)
+            .map(|lock| lock.amount.0)
+            .sum()
+    }
+
+    // Panics if `funder` took out of `delegate` some of the delegation it locked.
+    fn assert_locked_delegation_kept(&self, funder: &AccountId, delegate: &AccountId) {
+        let funded = self
+            .delegation_funds
+            .get(funder)
+            .and_then(|funds| funds.get(delegate).cloned())
             .unwrap_or_default();
-        let new_amount = prev_amount + amount.0;
-        self.delegations.insert(&account_id.to_string(), &new_amount);
-        self.total_delegation_amount += amount.0;
+        assert!(funded >= self.get_locked_delegation(funder, delegate), "ERR_DELEGATION_LOCKED");
+    }
+
+    pub fn internal_delegate(&mut self, account_id: &AccountId, amount: U128) {
+        self.internal_settle_rewards(account_id);
+        let prev_amount = self.get_user_weight(account_id);
+        let new_amount = prev_amount.checked_add(amount.0).expect("ERR_DELEGATION_OVERFLOW");
+        let token_account = self.token_account.clone();
+        self.internal_set_token_delegation(&token_account, account_id, new_amount);
+        if new_amount > 0 {
+            self.delegators.insert(account_id);
+        }
+        self.internal_record_delegation_change(account_id, amount.0 as i128, new_amount);
+        self.total_delegation_amount = self
+            .total_delegation_amount
+            .checked_add(amount.0)
+            .expect("ERR_DELEGATION_OVERFLOW");
     }
 
     pub fn internal_undelegate(&mut self, account_id: &AccountId, amount: U128) {
         self.internal_reduce_delegation(account_id, amount);
-        self.total_delegation_amount -= amount.0;
+        self.total_delegation_amount = self
+            .total_delegation_amount
+            .checked_sub(amount.0)
+            .expect("ERR_DELEGATION_UNDERFLOW");
+    }
+
+    // Records that `funder` delegated `amount` of their tokens to `delegate`.
+    pub fn internal_add_funds(&mut self, funder: &AccountId, delegate: &AccountId, amount: Balance) {
+        let mut funds = self.delegation_funds.get(funder).unwrap_or_default();
+        let funded = funds.entry(delegate.clone()).or_insert(0);
+        *funded = funded.checked_add(amount).expect("ERR_DELEGATION_OVERFLOW");
+        self.delegation_funds.insert(funder, &funds);
+        let total = self.funded_delegations.get(delegate).unwrap_or_default();
+        self.funded_delegations.insert(delegate, &(total + amount));
+    }
+
+    // Removes `amount` from what `funder` delegated to `delegate`, dropping empty entries.
+    pub fn internal_remove_funds(&mut self, funder: &AccountId, delegate: &AccountId, amount: Balance) {
+        let mut funds = self.delegation_funds.get(funder).unwrap_or_default();
+        let funded = funds.get(delegate).cloned().unwrap_or_default();
+        let remaining = funded.checked_sub(amount).expect("ERR_NOT_ENOUGH_FUNDED");
+        if remaining == 0 {
+            funds.remove(delegate);
+        } else {
+            funds.insert(delegate.clone(), remaining);
+        }
+        if funds.is_empty() {
+            self.delegation_funds.remove(funder);
+        } else {
+            self.delegation_funds.insert(funder, &funds);
+        }
+        match self.funded_delegations.get(delegate).unwrap_or_default().saturating_sub(amount) {
+            0 => self.funded_delegations.remove(delegate),
+            total => self.funded_delegations.insert(delegate, &total),
+        };
+    }
+
+    // Takes `amount` out of what `funder` delegated to `delegate`, after it was undelegated.
+    // Delegates can also take their own delegation from before funds were tracked,
+    // as long as what others funded stays covered.
+    pub fn internal_take_funds(&mut self, funder: &AccountId, delegate: &AccountId, amount: Balance) {
+        if funder != delegate {
+            self.internal_remove_funds(funder, delegate, amount);
+            return;
+        }
+        let funded = self
+            .delegation_funds
+            .get(funder)
+            .and_then(|funds| funds.get(delegate).cloned())
+            .unwrap_or_default();
+        self.internal_remove_funds(funder, delegate, std::cmp::min(funded, amount));
+        assert!(
+            self.funded_delegations.get(delegate).unwrap_or_default() <= self.get_user_weight(delegate),
+            "ERR_NOT_ENOUGH_FUNDED"
+        );
+    }
+
+    // Part of `amount` that can be delegated to given account without going over the cap of the policy.
+    pub fn acceptable_delegation(&self, delegate: &AccountId, amount: Balance) -> Balance {
+        match self.policy.max_delegation_per_account {
+            Some(cap) => std::cmp::min(amount, cap.0.saturating_sub(self.get_user_weight(delegate))),
+            None => amount,
+        }
+    }
+
+    // Delegation left to given account has to be either nothing or at least the minimum of the policy.
+    pub fn assert_remaining_delegation(&self, delegate: &AccountId) {
+        let remaining = self.get_user_weight(delegate);
+        assert!(
+            remaining == 0 || remaining >= self.policy.min_delegation_amount.0,
+            "ERR_DELEGATION_BELOW_MINIMUM"
+        );
+    }
+
+    // Funders other than the delegate need to be approved by it.
+    pub fn is_delegation_allowed(&self, funder: &AccountId, delegate: &AccountId) -> bool {
+        funder == delegate
+            || self
+                .delegation_approvals
+                .get(delegate)
+                .is_some_and(|approvals| approvals.contains(funder))
     }
 
+    // Removes the entry once nothing is left, to free its storage.
     pub fn internal_reduce_delegation(&mut self, account_id: &AccountId, amount: U128) {
-        let prev_amount = self.delegations.get(&account_id).unwrap_or_default();
-        assert!(prev_amount >= amount.0, "ERR_NOT_ACCOUNT_NOT_ENOUGH_BALANCE");
-        let new_amount = prev_amount - amount.0;
-        self.delegations.insert(&account_id.to_string(), &new_amount);
+        self.internal_settle_rewards(account_id);
+        let prev_amount = self.get_user_weight(account_id);
+        let new_amount = prev_amount.checked_sub(amount.0).expect("ERR_NOT_ACCOUNT_NOT_ENOUGH_BALANCE");
+        let token_account = self.token_account.clone();
+        self.internal_set_token_delegation(&token_account, account_id, new_amount);
+        if new_amount == 0 {
+            self.delegators.remove(account_id);
+        }
+        self.internal_record_delegation_change(account_id, -(amount.0 as i128), new_amount);
+    }
+
+    // Keeps the last `MAX_DELEGATION_HISTORY` changes of the delegation of given account.
+    fn internal_record_delegation_change(&mut self, account_id: &AccountId, delta: i128, balance: Balance) {
+        if delta == 0 {
+            return;
+        }
+        let mut history = self.delegation_history.get(account_id).unwrap_or_default();
+        if history.len() >= MAX_DELEGATION_HISTORY {
+            history.remove(0);
+        }
+        history.push(DelegationChange {
+            timestamp: U64(env::block_timestamp()),
+            delta: I128(delta),
+            balance: U128(balance),
+        });
+        self.delegation_history.insert(account_id, &history);
     }
 }
 
@@ -33,6 +337,12 @@ pub trait FungibleTokenContract {
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
 }
 
+#[ext_contract(ext_delegation)]
+pub trait DelegationCallbacks {
+    fn on_withdraw_undelegated(&mut self, account_id: AccountId, withdrawals: Vec<PendingWithdrawal>);
+    fn on_token_undelegated(&mut self, token_id: AccountId, account_id: AccountId, amount: U128);
+}
+
 #[near_bindgen]
 impl Contract {
     #[payable]
@@ -40,26 +350,813 @@ impl Contract {
         let token_account = self.token_account.clone();
         assert_account_id(&token_account);
         assert_eq!(env::attached_deposit(), 16 * env::storage_byte_cost());
-        self.delegations.insert(account_id, &0);
+        self.delegations.remove(account_id);
+        self.token_delegations.insert(&(token_account, account_id.clone()), &0);
+    }
+
+    // Adds accounts that delegated before the index of delegators existed, see `migrate`.
+    pub fn index_delegators(&mut self, account_ids: Vec<AccountId>) {
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        for account_id in account_ids {
+            if self.get_user_weight(&account_id) > 0 {
+                self.delegators.insert(&account_id);
+            }
+        }
+    }
+
+    // Lets given account delegate its tokens to the caller.
+    pub fn approve_delegation_from(&mut self, account_id: AccountId) {
+        let delegate = env::predecessor_account_id();
+        let mut approvals = self.delegation_approvals.get(&delegate).unwrap_or_default();
+        assert!(approvals.len() < MAX_DELEGATION_APPROVALS, "ERR_TOO_MANY_APPROVALS");
+        if !approvals.contains(&account_id) {
+            approvals.push(account_id);
+            self.delegation_approvals.insert(&delegate, &approvals);
+        }
+    }
+
+    // Stops new delegations of given account to the caller, what it already delegated stays.
+    pub fn revoke_delegation_from(&mut self, account_id: AccountId) {
+        let delegate = env::predecessor_account_id();
+        let mut approvals = self.delegation_approvals.get(&delegate).unwrap_or_default();
+        approvals.retain(|approved| approved != &account_id);
+        if approvals.is_empty() {
+            self.delegation_approvals.remove(&delegate);
+        } else {
+            self.delegation_approvals.insert(&delegate, &approvals);
+        }
+    }
+
+    // Removes given amount of the caller's tokens from `delegate`, the caller by default, right away.
+    // Tokens can be withdrawn with `withdraw_undelegated` once the unbonding period of the policy has passed.
+    pub fn undelegate(&mut self, amount: U128, delegate: Option<AccountId>) {
+        self.assert_not_paused();
+        let account_id: AccountId = env::predecessor_account_id();
+        let delegate = delegate.unwrap_or_else(|| account_id.clone());
+        let mut withdrawals = self.pending_withdrawals.get(&account_id).unwrap_or_default();
+        assert!(withdrawals.len() < MAX_PENDING_WITHDRAWALS, "ERR_TOO_MANY_PENDING_WITHDRAWALS");
+        self.internal_release_expired_locks(&delegate);
+        self.internal_undelegate(&delegate, amount);
+        self.assert_remaining_delegation(&delegate);
+        self.internal_take_funds(&account_id, &delegate, amount.0);
+        self.assert_locked_delegation_kept(&account_id, &delegate);
+        DaoEvent::Undelegate(vec![DelegationData {
+            funder: account_id.clone(),
+            delegate: delegate.clone(),
+            amount,
+            balance: U128(self.get_user_weight(&delegate)),
+        }])
+        .emit();
+        withdrawals.push(PendingWithdrawal {
+            amount,
+            requested_at: U64(env::block_timestamp()),
+            available_at: U64(env::block_timestamp() + self.policy.unbonding_period.0),
+        });
+        self.pending_withdrawals.insert(&account_id, &withdrawals);
+        self.total_pending_withdrawals += amount.0;
+    }
+
+    // Moves `amount` of the caller's tokens delegated to `from_delegate` over to `to_delegate`.
+    // Weights already taken by votes in progress are kept, see `Proposal::voter_weights`.
+    pub fn redelegate(&mut self, from_delegate: AccountId, to_delegate: AccountId, amount: U128) {
+        self.assert_not_paused();
+        assert!(amount.0 > 0, "ERR_INVALID_AMOUNT");
+        assert!(env::is_valid_account_id(to_delegate.as_bytes()), "ERR_INVALID_DELEGATE");
+        let funder = env::predecessor_account_id();
+        assert!(self.is_delegation_allowed(&funder, &to_delegate), "ERR_DELEGATION_NOT_APPROVED");
+        assert_eq!(self.acceptable_delegation(&to_delegate, amount.0), amount.0, "ERR_DELEGATION_CAP_EXCEEDED");
+        self.internal_release_expired_locks(&from_delegate);
+        self.internal_undelegate(&from_delegate, amount);
+        self.assert_remaining_delegation(&from_delegate);
+        self.internal_take_funds(&funder, &from_delegate, amount.0);
+        self.assert_locked_delegation_kept(&funder, &from_delegate);
+        self.internal_delegate(&to_delegate, amount);
+        self.internal_add_funds(&funder, &to_delegate, amount.0);
+        DaoEvent::Redelegate(vec![RedelegationData {
+            funder,
+            from_delegate: from_delegate.clone(),
+            to_delegate: to_delegate.clone(),
+            amount,
+            from_balance: U128(self.get_user_weight(&from_delegate)),
+            to_balance: U128(self.get_user_weight(&to_delegate)),
+        }])
+        .emit();
+    }
+
+    // Sends back `amount` of the caller's delegation in given token other than the DAO token, which
+    // doesn't vote, so there's no unbonding period. It's delegated again if the transfer fails.
+    pub fn undelegate_token(&mut self, token_id: AccountId, amount: U128) -> Promise {
+        self.assert_not_paused();
+        assert_ne!(token_id, self.token_account, "ERR_UNDELEGATE_DAO_TOKEN");
+        assert!(amount.0 > 0, "ERR_INVALID_AMOUNT");
+        let account_id = env::predecessor_account_id();
+        self.internal_undelegate_token(&token_id, &account_id, amount.0);
+        ext_fungible_token::ft_transfer(account_id.clone(), amount, None, &token_id, ONE_YOCTO_NEAR, GAS_FOR_FT_TRANSFER)
+            .then(ext_delegation::on_token_undelegated(
+                token_id,
+                account_id,
+                amount,
+                &env::current_account_id(),
+                0,
+                GAS_FOR_EXECUTION_CALLBACK,
+            ))
     }
 
-    
-    // Removes given amount from given account's delegations.
-    // Returns previous, new amount of this account and total delegated amount.
+    // Same as `undelegate`, kept for existing callers.
     pub fn withdraw(&mut self, amount: U128) {
+        self.undelegate(amount, None);
+    }
+
+    // Sends the caller's undelegated tokens that are out of the unbonding period.
+    // They are pending again if the transfer fails, see `on_withdraw_undelegated`.
+    pub fn withdraw_undelegated(&mut self) -> Promise {
+        self.assert_not_paused();
         let account_id: AccountId = env::predecessor_account_id();
-        self.internal_undelegate(&account_id, amount);
+        let now = env::block_timestamp();
+        let (matured, pending): (Vec<PendingWithdrawal>, Vec<PendingWithdrawal>) = self
+            .pending_withdrawals
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .partition(|withdrawal| withdrawal.available_at.0 <= now);
+        assert!(!matured.is_empty(), "ERR_NOTHING_TO_WITHDRAW");
+        if pending.is_empty() {
+            self.pending_withdrawals.remove(&account_id);
+        } else {
+            self.pending_withdrawals.insert(&account_id, &pending);
+        }
+        let amount: Balance = matured.iter().map(|withdrawal| withdrawal.amount.0).sum();
+        self.locked_amount = self.locked_amount.checked_sub(amount).expect("ERR_LOCKED_AMOUNT_UNDERFLOW");
+        // Withdrawals requested before the total was tracked aren't part of it.
+        self.total_pending_withdrawals = self.total_pending_withdrawals.saturating_sub(amount);
         ext_fungible_token::ft_transfer(
             account_id.to_string(),
-            amount,
+            U128(amount),
             None,
             &self.token_account,
             ONE_YOCTO_NEAR,
             GAS_FOR_FT_TRANSFER
-        );
+        )
+        .then(ext_delegation::on_withdraw_undelegated(
+            account_id,
+            matured,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+
+    // Puts withdrawals back as pending if the transfer of `withdraw_undelegated` didn't go through.
+    #[private]
+    pub fn on_withdraw_undelegated(&mut self, account_id: AccountId, withdrawals: Vec<PendingWithdrawal>) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        let amount: Balance = withdrawals.iter().map(|withdrawal| withdrawal.amount.0).sum();
+        self.locked_amount = self.locked_amount.checked_add(amount).expect("ERR_LOCKED_AMOUNT_OVERFLOW");
+        self.total_pending_withdrawals += amount;
+        let mut pending = self.pending_withdrawals.get(&account_id).unwrap_or_default();
+        pending.extend(withdrawals);
+        self.pending_withdrawals.insert(&account_id, &pending);
+        env::log(b"ERR_WITHDRAW_FAILED");
+    }
+
+    #[private]
+    pub fn on_token_undelegated(&mut self, token_id: AccountId, account_id: AccountId, amount: U128) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        self.internal_delegate_token(&token_id, &account_id, amount.0);
+        env::log(b"ERR_WITHDRAW_FAILED");
     }
 }
 
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use std::convert::TryInto;
+
+    use near_sdk::test_utils::{accounts, get_logs, testing_env_with_promise_results, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
 
+    const UNBONDING_PERIOD: u64 = 1_000;
 
+    fn setup_unbonding_contract() -> (VMContextBuilder, Contract) {
+        setup_contract_with_policy(Policy {
+            unbonding_period: U64(UNBONDING_PERIOD),
+            ..test_policy()
+        })
+    }
+
+    #[test]
+    fn test_undelegate() {
+        let (mut context, mut contract) = setup_unbonding_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        delegate(&mut context, &mut contract, accounts(2), 50);
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(10).build());
+        contract.undelegate(U128(30), None);
+        // Tokens stay with the DAO until the unbonding period passes, but don't vote anymore.
+        assert!(created_receipts().is_empty());
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(70));
+        assert_eq!(contract.delegation_total_supply(), U128(120));
+        assert_eq!(contract.locked_amount, 150);
+        assert_eq!(
+            contract.get_pending_withdrawals(accounts(1).to_string()),
+            vec![PendingWithdrawalOutput {
+                withdrawal: PendingWithdrawal {
+                    amount: U128(30),
+                    requested_at: U64(10),
+                    available_at: U64(10 + UNBONDING_PERIOD),
+                },
+                is_matured: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_undelegate_in_parts() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        for amount in [40, 60].iter() {
+            testing_env!(context.predecessor_account_id(accounts(1)).build());
+            contract.undelegate(U128(*amount), None);
+        }
+        assert!(!contract.has_delegation_entry(&accounts(1).to_string()));
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(0));
+        assert_eq!(contract.delegation_total_supply(), U128(0));
+        assert_eq!(contract.get_pending_withdrawals(accounts(1).to_string()).len(), 2);
+
+        delegate(&mut context, &mut contract, accounts(1), 25);
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(25));
+        assert_eq!(contract.delegation_total_supply(), U128(25));
+        assert_eq!(contract.locked_amount, 125);
+        assert_eq!(contract.delegation_balance_of(accounts(2).to_string()), U128(0));
+    }
+
+    #[test]
+    fn test_withdraw_undelegated() {
+        let (mut context, mut contract) = setup_unbonding_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(0).build());
+        contract.undelegate(U128(30), None);
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(500).build());
+        contract.undelegate(U128(20), None);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(UNBONDING_PERIOD).build());
+        contract.withdraw_undelegated();
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains("ft_transfer") && r.contains("\\\"amount\\\":\\\"30\\\"")));
+        assert!(receipts.iter().any(|r| r.contains("on_withdraw_undelegated")));
+        assert_eq!(contract.locked_amount, 70);
+        assert_eq!(
+            contract.get_pending_withdrawals(accounts(1).to_string()),
+            vec![PendingWithdrawalOutput {
+                withdrawal: PendingWithdrawal {
+                    amount: U128(20),
+                    requested_at: U64(500),
+                    available_at: U64(500 + UNBONDING_PERIOD),
+                },
+                is_matured: false,
+            }]
+        );
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(500 + UNBONDING_PERIOD).build());
+        contract.withdraw_undelegated();
+        assert_eq!(contract.locked_amount, 50);
+        assert!(contract.pending_withdrawals.get(&accounts(1).to_string()).is_none());
+    }
 
+    #[test]
+    fn test_partially_matured_withdrawals() {
+        let (mut context, mut contract) = setup_unbonding_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        delegate(&mut context, &mut contract, accounts(2), 100);
+        let requests = [(accounts(1), 0, 10), (accounts(1), 600, 20), (accounts(2), 200, 40)];
+        for (account, timestamp, amount) in requests.iter() {
+            testing_env!(context.predecessor_account_id(account.clone()).block_timestamp(*timestamp).build());
+            contract.undelegate(U128(*amount), None);
+        }
+        assert_eq!(contract.get_total_pending_withdrawals(), U128(70));
+
+        testing_env!(context.block_timestamp(UNBONDING_PERIOD + 300).build());
+        let pending = contract.get_pending_withdrawals(accounts(1).to_string());
+        let states: Vec<(U128, U64, bool)> = pending
+            .iter()
+            .map(|output| (output.withdrawal.amount, output.withdrawal.requested_at, output.is_matured))
+            .collect();
+        assert_eq!(states, vec![(U128(10), U64(0), true), (U128(20), U64(600), false)]);
+        assert!(contract.get_pending_withdrawals(accounts(2).to_string())[0].is_matured);
+
+        // Only the matured part is withdrawn.
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.withdraw_undelegated();
+        assert_eq!(contract.get_total_pending_withdrawals(), U128(60));
+        assert_eq!(contract.get_pending_withdrawals(accounts(1).to_string()).len(), 1);
+        assert!(!contract.get_pending_withdrawals(accounts(1).to_string())[0].is_matured);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOTHING_TO_WITHDRAW")]
+    fn test_withdraw_undelegated_too_early() {
+        let (mut context, mut contract) = setup_unbonding_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(30), None);
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(UNBONDING_PERIOD - 1).build());
+        contract.withdraw_undelegated();
+    }
+
+    #[test]
+    fn test_failed_withdraw_rolls_back() {
+        let (mut context, mut contract) = setup_unbonding_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(30), None);
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(UNBONDING_PERIOD).build());
+        contract.withdraw_undelegated();
+        assert_eq!(contract.locked_amount, 70);
+
+        let withdrawals = vec![PendingWithdrawal {
+            amount: U128(30),
+            requested_at: U64(0),
+            available_at: U64(UNBONDING_PERIOD),
+        }];
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_withdraw_undelegated(accounts(1).to_string(), withdrawals.clone());
+        assert_eq!(contract.locked_amount, 100);
+        let pending = contract.get_pending_withdrawals(accounts(1).to_string());
+        assert_eq!(pending[0].withdrawal, withdrawals[0]);
+        assert_eq!(contract.get_total_pending_withdrawals(), U128(30));
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(70));
+    }
+
+    #[test]
+    fn test_delegators_index() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        delegate(&mut context, &mut contract, accounts(2), 20);
+        delegate(&mut context, &mut contract, accounts(1), 5);
+        delegate(&mut context, &mut contract, accounts(3), 30);
+        assert_eq!(contract.get_delegator_count(), 3);
+        assert_eq!(
+            contract.get_delegators(0, 2),
+            vec![(accounts(1).to_string(), U128(15)), (accounts(2).to_string(), U128(20))]
+        );
+        assert_eq!(contract.get_delegators(2, 10), vec![(accounts(3).to_string(), U128(30))]);
+        assert!(contract.get_delegators(3, 10).is_empty());
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.undelegate(U128(20), None);
+        assert_eq!(contract.get_delegator_count(), 2);
+        let delegators: Vec<AccountId> = contract.get_delegators(0, 10).into_iter().map(|(id, _)| id).collect();
+        assert!(!delegators.contains(&accounts(2).to_string()));
+    }
+
+    #[test]
+    fn test_unsolicited_delegation_is_refunded() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", accounts(2));
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, &msg), 100);
+        assert_eq!(contract.delegation_balance_of(accounts(2).to_string()), U128(0));
+        assert_eq!(contract.locked_amount, 0);
+
+        // Revoked approval refuses new delegations again.
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_delegation_from(accounts(1).to_string());
+        contract.revoke_delegation_from(accounts(1).to_string());
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, &msg), 100);
+    }
+
+    #[test]
+    fn test_approved_delegation() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_delegation_from(accounts(1).to_string());
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", accounts(2));
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, &msg), 0);
+        delegate(&mut context, &mut contract, accounts(2), 10);
+        assert_eq!(contract.delegation_balance_of(accounts(2).to_string()), U128(110));
+
+        // Funder keeps the right to take the tokens back.
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(40), Some(accounts(2).to_string()));
+        assert_eq!(contract.delegation_balance_of(accounts(2).to_string()), U128(70));
+        assert_eq!(contract.get_pending_withdrawals(accounts(1).to_string())[0].withdrawal.amount, U128(40));
+        assert!(contract.get_pending_withdrawals(accounts(2).to_string()).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_FUNDED")]
+    fn test_delegate_cannot_undelegate_funds_of_others() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_delegation_from(accounts(1).to_string());
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", accounts(2));
+        transfer_call(&mut context, &mut contract, accounts(1), 100, &msg);
+        delegate(&mut context, &mut contract, accounts(2), 10);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.undelegate(U128(11), None);
+    }
+
+    #[test]
+    fn test_delegation_events() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.approve_delegation_from(accounts(1).to_string());
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        assert_eq!(
+            get_logs(),
+            vec![
+                "EVENT_JSON:{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"delegate\",\
+                 \"data\":[{\"funder\":\"bob\",\"delegate\":\"bob\",\"amount\":\"100\",\"balance\":\"100\"}]}"
+            ]
+        );
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.redelegate(accounts(1).to_string(), accounts(3).to_string(), U128(30));
+        assert_eq!(
+            get_logs(),
+            vec![
+                "EVENT_JSON:{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"redelegate\",\
+                 \"data\":[{\"funder\":\"bob\",\"from_delegate\":\"bob\",\"to_delegate\":\"danny\",\
+                 \"amount\":\"30\",\"from_balance\":\"70\",\"to_balance\":\"30\"}]}"
+            ]
+        );
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(10), Some(accounts(3).to_string()));
+        assert_eq!(
+            get_logs(),
+            vec![
+                "EVENT_JSON:{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"undelegate\",\
+                 \"data\":[{\"funder\":\"bob\",\"delegate\":\"danny\",\"amount\":\"10\",\"balance\":\"20\"}]}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redelegate() {
+        let (mut context, mut contract) = setup_contract();
+        for delegate in [accounts(2), accounts(3)].iter() {
+            testing_env!(context.predecessor_account_id(delegate.clone()).build());
+            contract.approve_delegation_from(accounts(1).to_string());
+        }
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", accounts(2));
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, &msg), 0);
+        delegate(&mut context, &mut contract, accounts(3), 10);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.redelegate(accounts(2).to_string(), accounts(3).to_string(), U128(60));
+        assert_eq!(contract.delegation_balance_of(accounts(2).to_string()), U128(40));
+        assert_eq!(contract.delegation_balance_of(accounts(3).to_string()), U128(70));
+        assert_eq!(contract.delegation_total_supply(), U128(110));
+        assert_eq!(contract.locked_amount, 110);
+        let funds = contract.get_delegation_funds(accounts(1).to_string());
+        assert_eq!(funds.get(&accounts(2).to_string()), Some(&U128(40)));
+        assert_eq!(funds.get(&accounts(3).to_string()), Some(&U128(60)));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.redelegate(accounts(2).to_string(), accounts(3).to_string(), U128(40));
+        assert_eq!(contract.get_delegation_funds(accounts(1).to_string()).len(), 1);
+        assert!(!contract.has_delegation_entry(&accounts(2).to_string()));
+    }
+
+    fn setup_contract_with_cap(cap: Balance) -> (VMContextBuilder, Contract) {
+        setup_contract_with_policy(Policy {
+            max_delegation_per_account: Some(U128(cap)),
+            ..test_policy()
+        })
+    }
+
+    // Delegates to `account` from the same account, returns the refund.
+    fn delegate_to(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        account: ValidAccountId,
+        amount: Balance,
+    ) -> Balance {
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", account);
+        transfer_call(context, contract, account, amount, &msg)
+    }
+
+    #[test]
+    fn test_delegation_cap_exact_fill() {
+        let (mut context, mut contract) = setup_contract_with_cap(100);
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(1), 60), 0);
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(1), 40), 0);
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(100));
+        // Nothing more is accepted once the cap is filled.
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(1), 10), 10);
+        assert_eq!(contract.locked_amount, 100);
+    }
+
+    #[test]
+    fn test_delegation_cap_overflow_refunded() {
+        let (mut context, mut contract) = setup_contract_with_cap(100);
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(1), 70), 0);
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(1), 50), 20);
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(100));
+        assert_eq!(contract.delegation_total_supply(), U128(100));
+        assert_eq!(contract.locked_amount, 100);
+        let funds = contract.get_delegation_funds(accounts(1).to_string());
+        assert_eq!(funds.get(&accounts(1).to_string()), Some(&U128(100)));
+        // Cap is per account.
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(2), 100), 0);
+    }
+
+    #[test]
+    fn test_delegation_cap_lowered() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(1), 150), 0);
+        contract.policy.max_delegation_per_account = Some(U128(100));
+
+        // Delegation above the new cap stays, but can't grow.
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(1), 10), 10);
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(150));
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(70), None);
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(1), 30), 10);
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(100));
+
+        contract.policy.max_delegation_per_account = None;
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(1), 30), 0);
+    }
+
+    #[test]
+    fn test_delegation_history() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_delegation_from(accounts(1).to_string());
+        testing_env!(context.block_timestamp(10).build());
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(20).build());
+        contract.undelegate(U128(30), None);
+        contract.redelegate(accounts(1).to_string(), accounts(2).to_string(), U128(50));
+
+        let changes: Vec<(U64, I128, U128)> = contract
+            .get_delegation_history(accounts(1).to_string(), 0, 10)
+            .into_iter()
+            .map(|change| (change.timestamp, change.delta, change.balance))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (U64(10), I128(100), U128(100)),
+                (U64(20), I128(-30), U128(70)),
+                (U64(20), I128(-50), U128(20)),
+            ]
+        );
+        assert_eq!(
+            contract.get_delegation_history(accounts(2).to_string(), 0, 10),
+            vec![DelegationChange { timestamp: U64(20), delta: I128(50), balance: U128(50) }]
+        );
+        assert_eq!(contract.get_delegation_history(accounts(1).to_string(), 2, 10).len(), 1);
+    }
+
+    #[test]
+    fn test_delegation_history_wraps() {
+        let (mut context, mut contract) = setup_contract();
+        for amount in 1..=(MAX_DELEGATION_HISTORY as u128 + 5) {
+            delegate(&mut context, &mut contract, accounts(1), amount);
+        }
+        let history = contract.delegation_history.get(&accounts(1).to_string()).unwrap();
+        assert_eq!(history.len(), MAX_DELEGATION_HISTORY);
+        // Oldest changes are dropped first.
+        assert_eq!(history[0].delta, I128(6));
+        assert_eq!(history.last().unwrap().delta, I128(MAX_DELEGATION_HISTORY as i128 + 5));
+        let total = (MAX_DELEGATION_HISTORY as u128 + 5) * (MAX_DELEGATION_HISTORY as u128 + 6) / 2;
+        assert_eq!(history.last().unwrap().balance, U128(total));
+        assert_eq!(
+            contract.get_delegation_history(accounts(1).to_string(), 0, 100).len(),
+            MAX_VIEW_LIMIT as usize
+        );
+    }
+
+    // Delegates to `account` from the same account, locked for `duration`.
+    fn delegate_locked(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        account: ValidAccountId,
+        amount: Balance,
+        duration: u64,
+    ) {
+        let msg = format!(
+            "{{\"purpose\":{{\"Delegate\":\"{}\"}},\"lock_duration\":\"{}\"}}",
+            account, duration
+        );
+        assert_eq!(transfer_call(context, contract, account, amount, &msg), 0);
+    }
+
+    #[test]
+    fn test_locked_delegation_boosts_votes() {
+        let (mut context, mut contract) = setup_contract();
+        delegate_locked(&mut context, &mut contract, accounts(1), 100, MAX_LOCK_DURATION);
+        delegate_locked(&mut context, &mut contract, accounts(2), 100, MAX_LOCK_DURATION / 2);
+        delegate(&mut context, &mut contract, accounts(3), 300);
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(100));
+        assert_eq!(contract.delegation_voting_weight_of(accounts(1).to_string()), U128(200));
+        assert_eq!(contract.delegation_voting_weight_of(accounts(2).to_string()), U128(150));
+        assert_eq!(contract.delegation_voting_weight_of(accounts(3).to_string()), U128(300));
+
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.total_delegation_snapshot, 650);
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        act(&mut context, &mut contract, accounts(2), id, Action::VoteReject);
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.option_delegations.get(VOTE_APPROVE), Some(&200));
+        assert_eq!(proposal.option_delegations.get(VOTE_REJECT), Some(&150));
+        assert_eq!(contract.delegation_total_supply(), U128(500));
+    }
+
+    #[test]
+    fn test_locked_delegation_withdrawal() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 50);
+        delegate_locked(&mut context, &mut contract, accounts(1), 100, 1_000);
+        // What isn't locked can be undelegated right away.
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(999).build());
+        contract.undelegate(U128(50), None);
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(100));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(1_000).build());
+        assert_eq!(contract.delegation_voting_weight_of(accounts(1).to_string()), U128(100));
+        contract.undelegate(U128(100), None);
+        assert!(contract.delegation_locks.get(&accounts(1).to_string()).is_none());
+        assert_eq!(contract.total_delegation_bonus, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DELEGATION_LOCKED")]
+    fn test_locked_delegation_early_withdrawal() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 50);
+        delegate_locked(&mut context, &mut contract, accounts(1), 100, 1_000);
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(999).build());
+        contract.undelegate(U128(60), None);
+    }
+
+    #[test]
+    fn test_invalid_lock_duration_refunded() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = format!(
+            "{{\"purpose\":{{\"Delegate\":\"{}\"}},\"lock_duration\":\"{}\"}}",
+            accounts(1),
+            MAX_LOCK_DURATION + 1
+        );
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, &msg), 100);
+        let msg = "{\"purpose\":\"OpenDonate\",\"lock_duration\":\"10\"}";
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, msg), 100);
+        assert_eq!(contract.locked_amount, 0);
+    }
+
+    fn setup_contract_with_minimum(min_delegation_amount: Balance) -> (VMContextBuilder, Contract) {
+        setup_contract_with_policy(Policy {
+            min_delegation_amount: U128(min_delegation_amount),
+            ..test_policy()
+        })
+    }
+
+    #[test]
+    fn test_delegation_below_minimum_refunded() {
+        let (mut context, mut contract) = setup_contract_with_minimum(50);
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(1), 49), 49);
+        assert!(!contract.has_delegation_entry(&accounts(1).to_string()));
+        assert!(contract.get_delegation_funds(accounts(1).to_string()).is_empty());
+        assert_eq!(contract.locked_amount, 0);
+        assert_eq!(delegate_to(&mut context, &mut contract, accounts(1), 50), 0);
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(50));
+    }
+
+    #[test]
+    fn test_undelegate_to_minimum_or_everything() {
+        let (mut context, mut contract) = setup_contract_with_minimum(50);
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(50), None);
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(50));
+        // Full exit is always allowed.
+        contract.undelegate(U128(50), None);
+        assert!(!contract.has_delegation_entry(&accounts(1).to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DELEGATION_BELOW_MINIMUM")]
+    fn test_undelegate_below_minimum() {
+        let (mut context, mut contract) = setup_contract_with_minimum(50);
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(60), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DELEGATION_CAP_EXCEEDED")]
+    fn test_redelegate_over_cap() {
+        let (mut context, mut contract) = setup_contract_with_cap(100);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_delegation_from(accounts(1).to_string());
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        delegate(&mut context, &mut contract, accounts(2), 50);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.redelegate(accounts(1).to_string(), accounts(2).to_string(), U128(60));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_FUNDED")]
+    fn test_redelegate_more_than_funded() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.approve_delegation_from(accounts(1).to_string());
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", accounts(2));
+        transfer_call(&mut context, &mut contract, accounts(1), 100, &msg);
+        // Delegate itself can't move what others funded.
+        delegate(&mut context, &mut contract, accounts(2), 10);
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.approve_delegation_from(accounts(2).to_string());
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.redelegate(accounts(2).to_string(), accounts(3).to_string(), U128(11));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ACCOUNT_NOT_ENOUGH_BALANCE")]
+    fn test_undelegate_above_delegation() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(101), None);
+    }
+
+    fn usdc() -> ValidAccountId {
+        "usdc.near".try_into().unwrap()
+    }
+
+    fn delegate_usdc(context: &mut VMContextBuilder, contract: &mut Contract, account: ValidAccountId, amount: Balance) {
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", account);
+        assert_eq!(transfer_call_from(context, contract, usdc(), account, amount, &msg), 0);
+    }
+
+    #[test]
+    fn test_delegations_per_token() {
+        let (mut context, mut contract) = setup_contract();
+        accept_token(&mut context, &mut contract, usdc());
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        delegate_usdc(&mut context, &mut contract, accounts(1), 40);
+        delegate_usdc(&mut context, &mut contract, accounts(2), 10);
+        // Only the DAO token votes.
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(100));
+        assert_eq!(contract.get_delegation_total(None), U128(100));
+        assert_eq!(contract.get_token_delegation_of(usdc().to_string(), accounts(1).to_string()), U128(40));
+        assert_eq!(contract.get_delegation_total(Some(usdc().to_string())), U128(50));
+        assert_eq!(contract.locked_amount, 100);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate_token(usdc().to_string(), U128(15));
+        let receipts = created_receipts();
+        assert!(receipts[0].contains("usdc.near") && receipts[0].contains("\\\"amount\\\":\\\"15\\\""));
+        assert_eq!(contract.get_token_delegation_of(usdc().to_string(), accounts(1).to_string()), U128(25));
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(100));
+
+        // Failed transfer puts the delegation back.
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_token_undelegated(usdc().to_string(), accounts(1).to_string(), U128(15));
+        assert_eq!(contract.get_token_delegation_of(usdc().to_string(), accounts(1).to_string()), U128(40));
+        assert_eq!(contract.get_delegation_total(Some(usdc().to_string())), U128(50));
+    }
+
+    #[test]
+    fn test_token_delegation_only_to_self() {
+        let (mut context, mut contract) = setup_contract();
+        accept_token(&mut context, &mut contract, usdc());
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", accounts(2));
+        assert_eq!(transfer_call_from(&mut context, &mut contract, usdc(), accounts(1), 10, &msg), 10);
+        assert_eq!(get_logs(), vec!["ERR_DELEGATE_ONLY_TO_SELF"]);
+    }
+
+    #[test]
+    fn test_legacy_delegation_migrates() {
+        let (mut context, mut contract) = setup_contract();
+        // Delegation written before delegations were kept per token.
+        contract.delegations.insert(&accounts(1).to_string(), &30);
+        contract.total_delegation_amount = 30;
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(30));
+        assert_eq!(contract.get_token_delegation_of(token().to_string(), accounts(1).to_string()), U128(30));
+
+        delegate(&mut context, &mut contract, accounts(1), 20);
+        assert!(contract.delegations.get(&accounts(1).to_string()).is_none());
+        assert_eq!(contract.token_delegations.get(&(token().to_string(), accounts(1).to_string())), Some(50));
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(50));
+        assert_eq!(contract.delegation_total_supply(), U128(50));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_UNDELEGATE_DAO_TOKEN")]
+    fn test_undelegate_token_dao_token() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate_token(token().to_string(), U128(10));
+    }
+}
@@ -1,9 +1,1046 @@
 use crate::*;
 
+// What a donation was made for.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum DonationPurpose {
+    Open,
+    Proposal(u64),
+    Campaign(u64),
+}
+
+// Single donation, as kept in the donation feed.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DonationRecord {
+    pub donor: AccountId,
+    pub token: AccountId,
+    pub amount: U128,
+    pub timestamp: U64,
+    pub purpose: DonationPurpose,
+    // Donor is only shown to the owner, see `TransferArgs::anonymous`.
+    #[serde(default)]
+    pub anonymous: bool,
+    // Category of open donations, see `TransferArgs::category`.
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+// Withdrawal of open donations waiting for its transfer, see `withdraw_donations`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DonationWithdrawal {
+    pub receiver_id: AccountId,
+    pub token_id: AccountId,
+    pub category: String,
+    pub amount: U128,
+    pub memo: String,
+}
+
+impl DonationRecord {
+    // Record as shown by the public views, without the donor of anonymous donations.
+    pub fn into_public(mut self) -> Self {
+        if self.anonymous {
+            self.donor = ANONYMOUS_DONOR.to_string();
+        }
+        self
+    }
+}
+
 impl Contract {
-    pub fn open_donate(&mut self, account_id: &AccountId, amount: U128) {
+    // Donations of the DAO token are kept in `donations`, the ones of other tokens per token and donor.
+    // Anonymous donations of the DAO token are also kept in `anonymous_donations`, to leave them out of the top donors.
+    pub fn open_donate(
+        &mut self,
+        account_id: &AccountId,
+        token_id: &AccountId,
+        amount: U128,
+        anonymous: bool,
+        category: &str,
+    ) {
+        if token_id == &self.token_account {
+            self.internal_add_donor_donation(account_id, amount.0);
+            if anonymous {
+                let prev_amount = self.anonymous_donations.get(account_id).unwrap_or_default();
+                self.anonymous_donations.insert(account_id, &(prev_amount + amount.0));
+            }
+        } else {
+            let key = (token_id.clone(), account_id.clone());
+            let prev_amount = self.token_open_donations.get(&key).unwrap_or_default();
+            self.token_open_donations.insert(&key, &(prev_amount + amount.0));
+        }
+        self.internal_add_open_donations(category, token_id, amount.0);
+        self.internal_record_donation(
+            account_id,
+            token_id,
+            amount.0,
+            DonationPurpose::Open,
+            anonymous,
+            Some(category.to_string()),
+        );
+    }
+
+    // Adds to the open donations of given token that can be withdrawn, in total and in given category.
+    pub(crate) fn internal_add_open_donations(&mut self, category: &str, token_id: &AccountId, amount: Balance) {
+        let total = self.open_donation_totals.get(token_id).unwrap_or_default();
+        self.open_donation_totals.insert(token_id, &(total + amount));
+        let key = (category.to_string(), token_id.clone());
+        let category_total = self.category_donations.get(&key).unwrap_or_default();
+        self.category_donations.insert(&key, &(category_total + amount));
+    }
+
+    // Takes from the open donations of given token in given category, panics if it doesn't have enough.
+    pub(crate) fn internal_take_open_donations(&mut self, category: &str, token_id: &AccountId, amount: Balance) {
+        let key = (category.to_string(), token_id.clone());
+        let category_total = self
+            .category_donations
+            .get(&key)
+            .unwrap_or_default()
+            .checked_sub(amount)
+            .expect("ERR_NOT_ENOUGH_DONATIONS");
+        self.category_donations.insert(&key, &category_total);
+        let total = self.open_donation_totals.get(token_id).unwrap_or_default();
+        self.open_donation_totals.insert(token_id, &(total - amount));
+    }
+
+    // Returns open donations of given token that can be withdrawn from given category.
+    pub(crate) fn internal_category_donations(&self, category: &str, token_id: &AccountId) -> Balance {
+        self.category_donations.get(&(category.to_string(), token_id.clone())).unwrap_or_default()
+    }
+
+    // Donations without category go to `GENERAL_DONATION_CATEGORY`, others have to be added by `add_donation_category`.
+    pub fn is_donation_category(&self, category: &str) -> bool {
+        category == GENERAL_DONATION_CATEGORY || self.donation_categories.contains(&category.to_string())
+    }
+
+    // Credits donation of the DAO token to the donor, keeping `total_donations` the sum of `donations`.
+    pub(crate) fn internal_add_donor_donation(&mut self, account_id: &AccountId, amount: Balance) {
         let prev_amount = self.donations.get(account_id).unwrap_or_default();
-        let new_amount = prev_amount + amount.0;
-        self.donations.insert(account_id, &new_amount);
+        self.donations.insert(account_id, &(prev_amount + amount));
+        self.total_donations += amount;
+    }
+
+    // Open donations are accepted in the DAO token and in the whitelisted donation tokens.
+    pub fn is_donation_token(&self, token_id: &AccountId) -> bool {
+        token_id == &self.token_account || self.donation_tokens.contains(token_id)
+    }
+
+    // Proposal donors are shown as anonymous once they donated anonymously to the proposal.
+    pub(crate) fn internal_add_anonymous_proposal_donor(&mut self, proposal_id: u64, account_id: &AccountId) {
+        let mut donors = self.anonymous_proposal_donors.get(&proposal_id).unwrap_or_default();
+        if !donors.contains(account_id) {
+            donors.push(account_id.clone());
+            self.anonymous_proposal_donors.insert(&proposal_id, &donors);
+        }
+    }
+
+    // Open donations of the DAO token of given account still in the refund period, newest first,
+    // as index of the donation record, its category and the part of it that wasn't refunded.
+    pub(crate) fn internal_refundable_donations(&self, account_id: &AccountId) -> Vec<(u64, String, Balance)> {
+        let now = env::block_timestamp();
+        let period = self.policy.donation_refund_period.0;
+        let mut refundable = vec![];
+        for index in self.donation_records_by_account.get(account_id).unwrap_or_default().into_iter().rev() {
+            let record = self.donation_records.get(index).expect("ERR_NO_DONATION_RECORD");
+            // Records are in order of time, the older ones are out of the period too.
+            if record.timestamp.0.saturating_add(period) <= now {
+                break;
+            }
+            if record.purpose != DonationPurpose::Open || record.token != self.token_account {
+                continue;
+            }
+            let amount = record.amount.0 - self.refunded_donations.get(&index).unwrap_or_default();
+            if amount > 0 {
+                let category = record.category.unwrap_or_else(|| GENERAL_DONATION_CATEGORY.to_string());
+                refundable.push((index, category, amount));
+            }
+        }
+        refundable
+    }
+
+    // Parts of the donations of given account to refund for `amount`. Withdrawn donations can't be refunded,
+    // so each category only refunds what is left of it. Refunds as much as possible when `amount` is more.
+    pub(crate) fn internal_refund_parts(&self, account_id: &AccountId, amount: Balance) -> Vec<(u64, U128)> {
+        let mut available: HashMap<String, Balance> = HashMap::new();
+        let mut rest = amount;
+        let mut parts = vec![];
+        for (index, category, refundable) in self.internal_refundable_donations(account_id) {
+            if rest == 0 {
+                break;
+            }
+            let category_available = available
+                .entry(category.clone())
+                .or_insert_with(|| self.internal_category_donations(&category, &self.token_account));
+            let part = std::cmp::min(std::cmp::min(rest, refundable), *category_available);
+            if part > 0 {
+                *category_available -= part;
+                parts.push((index, U128(part)));
+                rest -= part;
+            }
+        }
+        parts
+    }
+
+    // Returns what given account can take back with `refund_open_donation`.
+    pub(crate) fn internal_refundable_amount(&self, account_id: &AccountId) -> Balance {
+        self.internal_refund_parts(account_id, Balance::MAX).iter().map(|(_, amount)| amount.0).sum()
+    }
+
+    // Takes refunded parts of the donation records out of the donations, or puts them back when `restore` is set.
+    fn internal_apply_donation_refund(&mut self, account_id: &AccountId, parts: &[(u64, U128)], restore: bool) {
+        let token_id = self.token_account.clone();
+        let update = |value: Balance, amount: Balance| if restore { value + amount } else { value - amount };
+        let mut total = 0;
+        for (index, amount) in parts {
+            let refunded = self.refunded_donations.get(index).unwrap_or_default();
+            let refunded = if restore { refunded - amount.0 } else { refunded + amount.0 };
+            if refunded == 0 {
+                self.refunded_donations.remove(index);
+            } else {
+                self.refunded_donations.insert(index, &refunded);
+            }
+            let record = self.donation_records.get(*index).expect("ERR_NO_DONATION_RECORD");
+            if record.anonymous {
+                let anonymous_amount = self.anonymous_donations.get(account_id).unwrap_or_default();
+                self.anonymous_donations.insert(account_id, &update(anonymous_amount, amount.0));
+            }
+            let category = record.category.unwrap_or_else(|| GENERAL_DONATION_CATEGORY.to_string());
+            if restore {
+                self.internal_add_open_donations(&category, &token_id, amount.0);
+            } else {
+                self.internal_take_open_donations(&category, &token_id, amount.0);
+            }
+            total += amount.0;
+        }
+        let donation = update(self.donations.get(account_id).unwrap_or_default(), total);
+        if donation == 0 {
+            self.donations.remove(account_id);
+        } else {
+            self.donations.insert(account_id, &donation);
+        }
+        self.total_donations = update(self.total_donations, total);
+    }
+
+    pub(crate) fn assert_owner_or_dao(&self) {
+        let predecessor_id = env::predecessor_account_id();
+        assert!(
+            predecessor_id == self.owner_id || predecessor_id == env::current_account_id(),
+            "ERR_NOT_ALLOWED"
+        );
+    }
+
+    // Adds donation to the feed and to the index of its donor, and emits it.
+    pub(crate) fn internal_record_donation(
+        &mut self,
+        donor: &AccountId,
+        token_id: &AccountId,
+        amount: Balance,
+        purpose: DonationPurpose,
+        anonymous: bool,
+        category: Option<String>,
+    ) {
+        DaoEvent::Donation(vec![DonationData {
+            donor: if anonymous { ANONYMOUS_DONOR.to_string() } else { donor.clone() },
+            token: token_id.clone(),
+            amount: U128(amount),
+            proposal_id: match purpose {
+                DonationPurpose::Proposal(proposal_id) => Some(proposal_id),
+                _ => None,
+            },
+            campaign_id: match purpose {
+                DonationPurpose::Campaign(campaign_id) => Some(campaign_id),
+                _ => None,
+            },
+        }])
+        .emit();
+        self.donation_records.push(&DonationRecord {
+            donor: donor.clone(),
+            token: token_id.clone(),
+            amount: U128(amount),
+            timestamp: U64(env::block_timestamp()),
+            purpose,
+            anonymous,
+            category,
+        });
+        let index = self.donation_records.len() - 1;
+        let mut indexes = self.donation_records_by_account.get(donor).unwrap_or_default();
+        indexes.push(index);
+        self.donation_records_by_account.insert(donor, &indexes);
+        // Badges would show anonymous donors.
+        if !anonymous && token_id == &self.token_account {
+            self.internal_mint_badge(donor, index, amount);
+        }
+    }
+}
+
+#[ext_contract(ext_donations)]
+pub trait DonationCallbacks {
+    fn on_donations_withdrawn(&mut self, withdrawal: DonationWithdrawal);
+    fn on_near_donations_withdrawn(&mut self, amount: U128);
+    fn on_open_donation_refunded(&mut self, account_id: AccountId, parts: Vec<(u64, U128)>);
+}
+
+#[near_bindgen]
+impl Contract {
+    // Accepts open donations in given token, in addition to the DAO token.
+    pub fn add_donation_token(&mut self, token_id: ValidAccountId) {
+        self.assert_owner_or_dao();
+        assert!(self.donation_tokens.len() < MAX_DONATION_TOKENS, "ERR_TOO_MANY_DONATION_TOKENS");
+        self.donation_tokens.insert(token_id.as_ref());
+    }
+
+    // Stops open donations in given token, its donations can still be withdrawn.
+    pub fn remove_donation_token(&mut self, token_id: ValidAccountId) {
+        self.assert_owner_or_dao();
+        self.donation_tokens.remove(token_id.as_ref());
+    }
+
+    // Accepts open donations in given category, in addition to `GENERAL_DONATION_CATEGORY`.
+    pub fn add_donation_category(&mut self, category: String) {
+        self.assert_owner_or_dao();
+        assert!(
+            !category.is_empty() && category.len() <= MAX_DONATION_CATEGORY_LENGTH,
+            "ERR_INVALID_CATEGORY"
+        );
+        assert!(self.donation_categories.len() < MAX_DONATION_CATEGORIES, "ERR_TOO_MANY_DONATION_CATEGORIES");
+        self.donation_categories.insert(&category);
+    }
+
+    // Stops open donations in given category, its donations can still be withdrawn.
+    pub fn remove_donation_category(&mut self, category: String) {
+        self.assert_owner_or_dao();
+        self.donation_categories.remove(&category);
+    }
+
+    // Sends `amount` of the open donations in given token, the DAO token by default, to `receiver_id`.
+    // Donations are taken from given category, `GENERAL_DONATION_CATEGORY` by default.
+    // Delegations and other locked tokens can't be spent this way.
+    pub fn withdraw_donations(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: String,
+        token_id: Option<ValidAccountId>,
+        category: Option<String>,
+    ) -> Promise {
+        self.assert_not_paused();
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        assert!(env::is_valid_account_id(receiver_id.as_bytes()), "ERR_INVALID_RECEIVER");
+        assert!(amount.0 > 0, "ERR_INVALID_AMOUNT");
+        let token_id: AccountId = token_id.map_or_else(|| self.token_account.clone(), |token_id| token_id.into());
+        let category = category.unwrap_or_else(|| GENERAL_DONATION_CATEGORY.to_string());
+        self.internal_take_open_donations(&category, &token_id, amount.0);
+        ext_fungible_token::ft_transfer(
+            receiver_id.clone(),
+            amount,
+            Some(memo.clone()),
+            &token_id,
+            ONE_YOCTO_NEAR,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_donations::on_donations_withdrawn(
+            DonationWithdrawal { receiver_id, token_id, category, amount, memo },
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+
+    // Emits the withdrawal once sent, puts the amount back to the open donations otherwise.
+    #[private]
+    pub fn on_donations_withdrawn(&mut self, withdrawal: DonationWithdrawal) {
+        let DonationWithdrawal { receiver_id, token_id, category, amount, memo } = withdrawal;
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            DaoEvent::DonationWithdraw(vec![DonationWithdrawData { receiver_id, token: token_id, amount, memo }]).emit();
+            return;
+        }
+        self.internal_add_open_donations(&category, &token_id, amount.0);
+        env::log(b"ERR_DONATION_WITHDRAW_FAILED");
+    }
+
+    // Sends `amount` of the open donations of the DAO token the caller made in the refund period back to them,
+    // newest donations first.
+    pub fn refund_open_donation(&mut self, amount: U128) -> Promise {
+        self.assert_not_paused();
+        assert!(amount.0 > 0, "ERR_INVALID_AMOUNT");
+        let account_id = env::predecessor_account_id();
+        let parts = self.internal_refund_parts(&account_id, amount.0);
+        let refundable: Balance = parts.iter().map(|(_, part)| part.0).sum();
+        assert!(refundable == amount.0, "ERR_NOT_ENOUGH_REFUNDABLE_DONATIONS");
+        self.internal_apply_donation_refund(&account_id, &parts, false);
+        ext_fungible_token::ft_transfer(
+            account_id.clone(),
+            amount,
+            None,
+            &self.token_account,
+            ONE_YOCTO_NEAR,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_donations::on_open_donation_refunded(
+            account_id,
+            parts,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+
+    // Puts the refunded donations back if the transfer failed.
+    #[private]
+    pub fn on_open_donation_refunded(&mut self, account_id: AccountId, parts: Vec<(u64, U128)>) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        self.internal_apply_donation_refund(&account_id, &parts, true);
+        env::log(b"ERR_DONATION_REFUND_FAILED");
+    }
+
+    // Moves donations of given accounts from the lookup map of the previous version, see `migrate`.
+    pub fn index_donors(&mut self, account_ids: Vec<AccountId>) {
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        let mut legacy_donations: LookupMap<AccountId, Balance> = LookupMap::new(StorageKeys::Donations);
+        for account_id in account_ids {
+            if let Some(amount) = legacy_donations.remove(&account_id) {
+                self.internal_add_donor_donation(&account_id, amount);
+            }
+        }
+    }
+
+    // Donates the attached $NEAR to the DAO, or to given donation proposal.
+    // These are kept apart from donations of tokens and only go to the NEAR donation pool.
+    #[payable]
+    pub fn donate_near(&mut self, proposal_id: Option<u64>) {
+        self.assert_not_paused();
+        let amount = env::attached_deposit();
+        assert!(amount > 0, "ERR_INVALID_AMOUNT");
+        let account_id = env::predecessor_account_id();
+        assert!(self.is_registered(&account_id), "ERR_NOT_REGISTERED");
+        if let Some(proposal_id) = proposal_id {
+            let proposal: Proposal = self.proposals.get(&proposal_id).expect("ERR_NO_PROPOSAL").into();
+            assert!(proposal.kind == ProposalKind::Donate, "PROPOSAL_IS_NOT_DONATION_KIND");
+            assert_eq!(proposal.get_status(), ProposalStatus::InProgress, "ERR_PROPOSAL_NOT_IN_PROGRESS");
+            assert!(!proposal.is_deadline_passed(), "ERR_DONATION_DEADLINE_PASSED");
+            let total = self.proposal_near_donations.get(&proposal_id).unwrap_or_default();
+            self.proposal_near_donations.insert(&proposal_id, &(total + amount));
+        }
+        let prev_amount = self.near_donations.get(&account_id).unwrap_or_default();
+        self.near_donations.insert(&account_id, &(prev_amount + amount));
+        self.total_near_donations += amount;
+    }
+
+    // Sends `amount` of the NEAR donation pool to `receiver_id`, keeping $NEAR of bonds and storage.
+    pub fn withdraw_near_donations(&mut self, receiver_id: AccountId, amount: U128) -> Promise {
+        self.assert_not_paused();
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        assert!(env::is_valid_account_id(receiver_id.as_bytes()), "ERR_INVALID_RECEIVER");
+        assert!(amount.0 > 0, "ERR_INVALID_AMOUNT");
+        let available_amount = env::account_balance()
+            .saturating_sub(self.get_locked_storage_amount().0)
+            .saturating_sub(self.locked_near)
+            .saturating_sub(self.near_bounty_escrow);
+        assert!(amount.0 <= available_amount, "ERR_NOT_ENOUGH_AVAILABLE_AMOUNT");
+        self.total_near_donations = self
+            .total_near_donations
+            .checked_sub(amount.0)
+            .expect("ERR_NOT_ENOUGH_DONATIONS");
+        Promise::new(receiver_id).transfer(amount.0).then(ext_donations::on_near_donations_withdrawn(
+            amount,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+
+    // Puts the amount back to the NEAR donation pool if the transfer didn't go through.
+    #[private]
+    pub fn on_near_donations_withdrawn(&mut self, amount: U128) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        self.total_near_donations += amount.0;
+        env::log(b"ERR_DONATION_WITHDRAW_FAILED");
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use std::convert::TryInto;
+
+    use near_sdk::test_utils::{accounts, get_logs, testing_env_with_promise_results, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
+
+    fn open_donate(context: &mut VMContextBuilder, contract: &mut Contract, amount: Balance) {
+        assert_eq!(transfer_call(context, contract, accounts(1), amount, "{\"purpose\":\"OpenDonate\"}"), 0);
+    }
+
+    #[test]
+    fn test_donation_records() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        testing_env!(context.block_timestamp(10).build());
+        open_donate(&mut context, &mut contract, 30);
+        testing_env!(context.block_timestamp(20).build());
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(2), 40, &msg), 0);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 50, &msg), 0);
+
+        let records = contract.get_donation_records(0, 10);
+        assert_eq!(
+            records[..2],
+            [
+                DonationRecord {
+                    donor: accounts(1).to_string(),
+                    token: token().to_string(),
+                    amount: U128(30),
+                    timestamp: U64(10),
+                    purpose: DonationPurpose::Open,
+                    anonymous: false,
+                    category: Some(GENERAL_DONATION_CATEGORY.to_string()),
+                },
+                DonationRecord {
+                    donor: accounts(2).to_string(),
+                    token: token().to_string(),
+                    amount: U128(40),
+                    timestamp: U64(20),
+                    purpose: DonationPurpose::Proposal(id),
+                    anonymous: false,
+                    category: None,
+                },
+            ]
+        );
+        assert_eq!(records.len(), 3);
+        assert_eq!(contract.get_donation_records(2, 10)[0].amount, U128(50));
+
+        let records = contract.get_donation_records_by_account(accounts(1).to_string(), 0, 10);
+        let amounts: Vec<U128> = records.iter().map(|record| record.amount).collect();
+        assert_eq!(amounts, vec![U128(30), U128(50)]);
+        assert_eq!(contract.get_donation_records_by_account(accounts(1).to_string(), 1, 10).len(), 1);
+        assert!(contract.get_donation_records_by_account(accounts(3).to_string(), 0, 10).is_empty());
+    }
+
+    #[test]
+    fn test_top_donors() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = "{\"purpose\":\"OpenDonate\"}";
+        let donors = ["d1", "d2", "d3", "d4", "d5", "d6", "d7", "d8", "d9", "d10", "d11", "d12"];
+        for (index, donor) in donors.iter().enumerate() {
+            let donor: ValidAccountId = donor.to_string().try_into().unwrap();
+            // Donors 1 and 2 are tied, donor 12 gives most in two parts.
+            let amount = match index {
+                0 | 1 => 5,
+                11 => 60,
+                _ => 10 * index as Balance,
+            };
+            assert_eq!(transfer_call(&mut context, &mut contract, donor.clone(), amount, msg), 0);
+            if index == 11 {
+                assert_eq!(transfer_call(&mut context, &mut contract, donor, 60, msg), 0);
+            }
+        }
+
+        let top = contract.get_top_donors(4);
+        assert_eq!(
+            top,
+            vec![
+                ("d12".to_string(), U128(120)),
+                ("d11".to_string(), U128(100)),
+                ("d10".to_string(), U128(90)),
+                ("d9".to_string(), U128(80)),
+            ]
+        );
+        // Ties are ordered by account.
+        let all = contract.get_top_donors(1_000);
+        assert_eq!(all.len(), 12);
+        assert_eq!(all[10..], [("d1".to_string(), U128(5)), ("d2".to_string(), U128(5))]);
+    }
+
+    #[test]
+    fn test_donation_events() {
+        let (mut context, mut contract) = setup_contract();
+        open_donate(&mut context, &mut contract, 30);
+        assert_eq!(
+            get_logs(),
+            vec![
+                "EVENT_JSON:{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"donation\",\
+                 \"data\":[{\"donor\":\"bob\",\"token\":\"token.near\",\"amount\":\"30\",\"proposal_id\":null}]}"
+            ]
+        );
+
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(2), 40, &msg), 0);
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                "EVENT_JSON:{{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"donation\",\
+                 \"data\":[{{\"donor\":\"charlie\",\"token\":\"token.near\",\"amount\":\"40\",\"proposal_id\":{}}}]}}",
+                id
+            )]
+        );
+    }
+
+    #[test]
+    fn test_anonymous_donations() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = "{\"purpose\":\"OpenDonate\",\"anonymous\":true}";
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 30, msg), 0);
+        assert_eq!(
+            get_logs(),
+            vec![
+                "EVENT_JSON:{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"donation\",\
+                 \"data\":[{\"donor\":\"anonymous\",\"token\":\"token.near\",\"amount\":\"30\",\"proposal_id\":null}]}"
+            ]
+        );
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(2), 10, "{\"purpose\":\"OpenDonate\"}"), 0);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}},\"anonymous\":true}}", id);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 20, &msg), 0);
+
+        // Still credited to the donor.
+        assert_eq!(contract.get_open_donation_balance(accounts(1).to_string(), None), U128(30));
+        let donors: Vec<AccountId> = contract.get_donation_records(0, 10).into_iter().map(|record| record.donor).collect();
+        assert_eq!(donors, vec![ANONYMOUS_DONOR.to_string(), accounts(2).to_string(), ANONYMOUS_DONOR.to_string()]);
+        assert!(contract.get_donation_records_by_account(accounts(1).to_string(), 0, 10).is_empty());
+        assert_eq!(contract.get_top_donors(10), vec![(accounts(2).to_string(), U128(10))]);
+        let proposal_donors = contract.get_proposal_donors(id, 0, 10);
+        assert_eq!(proposal_donors[0].account_id, ANONYMOUS_DONOR);
+        assert_eq!(proposal_donors[0].record.amount, U128(20));
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        let records = contract.get_donation_records_with_donors(0, 10);
+        let donors: Vec<AccountId> = records.iter().map(|record| record.donor.clone()).collect();
+        assert_eq!(donors, vec![accounts(1).to_string(), accounts(2).to_string(), accounts(1).to_string()]);
+        assert!(records[0].anonymous && !records[1].anonymous);
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY_OWNER")]
+    fn test_donation_records_with_donors_not_owner() {
+        let (mut context, contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.get_donation_records_with_donors(0, 10);
+    }
+
+    #[test]
+    fn test_anonymous_delegation_refunded() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}},\"anonymous\":true}}", accounts(1));
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 30, &msg), 30);
+        assert_eq!(contract.get_user_weight(&accounts(1).to_string()), 0);
+    }
+
+    #[test]
+    fn test_min_donation_amount() {
+        let (mut context, mut contract) = setup_contract_with_policy(Policy {
+            min_donation_amount: U128(10),
+            ..test_policy()
+        });
+        assert_eq!(contract.get_min_donation_amount(), U128(10));
+        let msg = "{\"purpose\":\"OpenDonate\"}";
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 9, msg), 9);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, msg), 0);
+        assert_eq!(contract.get_total_open_donations(None), U128(10));
+
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 9, &msg), 9);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, &msg), 0);
+        assert_eq!(get_proposal(&contract, id).total_donations, 10);
+        assert_eq!(contract.get_donation_records(0, 10).len(), 2);
+    }
+
+    #[test]
+    fn test_change_min_donation_amount() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        let policy = Policy {
+            min_donation_amount: U128(100),
+            ..test_policy()
+        };
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::ChangePolicy { policy });
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        assert_eq!(contract.get_min_donation_amount(), U128(100));
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(2), 99, "{\"purpose\":\"OpenDonate\"}"), 99);
+    }
+
+    fn usdc() -> ValidAccountId {
+        "usdc.near".try_into().unwrap()
+    }
+
+    #[test]
+    fn test_open_donations_per_token() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = "{\"purpose\":\"OpenDonate\"}";
+        // Tokens need to be whitelisted first.
+        assert_eq!(transfer_call_from(&mut context, &mut contract, usdc(), accounts(1), 40, msg), 40);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.add_donation_token(usdc());
+        assert_eq!(contract.get_donation_tokens(), vec![usdc().to_string()]);
+
+        assert_eq!(transfer_call_from(&mut context, &mut contract, usdc(), accounts(1), 40, msg), 0);
+        assert_eq!(transfer_call_from(&mut context, &mut contract, usdc(), accounts(2), 25, msg), 0);
+        open_donate(&mut context, &mut contract, 30);
+        assert_eq!(contract.get_total_open_donations(None), U128(30));
+        assert_eq!(contract.get_total_open_donations(Some(usdc().to_string())), U128(65));
+        assert_eq!(contract.get_open_donation_balance(accounts(1).to_string(), None), U128(30));
+        assert_eq!(
+            contract.get_open_donation_balance(accounts(1).to_string(), Some(usdc().to_string())),
+            U128(40)
+        );
+        assert_eq!(contract.get_donation_records(0, 10)[0].token, usdc().to_string());
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_donations(accounts(3).to_string(), U128(65), "".to_string(), Some(usdc()), None);
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains("ft_transfer") && r.contains(&usdc().to_string())));
+        assert_eq!(contract.get_total_open_donations(Some(usdc().to_string())), U128(0));
+        assert_eq!(contract.get_total_open_donations(None), U128(30));
+
+        // Removed tokens are refunded again.
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.remove_donation_token(usdc());
+        assert_eq!(transfer_call_from(&mut context, &mut contract, usdc(), accounts(1), 10, msg), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_DONATIONS")]
+    fn test_withdraw_donations_of_other_token() {
+        let (mut context, mut contract) = setup_contract();
+        open_donate(&mut context, &mut contract, 30);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_donations(accounts(3).to_string(), U128(10), "".to_string(), Some(usdc()), None);
+    }
+
+    #[test]
+    fn test_withdraw_donations() {
+        let (mut context, mut contract) = setup_contract();
+        open_donate(&mut context, &mut contract, 30);
+        open_donate(&mut context, &mut contract, 20);
+        assert_eq!(contract.get_total_open_donations(None), U128(50));
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_donations(accounts(2).to_string(), U128(40), "grant".to_string(), None, None);
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains("ft_transfer")
+            && r.contains(&accounts(2).to_string())
+            && r.contains("\\\"memo\\\":\\\"grant\\\"")));
+        assert!(receipts.iter().any(|r| r.contains("on_donations_withdrawn")));
+        assert_eq!(contract.get_total_open_donations(None), U128(10));
+
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(vec![]),
+        );
+        contract.on_donations_withdrawn(DonationWithdrawal {
+            receiver_id: accounts(2).to_string(),
+            token_id: token().to_string(),
+            category: GENERAL_DONATION_CATEGORY.to_string(),
+            amount: U128(40),
+            memo: "grant".to_string(),
+        });
+        assert_eq!(contract.get_total_open_donations(None), U128(10));
+        assert_eq!(
+            get_logs(),
+            vec![
+                "EVENT_JSON:{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"donation_withdraw\",\
+                 \"data\":[{\"receiver_id\":\"charlie\",\"token\":\"token.near\",\"amount\":\"40\",\"memo\":\"grant\"}]}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_donations_withdraw_rolls_back() {
+        let (mut context, mut contract) = setup_contract();
+        open_donate(&mut context, &mut contract, 50);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_donations(accounts(2).to_string(), U128(50), "".to_string(), None, None);
+        assert_eq!(contract.get_total_open_donations(None), U128(0));
+
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_donations_withdrawn(DonationWithdrawal {
+            receiver_id: accounts(2).to_string(),
+            token_id: token().to_string(),
+            category: GENERAL_DONATION_CATEGORY.to_string(),
+            amount: U128(50),
+            memo: "".to_string(),
+        });
+        assert_eq!(contract.get_total_open_donations(None), U128(50));
+        assert_eq!(get_logs(), vec!["ERR_DONATION_WITHDRAW_FAILED"]);
+    }
+
+    #[test]
+    fn test_total_donations() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(contract.get_donation_of(accounts(1).to_string()), U128(0));
+        let msg = "{\"purpose\":\"OpenDonate\"}";
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 30, msg), 0);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(2), 20, msg), 0);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 5, msg), 0);
+        // Donations of other targets aren't part of it.
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let proposal_msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(3), 40, &proposal_msg), 0);
+        assert_eq!(contract.get_donation_of(accounts(1).to_string()), U128(35));
+        assert_eq!(contract.get_total_donations(), U128(55));
+        assert_eq!(contract.get_donor_count(), 2);
+
+        // Withdrawals spend the donations, they stay credited to the donors.
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_donations(accounts(3).to_string(), U128(40), "".to_string(), None, None);
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_donations_withdrawn(DonationWithdrawal {
+            receiver_id: accounts(3).to_string(),
+            token_id: token().to_string(),
+            category: GENERAL_DONATION_CATEGORY.to_string(),
+            amount: U128(40),
+            memo: "".to_string(),
+        });
+        assert_eq!(contract.get_total_open_donations(None), U128(55));
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_donations(accounts(3).to_string(), U128(50), "".to_string(), None, None);
+        assert_eq!(contract.get_total_open_donations(None), U128(5));
+        assert_eq!(contract.get_total_donations(), U128(55));
+        let sum: Balance = contract.donations.iter().map(|(_, amount)| amount).sum();
+        assert_eq!(contract.get_total_donations(), U128(sum));
+    }
+
+    fn category_donate(context: &mut VMContextBuilder, contract: &mut Contract, amount: Balance, category: &str) -> Balance {
+        let msg = format!("{{\"purpose\":\"OpenDonate\",\"category\":\"{}\"}}", category);
+        transfer_call(context, contract, accounts(1), amount, &msg)
+    }
+
+    #[test]
+    fn test_donation_categories() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(category_donate(&mut context, &mut contract, 30, "development"), 30);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.add_donation_category("development".to_string());
+        contract.add_donation_category("community".to_string());
+        assert_eq!(category_donate(&mut context, &mut contract, 30, "development"), 0);
+        open_donate(&mut context, &mut contract, 20);
+        // Only open donations have a category.
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}},\"category\":\"development\"}}", accounts(1));
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, &msg), 10);
+
+        let category = |category: &str, balance: Balance| DonationCategoryOutput {
+            category: category.to_string(),
+            balance: U128(balance),
+        };
+        assert_eq!(
+            contract.get_donation_categories(None),
+            vec![category(GENERAL_DONATION_CATEGORY, 20), category("development", 30), category("community", 0)]
+        );
+        assert_eq!(contract.get_total_open_donations(None), U128(50));
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_donations(accounts(3).to_string(), U128(25), "".to_string(), None, Some("development".to_string()));
+        assert_eq!(contract.get_category_donations("development".to_string(), None), U128(5));
+        assert_eq!(contract.get_total_open_donations(None), U128(25));
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_donations_withdrawn(DonationWithdrawal {
+            receiver_id: accounts(3).to_string(),
+            token_id: token().to_string(),
+            category: "development".to_string(),
+            amount: U128(25),
+            memo: "".to_string(),
+        });
+        assert_eq!(contract.get_category_donations("development".to_string(), None), U128(30));
+
+        // Removed category keeps its donations.
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.remove_donation_category("development".to_string());
+        assert_eq!(category_donate(&mut context, &mut contract, 30, "development"), 30);
+        assert_eq!(contract.get_category_donations("development".to_string(), None), U128(30));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_DONATIONS")]
+    fn test_withdraw_from_wrong_category() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.add_donation_category("development".to_string());
+        contract.add_donation_category("community".to_string());
+        assert_eq!(category_donate(&mut context, &mut contract, 30, "development"), 0);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_donations(accounts(3).to_string(), U128(10), "".to_string(), None, Some("community".to_string()));
+    }
+
+    #[test]
+    fn test_refund_categories() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.add_donation_category("development".to_string());
+        assert_eq!(category_donate(&mut context, &mut contract, 30, "development"), 0);
+        open_donate(&mut context, &mut contract, 20);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_donations(accounts(3).to_string(), U128(25), "".to_string(), None, Some("development".to_string()));
+        // What is left of the development donations and the general one.
+        assert_eq!(contract.get_refundable_donation(accounts(1).to_string()), U128(25));
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.refund_open_donation(U128(25));
+        assert_eq!(contract.get_total_open_donations(None), U128(0));
+        assert_eq!(contract.get_category_donations("development".to_string(), None), U128(0));
+    }
+
+    #[test]
+    fn test_refund_open_donation() {
+        let (mut context, mut contract) = setup_contract();
+        let period = DEFAULT_DONATION_REFUND_PERIOD;
+        testing_env!(context.block_timestamp(10).build());
+        open_donate(&mut context, &mut contract, 30);
+        testing_env!(context.block_timestamp(20).build());
+        open_donate(&mut context, &mut contract, 20);
+        assert_eq!(contract.get_refundable_donation(accounts(1).to_string()), U128(50));
+
+        // Newest donation is refunded first.
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(10 + period - 1).build());
+        contract.refund_open_donation(U128(25));
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains("ft_transfer") && r.contains("\\\"amount\\\":\\\"25\\\"")));
+        assert_eq!(contract.refunded_donations.get(&1), Some(20));
+        assert_eq!(contract.refunded_donations.get(&0), Some(5));
+        assert_eq!(contract.get_donation_of(accounts(1).to_string()), U128(25));
+        assert_eq!(contract.get_total_donations(), U128(25));
+        assert_eq!(contract.get_total_open_donations(None), U128(25));
+        assert_eq!(contract.get_refundable_donation(accounts(1).to_string()), U128(25));
+
+        // First donation is out of the refund period.
+        testing_env!(context.block_timestamp(10 + period).build());
+        assert_eq!(contract.get_refundable_donation(accounts(1).to_string()), U128(0));
+    }
+
+    #[test]
+    fn test_refund_open_donation_failed() {
+        let (mut context, mut contract) = setup_contract();
+        open_donate(&mut context, &mut contract, 30);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.refund_open_donation(U128(30));
+        assert_eq!(contract.get_donor_count(), 0);
+
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_open_donation_refunded(accounts(1).to_string(), vec![(0, U128(30))]);
+        assert_eq!(get_logs(), vec!["ERR_DONATION_REFUND_FAILED"]);
+        assert_eq!(contract.get_donation_of(accounts(1).to_string()), U128(30));
+        assert_eq!(contract.get_total_donations(), U128(30));
+        assert_eq!(contract.get_total_open_donations(None), U128(30));
+        assert_eq!(contract.get_refundable_donation(accounts(1).to_string()), U128(30));
+    }
+
+    #[test]
+    fn test_refund_after_withdrawal() {
+        let (mut context, mut contract) = setup_contract();
+        open_donate(&mut context, &mut contract, 30);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_donations(accounts(3).to_string(), U128(20), "".to_string(), None, None);
+        // Withdrawn part can't be refunded.
+        assert_eq!(contract.get_refundable_donation(accounts(1).to_string()), U128(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_REFUNDABLE_DONATIONS")]
+    fn test_refund_more_than_donated() {
+        let (mut context, mut contract) = setup_contract();
+        open_donate(&mut context, &mut contract, 30);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.refund_open_donation(U128(31));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_DONATIONS")]
+    fn test_withdraw_donations_keeps_locked_amount() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(3), 100);
+        open_donate(&mut context, &mut contract, 30);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_donations(accounts(2).to_string(), U128(31), "".to_string(), None, None);
+    }
+
+    fn donate_near(context: &mut VMContextBuilder, contract: &mut Contract, amount: Balance, proposal_id: Option<u64>) {
+        contract.storage_deposits.insert(&accounts(1).to_string(), &contract.storage_registration_cost());
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(amount).build());
+        contract.donate_near(proposal_id);
+        testing_env!(context.attached_deposit(0).build());
+    }
+
+    #[test]
+    fn test_donate_near() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        donate_near(&mut context, &mut contract, 30, None);
+        donate_near(&mut context, &mut contract, 20, Some(id));
+        assert_eq!(contract.get_near_donation_balance(accounts(1).to_string()), U128(50));
+        assert_eq!(contract.get_proposal_near_donations(id), U128(20));
+        assert_eq!(contract.get_total_near_donations(), U128(50));
+        // Token donations are left as they were.
+        assert_eq!(contract.get_total_open_donations(None), U128(0));
+        assert_eq!(get_proposal(&contract, id).total_donations, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_AMOUNT")]
+    fn test_donate_near_without_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        donate_near(&mut context, &mut contract, 0, None);
+    }
+
+    #[test]
+    fn test_withdraw_near_donations() {
+        let (mut context, mut contract) = setup_contract();
+        donate_near(&mut context, &mut contract, 50, None);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_near_donations(accounts(2).to_string(), U128(50));
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains(&accounts(2).to_string()) && r.contains("deposit: 50 ")));
+        assert_eq!(contract.get_total_near_donations(), U128(0));
+
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_near_donations_withdrawn(U128(50));
+        assert_eq!(contract.get_total_near_donations(), U128(50));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_DONATIONS")]
+    fn test_withdraw_near_donations_above_pool() {
+        let (mut context, mut contract) = setup_contract();
+        donate_near(&mut context, &mut contract, 50, None);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.withdraw_near_donations(accounts(2).to_string(), U128(51));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_AVAILABLE_AMOUNT")]
+    fn test_withdraw_near_donations_keeps_locked_near() {
+        let (mut context, mut contract) = setup_contract();
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        donate_near(&mut context, &mut contract, 50, None);
+        let balance = contract.get_locked_storage_amount().0 + contract.locked_near + 40;
+        testing_env!(context.predecessor_account_id(owner()).account_balance(balance).build());
+        contract.withdraw_near_donations(accounts(2).to_string(), U128(50));
+    }
+
+    #[test]
+    fn test_withdraw_near_donations_with_delegations() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(3), 100);
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        donate_near(&mut context, &mut contract, 50, None);
+        // Delegated tokens aren't $NEAR, only the bond is kept.
+        let balance = contract.get_locked_storage_amount().0 + contract.locked_near + 50;
+        testing_env!(context.predecessor_account_id(owner()).account_balance(balance).build());
+        contract.withdraw_near_donations(accounts(2).to_string(), U128(50));
+        assert_eq!(contract.get_total_near_donations(), U128(0));
+        assert_eq!(contract.get_available_amount(), U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY_OWNER")]
+    fn test_withdraw_donations_not_owner() {
+        let (mut context, mut contract) = setup_contract();
+        open_donate(&mut context, &mut contract, 30);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.withdraw_donations(accounts(1).to_string(), U128(30), "".to_string(), None, None);
     }
-}
\ No newline at end of file
+}
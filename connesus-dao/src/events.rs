@@ -0,0 +1,177 @@
+use crate::*;
+
+// Standard name and version of the events emitted by this contract, see NEP-297.
+pub const EVENT_STANDARD: &str = "connesus_dao";
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalCreateData {
+    pub proposal_id: u64,
+    pub proposer: AccountId,
+    pub kind: String,
+    pub bond: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalVoteData {
+    pub proposal_id: u64,
+    pub voter: AccountId,
+    pub option: String,
+    pub weight: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalFinalizeData {
+    pub proposal_id: u64,
+    pub actor: AccountId,
+    pub status: ProposalStatus,
+    pub total_votes: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalVetoData {
+    pub proposal_id: u64,
+    pub actor: AccountId,
+    pub reason: String,
+    pub slashed_bond: U128,
+}
+
+// Delegation change, `balance` is the delegation of `delegate` after it.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DelegationData {
+    pub funder: AccountId,
+    pub delegate: AccountId,
+    pub amount: U128,
+    pub balance: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RedelegationData {
+    pub funder: AccountId,
+    pub from_delegate: AccountId,
+    pub to_delegate: AccountId,
+    pub amount: U128,
+    pub from_balance: U128,
+    pub to_balance: U128,
+}
+
+// Donation of `amount` of `token`, to given proposal or campaign, or open when there is none.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DonationData {
+    pub donor: AccountId,
+    pub token: AccountId,
+    pub amount: U128,
+    pub proposal_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub campaign_id: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DonationWithdrawData {
+    pub receiver_id: AccountId,
+    pub token: AccountId,
+    pub amount: U128,
+    pub memo: String,
+}
+
+// Change of given bounty by `actor`. `amount` of `token` is the escrow for create and cancel, the payout
+// for approve, the tokens added for top up and the amount of one completion otherwise. `claimant` is set when the actor approves their work.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyData {
+    pub bounty_id: u64,
+    pub actor: AccountId,
+    pub token: BountyToken,
+    pub amount: U128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claimant: Option<AccountId>,
+}
+
+// Owner of the DAO changed, see `accept_ownership`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnerChangeData {
+    pub old_owner: AccountId,
+    pub new_owner: AccountId,
+}
+
+// Events of the DAO, each one is logged with its list of data.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum DaoEvent {
+    ProposalCreate(Vec<ProposalCreateData>),
+    ProposalVote(Vec<ProposalVoteData>),
+    ProposalUnvote(Vec<ProposalVoteData>),
+    ProposalFinalize(Vec<ProposalFinalizeData>),
+    ProposalVeto(Vec<ProposalVetoData>),
+    Delegate(Vec<DelegationData>),
+    Undelegate(Vec<DelegationData>),
+    Redelegate(Vec<RedelegationData>),
+    Donation(Vec<DonationData>),
+    DonationWithdraw(Vec<DonationWithdrawData>),
+    BountyCreate(Vec<BountyData>),
+    BountyClaim(Vec<BountyData>),
+    BountyDone(Vec<BountyData>),
+    BountyApprove(Vec<BountyData>),
+    BountyGiveup(Vec<BountyData>),
+    BountyCancel(Vec<BountyData>),
+    BountyTopUp(Vec<BountyData>),
+    OwnerChange(Vec<OwnerChangeData>),
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a DaoEvent,
+}
+
+impl DaoEvent {
+    // Logs the event as `EVENT_JSON:` followed by its JSON.
+    pub fn emit(&self) {
+        let log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event: self,
+        };
+        env::log(format!("EVENT_JSON:{}", near_sdk::serde_json::to_string(&log).unwrap()).as_bytes());
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{get_logs, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+
+    #[test]
+    fn test_emit() {
+        testing_env!(VMContextBuilder::new().build());
+        DaoEvent::ProposalVote(vec![ProposalVoteData {
+            proposal_id: 3,
+            voter: "alice".to_string(),
+            option: VOTE_APPROVE.to_string(),
+            weight: U128(10),
+        }])
+        .emit();
+        assert_eq!(
+            get_logs(),
+            vec![
+                "EVENT_JSON:{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"proposal_vote\",\
+                 \"data\":[{\"proposal_id\":3,\"voter\":\"alice\",\"option\":\"approve\",\"weight\":\"10\"}]}"
+            ]
+        );
+    }
+}
@@ -1,11 +1,13 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap};
-use near_sdk::json_types::{U128, U64, ValidAccountId};
+use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::json_types::{Base58CryptoHash, Base64VecU8, I128, U128, U64, ValidAccountId};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
     env, ext_contract, near_bindgen, AccountId, Balance, BorshStorageKey,
-    PanicOnDefault, PromiseOrValue, Gas, testing_env
+    PanicOnDefault, Promise, PromiseOrValue, PromiseResult, Gas
 };
+#[cfg(test)]
+use near_sdk::testing_env;
 use std::collections::HashMap;
 use near_contract_standards::fungible_token::core_impl::ext_fungible_token;
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
@@ -15,25 +17,84 @@ near_sdk::setup_alloc!();
 pub use crate::proposals::*;
 pub use crate::types::*;
 pub use crate::views::*;
-pub use crate::types::*;
 pub use crate::donations::*;
 pub use crate::bounty::*;
+pub use crate::campaigns::*;
+pub use crate::matching::*;
+pub use crate::events::*;
+pub use crate::delegation::{DelegationChange, DelegationLock, PendingWithdrawal};
+pub use crate::rewards::RewardAccount;
+pub use crate::roles::*;
 use crate::utils::*;
 
 mod delegation;
 mod proposals;
+mod rewards;
 mod types;
 pub mod views;
 mod donations;
+mod badge;
 mod bounty;
+mod campaigns;
+mod matching;
+mod events;
+mod storage;
+mod tokens;
+mod ownership;
+mod roles;
 mod utils;
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test_utils;
 
 #[derive(BorshStorageKey, BorshSerialize)]
 pub enum StorageKeys {
     Delegations,
     Proposals,
     Donations,
-    Bounties
+    Bounties,
+    ActiveProposals,
+    ProposalsByTag,
+    PendingWithdrawals,
+    Delegators,
+    DelegationFunds,
+    FundedDelegations,
+    DelegationApprovals,
+    StorageDeposits,
+    RewardAccounts,
+    DelegationHistory,
+    DelegationLocks,
+    DonationRecords,
+    DonationRecordsByAccount,
+    NearDonations,
+    ProposalNearDonations,
+    DonorTotals,
+    DonationTokens,
+    TokenOpenDonations,
+    OpenDonationTotals,
+    Campaigns,
+    AnonymousDonations,
+    AnonymousProposalDonors,
+    MatchingPools,
+    RefundedDonations,
+    DonationCategories,
+    CategoryDonations,
+    BountyClaims,
+    BountiesByCreator,
+    BountyClaimsByAccount,
+    BountyEscrow,
+    BountiesByTag,
+    BountyTags,
+    BountyApplications,
+    BountyPayouts,
+    AcceptedTokens,
+    TokenDelegations,
+    TokenDelegationTotals,
+    ProposalDonationTotals,
+    Council,
+    Permissions,
+    TokenDelegators,
+    TokenDelegatorSet { token_id: AccountId },
+    CampaignDonationTotals,
 }
 
 #[near_bindgen]
@@ -42,51 +103,244 @@ pub struct Contract {
     // DAO Metadata.
     pub dao_metadata: DaoMetadata,
     // Voting and permissions policy.
+    pub policy: Policy,
 
-    // Amount of $NEAR locked for bonds.
+    // Amount of the DAO token held for delegations, undelegated tokens, rewards and transfers in flight.
     pub locked_amount: Balance,
+    // Amount of $NEAR locked for the bonds of proposals and bounty claims.
+    pub locked_near: Balance,
 
     // Vote staking contract id. That contract must have this account as owner.
     pub token_account: OldAccountId,
     // Delegated  token total amount.
     pub total_delegation_amount: Balance,
-    // Delegations per user.
+    // Delegations per user, written before delegations were kept per token. Entries move to
+    // `token_delegations` once they change.
     pub delegations: LookupMap<AccountId, Balance>,
     // Last available id for the proposals.
     pub last_proposal_id: u64,
     // Proposal map from ID to proposal information.
     pub proposals: LookupMap<u64, VersionedProposal>,
 
-    pub donations: LookupMap<AccountId, Balance>,
+    pub donations: UnorderedMap<AccountId, Balance>,
+    // Total of `donations`.
+    pub total_donations: Balance,
 
     pub owner_id: AccountId,
 
     pub last_bounty_id: u64,
 
     pub bounties: LookupMap<u64, VersionedBounty>,
+    // Claims of each bounty, in order they were made.
+    pub bounty_claims: LookupMap<u64, Vec<BountyClaim>>,
+    // Ids of the bounties created by each account, in order of creation.
+    pub bounties_by_creator: LookupMap<AccountId, Vec<u64>>,
+    // Ids of the bounties each account has a claim on.
+    pub bounty_claims_by_account: LookupMap<AccountId, Vec<u64>>,
+    // Tokens of the bounties left to pay out, per token.
+    pub bounty_escrow: LookupMap<AccountId, Balance>,
+    // Ids of the bounties with given tag, in order of creation.
+    pub bounties_by_tag: LookupMap<String, Vec<u64>>,
+    // Number of bounties per tag in use.
+    pub bounty_tags: UnorderedMap<String, BountyTagCount>,
+    // $NEAR of the bounties left to pay out, not part of `locked_near`.
+    pub near_bounty_escrow: Balance,
+    // Applications for each bounty that requires them, in order they were made.
+    pub bounty_applications: LookupMap<u64, Vec<BountyApplication>>,
+    // Split payout of each bounty, until all of its transfers succeed.
+    pub bounty_payouts: LookupMap<u64, BountySplitPayout>,
+
+    // Number of proposals in progress per proposer.
+    pub active_proposals: LookupMap<AccountId, u64>,
+
+    // Ids of the proposals with given tag, in order of creation.
+    pub proposals_by_tag: LookupMap<String, Vec<u64>>,
+
+    // Undelegated tokens per account, until they are withdrawn.
+    pub pending_withdrawals: LookupMap<AccountId, Vec<PendingWithdrawal>>,
+
+    // Accounts with some delegation, to list them.
+    pub delegators: UnorderedSet<AccountId>,
+
+    // Tokens each funder delegated, per delegate.
+    pub delegation_funds: LookupMap<AccountId, HashMap<AccountId, Balance>>,
+    // Total of `delegation_funds` per delegate.
+    pub funded_delegations: LookupMap<AccountId, Balance>,
+    // Accounts each delegate accepts delegations from.
+    pub delegation_approvals: LookupMap<AccountId, Vec<AccountId>>,
+
+    // Storage deposit of each registered delegator or donor.
+    pub storage_deposits: LookupMap<AccountId, Balance>,
+
+    // Rewards distributed per delegated token so far, scaled by `REWARD_PRECISION`.
+    pub reward_per_token: Balance,
+    // Value of `reward_per_token` when the DAO token was last changed, nothing was delegated in the
+    // new token before, so no rewards are earned below it.
+    pub reward_checkpoint: Balance,
+    // Rewards of each delegator, see `distribute_rewards`.
+    pub reward_accounts: LookupMap<AccountId, RewardAccount>,
+
+    // Undelegated tokens of all accounts that weren't withdrawn yet.
+    pub total_pending_withdrawals: Balance,
+
+    // Last changes of the delegation of each account, oldest first.
+    pub delegation_history: LookupMap<AccountId, Vec<DelegationChange>>,
+
+    // Locked delegations of each delegate, see `TransferArgs::lock_duration`.
+    pub delegation_locks: LookupMap<AccountId, Vec<DelegationLock>>,
+    // Voting weight added by the locks that weren't released yet.
+    pub total_delegation_bonus: Balance,
+
+    // Tokens other than the DAO token accepted for open donations.
+    pub donation_tokens: UnorderedSet<AccountId>,
+    // Open donations in the donation tokens, per token and donor.
+    pub token_open_donations: LookupMap<(AccountId, AccountId), Balance>,
+    // Open donations of each token that weren't withdrawn, see `withdraw_donations`.
+    pub open_donation_totals: LookupMap<AccountId, Balance>,
+
+    // Every donation, oldest first.
+    pub donation_records: Vector<DonationRecord>,
+    // Indexes in `donation_records` of the donations of each account.
+    pub donation_records_by_account: LookupMap<AccountId, Vec<u64>>,
+
+    // $NEAR donated by each account, see `donate_near`.
+    pub near_donations: LookupMap<AccountId, Balance>,
+    // $NEAR donated to each donation proposal.
+    pub proposal_near_donations: LookupMap<u64, Balance>,
+    // Donated $NEAR that wasn't withdrawn.
+    pub total_near_donations: Balance,
+
+    // Last available id for the campaigns.
+    pub last_campaign_id: u64,
+    pub campaigns: LookupMap<u64, VersionedCampaign>,
+    // Tokens raised by the campaigns per token, matched amounts included.
+    pub campaign_donation_totals: LookupMap<AccountId, Balance>,
+
+    // Part of `donations` each account donated anonymously.
+    pub anonymous_donations: LookupMap<AccountId, Balance>,
+    // Donors of each proposal that donated to it anonymously.
+    pub anonymous_proposal_donors: LookupMap<u64, Vec<AccountId>>,
+
+    // Last available id for the matching pools.
+    pub last_matching_pool_id: u64,
+    pub matching_pools: LookupMap<u64, MatchingPool>,
+    // Ids of the pools that didn't run out yet, oldest first.
+    pub active_matching_pools: Vec<u64>,
+
+    // Part of each donation record refunded with `refund_open_donation`.
+    pub refunded_donations: LookupMap<u64, Balance>,
+
+    // Categories open donations can be made to, besides the general one.
+    pub donation_categories: UnorderedSet<String>,
+    // Part of `open_donation_totals` in each category, per category and token.
+    pub category_donations: LookupMap<(String, AccountId), Balance>,
+
+    // Tokens that can be transferred to the DAO besides the DAO token, see `add_accepted_token`.
+    pub accepted_tokens: UnorderedSet<AccountId>,
+
+    // Delegation of each account per token, see `get_token_delegation`.
+    pub token_delegations: LookupMap<(AccountId, AccountId), Balance>,
+    // Total delegation per token other than the DAO token, which is `total_delegation_amount`.
+    pub token_delegation_totals: LookupMap<AccountId, Balance>,
+    // Accounts with some delegation per token other than the DAO token, they become `delegators`
+    // when the token is made the DAO token.
+    pub token_delegators: LookupMap<AccountId, UnorderedSet<AccountId>>,
+
+    // Donations held by proposals per token, until they are released or refunded.
+    pub proposal_donation_totals: LookupMap<AccountId, Balance>,
+
+    // Account proposed as the next owner, see `propose_owner`.
+    pub pending_owner_id: Option<AccountId>,
+
+    // Members of `Role::Council`.
+    pub council: UnorderedSet<AccountId>,
+    // Roles allowed to take given action on proposals with given kind label, see `set_permission`.
+    pub permissions: UnorderedMap<(String, PermissionAction), Vec<Role>>,
+
+    // Contract is frozen, see `pause`.
+    pub paused: bool,
 }
 
 #[near_bindgen]
 impl Contract {
     #[init]
-    pub fn new(metadata: DaoMetadata, token_contract_id: AccountId, owner_id: AccountId) -> Self {
-        let this = Self {
+    pub fn new(metadata: DaoMetadata, token_contract_id: AccountId, owner_id: AccountId, policy: Option<Policy>) -> Self {
+        let policy = policy.unwrap_or_default();
+        policy.assert_valid();
+        Self {
             dao_metadata: metadata,
+            policy,
             token_account: token_contract_id,
             total_delegation_amount: 0,
             delegations: LookupMap::new(StorageKeys::Delegations),
             last_proposal_id: 0,
             proposals: LookupMap::new(StorageKeys::Proposals),
             locked_amount: 0,
-            donations: LookupMap::new(StorageKeys::Donations),
-            owner_id: owner_id,
+            locked_near: 0,
+            donations: UnorderedMap::new(StorageKeys::DonorTotals),
+            total_donations: 0,
+            owner_id,
             last_bounty_id: 0,
             bounties: LookupMap::new(StorageKeys::Bounties),
-        };
-        this
+            bounty_claims: LookupMap::new(StorageKeys::BountyClaims),
+            bounties_by_creator: LookupMap::new(StorageKeys::BountiesByCreator),
+            bounty_claims_by_account: LookupMap::new(StorageKeys::BountyClaimsByAccount),
+            bounty_escrow: LookupMap::new(StorageKeys::BountyEscrow),
+            bounties_by_tag: LookupMap::new(StorageKeys::BountiesByTag),
+            bounty_tags: UnorderedMap::new(StorageKeys::BountyTags),
+            near_bounty_escrow: 0,
+            bounty_applications: LookupMap::new(StorageKeys::BountyApplications),
+            bounty_payouts: LookupMap::new(StorageKeys::BountyPayouts),
+            accepted_tokens: UnorderedSet::new(StorageKeys::AcceptedTokens),
+            token_delegations: LookupMap::new(StorageKeys::TokenDelegations),
+            token_delegation_totals: LookupMap::new(StorageKeys::TokenDelegationTotals),
+            token_delegators: LookupMap::new(StorageKeys::TokenDelegators),
+            proposal_donation_totals: LookupMap::new(StorageKeys::ProposalDonationTotals),
+            pending_owner_id: None,
+            council: UnorderedSet::new(StorageKeys::Council),
+            permissions: UnorderedMap::new(StorageKeys::Permissions),
+            paused: false,
+            active_proposals: LookupMap::new(StorageKeys::ActiveProposals),
+            proposals_by_tag: LookupMap::new(StorageKeys::ProposalsByTag),
+            pending_withdrawals: LookupMap::new(StorageKeys::PendingWithdrawals),
+            delegators: UnorderedSet::new(StorageKeys::Delegators),
+            delegation_funds: LookupMap::new(StorageKeys::DelegationFunds),
+            funded_delegations: LookupMap::new(StorageKeys::FundedDelegations),
+            delegation_approvals: LookupMap::new(StorageKeys::DelegationApprovals),
+            storage_deposits: LookupMap::new(StorageKeys::StorageDeposits),
+            reward_per_token: 0,
+            reward_checkpoint: 0,
+            reward_accounts: LookupMap::new(StorageKeys::RewardAccounts),
+            total_pending_withdrawals: 0,
+            delegation_history: LookupMap::new(StorageKeys::DelegationHistory),
+            delegation_locks: LookupMap::new(StorageKeys::DelegationLocks),
+            total_delegation_bonus: 0,
+            donation_tokens: UnorderedSet::new(StorageKeys::DonationTokens),
+            token_open_donations: LookupMap::new(StorageKeys::TokenOpenDonations),
+            open_donation_totals: LookupMap::new(StorageKeys::OpenDonationTotals),
+            donation_records: Vector::new(StorageKeys::DonationRecords),
+            donation_records_by_account: LookupMap::new(StorageKeys::DonationRecordsByAccount),
+            near_donations: LookupMap::new(StorageKeys::NearDonations),
+            proposal_near_donations: LookupMap::new(StorageKeys::ProposalNearDonations),
+            total_near_donations: 0,
+            last_campaign_id: 0,
+            campaigns: LookupMap::new(StorageKeys::Campaigns),
+            campaign_donation_totals: LookupMap::new(StorageKeys::CampaignDonationTotals),
+            anonymous_donations: LookupMap::new(StorageKeys::AnonymousDonations),
+            anonymous_proposal_donors: LookupMap::new(StorageKeys::AnonymousProposalDonors),
+            last_matching_pool_id: 0,
+            matching_pools: LookupMap::new(StorageKeys::MatchingPools),
+            active_matching_pools: vec![],
+            refunded_donations: LookupMap::new(StorageKeys::RefundedDonations),
+            donation_categories: UnorderedSet::new(StorageKeys::DonationCategories),
+            category_donations: LookupMap::new(StorageKeys::CategoryDonations),
+        }
     }
 
-    
+
+    // Upgrades the state written by the deployed version, everything added since starts empty with
+    // the default policy. Existing delegators are indexed afterwards with `index_delegators`, donors
+    // with `index_donors`.
     #[init(ignore_state)]
     pub fn migrate() -> Self {
         assert_eq!(
@@ -94,24 +348,96 @@ impl Contract {
             env::current_account_id(),
             "ERR_NOT_ALLOWED"
         );
-        let this: Contract = env::state_read().expect("ERR_CONTRACT_IS_NOT_INITIALIZED");
-        // Self {
-        //     dao_metadata: this.dao_metadata,
-        //     token_account: this.token_account,
-        //     total_delegation_amount: 0,
-        //     delegations: LookupMap::new(StorageKeys::Delegations),
-        //     last_proposal_id: 0,
-        //     proposals: LookupMap::new(StorageKeys::Proposals),
-        //     locked_amount: 0,
-        //     donations: LookupMap::new(StorageKeys::Donations),
-        //     owner_id: this.owner_id,
-        //     last_bounty_id: 0,
-        //     bounties: LookupMap::new(StorageKeys::Bounties),
-        // }
-        this
+        let this: OldContract = env::state_read().expect("ERR_CONTRACT_IS_NOT_INITIALIZED");
+        Self {
+            dao_metadata: this.dao_metadata,
+            policy: Policy::default(),
+            locked_amount: this.locked_amount,
+            // The deployed version has no bonds.
+            locked_near: 0,
+            token_account: this.token_account,
+            total_delegation_amount: this.total_delegation_amount,
+            delegations: this.delegations,
+            last_proposal_id: this.last_proposal_id,
+            proposals: this.proposals,
+            donations: UnorderedMap::new(StorageKeys::DonorTotals),
+            total_donations: 0,
+            owner_id: this.owner_id,
+            last_bounty_id: this.last_bounty_id,
+            bounties: this.bounties,
+            bounty_claims: LookupMap::new(StorageKeys::BountyClaims),
+            bounties_by_creator: LookupMap::new(StorageKeys::BountiesByCreator),
+            bounty_claims_by_account: LookupMap::new(StorageKeys::BountyClaimsByAccount),
+            bounty_escrow: LookupMap::new(StorageKeys::BountyEscrow),
+            bounties_by_tag: LookupMap::new(StorageKeys::BountiesByTag),
+            bounty_tags: UnorderedMap::new(StorageKeys::BountyTags),
+            near_bounty_escrow: 0,
+            bounty_applications: LookupMap::new(StorageKeys::BountyApplications),
+            bounty_payouts: LookupMap::new(StorageKeys::BountyPayouts),
+            accepted_tokens: UnorderedSet::new(StorageKeys::AcceptedTokens),
+            token_delegations: LookupMap::new(StorageKeys::TokenDelegations),
+            token_delegation_totals: LookupMap::new(StorageKeys::TokenDelegationTotals),
+            token_delegators: LookupMap::new(StorageKeys::TokenDelegators),
+            proposal_donation_totals: LookupMap::new(StorageKeys::ProposalDonationTotals),
+            pending_owner_id: None,
+            council: UnorderedSet::new(StorageKeys::Council),
+            permissions: UnorderedMap::new(StorageKeys::Permissions),
+            paused: false,
+            active_proposals: LookupMap::new(StorageKeys::ActiveProposals),
+            proposals_by_tag: LookupMap::new(StorageKeys::ProposalsByTag),
+            pending_withdrawals: LookupMap::new(StorageKeys::PendingWithdrawals),
+            delegators: UnorderedSet::new(StorageKeys::Delegators),
+            delegation_funds: LookupMap::new(StorageKeys::DelegationFunds),
+            funded_delegations: LookupMap::new(StorageKeys::FundedDelegations),
+            delegation_approvals: LookupMap::new(StorageKeys::DelegationApprovals),
+            storage_deposits: LookupMap::new(StorageKeys::StorageDeposits),
+            reward_per_token: 0,
+            reward_checkpoint: 0,
+            reward_accounts: LookupMap::new(StorageKeys::RewardAccounts),
+            total_pending_withdrawals: 0,
+            delegation_history: LookupMap::new(StorageKeys::DelegationHistory),
+            delegation_locks: LookupMap::new(StorageKeys::DelegationLocks),
+            total_delegation_bonus: 0,
+            donation_tokens: UnorderedSet::new(StorageKeys::DonationTokens),
+            token_open_donations: LookupMap::new(StorageKeys::TokenOpenDonations),
+            // Donations made before the totals were tracked aren't part of them.
+            open_donation_totals: LookupMap::new(StorageKeys::OpenDonationTotals),
+            donation_records: Vector::new(StorageKeys::DonationRecords),
+            donation_records_by_account: LookupMap::new(StorageKeys::DonationRecordsByAccount),
+            near_donations: LookupMap::new(StorageKeys::NearDonations),
+            proposal_near_donations: LookupMap::new(StorageKeys::ProposalNearDonations),
+            total_near_donations: 0,
+            last_campaign_id: 0,
+            campaigns: LookupMap::new(StorageKeys::Campaigns),
+            campaign_donation_totals: LookupMap::new(StorageKeys::CampaignDonationTotals),
+            anonymous_donations: LookupMap::new(StorageKeys::AnonymousDonations),
+            anonymous_proposal_donors: LookupMap::new(StorageKeys::AnonymousProposalDonors),
+            last_matching_pool_id: 0,
+            matching_pools: LookupMap::new(StorageKeys::MatchingPools),
+            active_matching_pools: vec![],
+            refunded_donations: LookupMap::new(StorageKeys::RefundedDonations),
+            donation_categories: UnorderedSet::new(StorageKeys::DonationCategories),
+            category_donations: LookupMap::new(StorageKeys::CategoryDonations),
+        }
     }
 }
 
+// State of the deployed version, read by `migrate`.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct OldContract {
+    pub dao_metadata: DaoMetadata,
+    pub locked_amount: Balance,
+    pub token_account: OldAccountId,
+    pub total_delegation_amount: Balance,
+    pub delegations: LookupMap<AccountId, Balance>,
+    pub last_proposal_id: u64,
+    pub proposals: LookupMap<u64, VersionedProposal>,
+    pub donations: LookupMap<AccountId, Balance>,
+    pub owner_id: AccountId,
+    pub last_bounty_id: u64,
+    pub bounties: LookupMap<u64, VersionedBounty>,
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub enum TransferPurpose {
@@ -119,12 +445,85 @@ pub enum TransferPurpose {
     OpenDonate,
     ProposalDonate(u64),
     CreateBounty(BountyInput),
+    // Raises the reward of the bounty, only its creator can.
+    TopUpBounty { bounty_id: u64 },
+    CampaignDonate { campaign_id: u64 },
+    FundMatchingPool { ratio_bps: u32, applies_to: MatchScope },
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct TransferArgs {
     pub purpose: TransferPurpose, // 1 for delegate, 2 for open donate, 3 for proposal donate,
+    // Time the delegation can't be undelegated for, in nanoseconds, boosting its voting weight meanwhile.
+    #[serde(default)]
+    pub lock_duration: Option<U64>,
+    // Donation is credited to the sender, but public views and events don't show them.
+    #[serde(default)]
+    pub anonymous: bool,
+    // Category of open donation, see `add_donation_category`.
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+// Message of `ft_transfer_call`, each purpose with only the options it takes, e.g.
+// `{"type":"ProposalDonate","proposal_id":3}`. Messages without `type` are read as `TransferArgs`.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde", tag = "type")]
+pub enum TransferMsg {
+    Delegate {
+        delegate: AccountId,
+        #[serde(default)]
+        lock_duration: Option<U64>,
+    },
+    OpenDonate {
+        #[serde(default)]
+        anonymous: bool,
+        #[serde(default)]
+        category: Option<String>,
+    },
+    ProposalDonate {
+        proposal_id: u64,
+        #[serde(default)]
+        anonymous: bool,
+    },
+    CreateBounty { bounty: BountyInput },
+    TopUpBounty { bounty_id: u64 },
+    CampaignDonate {
+        campaign_id: u64,
+        #[serde(default)]
+        anonymous: bool,
+    },
+    FundMatchingPool { ratio_bps: u32, applies_to: MatchScope },
+}
+
+impl From<TransferMsg> for TransferArgs {
+    fn from(msg: TransferMsg) -> Self {
+        let (purpose, lock_duration, anonymous, category) = match msg {
+            TransferMsg::Delegate { delegate, lock_duration } => (TransferPurpose::Delegate(delegate), lock_duration, false, None),
+            TransferMsg::OpenDonate { anonymous, category } => (TransferPurpose::OpenDonate, None, anonymous, category),
+            TransferMsg::ProposalDonate { proposal_id, anonymous } => {
+                (TransferPurpose::ProposalDonate(proposal_id), None, anonymous, None)
+            },
+            TransferMsg::CreateBounty { bounty } => (TransferPurpose::CreateBounty(bounty), None, false, None),
+            TransferMsg::TopUpBounty { bounty_id } => (TransferPurpose::TopUpBounty { bounty_id }, None, false, None),
+            TransferMsg::CampaignDonate { campaign_id, anonymous } => {
+                (TransferPurpose::CampaignDonate { campaign_id }, None, anonymous, None)
+            },
+            TransferMsg::FundMatchingPool { ratio_bps, applies_to } => {
+                (TransferPurpose::FundMatchingPool { ratio_bps, applies_to }, None, false, None)
+            },
+        };
+        TransferArgs { purpose, lock_duration, anonymous, category }
+    }
+}
+
+// Reads `TransferMsg`, falling back to `TransferArgs` of the older integrations.
+pub fn parse_transfer_msg(msg: &str) -> Option<TransferArgs> {
+    near_sdk::serde_json::from_str::<TransferMsg>(msg)
+        .map(TransferArgs::from)
+        .or_else(|_| near_sdk::serde_json::from_str::<TransferArgs>(msg))
+        .ok()
 }
 
 /**
@@ -141,54 +540,525 @@ impl FungibleTokenReceiver for Contract {
         amount: U128,
         msg: String,
     ) -> PromiseOrValue<U128> {
-        let TransferArgs { purpose } = near_sdk::serde_json::from_str(&msg).expect("Not valid transfer args");
+        if self.paused {
+            return refund_transfer(amount, "ERR_PAUSED");
+        }
+        if !self.is_accepted_token(&env::predecessor_account_id()) {
+            return refund_transfer(amount, "ERR_TOKEN_NOT_WHITELISTED");
+        }
+        if msg.len() > MAX_TRANSFER_MSG_LEN {
+            return refund_transfer(amount, "ERR_TRANSFER_MSG_TOO_LONG");
+        }
+        let (purpose, lock_duration, anonymous, category) = match parse_transfer_msg(&msg) {
+            Some(TransferArgs { purpose, lock_duration, anonymous, category }) => {
+                (purpose, lock_duration.map_or(0, |duration| duration.0), anonymous, category)
+            },
+            None => return refund_transfer(amount, "ERR_INVALID_TRANSFER_ARGS"),
+        };
+        if lock_duration > 0 && !matches!(purpose, TransferPurpose::Delegate(_)) {
+            return refund_transfer(amount, "ERR_LOCK_ONLY_FOR_DELEGATE");
+        }
+        if anonymous
+            && matches!(
+                purpose,
+                TransferPurpose::Delegate(_)
+                    | TransferPurpose::CreateBounty(_)
+                    | TransferPurpose::TopUpBounty { .. }
+                    | TransferPurpose::FundMatchingPool { .. }
+            )
+        {
+            return refund_transfer(amount, "ERR_ANONYMOUS_ONLY_FOR_DONATIONS");
+        }
+        if category.is_some() && !matches!(purpose, TransferPurpose::OpenDonate) {
+            return refund_transfer(amount, "ERR_CATEGORY_ONLY_FOR_OPEN_DONATE");
+        }
+        // Refunding is cheaper than running out of gas halfway through the records.
+        if env::prepaid_gas().saturating_sub(env::used_gas()) < min_gas_for_transfer(&purpose) {
+            return refund_transfer(amount, "ERR_NOT_ENOUGH_GAS");
+        }
         let token_account = self.token_account.clone();
+        // Records of delegations and donations are paid by the sender's storage deposit.
+        let creates_records = !matches!(
+            purpose,
+            TransferPurpose::CreateBounty(_) | TransferPurpose::TopUpBounty { .. } | TransferPurpose::FundMatchingPool { .. }
+        );
+        if creates_records && !self.is_registered(&sender_id.to_string()) {
+            return refund_transfer(amount, "ERR_NOT_REGISTERED: call storage_deposit first");
+        }
         match purpose {
             TransferPurpose::Delegate(delegate) => {
-                assert_account_id(&token_account);
-                self.internal_delegate(&delegate, amount);
-                self.locked_amount += amount.0;
+                let token_id = env::predecessor_account_id();
+                if token_id != token_account {
+                    // Only the DAO token votes, other tokens are just kept for their funder, see `undelegate_token`.
+                    if delegate != sender_id.to_string() {
+                        return refund_transfer(amount, "ERR_DELEGATE_ONLY_TO_SELF");
+                    }
+                    if lock_duration > 0 {
+                        return refund_transfer(amount, "ERR_LOCK_ONLY_FOR_DAO_TOKEN");
+                    }
+                    self.internal_delegate_token(&token_id, &delegate, amount.0);
+                    return PromiseOrValue::Value(U128(0));
+                }
+                if !self.is_delegation_allowed(&sender_id.to_string(), &delegate) {
+                    return refund_transfer(amount, "ERR_DELEGATION_NOT_APPROVED");
+                }
+                if amount.0 < self.policy.min_delegation_amount.0 {
+                    return refund_transfer(amount, "ERR_DELEGATION_BELOW_MINIMUM");
+                }
+                if lock_duration > MAX_LOCK_DURATION {
+                    return refund_transfer(amount, "ERR_INVALID_LOCK_DURATION");
+                }
+                self.internal_release_expired_locks(&delegate);
+                if lock_duration > 0
+                    && self.delegation_locks.get(&delegate).map_or(0, |locks| locks.len()) >= MAX_DELEGATION_LOCKS
+                {
+                    return refund_transfer(amount, "ERR_TOO_MANY_LOCKS");
+                }
+                // Only accept what is left until the cap, the rest goes back to the sender.
+                let accepted = self.acceptable_delegation(&delegate, amount.0);
+                if accepted == 0 {
+                    return refund_transfer(amount, "ERR_DELEGATION_CAP_REACHED");
+                }
+                self.internal_delegate(&delegate, U128(accepted));
+                self.internal_add_funds(&sender_id.to_string(), &delegate, accepted);
+                if lock_duration > 0 {
+                    self.internal_lock_delegation(&sender_id.to_string(), &delegate, accepted, lock_duration);
+                }
+                self.locked_amount += accepted;
+                DaoEvent::Delegate(vec![DelegationData {
+                    funder: sender_id.to_string(),
+                    delegate: delegate.clone(),
+                    amount: U128(accepted),
+                    balance: U128(self.get_user_weight(&delegate)),
+                }])
+                .emit();
+                return PromiseOrValue::Value(U128(amount.0 - accepted));
             },
             TransferPurpose::OpenDonate => {
-                assert_account_id(&token_account);
-                self.open_donate(&sender_id.to_string(), amount);
+                if amount.0 < self.policy.min_donation_amount.0 {
+                    return refund_transfer(amount, "ERR_DONATION_BELOW_MINIMUM");
+                }
+                let token_id = env::predecessor_account_id();
+                if !self.is_donation_token(&token_id) {
+                    return refund_transfer(amount, "ERR_TOKEN_NOT_ACCEPTED");
+                }
+                let category = category.unwrap_or_else(|| GENERAL_DONATION_CATEGORY.to_string());
+                if !self.is_donation_category(&category) {
+                    return refund_transfer(amount, "ERR_UNKNOWN_CATEGORY");
+                }
+                self.open_donate(&sender_id.to_string(), &token_id, amount, anonymous, &category);
+                let matched = self.internal_match_donation(&token_id, &DonationPurpose::Open, amount.0, Balance::MAX);
+                if matched > 0 {
+                    self.internal_add_open_donations(&category, &token_id, matched);
+                }
             }, 
             TransferPurpose::ProposalDonate(proposal_id) => {
-                assert_account_id(&token_account);
-                let mut proposal_obj: Proposal = self.proposals.get(&proposal_id).expect("ERR_NO_PROPOSAL").into();
-                match proposal_obj.kind {
-                    ProposalKind::Donate => {
-                        let new_proposal = proposal_obj.donate(&sender_id.to_string(), amount.0);
-                        self.proposals.insert(&proposal_id, &VersionedProposal::Default(new_proposal));
-                    },
-                    _ => {
-                        assert!(
-                            proposal_obj.kind.eq(&ProposalKind::Donate),
-                            "PROPOSAL_IS_NOT_DONATION_KIND"
-                        )
-                    },
-                    
+                if amount.0 < self.policy.min_donation_amount.0 {
+                    return refund_transfer(amount, "ERR_DONATION_BELOW_MINIMUM");
                 }
-            },
-            TransferPurpose::CreateBounty(bounty_input) => {
-                assert_account_id(&bounty_input.token);
-                let mut total_token_receive = 0;
-                for (key, value) in &bounty_input.claimer {
-                    total_token_receive += value;
-                }
-                assert_eq!(
-                    sender_id.to_string(),
-                    self.owner_id,
-                    "ONLY_OWNER"
+                let mut proposal_obj: Proposal = match self.proposals.get(&proposal_id) {
+                    Some(proposal) => proposal.into(),
+                    None => return refund_transfer(amount, "ERR_NO_PROPOSAL"),
+                };
+                if proposal_obj.kind != ProposalKind::Donate {
+                    return refund_transfer(amount, "PROPOSAL_IS_NOT_DONATION_KIND");
+                }
+                if proposal_obj.get_status() != ProposalStatus::InProgress {
+                    return refund_transfer(amount, "ERR_PROPOSAL_NOT_IN_PROGRESS");
+                }
+                if proposal_obj.is_deadline_passed() {
+                    return refund_transfer(amount, "ERR_DONATION_DEADLINE_PASSED");
+                }
+                proposal_obj.assign_legacy_donations(&token_account);
+                let token_id = env::predecessor_account_id();
+                if !proposal_obj.accepted_tokens.contains(&token_id) {
+                    return refund_transfer(amount, "ERR_TOKEN_NOT_ACCEPTED");
+                }
+                // Only accept what is left until the target, the rest goes back to the sender.
+                let accepted = proposal_obj.acceptable_donation(amount.0);
+                let mut new_proposal = proposal_obj.donate(&sender_id.to_string(), &token_id, accepted);
+                self.internal_add_proposal_donations(&token_id, accepted);
+                if accepted > 0 {
+                    self.internal_record_donation(
+                        &sender_id.to_string(),
+                        &token_id,
+                        accepted,
+                        DonationPurpose::Proposal(proposal_id),
+                        anonymous,
+                        None,
+                    );
+                }
+                let actor = if anonymous {
+                    self.internal_add_anonymous_proposal_donor(proposal_id, &sender_id.to_string());
+                    ANONYMOUS_DONOR.to_string()
+                } else {
+                    sender_id.to_string()
+                };
+                new_proposal.record_activity(
+                    ProposalAction::Donate { token: token_id.clone(), amount: U128(accepted) },
+                    &actor,
                 );
-                assert_eq!(
-                    total_token_receive,
-                    amount.0,
-                    "ERR_NOT_DEPOSIT_ENOUGH_TOKEN"
+                // Match is donated by the DAO itself, up to the target.
+                let matched = self.internal_match_donation(
+                    &token_id,
+                    &DonationPurpose::Proposal(proposal_id),
+                    accepted,
+                    new_proposal.acceptable_donation(Balance::MAX),
                 );
-                self.create_bounty(bounty_input);
+                if matched > 0 {
+                    new_proposal = new_proposal.donate(&env::current_account_id(), &token_id, matched);
+                    self.internal_add_proposal_donations(&token_id, matched);
+                }
+                if new_proposal.status == ProposalStatus::Funded {
+                    self.internal_refund_bond(&mut new_proposal);
+                    self.internal_remove_active_proposal(&new_proposal.proposer);
+                }
+                self.proposals.insert(&proposal_id, &VersionedProposal::V2(new_proposal));
+                return PromiseOrValue::Value(U128(amount.0 - accepted));
+            },
+            TransferPurpose::CreateBounty(bounty_input) => {
+                // Escrow must be funded with the token the bounty pays out in.
+                if env::predecessor_account_id() != bounty_input.token {
+                    return refund_transfer(amount, "ERR_WRONG_BOUNTY_TOKEN");
+                }
+                if sender_id.to_string() != self.owner_id {
+                    return refund_transfer(amount, "ONLY_OWNER");
+                }
+                let total = match bounty_input.validate() {
+                    Ok(total) if total <= amount.0 => total,
+                    Ok(_) => return refund_transfer(amount, "ERR_NOT_DEPOSIT_ENOUGH_TOKEN"),
+                    Err(err) => return refund_transfer(amount, err),
+                };
+                self.create_bounty(&sender_id.to_string(), bounty_input);
+                // Anything above the reward of all completions goes back to the sender.
+                return PromiseOrValue::Value(U128(amount.0 - total));
+            },
+            TransferPurpose::TopUpBounty { bounty_id } => {
+                let token_id = env::predecessor_account_id();
+                if let Err(err) = self.internal_top_up_bounty(&sender_id.to_string(), &token_id, bounty_id, amount.0) {
+                    return refund_transfer(amount, err);
+                }
+            },
+            TransferPurpose::CampaignDonate { campaign_id } => {
+                if amount.0 < self.policy.min_donation_amount.0 {
+                    return refund_transfer(amount, "ERR_DONATION_BELOW_MINIMUM");
+                }
+                let token_id = env::predecessor_account_id();
+                // Only accept what is left until the goal, the rest goes back to the sender.
+                match self.internal_campaign_donate(&sender_id.to_string(), &token_id, campaign_id, amount.0, anonymous) {
+                    Ok(accepted) => return PromiseOrValue::Value(U128(amount.0 - accepted)),
+                    Err(err) => return refund_transfer(amount, err),
+                }
+            },
+            TransferPurpose::FundMatchingPool { ratio_bps, applies_to } => {
+                if sender_id.to_string() != self.owner_id {
+                    return refund_transfer(amount, "ONLY_OWNER");
+                }
+                if let Err(err) = self.validate_matching_pool(ratio_bps) {
+                    return refund_transfer(amount, err);
+                }
+                self.create_matching_pool(&env::predecessor_account_id(), amount, ratio_bps, applies_to);
             }
         }
         PromiseOrValue::Value(U128(0))
     }
 }
+
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_migrate_indexes_delegators() {
+        let mut context = get_context(owner());
+        testing_env!(context.build());
+        // State written by the deployed version, with donations in a lookup map.
+        let mut delegations = LookupMap::new(StorageKeys::Delegations);
+        delegations.insert(&accounts(1).to_string(), &10);
+        delegations.insert(&accounts(2).to_string(), &20);
+        let mut donations = LookupMap::new(StorageKeys::Donations);
+        donations.insert(&accounts(3).to_string(), &15);
+        env::state_write(&OldContract {
+            dao_metadata: DaoMetadata::test_config(),
+            locked_amount: 30,
+            token_account: token().to_string(),
+            total_delegation_amount: 30,
+            delegations,
+            last_proposal_id: 0,
+            proposals: LookupMap::new(StorageKeys::Proposals),
+            donations,
+            owner_id: owner().to_string(),
+            last_bounty_id: 0,
+            bounties: LookupMap::new(StorageKeys::Bounties),
+        });
+
+        testing_env!(context.predecessor_account_id(dao()).build());
+        let mut contract = Contract::migrate();
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(10));
+        assert_eq!(contract.delegation_total_supply(), U128(30));
+        assert_eq!(contract.owner_id, owner().to_string());
+        assert_eq!(contract.token_account, token().to_string());
+        assert_eq!(contract.policy, Policy::default());
+        assert_eq!(contract.get_delegator_count(), 0);
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.index_delegators(vec![accounts(1).to_string(), accounts(2).to_string(), accounts(3).to_string()]);
+        assert_eq!(
+            contract.get_delegators(0, 10),
+            vec![(accounts(1).to_string(), U128(10)), (accounts(2).to_string(), U128(20))]
+        );
+
+        assert_eq!(contract.get_donation_balance(accounts(3).to_string()), None);
+        contract.index_donors(vec![accounts(3).to_string()]);
+        assert_eq!(contract.get_donation_balance(accounts(3).to_string()), Some(15));
+        assert_eq!(contract.get_top_donors(10), vec![(accounts(3).to_string(), U128(15))]);
+        assert_eq!(contract.get_total_donations(), U128(15));
+        env::state_write(&contract);
+        let contract: Contract = env::state_read().unwrap();
+        assert_eq!(contract.get_delegator_count(), 2);
+    }
+
+    #[test]
+    fn test_refund_on_garbage_msg() {
+        let (mut context, mut contract) = setup_contract();
+        let refund = transfer_call(&mut context, &mut contract, accounts(1), 100, "not a json");
+        assert_eq!(refund, 100);
+        let refund = transfer_call(&mut context, &mut contract, accounts(1), 100, "{\"purpose\":\"Unknown\"}");
+        assert_eq!(refund, 100);
+        assert_eq!(contract.get_donation_balance(accounts(1).to_string()), None);
+    }
+
+    #[test]
+    fn test_refund_on_missing_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        let refund = transfer_call(&mut context, &mut contract, accounts(1), 100, "{\"purpose\":{\"ProposalDonate\":7}}");
+        assert_eq!(refund, 100);
+        let refund = transfer_call(&mut context, &mut contract, accounts(1), 100, "{\"purpose\":\"ProposalDonate\"}");
+        assert_eq!(refund, 100);
+    }
+
+    #[test]
+    fn test_refund_on_invalid_bounty_input() {
+        let (mut context, mut contract) = setup_contract();
+        let refund = transfer_call(&mut context, &mut contract, owner(), 100, "{\"purpose\":\"CreateBounty\"}");
+        assert_eq!(refund, 100);
+        let msg = format!(
+            "{{\"purpose\":{{\"CreateBounty\":{{\"description\":\"d\",\"token\":\"{}\",\"amount\":\"100\",\"times\":1,\"max_deadline\":\"1\"}}}}}}",
+            token()
+        );
+        let refund = transfer_call(&mut context, &mut contract, owner(), 100, &msg);
+        assert_eq!(refund, 100);
+        assert_eq!(contract.get_last_bounty_id(), 0);
+    }
+
+    #[test]
+    fn test_happy_paths_keep_tokens() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, &msg), 0);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, "{\"purpose\":\"OpenDonate\"}"), 0);
+        assert_eq!(contract.get_donation_balance(accounts(1).to_string()), Some(100));
+    }
+
+    #[test]
+    fn test_proposal_donations_are_persisted() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, &msg), 0);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 50, &msg), 0);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(2), 10, &msg), 0);
+
+        env::state_write(&contract);
+        let contract: Contract = env::state_read().unwrap();
+        assert_eq!(contract.get_proposal_total_donations(id), Some(U128(160)));
+        let donations = contract.get_proposal_donation(id, 0, 10);
+        assert_eq!(donations.len(), 2);
+        assert_eq!(donations[0].account, accounts(1).to_string());
+        assert_eq!(donations[0].doate_balance, 150);
+        assert_eq!(donations[1].doate_balance, 10);
+    }
+
+    #[test]
+    fn test_donor_records_are_aggregated() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        testing_env!(context.block_timestamp(100).build());
+        transfer_call(&mut context, &mut contract, accounts(2), 10, &msg);
+        transfer_call(&mut context, &mut contract, accounts(1), 100, &msg);
+        testing_env!(context.block_timestamp(200).build());
+        transfer_call(&mut context, &mut contract, accounts(1), 50, &msg);
+
+        env::state_write(&contract);
+        let contract: Contract = env::state_read().unwrap();
+        let donors = contract.get_proposal_donors(id, 0, 10);
+        assert_eq!(donors.len(), 2);
+        assert_eq!(donors[0].account_id, accounts(1).to_string());
+        assert_eq!(
+            donors[0].record,
+            DonorRecord {
+                amount: U128(150),
+                tokens: vec![(token().to_string(), U128(150))].into_iter().collect(),
+                first_donation_time: U64(100),
+                last_donation_time: U64(200)
+            }
+        );
+        assert_eq!(donors[1].account_id, accounts(2).to_string());
+        assert_eq!(donors[1].record.amount, U128(10));
+
+        let donors = contract.get_proposal_donors(id, 1, 10);
+        assert_eq!(donors.len(), 1);
+        assert_eq!(donors[0].account_id, accounts(2).to_string());
+        let json = near_sdk::serde_json::to_string(&donors[0]).unwrap();
+        assert!(json.contains("\"amount\":\"10\""));
+    }
+
+    fn add_donate_proposal(context: &mut VMContextBuilder, contract: &mut Contract, target: Balance) -> String {
+        testing_env!(context
+            .predecessor_account_id(owner())
+            .attached_deposit(DEFAULT_PROPOSAL_BOND)
+            .build());
+        let id = contract.add_proposal(ProposalInput {
+            target: Some(U128(target)),
+            ..proposal_input(ProposalKind::Donate)
+        });
+        testing_env!(context.attached_deposit(0).build());
+        format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id)
+    }
+
+    #[test]
+    fn test_donation_exact_fill() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_donate_proposal(&mut context, &mut contract, 100);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 60, &msg), 0);
+        assert_eq!(get_proposal(&contract, 0).status, ProposalStatus::InProgress);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(2), 40, &msg), 0);
+        let proposal = get_proposal(&contract, 0);
+        assert_eq!(proposal.status, ProposalStatus::Funded);
+        assert_eq!(proposal.total_donations, 100);
+        assert_eq!(proposal.bond, 0);
+    }
+
+    #[test]
+    fn test_donation_overshoot_is_split() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_donate_proposal(&mut context, &mut contract, 100);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 70, &msg), 0);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(2), 50, &msg), 20);
+        let proposal = get_proposal(&contract, 0);
+        assert_eq!(proposal.status, ProposalStatus::Funded);
+        assert_eq!(proposal.total_donations, 100);
+        assert_eq!(proposal.donations.get(&accounts(2).to_string()).unwrap().amount, U128(30));
+    }
+
+    #[test]
+    fn test_donation_refused_after_funded() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_donate_proposal(&mut context, &mut contract, 100);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, &msg), 0);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(2), 10, &msg), 10);
+        let proposal = get_proposal(&contract, 0);
+        assert_eq!(proposal.total_donations, 100);
+        assert!(!proposal.donations.contains_key(&accounts(2).to_string()));
+    }
+
+    #[test]
+    fn test_donation_refused_after_deadline() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(owner())
+            .attached_deposit(DEFAULT_PROPOSAL_BOND)
+            .build());
+        let id = contract.add_proposal(ProposalInput {
+            deadline: Some(U64(1_000)),
+            ..proposal_input(ProposalKind::Donate)
+        });
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        testing_env!(context.attached_deposit(0).block_timestamp(1_000).build());
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, &msg), 10);
+        assert_eq!(get_proposal(&contract, id).total_donations, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TARGET_ONLY_FOR_DONATE")]
+    fn test_target_only_for_donate() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(owner())
+            .attached_deposit(DEFAULT_PROPOSAL_BOND)
+            .build());
+        contract.add_proposal(ProposalInput {
+            target: Some(U128(100)),
+            ..proposal_input(ProposalKind::Poll { options: vec!["a".to_string()] })
+        });
+    }
+
+    #[test]
+    fn test_tagged_transfer_msg() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = "{\"type\":\"Delegate\",\"delegate\":\"bob\"}";
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, msg), 0);
+        assert_eq!(contract.get_user_weight(&accounts(1).to_string()), 10);
+
+        add_donate_proposal(&mut context, &mut contract, 100);
+        let msg = "{\"type\":\"ProposalDonate\",\"proposal_id\":0}";
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(2), 30, msg), 0);
+        assert_eq!(get_proposal(&contract, 0).total_donations, 30);
+
+        let msg = "{\"type\":\"OpenDonate\",\"anonymous\":true}";
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(2), 5, msg), 0);
+        assert_eq!(contract.get_total_open_donations(None), U128(5));
+    }
+
+    #[test]
+    fn test_transfer_msg_precedence() {
+        // Tagged message wins over the old `purpose` in the same message.
+        let args = parse_transfer_msg("{\"type\":\"OpenDonate\",\"purpose\":{\"Delegate\":\"bob\"}}").unwrap();
+        assert!(matches!(args.purpose, TransferPurpose::OpenDonate));
+        // Invalid tagged message falls back to the old format.
+        let args = parse_transfer_msg("{\"type\":\"Delegate\",\"purpose\":\"OpenDonate\"}").unwrap();
+        assert!(matches!(args.purpose, TransferPurpose::OpenDonate));
+        let args = parse_transfer_msg("{\"purpose\":{\"Delegate\":\"bob\"},\"lock_duration\":\"5\"}").unwrap();
+        assert!(matches!(args.purpose, TransferPurpose::Delegate(ref delegate) if delegate == "bob"));
+        assert_eq!(args.lock_duration, Some(U64(5)));
+        assert!(parse_transfer_msg("{\"type\":\"Delegate\"}").is_none());
+    }
+
+    #[test]
+    fn test_invalid_tagged_transfer_msg_refunded() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = "{\"type\":\"ProposalDonate\",\"proposal_id\":\"x\"}";
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, msg), 10);
+    }
+
+    #[test]
+    fn test_transfer_msg_length_cap() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = format!("{:<width$}", "{\"purpose\":\"OpenDonate\"}", width = MAX_TRANSFER_MSG_LEN);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, &msg), 0);
+        let msg = format!("{} ", msg);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, &msg), 10);
+        assert_eq!(get_logs(), vec!["ERR_TRANSFER_MSG_TOO_LONG"]);
+        assert_eq!(contract.get_total_open_donations(None), U128(10));
+    }
+
+    #[test]
+    fn test_transfer_with_not_enough_gas() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.prepaid_gas(GAS_FOR_DONATION - 1).build());
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, "{\"purpose\":\"OpenDonate\"}"), 10);
+        assert_eq!(get_logs(), vec!["ERR_NOT_ENOUGH_GAS"]);
+        assert_eq!(contract.get_total_open_donations(None), U128(0));
+        // Delegations mint no badges, so they need less.
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, "{\"purpose\":{\"Delegate\":\"bob\"}}"), 0);
+        assert_eq!(contract.get_user_weight(&accounts(1).to_string()), 10);
+    }
+}
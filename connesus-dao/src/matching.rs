@@ -0,0 +1,286 @@
+use crate::*;
+
+// Donations a matching pool applies to.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MatchScope {
+    All,
+    Open,
+    Proposal(u64),
+    Campaign(u64),
+}
+
+impl MatchScope {
+    pub fn applies_to(&self, purpose: &DonationPurpose) -> bool {
+        match (self, purpose) {
+            (MatchScope::All, _) => true,
+            (MatchScope::Open, DonationPurpose::Open) => true,
+            (MatchScope::Proposal(id), DonationPurpose::Proposal(proposal_id)) => id == proposal_id,
+            (MatchScope::Campaign(id), DonationPurpose::Campaign(campaign_id)) => id == campaign_id,
+            _ => false,
+        }
+    }
+}
+
+// Tokens of the treasury matching donations, `ratio_bps` of each donation until `remaining` runs out.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MatchingPool {
+    pub id: u64,
+    pub token: AccountId,
+    pub ratio_bps: u32,
+    pub applies_to: MatchScope,
+    pub amount: U128,
+    pub remaining: U128,
+}
+
+impl MatchingPool {
+    // Part of the pool matching donation of `amount`.
+    pub fn matching_amount(&self, amount: Balance) -> Balance {
+        let ratio = self.ratio_bps as Balance;
+        let basis_points = MAX_BASIS_POINTS as Balance;
+        // Split to not overflow on large amounts.
+        let matched = amount / basis_points * ratio + amount % basis_points * ratio / basis_points;
+        std::cmp::min(matched, self.remaining.0)
+    }
+}
+
+impl Contract {
+    // Returns the error code if a pool can't be created with given ratio.
+    pub fn validate_matching_pool(&self, ratio_bps: u32) -> Result<(), &'static str> {
+        if ratio_bps == 0 || ratio_bps > MAX_MATCHING_RATIO_BPS {
+            return Err("ERR_INVALID_MATCHING_RATIO");
+        }
+        if self.active_matching_pools.len() >= MAX_ACTIVE_MATCHING_POOLS {
+            return Err("ERR_TOO_MANY_MATCHING_POOLS");
+        }
+        Ok(())
+    }
+
+    // Funded with `TransferPurpose::FundMatchingPool`.
+    pub fn create_matching_pool(&mut self, token_id: &AccountId, amount: U128, ratio_bps: u32, applies_to: MatchScope) -> u64 {
+        self.validate_matching_pool(ratio_bps).unwrap_or_else(|err| env::panic(err.as_bytes()));
+        let id = self.last_matching_pool_id;
+        let pool = MatchingPool {
+            id,
+            token: token_id.clone(),
+            ratio_bps,
+            applies_to,
+            amount,
+            remaining: amount,
+        };
+        self.matching_pools.insert(&id, &pool);
+        self.active_matching_pools.push(id);
+        self.last_matching_pool_id += 1;
+        id
+    }
+
+    // Takes the match of donation of `amount` from the oldest pool it qualifies for, returns the matched amount.
+    // Pool running out matches only partially, `max_amount` is what the target can still take.
+    pub(crate) fn internal_match_donation(
+        &mut self,
+        token_id: &AccountId,
+        purpose: &DonationPurpose,
+        amount: Balance,
+        max_amount: Balance,
+    ) -> Balance {
+        let pool_id = self.active_matching_pools.iter().copied().find(|id| {
+            self.matching_pools
+                .get(id)
+                .is_some_and(|pool| &pool.token == token_id && pool.applies_to.applies_to(purpose))
+        });
+        let mut pool = match pool_id.and_then(|id| self.matching_pools.get(&id)) {
+            Some(pool) => pool,
+            None => return 0,
+        };
+        let matched = std::cmp::min(pool.matching_amount(amount), max_amount);
+        pool.remaining = U128(pool.remaining.0 - matched);
+        if pool.remaining.0 == 0 {
+            self.active_matching_pools.retain(|id| id != &pool.id);
+        }
+        self.matching_pools.insert(&pool.id, &pool);
+        matched
+    }
+}
+
+#[ext_contract(ext_matching)]
+pub trait MatchingCallbacks {
+    fn on_matching_pool_closed(&mut self, id: u64, amount: U128);
+}
+
+#[near_bindgen]
+impl Contract {
+    // Stops given pool from matching donations and sends what is left of it back to the owner.
+    pub fn close_matching_pool(&mut self, id: u64) -> Promise {
+        self.assert_not_paused();
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        assert!(self.active_matching_pools.contains(&id), "ERR_MATCHING_POOL_NOT_ACTIVE");
+        let mut pool = self.matching_pools.get(&id).expect("ERR_NO_MATCHING_POOL");
+        let amount = pool.remaining;
+        pool.remaining = U128(0);
+        self.matching_pools.insert(&id, &pool);
+        self.active_matching_pools.retain(|pool_id| pool_id != &id);
+        ext_fungible_token::ft_transfer(
+            self.owner_id.clone(),
+            amount,
+            None,
+            &pool.token,
+            ONE_YOCTO_NEAR,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_matching::on_matching_pool_closed(
+            id,
+            amount,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+
+    // Puts the pool back if its tokens weren't sent.
+    #[private]
+    pub fn on_matching_pool_closed(&mut self, id: u64, amount: U128) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        let mut pool = self.matching_pools.get(&id).expect("ERR_NO_MATCHING_POOL");
+        pool.remaining = amount;
+        self.matching_pools.insert(&id, &pool);
+        self.active_matching_pools.push(id);
+        env::log(b"ERR_MATCHING_POOL_CLOSE_FAILED");
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, get_logs, testing_env_with_promise_results, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
+
+    fn fund_pool(context: &mut VMContextBuilder, contract: &mut Contract, amount: Balance, ratio_bps: u32, scope: &str) -> Balance {
+        let msg = format!(
+            "{{\"purpose\":{{\"FundMatchingPool\":{{\"ratio_bps\":{},\"applies_to\":{}}}}}}}",
+            ratio_bps, scope
+        );
+        transfer_call(context, contract, owner(), amount, &msg)
+    }
+
+    fn open_donate(context: &mut VMContextBuilder, contract: &mut Contract, amount: Balance) {
+        assert_eq!(transfer_call(context, contract, accounts(1), amount, "{\"purpose\":\"OpenDonate\"}"), 0);
+    }
+
+    #[test]
+    fn test_matching_ratio() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(fund_pool(&mut context, &mut contract, 1_000, 5_000, "\"Open\""), 0);
+        open_donate(&mut context, &mut contract, 101);
+        // Half of the donation, rounded down.
+        assert_eq!(contract.get_total_open_donations(None), U128(151));
+        assert_eq!(contract.get_matching_pool(0).unwrap().remaining, U128(950));
+        // Donor is only credited with their own donation.
+        assert_eq!(contract.get_open_donation_balance(accounts(1).to_string(), None), U128(101));
+
+        let pool = MatchingPool {
+            id: 0,
+            token: token().to_string(),
+            ratio_bps: 25_000,
+            applies_to: MatchScope::All,
+            amount: U128(Balance::MAX),
+            remaining: U128(Balance::MAX),
+        };
+        assert_eq!(pool.matching_amount(10), 25);
+        assert_eq!(pool.matching_amount(Balance::MAX / 4), 212_676_479_325_586_539_664_609_129_644_855_132_157);
+    }
+
+    #[test]
+    fn test_matching_pool_exhausted() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(fund_pool(&mut context, &mut contract, 70, 10_000, "\"All\""), 0);
+        open_donate(&mut context, &mut contract, 50);
+        // Only what is left in the pool is matched.
+        open_donate(&mut context, &mut contract, 50);
+        assert_eq!(contract.get_total_open_donations(None), U128(170));
+        let pool = contract.get_matching_pool(0).unwrap();
+        assert_eq!(pool.remaining, U128(0));
+        assert!(contract.get_matching_pools(0, 10).is_empty());
+        open_donate(&mut context, &mut contract, 50);
+        assert_eq!(contract.get_total_open_donations(None), U128(220));
+    }
+
+    #[test]
+    fn test_matching_scope() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).block_timestamp(0).build());
+        let campaign_id = contract.create_campaign("drive".to_string(), U128(100), U64(1_000), None);
+        let scope = format!("{{\"Campaign\":{}}}", campaign_id);
+        assert_eq!(fund_pool(&mut context, &mut contract, 1_000, 10_000, &scope), 0);
+        open_donate(&mut context, &mut contract, 30);
+        assert_eq!(contract.get_total_open_donations(None), U128(30));
+
+        let msg = format!("{{\"purpose\":{{\"CampaignDonate\":{{\"campaign_id\":{}}}}}}}", campaign_id);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 30, &msg), 0);
+        assert_eq!(contract.get_campaign(campaign_id).unwrap().campaign.raised, U128(60));
+        // Match is limited to what the campaign can still take.
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 30, &msg), 0);
+        let output = contract.get_campaign(campaign_id).unwrap();
+        assert_eq!(output.campaign.raised, U128(100));
+        assert_eq!(output.campaign.status, CampaignStatus::Completed);
+        assert_eq!(contract.get_matching_pool(0).unwrap().remaining, U128(960));
+    }
+
+    #[test]
+    fn test_matching_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        assert_eq!(fund_pool(&mut context, &mut contract, 1_000, 20_000, &format!("{{\"Proposal\":{}}}", id)), 0);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 40, &msg), 0);
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.total_donations, 120);
+        // Match is credited to the DAO itself.
+        assert_eq!(proposal.donations.get(&dao().to_string()).unwrap().amount, U128(80));
+    }
+
+    #[test]
+    fn test_close_matching_pool() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(fund_pool(&mut context, &mut contract, 100, 10_000, "\"Open\""), 0);
+        open_donate(&mut context, &mut contract, 30);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.close_matching_pool(0);
+        let receipts = created_receipts();
+        assert!(receipts[0].contains("ft_transfer") && receipts[0].contains("\\\"amount\\\":\\\"70\\\""));
+        assert!(contract.get_matching_pools(0, 10).is_empty());
+        assert_eq!(contract.internal_tracked_balance(&token().to_string()), contract.locked_amount + 60);
+        open_donate(&mut context, &mut contract, 30);
+        assert_eq!(contract.get_total_open_donations(None), U128(90));
+
+        // Failed transfer puts the pool back.
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_matching_pool_closed(0, U128(70));
+        assert_eq!(get_logs(), vec!["ERR_MATCHING_POOL_CLOSE_FAILED"]);
+        assert_eq!(contract.get_matching_pools(0, 10)[0].remaining, U128(70));
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY_OWNER")]
+    fn test_close_matching_pool_not_owner() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(fund_pool(&mut context, &mut contract, 100, 10_000, "\"Open\""), 0);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.close_matching_pool(0);
+    }
+
+    #[test]
+    fn test_fund_matching_pool_refunds() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = "{\"purpose\":{\"FundMatchingPool\":{\"ratio_bps\":10000,\"applies_to\":\"Open\"}}}";
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 100, msg), 100);
+        assert_eq!(fund_pool(&mut context, &mut contract, 100, 0, "\"Open\""), 100);
+        assert_eq!(fund_pool(&mut context, &mut contract, 100, MAX_MATCHING_RATIO_BPS + 1, "\"Open\""), 100);
+        assert_eq!(contract.get_last_matching_pool_id(), 0);
+    }
+}
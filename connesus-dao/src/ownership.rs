@@ -0,0 +1,227 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    // Proposes `new_owner` to take over the DAO, which it has to confirm with `accept_ownership`.
+    // Replaces the previous proposal, if any.
+    pub fn propose_owner(&mut self, new_owner: ValidAccountId) {
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        assert_ne!(self.owner_id, new_owner.to_string(), "ERR_ALREADY_OWNER");
+        self.pending_owner_id = Some(new_owner.into());
+    }
+
+    pub fn cancel_owner_proposal(&mut self) {
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        assert!(self.pending_owner_id.take().is_some(), "ERR_NO_OWNER_PROPOSAL");
+    }
+
+    // Makes the proposed account the owner, the previous owner loses access right away.
+    pub fn accept_ownership(&mut self) {
+        let pending_owner_id = self.pending_owner_id.clone().expect("ERR_NO_OWNER_PROPOSAL");
+        assert_eq!(pending_owner_id, env::predecessor_account_id(), "ERR_NOT_PROPOSED_OWNER");
+        self.pending_owner_id = None;
+        let old_owner = std::mem::replace(&mut self.owner_id, pending_owner_id);
+        DaoEvent::OwnerChange(vec![OwnerChangeData {
+            old_owner,
+            new_owner: self.owner_id.clone(),
+        }])
+        .emit();
+    }
+
+    // Freezes transfers, withdrawals, proposal and bounty actions until `unpause`, e.g. when a bug is found.
+    // Council members can pause too, as guardians.
+    pub fn pause(&mut self) {
+        let predecessor_id = env::predecessor_account_id();
+        assert!(
+            self.council.contains(&predecessor_id)
+                || predecessor_id == self.owner_id
+                || predecessor_id == env::current_account_id(),
+            "ERR_NOT_ALLOWED"
+        );
+        assert!(!self.paused, "ERR_PAUSED");
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.assert_owner_or_dao();
+        assert!(self.paused, "ERR_NOT_PAUSED");
+        self.paused = false;
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_transfer_ownership() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.propose_owner(accounts(1));
+        assert_eq!(contract.get_pending_owner(), Some(accounts(1).to_string()));
+        // Owner doesn't change until the proposal is accepted.
+        assert_eq!(contract.get_owner(), owner().to_string());
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.accept_ownership();
+        assert_eq!(contract.get_owner(), accounts(1).to_string());
+        assert!(contract.get_pending_owner().is_none());
+        assert_eq!(
+            get_logs(),
+            vec![
+                "EVENT_JSON:{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"owner_change\",\
+                 \"data\":[{\"old_owner\":\"alice\",\"new_owner\":\"bob\"}]}"
+            ]
+        );
+        contract.add_accepted_token(accounts(3));
+        assert!(contract.get_accepted_tokens().contains(&accounts(3).to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY_OWNER")]
+    fn test_old_owner_loses_access() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.propose_owner(accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.accept_ownership();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.create_campaign("drive".to_string(), U128(100), U64(1_000), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_PROPOSED_OWNER")]
+    fn test_accept_ownership_wrong_account() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.propose_owner(accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.accept_ownership();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NO_OWNER_PROPOSAL")]
+    fn test_cancel_owner_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.propose_owner(accounts(1));
+        contract.cancel_owner_proposal();
+        assert!(contract.get_pending_owner().is_none());
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.accept_ownership();
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY_OWNER")]
+    fn test_propose_owner_not_owner() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.propose_owner(accounts(1));
+    }
+
+    fn delegate_msg() -> String {
+        format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", accounts(1))
+    }
+
+    #[test]
+    fn test_pause_delegation() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.pause();
+        assert!(contract.is_paused());
+        // Transfer is refunded rather than failing.
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, &delegate_msg()), 10);
+        assert_eq!(get_logs(), vec!["ERR_PAUSED"]);
+        assert_eq!(contract.get_user_weight(&accounts(1).to_string()), 0);
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.unpause();
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, &delegate_msg()), 0);
+        assert_eq!(contract.get_user_weight(&accounts(1).to_string()), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PAUSED")]
+    fn test_undelegate_while_paused() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.pause();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(10), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PAUSED")]
+    fn test_add_proposal_while_paused() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.pause();
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PAUSED")]
+    fn test_veto_proposal_while_paused() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.pause();
+        contract.veto_proposal(id, "spam".to_string());
+    }
+
+    #[test]
+    fn test_council_pauses() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.add_council_member(accounts(2));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.pause();
+        assert!(contract.is_paused());
+        // Views stay available.
+        assert_eq!(contract.get_owner(), owner().to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_council_cannot_unpause() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.add_council_member(accounts(2));
+        contract.pause();
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.unpause();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_pause_not_allowed() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.pause();
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PAUSED")]
+    fn test_withdraw_donations_while_paused() {
+        let (mut context, mut contract) = setup_contract();
+        transfer_call(&mut context, &mut contract, accounts(1), 10, "{\"purpose\":\"OpenDonate\"}");
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.pause();
+        contract.withdraw_donations(accounts(2).to_string(), U128(10), "grant".to_string(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PAUSED")]
+    fn test_rescue_tokens_while_paused() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.pause();
+        contract.rescue_tokens(accounts(4), accounts(2), U128(10));
+    }
+}
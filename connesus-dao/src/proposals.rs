@@ -1,10 +1,24 @@
 use crate::*;
 
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub enum ProposalStatus {
     InProgress,
     Expired,
+    // Approve votes reached majority of delegations.
+    Approved,
+    // Reject votes reached majority of delegations.
+    Rejected,
+    // Withdrawn by the proposer.
+    Cancelled,
+    // Blocked by the owner.
+    Vetoed,
+    // Approved, but its action could not be executed.
+    Failed,
+    // Approved and its action was executed.
+    Executed,
+    // Donations reached the target of the proposal.
+    Funded,
 }
 
 // Kinds of proposals, doing different action.
@@ -13,17 +27,112 @@ pub enum ProposalStatus {
 pub enum ProposalKind {
     Donate,
     Vote {vote_kind: VoteKind},
+    // Pays out DAO tokens from the treasury once approved.
+    Transfer { receiver_id: AccountId, amount: U128 },
+    // Calls given contract once approved, actions are executed in a single batch.
+    FunctionCall { receiver_id: AccountId, actions: Vec<ActionCall> },
+    // Replaces metadata of the DAO once approved.
+    ChangeMetadata { metadata: DaoMetadata },
+    // Replaces the vote token once approved and nothing is delegated or locked anymore.
+    ChangeStakingToken { token_id: AccountId },
+    // Multiple choice vote, winning option is recorded at finalization.
+    Poll { options: Vec<String> },
+    // Replaces the policy of the DAO once approved.
+    ChangePolicy { policy: Policy },
+    // Pays out the rejected work of `claimant` on given bounty once approved, see `bounty_dispute`.
+    ResolveBountyDispute { bounty_id: u64, claimant: AccountId },
+    // Creates the bounty once approved, funded out of the treasury that isn't locked or in escrow.
+    AddBounty { bounty: BountyInput },
+}
+
+// Function call executed by `FunctionCall` proposal.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ActionCall {
+    pub method_name: String,
+    pub args: Base64VecU8,
+    pub deposit: U128,
+    pub gas: U64,
+}
+
+// Validates actions of `FunctionCall` proposal against the $NEAR the DAO can spend.
+pub fn assert_valid_action_calls(actions: &[ActionCall], available_amount: Balance) {
+    assert!(!actions.is_empty(), "ERR_NO_ACTIONS");
+    assert!(actions.len() <= MAX_ACTION_CALLS, "ERR_TOO_MANY_ACTIONS");
+    let mut total_gas: Gas = 0;
+    let mut total_deposit: Balance = 0;
+    for action in actions {
+        assert!(!action.method_name.is_empty(), "ERR_EMPTY_METHOD_NAME");
+        assert!(action.gas.0 >= MIN_ACTION_CALL_GAS, "ERR_NOT_ENOUGH_GAS");
+        total_gas += action.gas.0;
+        total_deposit += action.deposit.0;
+    }
+    assert!(total_gas <= MAX_ACTION_CALLS_GAS, "ERR_TOO_MUCH_GAS");
+    assert!(total_deposit <= available_amount, "ERR_NOT_ENOUGH_AVAILABLE_AMOUNT");
 }
 
+// Kind of proposal without its payload, used for filtering proposals.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ProposalKindLabel {
+    Donate,
+    Vote,
+    Transfer,
+    FunctionCall,
+    ChangeMetadata,
+    ChangeStakingToken,
+    Poll,
+    ChangePolicy,
+    ResolveBountyDispute,
+    AddBounty,
+}
+
+// Labels of all proposal kinds, used as keys of the policy overrides.
+pub const PROPOSAL_KIND_LABELS: [&str; 10] = [
+    "donate",
+    "vote",
+    "transfer",
+    "function_call",
+    "change_metadata",
+    "change_staking_token",
+    "poll",
+    "change_policy",
+    "resolve_bounty_dispute",
+    "add_bounty",
+];
+
 impl ProposalKind {
     // Returns label of policy for given type of proposal.
     pub fn to_policy_label(&self) -> &str {
         match self {
             ProposalKind::Donate=> "donate",
             ProposalKind::Vote {..} => "vote",
+            ProposalKind::Transfer {..} => "transfer",
+            ProposalKind::FunctionCall {..} => "function_call",
+            ProposalKind::ChangeMetadata {..} => "change_metadata",
+            ProposalKind::ChangeStakingToken {..} => "change_staking_token",
+            ProposalKind::Poll {..} => "poll",
+            ProposalKind::ChangePolicy {..} => "change_policy",
+            ProposalKind::ResolveBountyDispute {..} => "resolve_bounty_dispute",
+            ProposalKind::AddBounty {..} => "add_bounty",
             // ProposalKind::Funding { threshold, min_amount, max_approved_option } => "funding",
         }
     }
+
+    pub fn to_label(&self) -> ProposalKindLabel {
+        match self {
+            ProposalKind::Donate => ProposalKindLabel::Donate,
+            ProposalKind::Vote {..} => ProposalKindLabel::Vote,
+            ProposalKind::Transfer {..} => ProposalKindLabel::Transfer,
+            ProposalKind::FunctionCall {..} => ProposalKindLabel::FunctionCall,
+            ProposalKind::ChangeMetadata {..} => ProposalKindLabel::ChangeMetadata,
+            ProposalKind::ChangeStakingToken {..} => ProposalKindLabel::ChangeStakingToken,
+            ProposalKind::Poll {..} => ProposalKindLabel::Poll,
+            ProposalKind::ChangePolicy {..} => ProposalKindLabel::ChangePolicy,
+            ProposalKind::ResolveBountyDispute {..} => ProposalKindLabel::ResolveBountyDispute,
+            ProposalKind::AddBounty {..} => ProposalKindLabel::AddBounty,
+        }
+    }
 }
 
 // Votes recorded in the proposal.
@@ -46,11 +155,125 @@ pub struct VoteOption {
 impl From<VersionedProposal> for Proposal {
     fn from(v: VersionedProposal) -> Self {
         match v {
-            VersionedProposal::Default(p) => p,
+            VersionedProposal::Default(p) => p.into(),
+            VersionedProposal::V2(p) => p,
+        }
+    }
+}
+
+// Proposal as it was stored before bonds, snapshots and donor records.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalV1 {
+    pub proposer: AccountId,
+    pub description: String,
+    pub kind: ProposalKind,
+    pub status: ProposalStatus,
+    pub options: HashMap<String, VoteOption>,
+    pub submission_time: U64,
+    pub duration: U64,
+    pub donations: HashMap<AccountId, Balance>,
+    pub total_donations: Balance,
+    pub total_delegation_amount: Balance,
+    pub votes: HashMap<AccountId, Vote>,
+    pub option_delegations: HashMap<String, Balance>,
+}
+
+impl From<ProposalV1> for Proposal {
+    fn from(p: ProposalV1) -> Self {
+        // Time of each donation wasn't recorded, submission time is used instead.
+        let submission_time = p.submission_time;
+        let donations = p
+            .donations
+            .into_iter()
+            .map(|(account_id, amount)| {
+                // Token of the donation is assigned once the proposal is used, see `assign_legacy_donations`.
+                let record = DonorRecord {
+                    amount: U128(amount),
+                    tokens: HashMap::default(),
+                    first_donation_time: submission_time,
+                    last_donation_time: submission_time,
+                };
+                (account_id, record)
+            })
+            .collect();
+        Self {
+            proposer: p.proposer,
+            description: p.description,
+            description_hash: None,
+            kind: p.kind,
+            status: p.status,
+            options: p.options,
+            submission_time: p.submission_time,
+            duration: p.duration,
+            donations,
+            total_donations: p.total_donations,
+            total_delegation_amount: p.total_delegation_amount,
+            votes: p.votes,
+            option_delegations: p.option_delegations,
+            total_delegation_snapshot: 0,
+            voter_weights: HashMap::default(),
+            bond: 0,
+            poll_winner: None,
+            target: None,
+            deadline: None,
+            extension_count: 0,
+            extended_period: U64(0),
+            milestones: vec![],
+            beneficiary: None,
+            activity: vec![],
+            tags: vec![],
+            accepted_tokens: vec![],
+            token_donations: HashMap::default(),
         }
     }
 }
 
+// Donations of a single account to `Donate` proposal.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DonorRecord {
+    // Total of the donations, in all tokens.
+    pub amount: U128,
+    // Donations per token.
+    pub tokens: HashMap<AccountId, U128>,
+    pub first_donation_time: U64,
+    pub last_donation_time: U64,
+}
+
+// Part of the donations of `Donate` proposal, paid out to the beneficiary once released by the owner.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Milestone {
+    pub amount: U128,
+    pub description: String,
+    // Token the milestone is paid in, first accepted token of the proposal if none.
+    #[serde(default)]
+    pub token: Option<AccountId>,
+    #[serde(default)]
+    pub paid: bool,
+}
+
+// Things that happened to a proposal, recorded in its activity log.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ProposalAction {
+    Create,
+    Vote { option: String, weight: U128 },
+    Unvote { option: String },
+    Veto,
+    Finalize { status: ProposalStatus },
+    Donate { token: AccountId, amount: U128 },
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalEvent {
+    pub action: ProposalAction,
+    pub actor: AccountId,
+    pub timestamp: U64,
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Vote {
@@ -66,6 +289,8 @@ pub struct Proposal {
     pub proposer: AccountId,
     // Description of this proposal.
     pub description: String,
+    // Hash of the description stored off-chain, description is empty when set.
+    pub description_hash: Option<Base58CryptoHash>,
     // Kind of proposal with relevant information.
     pub kind: ProposalKind,
     // Current status of the proposal.
@@ -77,18 +302,57 @@ pub struct Proposal {
     pub submission_time: U64,
     pub duration: U64,
 
-    pub donations: HashMap<AccountId, Balance>,
+    pub donations: HashMap<AccountId, DonorRecord>,
     pub total_donations: Balance,
 
     pub total_delegation_amount: Balance,
     pub votes: HashMap<AccountId, Vote>,
-    pub option_delegations: HashMap<String, Balance>
+    pub option_delegations: HashMap<String, Balance>,
+
+    // Total delegation of the DAO at submission time.
+    pub total_delegation_snapshot: Balance,
+    // Voting weight of each voter, taken at their first vote.
+    pub voter_weights: HashMap<AccountId, Balance>,
+
+    // Amount of $NEAR attached by the proposer, until it is refunded or slashed.
+    pub bond: Balance,
+
+    // Index of the option that won the poll, none if there was a tie.
+    pub poll_winner: Option<u32>,
+
+    // Amount of tokens `Donate` proposal is raising, uncapped if none.
+    pub target: Option<Balance>,
+    // Time after which `Donate` proposal doesn't accept donations anymore.
+    pub deadline: Option<U64>,
+
+    // Number of times and total time the proposer extended the voting period by.
+    pub extension_count: u32,
+    pub extended_period: U64,
+
+    // Milestones donations of `Donate` proposal are released in, to the beneficiary.
+    pub milestones: Vec<Milestone>,
+    // Receiver of the milestones, proposer if none.
+    pub beneficiary: Option<AccountId>,
+
+    // Latest `MAX_PROPOSAL_ACTIVITY` events of this proposal, oldest first.
+    pub activity: Vec<ProposalEvent>,
+
+    // Categories of the proposal, indexed in `proposals_by_tag`.
+    pub tags: Vec<String>,
+
+    // Tokens `Donate` proposal accepts, target counts donations in all of them.
+    pub accepted_tokens: Vec<AccountId>,
+    // Total donations per token.
+    pub token_donations: HashMap<AccountId, Balance>,
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
+// New proposals are stored as V2, V1 ones are only read to be upgraded.
+#[allow(clippy::large_enum_variant)]
 pub enum VersionedProposal {
-    Default(Proposal),
+    Default(ProposalV1),
+    V2(Proposal),
 }
 
 impl Proposal {
@@ -98,7 +362,7 @@ impl Proposal {
 
     // add total delegations, add vote, add vote delegations
     pub fn add_vote(&mut self, account_id: &AccountId, option_id: &String, vote_delegation: Balance, kind: &VoteKind) -> Self {
-        assert!(self.options.get(option_id).is_some(), "INVALID_OPTION_ID");
+        assert!(self.options.contains_key(option_id), "INVALID_OPTION_ID");
         match kind {
             VoteKind::VoteByDelegation => {
                 let vote = Vote {
@@ -145,14 +409,135 @@ impl Proposal {
         }
     }
 
-    pub fn donate(&mut self, account_id: &AccountId, amount: Balance) -> Self {
-        let prev_amount = self.donations.get(&account_id.to_string()).unwrap_or(&0).clone();
-        let new_amount = 0 + amount;
-        self.donations.insert(account_id.to_string(), new_amount);
+    pub fn donate(&mut self, account_id: &AccountId, token_id: &AccountId, amount: Balance) -> Self {
+        let now = U64::from(env::block_timestamp());
+        let record = self.donations.entry(account_id.to_string()).or_insert(DonorRecord {
+            amount: U128(0),
+            tokens: HashMap::default(),
+            first_donation_time: now,
+            last_donation_time: now,
+        });
+        record.amount = U128(record.amount.0 + amount);
+        let token_amount = record.tokens.entry(token_id.clone()).or_insert(U128(0));
+        token_amount.0 += amount;
+        record.last_donation_time = now;
         self.total_donations += amount;
+        *self.token_donations.entry(token_id.clone()).or_insert(0) += amount;
+        if self.target.is_some_and(|target| self.total_donations >= target) {
+            self.status = ProposalStatus::Funded;
+        }
         self.clone()
     } 
 
+    // Takes the donation of given account in given token out of the proposal, returns the part taken.
+    pub fn withdraw_donation(&mut self, account_id: &AccountId, token_id: &AccountId) -> DonorRecord {
+        let mut record = self.donations.remove(account_id).expect("ERR_NO_DONATION");
+        let amount = record.tokens.remove(token_id).expect("ERR_NO_DONATION");
+        record.amount = U128(record.amount.0 - amount.0);
+        let withdrawn = DonorRecord {
+            amount,
+            tokens: vec![(token_id.clone(), amount)].into_iter().collect(),
+            first_donation_time: record.first_donation_time,
+            last_donation_time: record.last_donation_time,
+        };
+        if !record.tokens.is_empty() {
+            self.donations.insert(account_id.clone(), record);
+        }
+        self.total_donations -= amount.0;
+        *self.token_donations.get_mut(token_id).expect("ERR_NO_DONATION") -= amount.0;
+        withdrawn
+    }
+
+    // Puts back donation taken out by `withdraw_donation`.
+    pub fn restore_donation(&mut self, account_id: &AccountId, withdrawn: DonorRecord) {
+        let record = self.donations.entry(account_id.clone()).or_insert(DonorRecord {
+            amount: U128(0),
+            tokens: HashMap::default(),
+            first_donation_time: withdrawn.first_donation_time,
+            last_donation_time: withdrawn.last_donation_time,
+        });
+        record.amount = U128(record.amount.0 + withdrawn.amount.0);
+        record.first_donation_time = std::cmp::min(record.first_donation_time.0, withdrawn.first_donation_time.0).into();
+        record.last_donation_time = std::cmp::max(record.last_donation_time.0, withdrawn.last_donation_time.0).into();
+        for (token_id, amount) in withdrawn.tokens {
+            record.tokens.entry(token_id.clone()).or_insert(U128(0)).0 += amount.0;
+            *self.token_donations.entry(token_id).or_insert(0) += amount.0;
+        }
+        self.total_donations += withdrawn.amount.0;
+    }
+
+    // Proposals from before multi-token donations only took the DAO token.
+    pub fn assign_legacy_donations(&mut self, dao_token: &AccountId) {
+        if self.kind != ProposalKind::Donate || !self.accepted_tokens.is_empty() {
+            return;
+        }
+        self.accepted_tokens = vec![dao_token.clone()];
+        if self.total_donations > 0 {
+            for record in self.donations.values_mut() {
+                record.tokens.insert(dao_token.clone(), record.amount);
+            }
+            self.token_donations.insert(dao_token.clone(), self.total_donations);
+        }
+    }
+
+    // Token milestone with given index is paid in.
+    pub fn get_milestone_token(&self, index: usize) -> AccountId {
+        self.milestones[index]
+            .token
+            .clone()
+            .unwrap_or_else(|| self.accepted_tokens[0].clone())
+    }
+
+    // Returns part of given donation that still fits under the target.
+    pub fn acceptable_donation(&self, amount: Balance) -> Balance {
+        match self.target {
+            Some(target) => std::cmp::min(amount, target.saturating_sub(self.total_donations)),
+            None => amount,
+        }
+    }
+
+    pub fn is_deadline_passed(&self) -> bool {
+        self.deadline.is_some_and(|deadline| env::block_timestamp() >= deadline.0)
+    }
+
+    // Appends event to the activity log, dropping the oldest one once the log is full.
+    pub fn record_activity(&mut self, action: ProposalAction, actor: &AccountId) {
+        if self.activity.len() >= MAX_PROPOSAL_ACTIVITY {
+            self.activity.remove(0);
+        }
+        self.activity.push(ProposalEvent {
+            action,
+            actor: actor.clone(),
+            timestamp: U64(env::block_timestamp()),
+        });
+    }
+
+    // Total amount of the milestones paid out so far, in all tokens.
+    pub fn get_released_amount(&self) -> Balance {
+        self.milestones
+            .iter()
+            .filter(|milestone| milestone.paid)
+            .map(|milestone| milestone.amount.0)
+            .sum()
+    }
+
+    // Amount of the milestones paid out so far in given token.
+    pub fn get_released_token_amount(&self, token_id: &AccountId) -> Balance {
+        (0..self.milestones.len())
+            .filter(|&index| self.milestones[index].paid && &self.get_milestone_token(index) == token_id)
+            .map(|index| self.milestones[index].amount.0)
+            .sum()
+    }
+
+    // Donations can be taken back once the proposal can't be funded anymore.
+    pub fn is_donation_refundable(&self) -> bool {
+        match self.get_status() {
+            ProposalStatus::Expired | ProposalStatus::Vetoed | ProposalStatus::Failed => true,
+            ProposalStatus::InProgress => self.is_deadline_passed(),
+            _ => false,
+        }
+    }
+
     // Adds vote of the given user with given `amount` of weight. If user already voted, fails.
     pub fn update_votes(
         &mut self,
@@ -161,17 +546,126 @@ impl Proposal {
         delegation_amount: Balance 
     ) -> Self {
         let proposal_kind = self.kind.clone();
-        let mut new_proposal = self.clone();
         match proposal_kind {
             ProposalKind::Vote { vote_kind } => {
-                if self.votes.get(account_id).is_some() {
+                if self.votes.contains_key(account_id) {
                     self.remove_vote(account_id, &vote_kind);
                 };
-                new_proposal = self.add_vote(account_id, option_id, delegation_amount, &vote_kind);
+                self.add_vote(account_id, option_id, delegation_amount, &vote_kind)
             },
             _ => unreachable!(),
+        }
+    }
+
+    // Removes vote of the given user from the tallies and returns it, fails if user didn't vote.
+    pub fn retract_vote(&mut self, account_id: &AccountId) -> Vote {
+        let vote = self.votes.remove(account_id).expect("ERR_DID_NOT_VOTED");
+        self.total_delegation_amount -= vote.delegations;
+        let option_prev_delegation_amount = self.option_delegations.get(&vote.option).unwrap_or(&0);
+        let option_new_delegation_amount = option_prev_delegation_amount - vote.delegations;
+        self.option_delegations.insert(vote.option.clone(), option_new_delegation_amount);
+        vote
+    }
+
+    // Adds approve / reject vote of the given user with given weight. If user already voted, fails.
+    pub fn add_approval_vote(&mut self, account_id: &AccountId, approve: bool, weight: Balance) {
+        let option = if approve { VOTE_APPROVE } else { VOTE_REJECT };
+        self.add_option_vote(account_id, option, weight);
+    }
+
+    // Adds vote of the given user for the poll option with given index.
+    pub fn add_poll_vote(&mut self, account_id: &AccountId, option_index: u32, weight: Balance) {
+        match &self.kind {
+            ProposalKind::Poll { options } => {
+                assert!((option_index as usize) < options.len(), "ERR_INVALID_OPTION_INDEX");
+            },
+            _ => env::panic(b"ERR_WRONG_ACTION_FOR_PROPOSAL_KIND"),
+        }
+        self.add_option_vote(account_id, &option_index.to_string(), weight);
+    }
+
+    // Returns vote weight of each poll option.
+    pub fn get_poll_tallies(&self) -> Vec<Balance> {
+        match &self.kind {
+            ProposalKind::Poll { options } => (0..options.len())
+                .map(|index| *self.option_delegations.get(&index.to_string()).unwrap_or(&0))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    // Records the poll option with the highest weight, ties have no winner.
+    pub fn update_poll_winner(&mut self) {
+        let tallies = self.get_poll_tallies();
+        let max_weight = tallies.iter().max().cloned().unwrap_or(0);
+        let winners: Vec<usize> = (0..tallies.len()).filter(|index| tallies[*index] == max_weight).collect();
+        self.poll_winner = if max_weight > 0 && winners.len() == 1 {
+            Some(winners[0] as u32)
+        } else {
+            None
+        };
+    }
+
+    fn add_option_vote(&mut self, account_id: &AccountId, option: &str, weight: Balance) {
+        let vote = Vote {
+            option: option.to_string(),
+            delegations: weight
         };
-        new_proposal
+        assert!(self.votes.insert(account_id.to_string(), vote).is_none(), "ERR_ALREADY_VOTED");
+        self.total_delegation_amount += weight;
+        let option_prev_delegation_amount = self.option_delegations.get(option).unwrap_or(&0);
+        let option_new_delegation_amount = option_prev_delegation_amount + weight;
+        self.option_delegations.insert(option.to_string(), option_new_delegation_amount);
+    }
+
+    // Returns voting weight of given user, snapshotting `current_weight` on the first vote.
+    pub fn snapshot_voter_weight(&mut self, account_id: &AccountId, current_weight: Option<Balance>) -> Balance {
+        if let Some(weight) = self.voter_weights.get(account_id) {
+            return *weight;
+        }
+        let weight = current_weight.expect("USER_NOT_REGISTERED");
+        assert!(weight > 0, "USER_ZERO_DELEGATION");
+        self.voter_weights.insert(account_id.to_string(), weight);
+        weight
+    }
+
+    // Moves proposal to approved / rejected once the outcome can't change anymore.
+    pub fn update_approval_status(&mut self, policy: &VotePolicy) {
+        let approve_amount = *self.option_delegations.get(VOTE_APPROVE).unwrap_or(&0);
+        let reject_amount = *self.option_delegations.get(VOTE_REJECT).unwrap_or(&0);
+        if policy.is_approval_decided(approve_amount, self.total_delegation_snapshot) {
+            self.update_status(ProposalStatus::Approved);
+        } else if policy.is_rejection_decided(reject_amount, self.total_delegation_snapshot) {
+            self.update_status(ProposalStatus::Rejected);
+        }
+    }
+
+    // Returns final status of approve / reject voting once voting is closed.
+    pub fn final_approval_status(&self, policy: &VotePolicy) -> ProposalStatus {
+        let approve_amount = *self.option_delegations.get(VOTE_APPROVE).unwrap_or(&0);
+        let votes_cast = self.total_delegation_amount;
+        if policy.is_approved(approve_amount, votes_cast, self.total_delegation_snapshot) {
+            ProposalStatus::Approved
+        } else if votes_cast > 0 && policy.is_approved(votes_cast, votes_cast, self.total_delegation_snapshot) {
+            // Quorum was reached, but not enough of the votes approve.
+            ProposalStatus::Rejected
+        } else {
+            ProposalStatus::Expired
+        }
+    }
+
+    // Returns true once voting period of this proposal is over.
+    pub fn is_expired(&self) -> bool {
+        env::block_timestamp() >= self.submission_time.0 + self.duration.0
+    }
+
+    // Returns status of the proposal, taking the voting period into account.
+    pub fn get_status(&self) -> ProposalStatus {
+        if self.status == ProposalStatus::InProgress && self.is_expired() {
+            ProposalStatus::Expired
+        } else {
+            self.status.clone()
+        }
     }
 
     pub fn update_status(&mut self, status: ProposalStatus) {
@@ -183,100 +677,2442 @@ impl Proposal {
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct ProposalInput {
-    // Description of this proposal.
+    // Description of this proposal, can be left out when `description_hash` is given.
+    #[serde(default)]
     pub description: String,
+    // Hash of the description stored off-chain, for texts longer than `MAX_PROPOSAL_DESCRIPTION_LENGTH`.
+    pub description_hash: Option<Base58CryptoHash>,
     // Kind of proposal with relevant information.
     pub kind: ProposalKind,
-    pub duration: U64,
+    // Voting period, in nanoseconds. Defaults to `proposal_period` of the policy.
+    pub duration: Option<U64>,
     pub options: HashMap<String, VoteOption>,
+    // Funding target and deadline, only for `Donate` proposals.
+    pub target: Option<U128>,
+    pub deadline: Option<U64>,
+    // Milestones and their receiver, only for `Donate` proposals.
+    #[serde(default)]
+    pub milestones: Vec<Milestone>,
+    pub beneficiary: Option<AccountId>,
+    // Tokens accepted by `Donate` proposal, the DAO token if empty.
+    #[serde(default)]
+    pub accepted_tokens: Vec<AccountId>,
+    // Categories like "dev" or "grants", at most `MAX_PROPOSAL_TAGS`.
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl From<ProposalInput> for Proposal {
     fn from(input: ProposalInput) -> Self {
-        assert!(input.duration.0 > 1000000000 * 60 * 2, "DURATION_MIN_ERROR");
-        match input.kind {
-            ProposalKind::Donate => {
-                Self {
-                    proposer: env::predecessor_account_id(),
-                    description: input.description,
-                    kind: input.kind,
-                    options: HashMap::default(),
-                    status: ProposalStatus::InProgress,
-                    submission_time: U64::from(env::block_timestamp()),
-                    donations: HashMap::default(),
-                    total_delegation_amount: 0,
-                    total_donations: 0,
-                    votes: HashMap::default(),
-                    option_delegations: HashMap::default(),
-                    duration: input.duration
+        let duration = input.duration.expect("ERR_NO_DURATION");
+        assert!(duration.0 > 1000000000 * 60 * 2, "DURATION_MIN_ERROR");
+        let options = match input.kind {
+            ProposalKind::Vote { .. } => input.options,
+            _ => HashMap::default(),
+        };
+        Self {
+            proposer: env::predecessor_account_id(),
+            description: input.description,
+            description_hash: input.description_hash,
+            kind: input.kind,
+            options,
+            status: ProposalStatus::InProgress,
+            submission_time: U64::from(env::block_timestamp()),
+            donations: HashMap::default(),
+            total_delegation_amount: 0,
+            total_donations: 0,
+            votes: HashMap::default(),
+            option_delegations: HashMap::default(),
+            duration,
+            total_delegation_snapshot: 0,
+            voter_weights: HashMap::default(),
+            bond: 0,
+            poll_winner: None,
+            target: input.target.map(|target| target.0),
+            deadline: input.deadline,
+            extension_count: 0,
+            extended_period: U64(0),
+            milestones: input.milestones,
+            beneficiary: input.beneficiary,
+            activity: vec![],
+            tags: input.tags,
+            accepted_tokens: input.accepted_tokens,
+            token_donations: HashMap::default(),
+        }
+        
+    }
+}
+
+#[ext_contract(ext_self)]
+pub trait ProposalCallbacks {
+    fn on_treasury_balance(&mut self, proposal_id: u64);
+    fn on_bounty_treasury_balance(&mut self, proposal_id: u64);
+    fn on_proposal_executed(&mut self, proposal_id: u64);
+    fn on_donation_refund(&mut self, proposal_id: u64, account_id: AccountId, record: DonorRecord);
+    fn on_milestone_released(&mut self, proposal_id: u64, index: u32);
+}
+
+impl Contract {
+    // Executes action of the proposal that was just approved.
+    // Actions done in place mark the proposal executed, promises record their result in a callback.
+    pub(crate) fn internal_execute_proposal(&mut self, id: u64, proposal: &mut Proposal) {
+        match &proposal.kind {
+            ProposalKind::Transfer { .. } => {
+                // Treasury balance is checked before paying out.
+                ext_fungible_token::ft_balance_of(
+                    env::current_account_id(),
+                    &self.token_account,
+                    0,
+                    GAS_FOR_FT_BALANCE_OF,
+                )
+                .then(ext_self::on_treasury_balance(
+                    id,
+                    &env::current_account_id(),
+                    0,
+                    GAS_FOR_TREASURY_TRANSFER,
+                ));
+            },
+            ProposalKind::FunctionCall { receiver_id, actions } => {
+                let mut promise = Promise::new(receiver_id.clone());
+                for action in actions {
+                    promise = promise.function_call(
+                        action.method_name.as_bytes().to_vec(),
+                        action.args.clone().into(),
+                        action.deposit.0,
+                        action.gas.0,
+                    );
                 }
-            }
-            ProposalKind::Vote { .. } => {
-                Self {
-                    proposer: env::predecessor_account_id(),
-                    description: input.description,
-                    kind: input.kind,
-                    options: input.options,
-                    status: ProposalStatus::InProgress,
-                    submission_time: U64::from(env::block_timestamp()),
-                    donations: HashMap::default(),
-                    total_delegation_amount: 0,
-                    total_donations: 0,
-                    votes: HashMap::default(),
-                    option_delegations: HashMap::default(),
-                    duration: input.duration
+                promise.then(ext_self::on_proposal_executed(
+                    id,
+                    &env::current_account_id(),
+                    0,
+                    GAS_FOR_EXECUTION_CALLBACK,
+                ));
+            },
+            ProposalKind::ChangeMetadata { metadata } => {
+                let old_metadata = std::mem::replace(&mut self.dao_metadata, metadata.clone());
+                env::log(
+                    format!("Old metadata: {}", near_sdk::serde_json::to_string(&old_metadata).unwrap())
+                        .as_bytes(),
+                );
+                proposal.update_status(ProposalStatus::Executed);
+            },
+            ProposalKind::ChangePolicy { policy } => {
+                self.policy = policy.clone();
+                proposal.update_status(ProposalStatus::Executed);
+            },
+            ProposalKind::ChangeStakingToken { token_id } => {
+                // Delegations in the old token would be stranded, proposal stays approved until
+                // everyone undelegates and it's executed again.
+                if self.total_delegation_amount == 0 && self.locked_amount == 0 {
+                    // Delegations already made in the new token start to vote.
+                    self.internal_change_staking_token(token_id);
+                    proposal.update_status(ProposalStatus::Executed);
+                } else {
+                    env::log(format!("Proposal {} not executed: ERR_DELEGATIONS_EXIST", id).as_bytes());
                 }
             },
-        } 
-        
+            ProposalKind::ResolveBountyDispute { bounty_id, claimant } => {
+                if self.internal_resolve_bounty_dispute(*bounty_id, claimant) {
+                    proposal.update_status(ProposalStatus::Executed);
+                } else {
+                    // Bounty was completed or cancelled in the meantime, the rejection stands.
+                    proposal.update_status(ProposalStatus::Failed);
+                    env::log(format!("Proposal {} failed: ERR_BOUNTY_NOT_OPEN", id).as_bytes());
+                }
+            },
+            ProposalKind::AddBounty { bounty } => {
+                // Treasury balance of the bounty token is checked before it's put in escrow.
+                ext_fungible_token::ft_balance_of(
+                    env::current_account_id(),
+                    &bounty.token,
+                    0,
+                    GAS_FOR_FT_BALANCE_OF,
+                )
+                .then(ext_self::on_bounty_treasury_balance(
+                    id,
+                    &env::current_account_id(),
+                    0,
+                    GAS_FOR_EXECUTION_CALLBACK,
+                ));
+            },
+            _ => {},
+        }
+    }
+
+    // Settles proposal that is still in progress from its recorded votes: executes it if approved
+    // and returns the bond. Does nothing for settled proposals.
+    pub(crate) fn internal_finalize_proposal(&mut self, id: u64, proposal: &mut Proposal) {
+        if proposal.status != ProposalStatus::InProgress {
+            return;
+        }
+        let final_status = match proposal.kind {
+            ProposalKind::Vote { .. } | ProposalKind::Poll { .. } => ProposalStatus::Expired,
+            _ => proposal.final_approval_status(&self.policy.vote_policy(Some(proposal.kind.to_policy_label()))),
+        };
+        self.internal_close_proposal(id, proposal, final_status);
+    }
+
+    // Closes proposal that is in progress with given status, see `internal_finalize_proposal`.
+    fn internal_close_proposal(&mut self, id: u64, proposal: &mut Proposal, final_status: ProposalStatus) {
+        proposal.update_status(final_status);
+        if matches!(proposal.kind, ProposalKind::Poll { .. }) {
+            proposal.update_poll_winner();
+        }
+        proposal.record_activity(
+            ProposalAction::Finalize { status: proposal.status.clone() },
+            &env::predecessor_account_id(),
+        );
+        DaoEvent::ProposalFinalize(vec![ProposalFinalizeData {
+            proposal_id: id,
+            actor: env::predecessor_account_id(),
+            status: proposal.status.clone(),
+            total_votes: U128(proposal.total_delegation_amount),
+        }])
+        .emit();
+        self.internal_refund_bond(proposal);
+        self.internal_remove_active_proposal(&proposal.proposer);
+        if proposal.status == ProposalStatus::Approved {
+            self.internal_execute_proposal(id, proposal);
+        } else {
+            self.internal_close_bounty_dispute(&proposal.kind, proposal.status == ProposalStatus::Rejected);
+        }
+    }
+
+    // Panics if the policy asks proposers to delegate and given one didn't delegate enough.
+    fn internal_assert_proposer_delegation(&self, proposer: &AccountId) {
+        if !self.policy.proposers_must_delegate {
+            return;
+        }
+        let delegation = self.get_user_weight(proposer);
+        assert!(
+            delegation > 0 && delegation >= self.policy.min_proposer_delegation.0,
+            "ERR_NOT_ENOUGH_DELEGATION"
+        );
+    }
+
+    // Validates proposal against the current state of the DAO before it's added.
+    fn internal_validate_proposal_input(&self, input: &ProposalInput) {
+        // Available $NEAR only bounds deposits of function calls.
+        let available_amount = match input.kind {
+            ProposalKind::FunctionCall { .. } => self.get_available_amount().0,
+            _ => 0,
+        };
+        input.validate(&self.token_account, available_amount);
+    }
+
+    // Adds already validated proposal with given bond, locking the bond.
+    pub(crate) fn internal_add_proposal(&mut self, proposal_input: ProposalInput, bond: Balance) -> u64 {
+        let proposal_input = ProposalInput {
+            duration: Some(proposal_input.duration.unwrap_or(
+                self.policy.vote_policy(Some(proposal_input.kind.to_policy_label())).proposal_period
+            )),
+            ..proposal_input
+        };
+        let mut proposal = Proposal::from(proposal_input);
+        proposal.total_delegation_snapshot = self.total_delegation_amount + self.total_delegation_bonus;
+        if proposal.kind == ProposalKind::Donate && proposal.accepted_tokens.is_empty() {
+            proposal.accepted_tokens = vec![self.token_account.clone()];
+        }
+        self.internal_add_active_proposal(&proposal.proposer);
+        proposal.record_activity(ProposalAction::Create, &proposal.proposer.clone());
+        proposal.bond = bond;
+        self.locked_near += bond;
+
+        let id = self.last_proposal_id;
+        DaoEvent::ProposalCreate(vec![ProposalCreateData {
+            proposal_id: id,
+            proposer: proposal.proposer.clone(),
+            kind: proposal.kind.to_policy_label().to_string(),
+            bond: U128(bond),
+        }])
+        .emit();
+        for tag in proposal.tags.iter() {
+            let mut ids = self.proposals_by_tag.get(tag).unwrap_or_default();
+            ids.push(id);
+            self.proposals_by_tag.insert(tag, &ids);
+        }
+        self.proposals.insert(&id, &VersionedProposal::V2(proposal));
+        self.last_proposal_id += 1;
+        id
+    }
+
+    // Counts new proposal of given proposer as in progress, up to the limit of the policy.
+    fn internal_add_active_proposal(&mut self, proposer: &AccountId) {
+        let active = self.active_proposals.get(proposer).unwrap_or(0);
+        assert!(active < self.policy.max_active_proposals, "ERR_TOO_MANY_ACTIVE_PROPOSALS");
+        self.active_proposals.insert(proposer, &(active + 1));
+    }
+
+    // Frees slot of given proposer once their proposal is not in progress anymore.
+    pub(crate) fn internal_remove_active_proposal(&mut self, proposer: &AccountId) {
+        match self.active_proposals.get(proposer).unwrap_or(0) {
+            0 | 1 => {
+                self.active_proposals.remove(proposer);
+            },
+            active => {
+                self.active_proposals.insert(proposer, &(active - 1));
+            },
+        }
+    }
+
+    pub(crate) fn internal_add_proposal_donations(&mut self, token_id: &AccountId, amount: Balance) {
+        let total = self.proposal_donation_totals.get(token_id).unwrap_or_default();
+        self.proposal_donation_totals.insert(token_id, &(total + amount));
+    }
+
+    // Donations made before the totals were kept aren't part of them.
+    fn internal_take_proposal_donations(&mut self, token_id: &AccountId, amount: Balance) {
+        match self.proposal_donation_totals.get(token_id).unwrap_or_default().saturating_sub(amount) {
+            0 => self.proposal_donation_totals.remove(token_id),
+            total => self.proposal_donation_totals.insert(token_id, &total),
+        };
+    }
+
+    // Marks approved proposal as failed.
+    fn internal_fail_proposal(&mut self, id: u64, reason: &str) {
+        let mut proposal: Proposal = self.proposals.get(&id).expect("ERR_NO_PROPOSAL").into();
+        proposal.update_status(ProposalStatus::Failed);
+        self.proposals.insert(&id, &VersionedProposal::V2(proposal));
+        env::log(format!("Proposal {} failed: {}", id, reason).as_bytes());
+    }
+
+    // Returns bond of given proposal back to the proposer.
+    pub(crate) fn internal_refund_bond(&mut self, proposal: &mut Proposal) {
+        if proposal.bond > 0 {
+            self.locked_near -= proposal.bond;
+            Promise::new(proposal.proposer.clone()).transfer(proposal.bond);
+            proposal.bond = 0;
+        }
+    }
+
+    // Keeps bond of given proposal in the DAO.
+    pub(crate) fn internal_slash_bond(&mut self, proposal: &mut Proposal) {
+        self.locked_near -= proposal.bond;
+        proposal.bond = 0;
     }
 }
 
 #[near_bindgen]
 impl Contract {
     // Add proposal to this DAO.
+    #[payable]
     pub fn add_proposal(&mut self, proposal_input: ProposalInput) -> u64 {
+        self.assert_not_paused();
+        self.assert_permission(proposal_input.kind.to_policy_label(), PermissionAction::AddProposal);
+        self.internal_assert_proposer_delegation(&env::predecessor_account_id());
+        self.internal_validate_proposal_input(&proposal_input);
+        let bond = env::attached_deposit();
+        assert!(bond >= self.policy.proposal_bond.0, "ERR_MIN_BOND");
+        self.internal_add_proposal(proposal_input, bond)
+    }
 
-        assert_eq!(
-            self.owner_id, 
-            env::predecessor_account_id(),
-            "ONLY_OWNER"
+    // Adds all given proposals or none of them, attached deposit is split between their bonds.
+    #[payable]
+    pub fn add_proposals(&mut self, inputs: Vec<ProposalInput>) -> Vec<u64> {
+        self.assert_not_paused();
+        assert!(!inputs.is_empty() && inputs.len() <= MAX_BATCH_PROPOSALS, "ERR_INVALID_BATCH_SIZE");
+        for input in inputs.iter() {
+            self.assert_permission(input.kind.to_policy_label(), PermissionAction::AddProposal);
+        }
+        self.internal_assert_proposer_delegation(&env::predecessor_account_id());
+        for input in inputs.iter() {
+            self.internal_validate_proposal_input(input);
+        }
+        let count = inputs.len() as Balance;
+        let deposit = env::attached_deposit();
+        assert!(deposit >= self.policy.proposal_bond.0 * count, "ERR_MIN_BOND");
+        let active = self.active_proposals.get(&env::predecessor_account_id()).unwrap_or(0);
+        assert!(
+            active + inputs.len() as u64 <= self.policy.max_active_proposals,
+            "ERR_TOO_MANY_ACTIVE_PROPOSALS"
         );
-        // 1. Validate proposal.
-        let  proposal = Proposal::from(proposal_input);
+        // Leftover of the split is added to the bond of the last proposal.
+        let bond = deposit / count;
+        let last_index = inputs.len() - 1;
+        inputs
+            .into_iter()
+            .enumerate()
+            .map(|(index, input)| {
+                let extra = if index == last_index { deposit % count } else { 0 };
+                self.internal_add_proposal(input, bond + extra)
+            })
+            .collect()
+    }
 
-        // 3. Actually add proposal to the current list of proposals.
-        let id = self.last_proposal_id;
-        self.proposals
-            .insert(&id, &VersionedProposal::Default(proposal.into()));
-        self.last_proposal_id += 1;
-        id
+    // Cancels proposal created by the caller, while it's in progress and has no donations.
+    pub fn cancel_proposal(&mut self, id: u64) {
+        self.assert_not_paused();
+        let mut proposal: Proposal = self.proposals.get(&id).expect("ERR_NO_PROPOSAL").into();
+        assert_eq!(proposal.proposer, env::predecessor_account_id(), "ONLY_PROPOSER");
+        assert_eq!(proposal.get_status(), ProposalStatus::InProgress, "ERR_PROPOSAL_NOT_IN_PROGRESS");
+        assert_eq!(proposal.total_donations, 0, "ERR_PROPOSAL_HAS_DONATIONS");
+        assert_eq!(self.proposal_near_donations.get(&id).unwrap_or_default(), 0, "ERR_PROPOSAL_HAS_DONATIONS");
+        proposal.update_status(ProposalStatus::Cancelled);
+        self.internal_refund_bond(&mut proposal);
+        self.internal_remove_active_proposal(&proposal.proposer);
+        self.internal_close_bounty_dispute(&proposal.kind, false);
+        self.proposals.insert(&id, &VersionedProposal::V2(proposal));
     }
 
-    pub fn act_proposal(&mut self, id: u64, action: Action) {
-        let account_id = env::predecessor_account_id();
+    // Blocks given proposal from further votes and donations, bond is kept by the DAO.
+    pub fn veto_proposal(&mut self, id: u64, reason: String) {
+        self.assert_not_paused();
+        let mut proposal: Proposal = self.proposals.get(&id).expect("ERR_NO_PROPOSAL").into();
+        self.assert_permission(proposal.kind.to_policy_label(), PermissionAction::Veto);
+        assert_eq!(proposal.get_status(), ProposalStatus::InProgress, "ERR_PROPOSAL_NOT_IN_PROGRESS");
+        proposal.update_status(ProposalStatus::Vetoed);
+        proposal.record_activity(ProposalAction::Veto, &env::predecessor_account_id());
+        DaoEvent::ProposalVeto(vec![ProposalVetoData {
+            proposal_id: id,
+            actor: env::predecessor_account_id(),
+            reason,
+            slashed_bond: U128(proposal.bond),
+        }])
+        .emit();
+        self.internal_slash_bond(&mut proposal);
+        self.internal_remove_active_proposal(&proposal.proposer);
+        self.internal_close_bounty_dispute(&proposal.kind, true);
+        self.proposals.insert(&id, &VersionedProposal::V2(proposal));
+    }
+
+    // Adds given time to the voting period of the proposal created by the caller, while it's in progress.
+    pub fn extend_proposal(&mut self, id: u64, extra_period: U64) {
+        self.assert_not_paused();
+        let mut proposal: Proposal = self.proposals.get(&id).expect("ERR_NO_PROPOSAL").into();
+        assert_eq!(proposal.proposer, env::predecessor_account_id(), "ONLY_PROPOSER");
+        assert_eq!(proposal.get_status(), ProposalStatus::InProgress, "ERR_PROPOSAL_NOT_IN_PROGRESS");
+        assert!(extra_period.0 > 0, "ERR_INVALID_EXTENSION");
+        assert!(proposal.extension_count < MAX_PROPOSAL_EXTENSIONS, "ERR_TOO_MANY_EXTENSIONS");
+        let extended_period = proposal.extended_period.0 + extra_period.0;
+        assert!(extended_period <= self.policy.max_extension_period.0, "ERR_EXTENSION_TOO_LONG");
+        proposal.extension_count += 1;
+        proposal.extended_period = U64(extended_period);
+        proposal.duration = U64(proposal.duration.0 + extra_period.0);
+        self.proposals.insert(&id, &VersionedProposal::V2(proposal));
+    }
+
+    // Settles proposal after its voting period is over, can be called by anyone.
+    pub fn finalize_proposal(&mut self, id: u64) {
+        self.assert_not_paused();
         let mut proposal: Proposal = self.proposals.get(&id).expect("ERR_NO_PROPOSAL").into();
-        let user_delegate = self.delegations.get(&account_id).expect("USER_NOT_REGISTERED");
-        assert!(user_delegate > 0, "USER_ZERO_DELEGATION");
-        {
-            let proposal_end_time_stamp = proposal.submission_time.0 + proposal.duration.0;
-            let current_block_timestamp = env::block_timestamp();
-            assert!(proposal_end_time_stamp > current_block_timestamp, "PROPOSAL_EXPIRED");
+        assert!(proposal.is_expired(), "ERR_PROPOSAL_NOT_EXPIRED");
+        if proposal.status == ProposalStatus::InProgress {
+            self.internal_finalize_proposal(id, &mut proposal);
+            self.proposals.insert(&id, &VersionedProposal::V2(proposal));
         }
+    }
+
+    // Executes approved proposal which could not be executed at approval time.
+    pub fn execute_proposal(&mut self, id: u64) {
+        self.assert_not_paused();
+        let mut proposal: Proposal = self.proposals.get(&id).expect("ERR_NO_PROPOSAL").into();
+        assert_eq!(proposal.status, ProposalStatus::Approved, "ERR_PROPOSAL_NOT_APPROVED");
+        assert!(
+            matches!(proposal.kind, ProposalKind::ChangeStakingToken { .. }),
+            "ERR_PROPOSAL_NOT_RETRYABLE"
+        );
+        self.internal_execute_proposal(id, &mut proposal);
+        assert_eq!(proposal.status, ProposalStatus::Executed, "ERR_DELEGATIONS_EXIST");
+        self.proposals.insert(&id, &VersionedProposal::V2(proposal));
+    }
+
+    pub fn act_proposal(&mut self, id: u64, action: Action, memo: Option<String>) {
+        self.assert_not_paused();
+        let account_id = env::predecessor_account_id();
+        let mut proposal: Proposal = self.proposals.get(&id).expect("ERR_NO_PROPOSAL").into();
         match action {
-            Action::Vote { option_id } => {
-                match &proposal.clone().kind {
-                    ProposalKind::Vote { vote_kind } => {
-                        let new_proposal = proposal.update_votes(&account_id, &option_id, user_delegate);
-                        self.proposals.insert(&id , &VersionedProposal::Default(new_proposal.into()));
-                    },
-                    _ => unreachable!()
+            Action::RemoveProposal => {
+                self.assert_permission(proposal.kind.to_policy_label(), PermissionAction::RemoveProposal);
+                // Donations are held until the proposal refunds or releases them.
+                assert_eq!(proposal.total_donations, 0, "ERR_PROPOSAL_HAS_DONATIONS");
+                assert_eq!(
+                    self.proposal_near_donations.get(&id).unwrap_or_default(),
+                    0,
+                    "ERR_PROPOSAL_HAS_DONATIONS"
+                );
+                // Action of the proposal is still to be executed.
+                assert_ne!(proposal.status, ProposalStatus::Approved, "ERR_PROPOSAL_NOT_EXECUTED");
+                self.internal_slash_bond(&mut proposal);
+                if proposal.status == ProposalStatus::InProgress {
+                    self.internal_remove_active_proposal(&proposal.proposer);
+                    self.internal_close_bounty_dispute(&proposal.kind, true);
+                }
+                for tag in proposal.tags.iter() {
+                    let mut ids = self.proposals_by_tag.get(tag).unwrap_or_default();
+                    ids.retain(|&tagged_id| tagged_id != id);
+                    if ids.is_empty() {
+                        self.proposals_by_tag.remove(tag);
+                    } else {
+                        self.proposals_by_tag.insert(tag, &ids);
+                    }
                 }
+                self.proposals.remove(&id);
             },
             Action::Finalize => {
-                assert_eq!(account_id, self.owner_id, "ONLY_OWNER");
-                proposal.update_status(ProposalStatus::Expired);
+                self.assert_permission(proposal.kind.to_policy_label(), PermissionAction::Finalize);
+                if proposal.is_expired() {
+                    self.internal_finalize_proposal(id, &mut proposal);
+                } else if proposal.status == ProposalStatus::InProgress {
+                    // Votes aren't final yet, the proposal is only closed without executing it.
+                    self.internal_close_proposal(id, &mut proposal, ProposalStatus::Expired);
+                }
+                self.proposals.insert(&id, &VersionedProposal::V2(proposal));
+            },
+            vote_action => {
+                assert!(!proposal.is_expired(), "PROPOSAL_EXPIRED");
+                assert_eq!(proposal.status, ProposalStatus::InProgress, "ERR_PROPOSAL_NOT_IN_PROGRESS");
+                self.internal_release_expired_locks(&account_id);
+                let current_weight = if self.has_delegation_entry(&account_id) {
+                    Some(self.get_voting_weight(&account_id))
+                } else {
+                    None
+                };
+                let user_delegate = proposal.snapshot_voter_weight(&account_id, current_weight);
+                let is_vote_kind = matches!(proposal.kind, ProposalKind::Vote { .. } | ProposalKind::Poll { .. });
+                let option = match vote_action {
+                    Action::Vote { option_id } => {
+                        assert!(
+                            matches!(proposal.kind, ProposalKind::Vote { .. }),
+                            "ERR_WRONG_ACTION_FOR_PROPOSAL_KIND"
+                        );
+                        proposal = proposal.update_votes(&account_id, &option_id, user_delegate);
+                        option_id
+                    },
+                    Action::VotePoll { option_index } => {
+                        if proposal.votes.contains_key(&account_id) {
+                            proposal.retract_vote(&account_id);
+                        }
+                        proposal.add_poll_vote(&account_id, option_index, user_delegate);
+                        option_index.to_string()
+                    },
+                    Action::VoteApprove | Action::VoteReject => {
+                        assert!(!is_vote_kind, "ERR_WRONG_ACTION_FOR_PROPOSAL_KIND");
+                        let approve = vote_action == Action::VoteApprove;
+                        if proposal.votes.contains_key(&account_id) {
+                            proposal.retract_vote(&account_id);
+                        }
+                        proposal.add_approval_vote(&account_id, approve, user_delegate);
+                        proposal.update_approval_status(&self.policy.vote_policy(Some(proposal.kind.to_policy_label())));
+                        if proposal.status != ProposalStatus::InProgress {
+                            self.internal_refund_bond(&mut proposal);
+                            self.internal_remove_active_proposal(&proposal.proposer);
+                        }
+                        if proposal.status == ProposalStatus::Approved {
+                            self.internal_execute_proposal(id, &mut proposal);
+                        } else if proposal.status == ProposalStatus::Rejected {
+                            self.internal_close_bounty_dispute(&proposal.kind, true);
+                        }
+                        if approve { VOTE_APPROVE } else { VOTE_REJECT }.to_string()
+                    },
+                    _ => unreachable!(),
+                };
+                proposal.record_activity(
+                    ProposalAction::Vote { option: option.clone(), weight: U128(user_delegate) },
+                    &account_id,
+                );
+                self.proposals.insert(&id, &VersionedProposal::V2(proposal));
+                DaoEvent::ProposalVote(vec![ProposalVoteData {
+                    proposal_id: id,
+                    voter: account_id,
+                    option,
+                    weight: U128(user_delegate),
+                }])
+                .emit();
+            }
+        }
+        if let Some(memo) = memo {
+            env::log(format!("Memo: {}", memo).as_bytes());
+        }
+    }
+
+    // Rewrites proposals stored in older versions in given id range, returns how many were upgraded.
+    // Old proposals are upgraded on read as well, this lets the owner migrate them in batches.
+    pub fn upgrade_proposals(&mut self, from_id: u64, limit: u64) -> u64 {
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        let mut upgraded = 0;
+        for id in from_id..std::cmp::min(self.last_proposal_id, from_id.saturating_add(limit)) {
+            if let Some(VersionedProposal::Default(proposal)) = self.proposals.get(&id) {
+                let mut proposal: Proposal = proposal.into();
+                proposal.assign_legacy_donations(&self.token_account);
+                self.proposals.insert(&id, &VersionedProposal::V2(proposal));
+                upgraded += 1;
             }
         }
+        upgraded
+    }
+
+    // Takes back vote of the caller, while the proposal is in progress.
+    pub fn unvote(&mut self, proposal_id: u64) {
+        self.assert_not_paused();
+        let account_id = env::predecessor_account_id();
+        let mut proposal: Proposal = self.proposals.get(&proposal_id).expect("ERR_NO_PROPOSAL").into();
+        assert!(!proposal.is_expired(), "PROPOSAL_EXPIRED");
+        assert_eq!(proposal.status, ProposalStatus::InProgress, "ERR_PROPOSAL_NOT_IN_PROGRESS");
+        let vote = proposal.retract_vote(&account_id);
+        proposal.record_activity(ProposalAction::Unvote { option: vote.option.clone() }, &account_id);
+        self.proposals.insert(&proposal_id, &VersionedProposal::V2(proposal));
+        DaoEvent::ProposalUnvote(vec![ProposalVoteData {
+            proposal_id,
+            voter: account_id,
+            option: vote.option,
+            weight: U128(vote.delegations),
+        }])
+        .emit();
+    }
+
+    // Pays out given milestone of `Donate` proposal to its beneficiary, milestones go in order.
+    pub fn release_milestone(&mut self, proposal_id: u64, index: u32) -> Promise {
+        self.assert_not_paused();
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        let mut proposal: Proposal = self.proposals.get(&proposal_id).expect("ERR_NO_PROPOSAL").into();
+        assert!(!proposal.is_donation_refundable(), "ERR_DONATION_REFUNDABLE");
+        proposal.assign_legacy_donations(&self.token_account);
+        let index = index as usize;
+        assert!(index < proposal.milestones.len(), "ERR_INVALID_MILESTONE");
+        assert!(!proposal.milestones[index].paid, "ERR_MILESTONE_ALREADY_PAID");
+        assert!(
+            proposal.milestones[..index].iter().all(|milestone| milestone.paid),
+            "ERR_MILESTONE_OUT_OF_ORDER"
+        );
+        let amount = proposal.milestones[index].amount;
+        let token_id = proposal.get_milestone_token(index);
+        let donated = proposal.token_donations.get(&token_id).cloned().unwrap_or(0);
+        assert!(
+            proposal.get_released_token_amount(&token_id) + amount.0 <= donated,
+            "ERR_NOT_ENOUGH_DONATIONS"
+        );
+        proposal.milestones[index].paid = true;
+        let beneficiary = proposal.beneficiary.clone().unwrap_or_else(|| proposal.proposer.clone());
+        self.proposals.insert(&proposal_id, &VersionedProposal::V2(proposal));
+        self.internal_take_proposal_donations(&token_id, amount.0);
+        ext_fungible_token::ft_transfer(
+            beneficiary,
+            amount,
+            None,
+            &token_id,
+            ONE_YOCTO_NEAR,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_self::on_milestone_released(
+            proposal_id,
+            index as u32,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+
+    // Sends donation of the caller in given token back, once `Donate` proposal failed, expired or got vetoed.
+    // Token defaults to the first accepted token of the proposal.
+    pub fn claim_donation_refund(&mut self, proposal_id: u64, token_id: Option<ValidAccountId>) -> Promise {
+        self.assert_not_paused();
+        let mut proposal: Proposal = self.proposals.get(&proposal_id).expect("ERR_NO_PROPOSAL").into();
+        assert!(proposal.kind == ProposalKind::Donate, "PROPOSAL_IS_NOT_DONATION_KIND");
+        assert!(proposal.is_donation_refundable(), "ERR_DONATION_NOT_REFUNDABLE");
+        // Released donations are already with the beneficiary.
+        assert_eq!(proposal.get_released_amount(), 0, "ERR_MILESTONES_RELEASED");
+        proposal.assign_legacy_donations(&self.token_account);
+        let token_id = token_id.map_or_else(|| proposal.accepted_tokens[0].clone(), |token_id| token_id.into());
+        let account_id = env::predecessor_account_id();
+        let record = proposal.withdraw_donation(&account_id, &token_id);
+        self.proposals.insert(&proposal_id, &VersionedProposal::V2(proposal));
+        self.internal_take_proposal_donations(&token_id, record.amount.0);
+        ext_fungible_token::ft_transfer(
+            account_id.clone(),
+            record.amount,
+            None,
+            &token_id,
+            ONE_YOCTO_NEAR,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_self::on_donation_refund(
+            proposal_id,
+            account_id,
+            record,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // Pays out approved transfer if treasury has enough tokens outside of `locked_amount` and the bounty escrow.
+    #[private]
+    pub fn on_treasury_balance(&mut self, proposal_id: u64) {
+        let proposal: Proposal = self.proposals.get(&proposal_id).expect("ERR_NO_PROPOSAL").into();
+        let (receiver_id, amount) = match proposal.kind {
+            ProposalKind::Transfer { receiver_id, amount } => (receiver_id, amount),
+            _ => env::panic(b"ERR_NOT_TRANSFER_PROPOSAL"),
+        };
+        let balance = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<U128>(&value).map(|balance| balance.0).unwrap_or(0)
+            },
+            _ => 0,
+        };
+        if balance.saturating_sub(self.internal_tracked_balance(&self.token_account)) < amount.0 {
+            self.internal_fail_proposal(proposal_id, "ERR_NOT_ENOUGH_TREASURY_BALANCE");
+            return;
+        }
+        // Keep the amount out of the treasury until the transfer is done.
+        self.locked_amount += amount.0;
+        ext_fungible_token::ft_transfer(
+            receiver_id,
+            amount,
+            None,
+            &self.token_account,
+            ONE_YOCTO_NEAR,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_self::on_proposal_executed(
+            proposal_id,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ));
+    }
+
+    // Creates approved bounty if treasury has enough of its token outside of `locked_amount` and the bounty escrow.
+    // The DAO is the creator of the bounty, so its escrow goes back to the treasury if it's cancelled.
+    #[private]
+    pub fn on_bounty_treasury_balance(&mut self, proposal_id: u64) {
+        let mut proposal: Proposal = self.proposals.get(&proposal_id).expect("ERR_NO_PROPOSAL").into();
+        let bounty = match &proposal.kind {
+            ProposalKind::AddBounty { bounty } => bounty.clone(),
+            _ => env::panic(b"ERR_NOT_ADD_BOUNTY_PROPOSAL"),
+        };
+        // Input was valid when proposed, but e.g. its expiration may have passed since.
+        let total = match bounty.validate() {
+            Ok(total) => total,
+            Err(err) => return self.internal_fail_proposal(proposal_id, err),
+        };
+        let balance = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<U128>(&value).map(|balance| balance.0).unwrap_or(0)
+            },
+            _ => 0,
+        };
+        if balance.saturating_sub(self.internal_tracked_balance(&bounty.token)) < total {
+            self.internal_fail_proposal(proposal_id, "ERR_NOT_ENOUGH_TREASURY_BALANCE");
+            return;
+        }
+        self.create_bounty(&env::current_account_id(), bounty);
+        proposal.update_status(ProposalStatus::Executed);
+        self.proposals.insert(&proposal_id, &VersionedProposal::V2(proposal));
+    }
+
+    // Marks the proposal as executed or failed, depending on the promise executing its action.
+    #[private]
+    pub fn on_proposal_executed(&mut self, proposal_id: u64) {
+        let mut proposal: Proposal = self.proposals.get(&proposal_id).expect("ERR_NO_PROPOSAL").into();
+        if let ProposalKind::Transfer { amount, .. } = &proposal.kind {
+            // Amount reserved for the transfer is either paid out or back in the treasury.
+            self.locked_amount -= amount.0;
+        }
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            proposal.update_status(ProposalStatus::Executed);
+        } else {
+            proposal.update_status(ProposalStatus::Failed);
+            env::log(format!("Proposal {} failed: ERR_EXECUTION_FAILED", proposal_id).as_bytes());
+        }
+        self.proposals.insert(&proposal_id, &VersionedProposal::V2(proposal));
+    }
+
+    // Restores donation record of the donor if the refund didn't go through.
+    #[private]
+    pub fn on_donation_refund(&mut self, proposal_id: u64, account_id: AccountId, record: DonorRecord) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        let mut proposal: Proposal = self.proposals.get(&proposal_id).expect("ERR_NO_PROPOSAL").into();
+        for (token_id, amount) in record.tokens.iter() {
+            self.internal_add_proposal_donations(token_id, amount.0);
+        }
+        proposal.restore_donation(&account_id, record);
+        self.proposals.insert(&proposal_id, &VersionedProposal::V2(proposal));
+        env::log(b"ERR_DONATION_REFUND_FAILED");
+    }
+
+    // Marks the milestone as not paid if its payout didn't go through.
+    #[private]
+    pub fn on_milestone_released(&mut self, proposal_id: u64, index: u32) {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return;
+        }
+        let mut proposal: Proposal = self.proposals.get(&proposal_id).expect("ERR_NO_PROPOSAL").into();
+        proposal.milestones[index as usize].paid = false;
+        let (token_id, amount) = (proposal.get_milestone_token(index as usize), proposal.milestones[index as usize].amount);
+        self.internal_add_proposal_donations(&token_id, amount.0);
+        self.proposals.insert(&proposal_id, &VersionedProposal::V2(proposal));
+        env::log(b"ERR_MILESTONE_RELEASE_FAILED");
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, get_logs, testing_env_with_promise_results, VMContextBuilder};
+    use near_sdk::{MockedBlockchain, PromiseResult};
+
+    use std::convert::TryInto;
+
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_approve_by_majority() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 40);
+        delegate(&mut context, &mut contract, accounts(2), 30);
+        delegate(&mut context, &mut contract, accounts(3), 30);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        act(&mut context, &mut contract, accounts(2), id, Action::VoteReject);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::InProgress);
+
+        act(&mut context, &mut contract, accounts(3), id, Action::VoteApprove);
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.status, ProposalStatus::Approved);
+        assert_eq!(proposal.option_delegations.get(VOTE_APPROVE), Some(&70));
+        assert_eq!(proposal.option_delegations.get(VOTE_REJECT), Some(&30));
+    }
+
+    #[test]
+    fn test_change_vote() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        delegate(&mut context, &mut contract, accounts(2), 30);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteReject);
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.option_delegations.get(VOTE_APPROVE), Some(&0));
+        assert_eq!(proposal.option_delegations.get(VOTE_REJECT), Some(&10));
+        assert_eq!(proposal.total_delegation_amount, 10);
+        assert_eq!(proposal.votes.get(&accounts(1).to_string()).unwrap().option, VOTE_REJECT);
+
+        // Same vote again doesn't count twice.
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteReject);
+        assert_eq!(get_proposal(&contract, id).option_delegations.get(VOTE_REJECT), Some(&10));
+    }
+
+    #[test]
+    fn test_unvote() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        delegate(&mut context, &mut contract, accounts(2), 30);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Poll { options: vec!["a".to_string(), "b".to_string()] });
+        act(&mut context, &mut contract, accounts(1), id, Action::VotePoll { option_index: 0 });
+        act(&mut context, &mut contract, accounts(2), id, Action::VotePoll { option_index: 1 });
+        act(&mut context, &mut contract, accounts(1), id, Action::VotePoll { option_index: 1 });
+        assert_eq!(get_proposal(&contract, id).get_poll_tallies(), vec![0, 40]);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.unvote(id);
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.get_poll_tallies(), vec![0, 10]);
+        assert_eq!(proposal.total_delegation_amount, 10);
+        assert!(!proposal.votes.contains_key(&accounts(2).to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DID_NOT_VOTED")]
+    fn test_unvote_without_vote() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.unvote(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "PROPOSAL_EXPIRED")]
+    fn test_unvote_after_expiry() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        delegate(&mut context, &mut contract, accounts(2), 30);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        testing_env!(context.block_timestamp(1_000_000_000 * 60 * 60).build());
+        contract.unvote(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PROPOSAL_NOT_IN_PROGRESS")]
+    fn test_change_vote_after_settled() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 40);
+        delegate(&mut context, &mut contract, accounts(2), 30);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Approved);
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteReject);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NO_PROPOSAL")]
+    fn test_vote_missing_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        act(&mut context, &mut contract, accounts(1), 3, Action::VoteApprove);
+    }
+
+    #[test]
+    fn test_remove_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        act(&mut context, &mut contract, owner(), id, Action::RemoveProposal);
+        assert!(contract.proposals.get(&id).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PROPOSAL_HAS_DONATIONS")]
+    fn test_remove_proposal_with_donations() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        transfer_call(&mut context, &mut contract, accounts(1), 10, &msg);
+        act(&mut context, &mut contract, owner(), id, Action::RemoveProposal);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PROPOSAL_HAS_DONATIONS")]
+    fn test_remove_proposal_with_near_donations() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        register(&mut contract, &accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(10).build());
+        contract.donate_near(Some(id));
+        testing_env!(context.attached_deposit(0).build());
+        act(&mut context, &mut contract, owner(), id, Action::RemoveProposal);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PROPOSAL_NOT_EXECUTED")]
+    fn test_remove_approved_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        let id = approve_transfer(&mut context, &mut contract);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Approved);
+        act(&mut context, &mut contract, owner(), id, Action::RemoveProposal);
+    }
+
+    #[test]
+    fn test_default_period_and_expiry() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.block_timestamp(1_000).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        let id = contract.add_proposal(ProposalInput {
+            duration: None,
+            ..proposal_input(ProposalKind::Donate)
+        });
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.submission_time.0, 1_000);
+        assert_eq!(proposal.duration.0, DEFAULT_PROPOSAL_PERIOD);
+
+        testing_env!(context.block_timestamp(1_000 + DEFAULT_PROPOSAL_PERIOD - 1).build());
+        let output = contract.get_proposal(id, None).unwrap();
+        assert_eq!(output.proposal.status, ProposalStatus::InProgress);
+
+        testing_env!(context.block_timestamp(1_000 + DEFAULT_PROPOSAL_PERIOD).build());
+        let output = contract.get_proposal(id, None).unwrap();
+        assert_eq!(output.proposal.status, ProposalStatus::Expired);
+        // Stored status is only settled by finalizing.
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::InProgress);
+        act(&mut context, &mut contract, owner(), id, Action::Finalize);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Expired);
+    }
+
+    #[test]
+    #[should_panic(expected = "PROPOSAL_EXPIRED")]
+    fn test_vote_on_expired_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.block_timestamp(1_000_000_000 * 60 * 60).build());
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+    }
+
+    #[test]
+    fn test_vote_weight_is_snapshotted() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 50);
+        delegate(&mut context, &mut contract, accounts(2), 50);
+        let mut options = HashMap::new();
+        for option in ["a", "b"].iter() {
+            options.insert(option.to_string(), VoteOption {
+                title: option.to_string(),
+                description: "".to_string(),
+                min_vote_weight: 0,
+            });
+        }
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        let id = contract.add_proposal(ProposalInput {
+            options,
+            ..proposal_input(ProposalKind::Vote { vote_kind: VoteKind::VoteByDelegation })
+        });
+        assert_eq!(get_proposal(&contract, id).total_delegation_snapshot, 100);
+
+        act(&mut context, &mut contract, accounts(1), id, Action::Vote { option_id: "a".to_string() });
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(0).build());
+        contract.withdraw(U128(30));
+        act(&mut context, &mut contract, accounts(1), id, Action::Vote { option_id: "b".to_string() });
+
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.option_delegations.get("a"), Some(&0));
+        assert_eq!(proposal.option_delegations.get("b"), Some(&50));
+        let weights = contract.get_proposal_voter_weights(id).unwrap();
+        assert_eq!(weights.get(&accounts(1).to_string()), Some(&U128(50)));
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(20));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MIN_BOND")]
+    fn test_insufficient_bond() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.attached_deposit(DEFAULT_PROPOSAL_BOND - 1).build());
+        contract.add_proposal(proposal_input(ProposalKind::Donate));
+    }
+
+    #[test]
+    fn test_bond_refunded_on_approval() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        assert_eq!(contract.locked_amount, 10);
+        assert_eq!(contract.locked_near, DEFAULT_PROPOSAL_BOND);
+        assert_eq!(get_proposal(&contract, id).bond, DEFAULT_PROPOSAL_BOND);
+
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        assert_eq!(contract.locked_near, 0);
+        assert_eq!(get_proposal(&contract, id).bond, 0);
+        let receipts = created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert!(receipts[0].contains(&format!("receiver_id: \"{}\"", owner())));
+        assert!(receipts[0].contains(&format!("Transfer(TransferAction {{ deposit: {} }})", DEFAULT_PROPOSAL_BOND)));
+    }
+
+    #[test]
+    fn test_bond_slashed_on_removal() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        act(&mut context, &mut contract, owner(), id, Action::RemoveProposal);
+        assert_eq!(contract.locked_near, 0);
+        assert!(created_receipts().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.cancel_proposal(id);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Cancelled);
+        assert_eq!(contract.locked_near, 0);
+        assert_eq!(created_receipts().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY_PROPOSER")]
+    fn test_cancel_proposal_by_other_account() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.cancel_proposal(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PROPOSAL_HAS_DONATIONS")]
+    fn test_cancel_proposal_with_donations() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        transfer_call(&mut context, &mut contract, accounts(1), 10, &msg);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.cancel_proposal(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PROPOSAL_HAS_DONATIONS")]
+    fn test_cancel_proposal_with_near_donations() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        register(&mut contract, &accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(10).build());
+        contract.donate_near(Some(id));
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(0).build());
+        contract.cancel_proposal(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY_OWNER")]
+    fn test_veto_by_non_owner() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.veto_proposal(id, "spam".to_string());
+    }
+
+    #[test]
+    fn test_donation_after_veto_is_refunded() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.veto_proposal(id, "scam".to_string());
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Vetoed);
+        assert_eq!(contract.locked_amount, 0);
+
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 10, &msg), 10);
+        assert_eq!(get_proposal(&contract, id).total_donations, 0);
+    }
+
+    fn approve_transfer(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        delegate(context, contract, accounts(1), 10);
+        let id = add_proposal(context, contract, ProposalKind::Transfer {
+            receiver_id: accounts(2).to_string(),
+            amount: U128(100),
+        });
+        act(context, contract, accounts(1), id, Action::VoteApprove);
+        id
+    }
+
+    #[test]
+    fn test_transfer_approval_checks_treasury() {
+        let (mut context, mut contract) = setup_contract();
+        let id = approve_transfer(&mut context, &mut contract);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Approved);
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains("ft_balance_of") && r.contains(&token().to_string())));
+        assert!(receipts.iter().any(|r| r.contains("on_treasury_balance")));
+    }
+
+    #[test]
+    fn test_transfer_with_unlocked_balance() {
+        let (mut context, mut contract) = setup_contract();
+        let id = approve_transfer(&mut context, &mut contract);
+        let balance = contract.locked_amount + 100;
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(balance)).unwrap()),
+        );
+        contract.on_treasury_balance(id);
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains("ft_transfer") && r.contains(&accounts(2).to_string())));
+        assert!(receipts.iter().any(|r| r.contains("on_proposal_executed")));
+        assert_eq!(contract.locked_amount, balance);
+
+        testing_env_with_promise_results(context.build(), PromiseResult::Successful(vec![]));
+        contract.on_proposal_executed(id);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Executed);
+        assert_eq!(contract.locked_amount, balance - 100);
+    }
+
+    #[test]
+    fn test_transfer_fails_without_unlocked_balance() {
+        let (mut context, mut contract) = setup_contract();
+        let id = approve_transfer(&mut context, &mut contract);
+        // Only delegated tokens are on the balance.
+        let balance = contract.locked_amount;
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(balance)).unwrap()),
+        );
+        contract.on_treasury_balance(id);
+        assert!(created_receipts().is_empty());
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Failed);
+    }
+
+    #[test]
+    fn test_transfer_keeps_bounty_escrow() {
+        let (mut context, mut contract) = setup_contract();
+        let id = approve_transfer(&mut context, &mut contract);
+        contract.create_bounty(&owner().to_string(), bounty_input(100));
+        let balance = contract.locked_amount + 100;
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(balance)).unwrap()),
+        );
+        contract.on_treasury_balance(id);
+        assert!(created_receipts().is_empty());
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Failed);
+    }
+
+    #[test]
+    fn test_transfer_keeps_donations() {
+        let (mut context, mut contract) = setup_contract();
+        let id = approve_transfer(&mut context, &mut contract);
+        transfer_call(&mut context, &mut contract, accounts(3), 60, "{\"purpose\":\"OpenDonate\"}");
+        let donate_id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", donate_id);
+        transfer_call(&mut context, &mut contract, accounts(3), 60, &msg);
+        // Only the donations are on the balance besides the delegations.
+        let balance = contract.locked_amount + 120;
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(balance)).unwrap()),
+        );
+        contract.on_treasury_balance(id);
+        assert!(created_receipts().is_empty());
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Failed);
+    }
+
+    fn bounty_input(amount: Balance) -> BountyInput {
+        BountyInput {
+            description: "d".to_string(),
+            token: token().to_string(),
+            amount: U128(amount),
+            times: 1,
+            max_deadline: U64(DEFAULT_PROPOSAL_PERIOD),
+            expires_at: None,
+            reviewers: vec![],
+            milestones: vec![],
+            tags: vec![],
+            requires_application: false,
+            review_period: None,
+            claimant_whitelist: None,
+        }
+    }
+
+    fn approve_add_bounty(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        delegate(context, contract, accounts(1), 10);
+        let id = add_proposal(context, contract, ProposalKind::AddBounty { bounty: bounty_input(100) });
+        act(context, contract, accounts(1), id, Action::VoteApprove);
+        id
+    }
+
+    fn treasury_balance(context: &mut VMContextBuilder, contract: &mut Contract, id: u64, balance: Balance) {
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(balance)).unwrap()),
+        );
+        contract.on_bounty_treasury_balance(id);
+    }
+
+    #[test]
+    fn test_add_bounty_from_treasury() {
+        let (mut context, mut contract) = setup_contract();
+        let id = approve_add_bounty(&mut context, &mut contract);
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains("ft_balance_of") && r.contains(&token().to_string())));
+        assert!(receipts.iter().any(|r| r.contains("on_bounty_treasury_balance")));
+
+        let balance = contract.locked_amount + 100;
+        treasury_balance(&mut context, &mut contract, id, balance);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Executed);
+        let bounty = contract.internal_get_bounty(0).unwrap();
+        assert_eq!(bounty.creator, dao().to_string());
+        assert_eq!(contract.get_bounty_escrow(None), U128(100));
+
+        // Cancelled bounty leaves the escrow in the treasury.
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.cancel_bounty(0);
+        assert_eq!(contract.get_bounty_escrow(None), U128(0));
+        assert!(created_receipts().iter().all(|r| !r.contains("ft_transfer")));
+    }
+
+    #[test]
+    fn test_add_bounty_fails_without_unencumbered_balance() {
+        let (mut context, mut contract) = setup_contract();
+        let id = approve_add_bounty(&mut context, &mut contract);
+        // Other bounty already holds part of the balance in escrow.
+        contract.create_bounty(&owner().to_string(), bounty_input(50));
+        let balance = contract.locked_amount + 100;
+        treasury_balance(&mut context, &mut contract, id, balance);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Failed);
+        assert_eq!(get_logs(), vec![format!("Proposal {} failed: ERR_NOT_ENOUGH_TREASURY_BALANCE", id)]);
+        assert_eq!(contract.get_last_bounty_id(), 1);
+        assert_eq!(contract.get_bounty_escrow(None), U128(50));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_TIMES")]
+    fn test_add_bounty_invalid_input() {
+        let (mut context, mut contract) = setup_contract();
+        add_proposal(&mut context, &mut contract, ProposalKind::AddBounty {
+            bounty: BountyInput { times: 0, ..bounty_input(100) },
+        });
+    }
+
+    #[test]
+    fn test_failed_transfer_marks_proposal_failed() {
+        let (mut context, mut contract) = setup_contract();
+        let id = approve_transfer(&mut context, &mut contract);
+        let locked_amount = contract.locked_amount;
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(locked_amount + 100)).unwrap()),
+        );
+        contract.on_treasury_balance(id);
+
+        testing_env_with_promise_results(context.build(), PromiseResult::Failed);
+        contract.on_proposal_executed(id);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Failed);
+        assert_eq!(contract.locked_amount, locked_amount);
+    }
+
+    fn function_call_kind(gas: Gas) -> ProposalKind {
+        ProposalKind::FunctionCall {
+            receiver_id: accounts(3).to_string(),
+            actions: vec![
+                ActionCall {
+                    method_name: "first".to_string(),
+                    args: Base64VecU8(b"{\"a\":1}".to_vec()),
+                    deposit: U128(0),
+                    gas: U64(gas),
+                },
+                ActionCall {
+                    method_name: "second".to_string(),
+                    args: Base64VecU8(b"{}".to_vec()),
+                    deposit: U128(1),
+                    gas: U64(gas),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_function_call_execution() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        let id = add_proposal(&mut context, &mut contract, function_call_kind(MIN_ACTION_CALL_GAS));
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+
+        let receipts = created_receipts();
+        let call = receipts.iter().find(|r| r.contains(&format!("receiver_id: \"{}\"", accounts(3)))).unwrap();
+        let first = call.find("method_name: \"first\"").unwrap();
+        let second = call.find("method_name: \"second\"").unwrap();
+        assert!(first < second);
+        assert!(call.contains("args: \"{\\\"a\\\":1}\""));
+        assert!(receipts.iter().any(|r| r.contains("on_proposal_executed")));
+
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_proposal_executed(id);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Failed);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_GAS")]
+    fn test_function_call_with_too_little_gas() {
+        let (mut context, mut contract) = setup_contract();
+        add_proposal(&mut context, &mut contract, function_call_kind(MIN_ACTION_CALL_GAS - 1));
+    }
+
+    fn new_metadata() -> DaoMetadata {
+        DaoMetadata {
+            name: "Renamed".to_string(),
+            ..DaoMetadata::test_config()
+        }
+    }
+
+    #[test]
+    fn test_change_metadata_on_approval() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::ChangeMetadata { metadata: new_metadata() });
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        assert_eq!(contract.get_metadata(), new_metadata());
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Executed);
+    }
+
+    #[test]
+    fn test_rejected_change_metadata() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::ChangeMetadata { metadata: new_metadata() });
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteReject);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Rejected);
+        assert_eq!(contract.get_metadata(), DaoMetadata::test_config());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_LINK")]
+    fn test_change_metadata_with_empty_link() {
+        let (mut context, mut contract) = setup_contract();
+        let metadata = DaoMetadata {
+            twitter: Some("".to_string()),
+            ..DaoMetadata::test_config()
+        };
+        add_proposal(&mut context, &mut contract, ProposalKind::ChangeMetadata { metadata });
+    }
+
+    #[test]
+    fn test_change_staking_token() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::ChangeStakingToken {
+            token_id: "new-token.near".to_string(),
+        });
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        // Refused while tokens are delegated.
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Approved);
+        assert_eq!(contract.token_account, token().to_string());
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(10), None);
+        // Undelegated tokens are still held by the DAO until withdrawn.
+        assert_eq!(contract.locked_amount, 10);
+        testing_env!(context.block_timestamp(DEFAULT_UNBONDING_PERIOD).build());
+        contract.withdraw_undelegated();
+        contract.execute_proposal(id);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Executed);
+        assert_eq!(contract.token_account, "new-token.near".to_string());
+
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", accounts(2));
+        let refund = transfer_call_from(
+            &mut context, &mut contract, "new-token.near".try_into().unwrap(), accounts(2), 5, &msg,
+        );
+        assert_eq!(refund, 0);
+        assert_eq!(contract.delegation_balance_of(accounts(2).to_string()), U128(5));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DELEGATIONS_EXIST")]
+    fn test_execute_change_staking_token_with_delegations() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::ChangeStakingToken {
+            token_id: "new-token.near".to_string(),
+        });
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        contract.execute_proposal(id);
+    }
+
+    #[test]
+    fn test_change_staking_token_with_bonds() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::ChangeStakingToken {
+            token_id: "new-token.near".to_string(),
+        });
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        // Bond of a proposal in progress is $NEAR, it doesn't hold the change back.
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(10), None);
+        testing_env!(context.block_timestamp(DEFAULT_UNBONDING_PERIOD).build());
+        contract.withdraw_undelegated();
+        contract.execute_proposal(id);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Executed);
+        assert_eq!(contract.locked_near, DEFAULT_PROPOSAL_BOND);
+    }
+
+    #[test]
+    fn test_change_staking_token_to_delegated_token() {
+        let (mut context, mut contract) = setup_contract();
+        accept_token(&mut context, &mut contract, usdc());
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", accounts(2));
+        assert_eq!(transfer_call_from(&mut context, &mut contract, usdc(), accounts(2), 40, &msg), 0);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::ChangeStakingToken {
+            token_id: usdc().to_string(),
+        });
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+
+        // Rewards of the old token are claimed before everyone undelegates.
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.distribute_rewards(U128(30));
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(40)).unwrap()),
+        );
+        contract.on_rewards_balance(U128(30));
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_rewards();
+        contract.undelegate(U128(10), None);
+        testing_env!(context.block_timestamp(DEFAULT_UNBONDING_PERIOD).build());
+        contract.withdraw_undelegated();
+        contract.execute_proposal(id);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Executed);
+        assert_eq!(contract.locked_amount, 40);
+        assert_eq!(contract.get_delegators(0, 10), vec![(accounts(2).to_string(), U128(40))]);
+        // Rewards distributed before the change aren't earned by delegations of the new token.
+        assert_eq!(contract.get_unclaimed_rewards(accounts(2).to_string()), U128(0));
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.undelegate(U128(40), None);
+        testing_env!(context.block_timestamp(2 * DEFAULT_UNBONDING_PERIOD).build());
+        contract.withdraw_undelegated();
+        assert!(created_receipts()[0].contains("usdc.near"));
+        assert_eq!(contract.locked_amount, 0);
+        assert_eq!(contract.get_delegator_count(), 0);
+    }
+
+    fn add_poll(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        delegate(context, contract, accounts(1), 30);
+        delegate(context, contract, accounts(2), 30);
+        delegate(context, contract, accounts(3), 10);
+        let options = vec!["red".to_string(), "green".to_string(), "blue".to_string()];
+        add_proposal(context, contract, ProposalKind::Poll { options })
+    }
+
+    #[test]
+    fn test_poll_winner() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_poll(&mut context, &mut contract);
+        act(&mut context, &mut contract, accounts(1), id, Action::VotePoll { option_index: 2 });
+        act(&mut context, &mut contract, accounts(2), id, Action::VotePoll { option_index: 0 });
+        act(&mut context, &mut contract, accounts(3), id, Action::VotePoll { option_index: 2 });
+        assert_eq!(contract.get_poll_tallies(id), Some(vec![U128(30), U128(0), U128(40)]));
+
+        act(&mut context, &mut contract, owner(), id, Action::Finalize);
+        assert_eq!(get_proposal(&contract, id).poll_winner, Some(2));
+    }
+
+    #[test]
+    fn test_poll_tie() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_poll(&mut context, &mut contract);
+        act(&mut context, &mut contract, accounts(1), id, Action::VotePoll { option_index: 0 });
+        act(&mut context, &mut contract, accounts(2), id, Action::VotePoll { option_index: 1 });
+        act(&mut context, &mut contract, owner(), id, Action::Finalize);
+        assert_eq!(get_proposal(&contract, id).poll_winner, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_OPTION_INDEX")]
+    fn test_poll_option_out_of_range() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_poll(&mut context, &mut contract);
+        act(&mut context, &mut contract, accounts(1), id, Action::VotePoll { option_index: 3 });
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_POLL_OPTIONS")]
+    fn test_poll_with_too_many_options() {
+        let (mut context, mut contract) = setup_contract();
+        let options = (0..=MAX_POLL_OPTIONS).map(|index| index.to_string()).collect();
+        add_proposal(&mut context, &mut contract, ProposalKind::Poll { options });
+    }
+
+    fn setup_quorum(first_weight: Balance, second_weight: Balance) -> (VMContextBuilder, Contract, u64) {
+        let (mut context, mut contract) = setup_contract_with_policy(Policy {
+            quorum: 5_000,
+            ..test_policy()
+        });
+        delegate(&mut context, &mut contract, accounts(1), first_weight);
+        delegate(&mut context, &mut contract, accounts(2), second_weight);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::InProgress);
+        expire(&mut context, &contract, id);
+        act(&mut context, &mut contract, owner(), id, Action::Finalize);
+        (context, contract, id)
+    }
+
+    // Moves the block time to the end of the voting period of given proposal.
+    fn expire(context: &mut VMContextBuilder, contract: &Contract, id: u64) {
+        let proposal = get_proposal(contract, id);
+        testing_env!(context.block_timestamp(proposal.submission_time.0 + proposal.duration.0).build());
+    }
+
+    #[test]
+    fn test_finalize_at_exact_quorum() {
+        let (_, contract, id) = setup_quorum(5_000, 5_000);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Approved);
+    }
+
+    #[test]
+    fn test_finalize_below_quorum() {
+        let (_, contract, id) = setup_quorum(4_999, 5_001);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Expired);
+    }
+
+    #[test]
+    fn test_finalize_below_threshold() {
+        let (mut context, mut contract) = setup_contract_with_policy(Policy {
+            quorum: 5_000,
+            threshold: 6_000,
+            ..test_policy()
+        });
+        delegate(&mut context, &mut contract, accounts(1), 30);
+        delegate(&mut context, &mut contract, accounts(2), 20);
+        delegate(&mut context, &mut contract, accounts(3), 50);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        act(&mut context, &mut contract, accounts(2), id, Action::VoteReject);
+        expire(&mut context, &contract, id);
+        act(&mut context, &mut contract, owner(), id, Action::Finalize);
+        // 60% of the votes approve, threshold requires more.
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Rejected);
+    }
+
+    #[test]
+    fn test_change_policy() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        let policy = Policy {
+            quorum: 2_000,
+            threshold: 6_600,
+            ..test_policy()
+        };
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::ChangePolicy { policy: policy.clone() });
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        assert_eq!(contract.policy, policy);
+    }
+
+    #[test]
+    fn test_kind_override() {
+        let mut kind_overrides = HashMap::new();
+        kind_overrides.insert("transfer".to_string(), VotePolicyOverride {
+            proposal_period: Some(U64(DEFAULT_PROPOSAL_PERIOD * 2)),
+            threshold: Some(7_500),
+            ..VotePolicyOverride::default()
+        });
+        let (mut context, mut contract) = setup_contract_with_policy(Policy {
+            kind_overrides,
+            ..test_policy()
+        });
+        delegate(&mut context, &mut contract, accounts(1), 70);
+        delegate(&mut context, &mut contract, accounts(2), 30);
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        let transfer_id = contract.add_proposal(ProposalInput {
+            duration: None,
+            ..proposal_input(ProposalKind::Transfer { receiver_id: accounts(3).to_string(), amount: U128(1) })
+        });
+        let poll_id = contract.add_proposal(ProposalInput {
+            duration: None,
+            ..proposal_input(ProposalKind::Poll { options: vec!["yes".to_string(), "no".to_string()] })
+        });
+        let donate_id = contract.add_proposal(proposal_input(ProposalKind::Donate));
+        assert_eq!(get_proposal(&contract, transfer_id).duration.0, DEFAULT_PROPOSAL_PERIOD * 2);
+        assert_eq!(get_proposal(&contract, poll_id).duration.0, DEFAULT_PROPOSAL_PERIOD);
+
+        act(&mut context, &mut contract, accounts(1), transfer_id, Action::VoteApprove);
+        act(&mut context, &mut contract, accounts(1), donate_id, Action::VoteApprove);
+        assert_eq!(get_proposal(&contract, transfer_id).status, ProposalStatus::InProgress);
+        assert_eq!(get_proposal(&contract, donate_id).status, ProposalStatus::Approved);
+        assert_eq!(contract.get_policy(), contract.policy);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_POLICY_LABEL")]
+    fn test_override_of_unknown_kind() {
+        let mut kind_overrides = HashMap::new();
+        kind_overrides.insert("unknown".to_string(), VotePolicyOverride::default());
+        setup_contract_with_policy(Policy {
+            kind_overrides,
+            ..test_policy()
+        });
+    }
+
+    fn add_transfer_with_partial_vote(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        delegate(context, contract, accounts(1), 40);
+        delegate(context, contract, accounts(2), 60);
+        let id = add_proposal(context, contract, ProposalKind::Transfer {
+            receiver_id: accounts(3).to_string(),
+            amount: U128(10),
+        });
+        act(context, contract, accounts(1), id, Action::VoteApprove);
+        id
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PROPOSAL_NOT_EXPIRED")]
+    fn test_finalize_before_expiry() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_transfer_with_partial_vote(&mut context, &mut contract);
+        contract.finalize_proposal(id);
+    }
+
+    #[test]
+    fn test_finalize_approved_transfer() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_transfer_with_partial_vote(&mut context, &mut contract);
+        let proposal = get_proposal(&contract, id);
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(proposal.submission_time.0 + proposal.duration.0)
+            .build());
+        contract.finalize_proposal(id);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Approved);
+        assert_eq!(contract.locked_amount, 100);
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains("ft_balance_of")));
+        assert!(receipts.iter().any(|r| r.contains("Transfer(TransferAction")));
+
+        // Finalizing again does nothing.
+        testing_env!(context.build());
+        contract.finalize_proposal(id);
+        assert!(created_receipts().is_empty());
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Approved);
+    }
+
+    #[test]
+    fn test_owner_finalize_before_expiry() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_transfer_with_partial_vote(&mut context, &mut contract);
+        act(&mut context, &mut contract, owner(), id, Action::Finalize);
+        // Later votes could still reject it, so it's only closed.
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Expired);
+        assert!(created_receipts().iter().all(|r| !r.contains("ft_balance_of")));
+    }
+
+    #[test]
+    fn test_owner_finalize_after_expiry() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_transfer_with_partial_vote(&mut context, &mut contract);
+        expire(&mut context, &contract, id);
+        act(&mut context, &mut contract, owner(), id, Action::Finalize);
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Approved);
+        assert!(created_receipts().iter().any(|r| r.contains("ft_balance_of")));
+    }
+
+    fn vetoed_donation(context: &mut VMContextBuilder, contract: &mut Contract) -> u64 {
+        let id = add_proposal(context, contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        assert_eq!(transfer_call(context, contract, accounts(1), 100, &msg), 0);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.veto_proposal(id, "spam".to_string());
+        id
+    }
+
+    #[test]
+    fn test_claim_donation_refund() {
+        let (mut context, mut contract) = setup_contract();
+        let id = vetoed_donation(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_donation_refund(id, None);
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains("ft_transfer") && r.contains(&token().to_string())));
+        assert!(receipts.iter().any(|r| r.contains("on_donation_refund")));
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.total_donations, 0);
+        assert!(proposal.donations.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NO_DONATION")]
+    fn test_claim_donation_refund_twice() {
+        let (mut context, mut contract) = setup_contract();
+        let id = vetoed_donation(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_donation_refund(id, None);
+        contract.claim_donation_refund(id, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DONATION_NOT_REFUNDABLE")]
+    fn test_claim_donation_refund_in_progress() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        transfer_call(&mut context, &mut contract, accounts(1), 100, &msg);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_donation_refund(id, None);
+    }
+
+    #[test]
+    fn test_claim_donation_refund_after_expiry() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        transfer_call(&mut context, &mut contract, accounts(1), 100, &msg);
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(1_000_000_000 * 60 * 60)
+            .build());
+        contract.claim_donation_refund(id, None);
+        assert_eq!(get_proposal(&contract, id).total_donations, 0);
+    }
+
+    #[test]
+    fn test_failed_donation_refund_restores_record() {
+        let (mut context, mut contract) = setup_contract();
+        let id = vetoed_donation(&mut context, &mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_donation_refund(id, None);
+
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        let record = DonorRecord {
+            amount: U128(100),
+            tokens: vec![(token().to_string(), U128(100))].into_iter().collect(),
+            first_donation_time: U64(0),
+            last_donation_time: U64(0),
+        };
+        contract.on_donation_refund(id, accounts(1).to_string(), record.clone());
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.total_donations, 100);
+        assert_eq!(proposal.donations.get(&accounts(1).to_string()), Some(&record));
+
+        // Donor can claim again after the failed refund.
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_donation_refund(id, None);
+        assert_eq!(get_proposal(&contract, id).total_donations, 0);
+    }
+
+    fn event_log(event: &str, data: &str) -> String {
+        format!(
+            "EVENT_JSON:{{\"standard\":\"connesus_dao\",\"version\":\"1.0.0\",\"event\":\"{}\",\"data\":[{}]}}",
+            event, data
+        )
+    }
+
+    #[test]
+    fn test_proposal_lifecycle_events() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 40);
+        delegate(&mut context, &mut contract, accounts(2), 60);
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        let id = contract.add_proposal(proposal_input(ProposalKind::Donate));
+        assert_eq!(
+            get_logs(),
+            vec![event_log(
+                "proposal_create",
+                &format!("{{\"proposal_id\":0,\"proposer\":\"alice\",\"kind\":\"donate\",\"bond\":\"{}\"}}", DEFAULT_PROPOSAL_BOND)
+            )]
+        );
+
+        testing_env!(context.attached_deposit(0).build());
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        assert_eq!(
+            get_logs(),
+            vec![event_log(
+                "proposal_vote",
+                "{\"proposal_id\":0,\"voter\":\"bob\",\"option\":\"approve\",\"weight\":\"40\"}"
+            )]
+        );
+
+        testing_env!(context.predecessor_account_id(accounts(3)).block_timestamp(1_000_000_000 * 60 * 60).build());
+        contract.finalize_proposal(id);
+        assert_eq!(
+            get_logs(),
+            vec![event_log(
+                "proposal_finalize",
+                "{\"proposal_id\":0,\"actor\":\"danny\",\"status\":\"Approved\",\"total_votes\":\"40\"}"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_proposal_veto_event() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.veto_proposal(id, "spam".to_string());
+        assert_eq!(
+            get_logs(),
+            vec![event_log(
+                "proposal_veto",
+                &format!(
+                    "{{\"proposal_id\":0,\"actor\":\"alice\",\"reason\":\"spam\",\"slashed_bond\":\"{}\"}}",
+                    DEFAULT_PROPOSAL_BOND
+                )
+            )]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOO_MANY_ACTIVE_PROPOSALS")]
+    fn test_active_proposals_cap() {
+        let (mut context, mut contract) = setup_contract();
+        for _ in 0..DEFAULT_MAX_ACTIVE_PROPOSALS {
+            add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        }
+        assert_eq!(contract.active_proposals.get(&owner().to_string()), Some(DEFAULT_MAX_ACTIVE_PROPOSALS));
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+    }
+
+    #[test]
+    fn test_settled_proposal_frees_slot() {
+        let (mut context, mut contract) = setup_contract_with_policy(Policy {
+            max_active_proposals: 3,
+            ..test_policy()
+        });
+        let first = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let second = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+
+        // Expired proposal keeps the slot until it's finalized.
+        testing_env!(context.block_timestamp(1_000_000_000 * 60 * 60).build());
+        contract.finalize_proposal(first);
+        assert_eq!(contract.active_proposals.get(&owner().to_string()), Some(2));
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.veto_proposal(3, "spam".to_string());
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        // Removing settled proposal doesn't free another slot.
+        act(&mut context, &mut contract, owner(), second, Action::RemoveProposal);
+        act(&mut context, &mut contract, owner(), first, Action::RemoveProposal);
+        assert_eq!(contract.active_proposals.get(&owner().to_string()), Some(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DESCRIPTION_TOO_LONG")]
+    fn test_too_long_description() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        contract.add_proposal(ProposalInput {
+            description: "a".repeat(MAX_PROPOSAL_DESCRIPTION_LENGTH + 1),
+            ..proposal_input(ProposalKind::Donate)
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DESCRIPTION_AND_HASH")]
+    fn test_description_with_hash() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        contract.add_proposal(ProposalInput {
+            description_hash: Some(Base58CryptoHash::from([1; 32])),
+            ..proposal_input(ProposalKind::Donate)
+        });
+    }
+
+    #[test]
+    fn test_description_hash_round_trip() {
+        let (mut context, mut contract) = setup_contract();
+        let hash = Base58CryptoHash::from([7; 32]);
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        let input: ProposalInput = near_sdk::serde_json::from_str(&format!(
+            "{{\"description_hash\":\"{}\",\"kind\":\"Donate\",\"options\":{{}}}}",
+            String::from(&hash)
+        ))
+        .unwrap();
+        let id = contract.add_proposal(input);
+
+        env::state_write(&contract);
+        let mut contract: Contract = env::state_read().unwrap();
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.description, "");
+        assert_eq!(proposal.description_hash, Some(hash));
+        let json = near_sdk::serde_json::to_string(&contract.get_proposal(id, None).unwrap()).unwrap();
+        assert!(json.contains(&format!("\"description_hash\":\"{}\"", String::from(&hash))));
+
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        assert_eq!(contract.get_proposal(id, None).unwrap().proposal.description_hash, None);
+    }
+
+    #[test]
+    fn test_extend_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        delegate(&mut context, &mut contract, accounts(2), 30);
+        let hour = 1_000_000_000 * 60 * 60;
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(owner()).block_timestamp(hour - 1).build());
+        contract.extend_proposal(id, U64(hour));
+        assert_eq!(get_proposal(&contract, id).duration, U64(2 * hour));
+
+        // Votes are accepted in the extended window.
+        testing_env!(context.block_timestamp(hour + 1).build());
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteReject);
+        let proposal = get_proposal(&contract, id);
+        assert_eq!(proposal.get_status(), ProposalStatus::InProgress);
+        assert_eq!(proposal.option_delegations.get(VOTE_REJECT), Some(&10));
+        assert_eq!(proposal.extension_count, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOO_MANY_EXTENSIONS")]
+    fn test_extend_proposal_count_cap() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        for _ in 0..=MAX_PROPOSAL_EXTENSIONS {
+            contract.extend_proposal(id, U64(1));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_EXTENSION_TOO_LONG")]
+    fn test_extend_proposal_period_cap() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.extend_proposal(id, U64(DEFAULT_PROPOSAL_PERIOD - 1));
+        contract.extend_proposal(id, U64(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PROPOSAL_NOT_IN_PROGRESS")]
+    fn test_extend_expired_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(owner()).block_timestamp(1_000_000_000 * 60 * 60).build());
+        contract.extend_proposal(id, U64(1));
+    }
+
+    fn add_milestone_proposal(context: &mut VMContextBuilder, contract: &mut Contract) -> String {
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        let milestone = |amount| Milestone {
+            amount: U128(amount),
+            description: "milestone".to_string(),
+            token: None,
+            paid: false,
+        };
+        let id = contract.add_proposal(ProposalInput {
+            milestones: vec![milestone(60), milestone(40)],
+            beneficiary: Some(accounts(4).to_string()),
+            ..proposal_input(ProposalKind::Donate)
+        });
+        format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id)
+    }
+
+    #[test]
+    fn test_release_milestones() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_milestone_proposal(&mut context, &mut contract);
+        transfer_call(&mut context, &mut contract, accounts(1), 100, &msg);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.release_milestone(0, 0);
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains("ft_transfer") && r.contains(&accounts(4).to_string()) && r.contains("\\\"60\\\"")));
+        assert!(receipts.iter().any(|r| r.contains("on_milestone_released")));
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.release_milestone(0, 1);
+        assert_eq!(get_proposal(&contract, 0).get_released_amount(), 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MILESTONE_OUT_OF_ORDER")]
+    fn test_release_milestone_out_of_order() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_milestone_proposal(&mut context, &mut contract);
+        transfer_call(&mut context, &mut contract, accounts(1), 100, &msg);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.release_milestone(0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_DONATIONS")]
+    fn test_release_milestone_partial_funding() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_milestone_proposal(&mut context, &mut contract);
+        transfer_call(&mut context, &mut contract, accounts(1), 80, &msg);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.release_milestone(0, 0);
+        contract.release_milestone(0, 1);
+    }
+
+    #[test]
+    fn test_failed_milestone_release_rolls_back() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_milestone_proposal(&mut context, &mut contract);
+        transfer_call(&mut context, &mut contract, accounts(1), 100, &msg);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.release_milestone(0, 0);
+        assert!(get_proposal(&contract, 0).milestones[0].paid);
+
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_milestone_released(0, 0);
+        let proposal = get_proposal(&contract, 0);
+        assert!(!proposal.milestones[0].paid);
+        assert_eq!(proposal.get_released_amount(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MILESTONES_RELEASED")]
+    fn test_no_refund_after_release() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_milestone_proposal(&mut context, &mut contract);
+        transfer_call(&mut context, &mut contract, accounts(1), 100, &msg);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.release_milestone(0, 0);
+        testing_env!(context.block_timestamp(1_000_000_000 * 60 * 60).predecessor_account_id(accounts(1)).build());
+        contract.claim_donation_refund(0, None);
+    }
+
+    fn proposal_v1() -> ProposalV1 {
+        let mut donations = HashMap::new();
+        donations.insert(accounts(1).to_string(), 30);
+        let mut options = HashMap::new();
+        options.insert("a".to_string(), VoteOption {
+            title: "a".to_string(),
+            description: "a".to_string(),
+            min_vote_weight: 0,
+        });
+        ProposalV1 {
+            proposer: owner().to_string(),
+            description: "old".to_string(),
+            kind: ProposalKind::Vote { vote_kind: VoteKind::VoteByDelegation },
+            status: ProposalStatus::InProgress,
+            options,
+            submission_time: U64(5),
+            duration: U64(1_000_000_000 * 60 * 60),
+            donations,
+            total_donations: 30,
+            total_delegation_amount: 0,
+            votes: HashMap::default(),
+            option_delegations: HashMap::default(),
+        }
+    }
+
+    // Stores V1 proposal under given id the way the old contract did.
+    fn store_raw_v1(contract: &mut Contract, id: u64) {
+        let mut key = StorageKeys::Proposals.try_to_vec().unwrap();
+        key.extend(id.try_to_vec().unwrap());
+        let value = VersionedProposal::Default(proposal_v1()).try_to_vec().unwrap();
+        // Old layout is variant 0 followed by the fields of the proposal.
+        assert_eq!(value[0], 0);
+        assert_eq!(value[1..], proposal_v1().try_to_vec().unwrap()[..]);
+        env::storage_write(&key, &value);
+        contract.last_proposal_id = std::cmp::max(contract.last_proposal_id, id + 1);
+    }
+
+    #[test]
+    fn test_read_v1_proposal() {
+        let (mut context, mut contract) = setup_contract();
+        store_raw_v1(&mut contract, 0);
+        let proposal = get_proposal(&contract, 0);
+        assert_eq!(proposal.description, "old");
+        assert_eq!(proposal.submission_time, U64(5));
+        assert_eq!(proposal.bond, 0);
+        assert_eq!(proposal.total_delegation_snapshot, 0);
+        assert!(proposal.voter_weights.is_empty());
+        assert_eq!(proposal.description_hash, None);
+        assert!(proposal.milestones.is_empty());
+        assert_eq!(
+            proposal.donations.get(&accounts(1).to_string()),
+            Some(&DonorRecord {
+                amount: U128(30),
+                tokens: HashMap::default(),
+                first_donation_time: U64(5),
+                last_donation_time: U64(5)
+            })
+        );
+
+        // Any mutation writes it back as V2.
+        delegate(&mut context, &mut contract, accounts(2), 10);
+        act(&mut context, &mut contract, accounts(2), 0, Action::Vote { option_id: "a".to_string() });
+        assert!(matches!(contract.proposals.get(&0), Some(VersionedProposal::V2(_))));
+        assert_eq!(get_proposal(&contract, 0).option_delegations.get("a"), Some(&10));
+    }
+
+    #[test]
+    fn test_upgrade_proposals() {
+        let (mut context, mut contract) = setup_contract();
+        store_raw_v1(&mut contract, 0);
+        store_raw_v1(&mut contract, 1);
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        store_raw_v1(&mut contract, 3);
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        assert_eq!(contract.upgrade_proposals(0, 2), 2);
+        assert_eq!(contract.upgrade_proposals(2, 10), 1);
+        assert_eq!(contract.upgrade_proposals(0, 10), 0);
+        for id in 0..4 {
+            assert!(matches!(contract.proposals.get(&id), Some(VersionedProposal::V2(_))));
+        }
+        assert_eq!(get_proposal(&contract, 3).total_donations, 30);
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY_OWNER")]
+    fn test_upgrade_proposals_only_owner() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.upgrade_proposals(0, 10);
+    }
+
+    #[test]
+    fn test_add_proposals() {
+        let (mut context, mut contract) = setup_contract();
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context
+            .predecessor_account_id(owner())
+            .attached_deposit(DEFAULT_PROPOSAL_BOND * 3 + 2)
+            .build());
+        let ids = contract.add_proposals(vec![
+            proposal_input(ProposalKind::Donate),
+            proposal_input(ProposalKind::Poll { options: vec!["a".to_string()] }),
+            proposal_input(ProposalKind::Donate),
+        ]);
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(get_proposal(&contract, 1).bond, DEFAULT_PROPOSAL_BOND);
+        assert_eq!(get_proposal(&contract, 3).bond, DEFAULT_PROPOSAL_BOND + 2);
+        assert_eq!(contract.get_active_proposals_count(owner().to_string()), 4);
+        assert_eq!(contract.locked_near, DEFAULT_PROPOSAL_BOND * 4 + 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_POLL_OPTIONS")]
+    fn test_add_proposals_with_invalid_input() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(owner())
+            .attached_deposit(DEFAULT_PROPOSAL_BOND * 2)
+            .build());
+        contract.add_proposals(vec![
+            proposal_input(ProposalKind::Donate),
+            proposal_input(ProposalKind::Poll { options: vec![] }),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MIN_BOND")]
+    fn test_add_proposals_without_enough_bond() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context
+            .predecessor_account_id(owner())
+            .attached_deposit(DEFAULT_PROPOSAL_BOND * 2 - 1)
+            .build());
+        contract.add_proposals(vec![proposal_input(ProposalKind::Donate), proposal_input(ProposalKind::Donate)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOO_MANY_ACTIVE_PROPOSALS")]
+    fn test_add_proposals_over_active_cap() {
+        let (mut context, mut contract) = setup_contract();
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let count = DEFAULT_MAX_ACTIVE_PROPOSALS as usize;
+        testing_env!(context
+            .predecessor_account_id(owner())
+            .attached_deposit(DEFAULT_PROPOSAL_BOND * count as u128)
+            .build());
+        contract.add_proposals((0..count).map(|_| proposal_input(ProposalKind::Donate)).collect());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_BATCH_SIZE")]
+    fn test_add_proposals_over_batch_cap() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.add_proposals((0..=MAX_BATCH_PROPOSALS).map(|_| proposal_input(ProposalKind::Donate)).collect());
+    }
+
+    #[test]
+    fn test_proposal_activity() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        delegate(&mut context, &mut contract, accounts(2), 30);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        testing_env!(context.block_timestamp(10).build());
+        transfer_call(&mut context, &mut contract, accounts(3), 5, &msg);
+        testing_env!(context.block_timestamp(20).build());
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteReject);
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(30).build());
+        contract.unvote(id);
+        testing_env!(context.predecessor_account_id(owner()).block_timestamp(40).build());
+        contract.veto_proposal(id, "spam".to_string());
+
+        let activity = contract.get_proposal_activity(id, 0, 10);
+        let actions: Vec<(ProposalAction, AccountId, U64)> = activity
+            .into_iter()
+            .map(|event| (event.action, event.actor, event.timestamp))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                (ProposalAction::Create, owner().to_string(), U64(0)),
+                (ProposalAction::Donate { token: token().to_string(), amount: U128(5) }, accounts(3).to_string(), U64(10)),
+                (
+                    ProposalAction::Vote { option: VOTE_REJECT.to_string(), weight: U128(10) },
+                    accounts(1).to_string(),
+                    U64(20)
+                ),
+                (ProposalAction::Unvote { option: VOTE_REJECT.to_string() }, accounts(1).to_string(), U64(30)),
+                (ProposalAction::Veto, owner().to_string(), U64(40)),
+            ]
+        );
+        assert_eq!(contract.get_proposal_activity(id, 3, 10).len(), 2);
+    }
+
+    #[test]
+    fn test_proposal_activity_cap() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        for amount in 1..=MAX_PROPOSAL_ACTIVITY as u128 {
+            transfer_call(&mut context, &mut contract, accounts(1), amount, &msg);
+        }
+        let activity = get_proposal(&contract, id).activity;
+        assert_eq!(activity.len(), MAX_PROPOSAL_ACTIVITY);
+        // Creation was dropped as the oldest entry.
+        assert_eq!(activity[0].action, ProposalAction::Donate { token: token().to_string(), amount: U128(1) });
+        assert_eq!(
+            activity[MAX_PROPOSAL_ACTIVITY - 1].action,
+            ProposalAction::Donate { token: token().to_string(), amount: U128(MAX_PROPOSAL_ACTIVITY as u128) }
+        );
+    }
+
+    fn usdc() -> ValidAccountId {
+        "usdc.near".try_into().unwrap()
+    }
+
+    // Adds `Donate` proposal accepting the DAO token and USDC, last milestone paid in USDC.
+    fn add_multi_token_proposal(context: &mut VMContextBuilder, contract: &mut Contract) -> String {
+        accept_token(context, contract, usdc());
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        let milestone = |amount, token: Option<ValidAccountId>| Milestone {
+            amount: U128(amount),
+            description: "milestone".to_string(),
+            token: token.map(|token| token.to_string()),
+            paid: false,
+        };
+        let id = contract.add_proposal(ProposalInput {
+            accepted_tokens: vec![token().to_string(), usdc().to_string()],
+            milestones: vec![milestone(50, None), milestone(30, Some(usdc()))],
+            ..proposal_input(ProposalKind::Donate)
+        });
+        testing_env!(context.attached_deposit(0).build());
+        format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id)
+    }
+
+    #[test]
+    fn test_multi_token_donations() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_multi_token_proposal(&mut context, &mut contract);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 50, &msg), 0);
+        assert_eq!(transfer_call_from(&mut context, &mut contract, usdc(), accounts(1), 20, &msg), 0);
+        assert_eq!(transfer_call_from(&mut context, &mut contract, usdc(), accounts(2), 10, &msg), 0);
+        let other: ValidAccountId = "other.near".try_into().unwrap();
+        assert_eq!(transfer_call_from(&mut context, &mut contract, other, accounts(2), 10, &msg), 10);
+
+        let proposal = get_proposal(&contract, 0);
+        assert_eq!(proposal.total_donations, 80);
+        assert_eq!(proposal.token_donations.get(&token().to_string()), Some(&50));
+        assert_eq!(proposal.token_donations.get(&usdc().to_string()), Some(&30));
+        let record = proposal.donations.get(&accounts(1).to_string()).unwrap();
+        assert_eq!(record.amount, U128(70));
+        assert_eq!(record.tokens.get(&usdc().to_string()), Some(&U128(20)));
+
+        // Proposals without a list only take the DAO token.
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        assert_eq!(transfer_call_from(&mut context, &mut contract, usdc(), accounts(1), 10, &msg), 10);
+        assert_eq!(get_proposal(&contract, id).accepted_tokens, vec![token().to_string()]);
+    }
+
+    #[test]
+    fn test_multi_token_refund() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_multi_token_proposal(&mut context, &mut contract);
+        transfer_call(&mut context, &mut contract, accounts(1), 50, &msg);
+        transfer_call_from(&mut context, &mut contract, usdc(), accounts(1), 20, &msg);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.veto_proposal(0, "spam".to_string());
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_donation_refund(0, Some(usdc()));
+        let receipts = created_receipts();
+        assert!(receipts[0].contains(&format!("receiver_id: \"{}\"", usdc())));
+        assert!(receipts[0].contains("\\\"amount\\\":\\\"20\\\""));
+        let proposal = get_proposal(&contract, 0);
+        assert_eq!(proposal.total_donations, 50);
+        assert_eq!(proposal.token_donations.get(&usdc().to_string()), Some(&0));
+        assert_eq!(proposal.donations.get(&accounts(1).to_string()).unwrap().amount, U128(50));
+
+        // Failed refund puts the USDC part back.
+        let refunded = DonorRecord {
+            amount: U128(20),
+            tokens: vec![(usdc().to_string(), U128(20))].into_iter().collect(),
+            first_donation_time: U64(0),
+            last_donation_time: U64(0),
+        };
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        contract.on_donation_refund(0, accounts(1).to_string(), refunded);
+        let proposal = get_proposal(&contract, 0);
+        assert_eq!(proposal.total_donations, 70);
+        assert_eq!(proposal.token_donations.get(&usdc().to_string()), Some(&20));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_donation_refund(0, None);
+        assert!(created_receipts()[0].contains(&format!("receiver_id: \"{}\"", token())));
+        let record = get_proposal(&contract, 0).donations.get(&accounts(1).to_string()).cloned().unwrap();
+        assert_eq!(record.amount, U128(20));
+        assert_eq!(record.tokens.get(&token().to_string()), None);
+    }
+
+    #[test]
+    fn test_multi_token_milestones() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_multi_token_proposal(&mut context, &mut contract);
+        transfer_call(&mut context, &mut contract, accounts(1), 50, &msg);
+        transfer_call_from(&mut context, &mut contract, usdc(), accounts(2), 30, &msg);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.release_milestone(0, 0);
+        assert!(created_receipts()[0].contains(&format!("receiver_id: \"{}\"", token())));
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.release_milestone(0, 1);
+        assert!(created_receipts()[0].contains(&format!("receiver_id: \"{}\"", usdc())));
+        assert_eq!(get_proposal(&contract, 0).get_released_token_amount(&usdc().to_string()), 30);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_DONATIONS")]
+    fn test_multi_token_milestone_needs_donations_in_its_token() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = add_multi_token_proposal(&mut context, &mut contract);
+        transfer_call(&mut context, &mut contract, accounts(1), 100, &msg);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.release_milestone(0, 0);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.release_milestone(0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_ACCEPTED_TOKENS")]
+    fn test_duplicate_accepted_tokens() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        contract.add_proposal(ProposalInput {
+            accepted_tokens: vec![usdc().to_string(), usdc().to_string()],
+            ..proposal_input(ProposalKind::Donate)
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_MILESTONE_TOKEN_NOT_ACCEPTED")]
+    fn test_milestone_token_not_accepted() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        contract.add_proposal(ProposalInput {
+            milestones: vec![Milestone {
+                amount: U128(10),
+                description: "milestone".to_string(),
+                token: Some(usdc().to_string()),
+                paid: false,
+            }],
+            ..proposal_input(ProposalKind::Donate)
+        });
+    }
+
+    fn delegating_proposers_policy(min_proposer_delegation: Balance) -> Policy {
+        Policy {
+            proposers_must_delegate: true,
+            min_proposer_delegation: U128(min_proposer_delegation),
+            ..test_policy()
+        }
+    }
+
+    #[test]
+    fn test_proposers_must_delegate() {
+        let (mut context, mut contract) = setup_contract_with_policy(delegating_proposers_policy(10));
+        delegate(&mut context, &mut contract, owner(), 10);
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND * 2).build());
+        contract.add_proposals(vec![proposal_input(ProposalKind::Donate), proposal_input(ProposalKind::Donate)]);
+        assert_eq!(contract.get_last_proposal_id(), 3);
+
+        // Without the flag the delegation doesn't matter.
+        let (mut context, mut contract) = setup_contract();
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_DELEGATION")]
+    fn test_proposer_below_min_delegation() {
+        let (mut context, mut contract) = setup_contract_with_policy(delegating_proposers_policy(10));
+        delegate(&mut context, &mut contract, owner(), 9);
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_DELEGATION")]
+    fn test_proposer_without_delegation() {
+        let (mut context, mut contract) = setup_contract_with_policy(delegating_proposers_policy(0));
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_DELEGATION")]
+    fn test_batch_proposer_without_delegation() {
+        let (mut context, mut contract) = setup_contract_with_policy(delegating_proposers_policy(10));
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        contract.add_proposals(vec![proposal_input(ProposalKind::Donate)]);
     }
 }
@@ -0,0 +1,281 @@
+use crate::*;
+
+// Scale of `reward_per_token`, so shares of small delegations aren't rounded away.
+pub const REWARD_PRECISION: Balance = 1_000_000_000_000;
+
+// Rewards of a delegator, settled each time their delegation changes.
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct RewardAccount {
+    // Value of `reward_per_token` when the rewards were last settled.
+    pub reward_per_token_paid: Balance,
+    // Rewards settled but not claimed yet.
+    pub unclaimed: Balance,
+}
+
+impl Contract {
+    // Returns rewards of given account, including the ones earned since they were last settled.
+    pub fn internal_reward_account(&self, account_id: &AccountId) -> RewardAccount {
+        let account = self.reward_accounts.get(account_id).unwrap_or_default();
+        let paid = std::cmp::max(account.reward_per_token_paid, self.reward_checkpoint);
+        let earned = self
+            .get_user_weight(account_id)
+            .checked_mul(self.reward_per_token - paid)
+            .expect("ERR_REWARD_OVERFLOW")
+            / REWARD_PRECISION;
+        RewardAccount {
+            reward_per_token_paid: self.reward_per_token,
+            unclaimed: account.unclaimed + earned,
+        }
+    }
+
+    // Settles rewards of given account at its current delegation, must run before the delegation changes.
+    pub fn internal_settle_rewards(&mut self, account_id: &AccountId) {
+        let account = self.internal_reward_account(account_id);
+        self.reward_accounts.insert(account_id, &account);
+    }
+}
+
+#[ext_contract(ext_rewards)]
+pub trait RewardCallbacks {
+    fn on_rewards_balance(&mut self, amount: U128);
+    fn on_rewards_claimed(&mut self, account_id: AccountId, amount: U128) -> U128;
+}
+
+#[near_bindgen]
+impl Contract {
+    // Shares `amount` of the treasury between delegators, pro rata to their current delegation.
+    // Called by the owner or by the DAO itself through a proposal.
+    pub fn distribute_rewards(&mut self, amount: U128) -> Promise {
+        let predecessor_id = env::predecessor_account_id();
+        assert!(
+            predecessor_id == self.owner_id || predecessor_id == env::current_account_id(),
+            "ERR_NOT_ALLOWED"
+        );
+        assert!(amount.0 > 0, "ERR_INVALID_AMOUNT");
+        assert!(self.total_delegation_amount > 0, "ERR_NO_DELEGATIONS");
+        // Treasury balance is checked before the rewards are recorded.
+        ext_fungible_token::ft_balance_of(
+            env::current_account_id(),
+            &self.token_account,
+            0,
+            GAS_FOR_FT_BALANCE_OF,
+        )
+        .then(ext_rewards::on_rewards_balance(
+            amount,
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+    }
+
+    // Records the reward checkpoint if treasury has enough tokens outside of `locked_amount`.
+    // Rewards stay locked until claimed.
+    #[private]
+    pub fn on_rewards_balance(&mut self, amount: U128) {
+        let balance = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<U128>(&value).map(|balance| balance.0).unwrap_or(0)
+            },
+            _ => 0,
+        };
+        if balance.saturating_sub(self.internal_tracked_balance(&self.token_account)) < amount.0 {
+            env::log(b"Rewards not distributed: ERR_NOT_ENOUGH_TREASURY_BALANCE");
+            return;
+        }
+        if self.total_delegation_amount == 0 {
+            env::log(b"Rewards not distributed: ERR_NO_DELEGATIONS");
+            return;
+        }
+        let reward_per_token = amount.0.checked_mul(REWARD_PRECISION).expect("ERR_REWARD_OVERFLOW")
+            / self.total_delegation_amount;
+        self.reward_per_token = self
+            .reward_per_token
+            .checked_add(reward_per_token)
+            .expect("ERR_REWARD_OVERFLOW");
+        self.locked_amount += amount.0;
+    }
+
+    // Sends rewards of the caller earned so far, returns the amount claimed.
+    pub fn claim_rewards(&mut self) -> PromiseOrValue<U128> {
+        self.assert_not_paused();
+        let account_id = env::predecessor_account_id();
+        self.internal_settle_rewards(&account_id);
+        let mut account = match self.reward_accounts.get(&account_id) {
+            Some(account) if account.unclaimed > 0 => account,
+            _ => return PromiseOrValue::Value(U128(0)),
+        };
+        let amount = account.unclaimed;
+        account.unclaimed = 0;
+        self.reward_accounts.insert(&account_id, &account);
+        self.locked_amount = self.locked_amount.checked_sub(amount).expect("ERR_LOCKED_AMOUNT_UNDERFLOW");
+        ext_fungible_token::ft_transfer(
+            account_id.clone(),
+            U128(amount),
+            None,
+            &self.token_account,
+            ONE_YOCTO_NEAR,
+            GAS_FOR_FT_TRANSFER,
+        )
+        .then(ext_rewards::on_rewards_claimed(
+            account_id,
+            U128(amount),
+            &env::current_account_id(),
+            0,
+            GAS_FOR_EXECUTION_CALLBACK,
+        ))
+        .into()
+    }
+
+    // Gives the rewards back to the account if the transfer of `claim_rewards` didn't go through.
+    #[private]
+    pub fn on_rewards_claimed(&mut self, account_id: AccountId, amount: U128) -> U128 {
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            return amount;
+        }
+        self.locked_amount = self.locked_amount.checked_add(amount.0).expect("ERR_LOCKED_AMOUNT_OVERFLOW");
+        let mut account = self.internal_reward_account(&account_id);
+        account.unclaimed += amount.0;
+        self.reward_accounts.insert(&account_id, &account);
+        env::log(format!("Rewards of {} not claimed: ERR_CLAIM_FAILED", account_id).as_bytes());
+        U128(0)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, testing_env_with_promise_results, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
+
+    fn distribute(context: &mut VMContextBuilder, contract: &mut Contract, amount: Balance) {
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.distribute_rewards(U128(amount));
+        let balance = contract.locked_amount + amount;
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(balance)).unwrap()),
+        );
+        contract.on_rewards_balance(U128(amount));
+    }
+
+    // Claims rewards of given account, returns the amount sent by the claim.
+    fn claim(context: &mut VMContextBuilder, contract: &mut Contract, account_id: ValidAccountId) -> Balance {
+        let locked_amount = contract.locked_amount;
+        testing_env!(context.predecessor_account_id(account_id).build());
+        match contract.claim_rewards() {
+            PromiseOrValue::Value(amount) => assert_eq!(amount, U128(0)),
+            PromiseOrValue::Promise(promise) => {
+                drop(promise);
+                let receipts = created_receipts();
+                assert!(receipts.iter().any(|r| r.contains("ft_transfer")));
+                assert!(receipts.iter().any(|r| r.contains("on_rewards_claimed")));
+            },
+        }
+        locked_amount - contract.locked_amount
+    }
+
+    #[test]
+    fn test_rewards_pro_rata() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 200);
+        delegate(&mut context, &mut contract, accounts(2), 100);
+        distribute(&mut context, &mut contract, 30);
+        assert_eq!(contract.locked_amount, 330);
+
+        assert_eq!(claim(&mut context, &mut contract, accounts(1)), 20);
+        assert_eq!(claim(&mut context, &mut contract, accounts(2)), 10);
+        assert_eq!(contract.locked_amount, 300);
+
+        // Claiming again gives nothing.
+        assert_eq!(claim(&mut context, &mut contract, accounts(1)), 0);
+        assert_eq!(contract.locked_amount, 300);
+    }
+
+    #[test]
+    fn test_rewards_follow_delegation_changes() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        distribute(&mut context, &mut contract, 50);
+
+        // Rewards already earned stay with the delegator, later ones follow the new delegations.
+        delegate(&mut context, &mut contract, accounts(2), 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(50), None);
+        distribute(&mut context, &mut contract, 30);
+        assert_eq!(contract.get_unclaimed_rewards(accounts(1).to_string()), U128(60));
+        assert_eq!(contract.get_unclaimed_rewards(accounts(2).to_string()), U128(20));
+
+        assert_eq!(claim(&mut context, &mut contract, accounts(1)), 60);
+        assert_eq!(claim(&mut context, &mut contract, accounts(2)), 20);
+        assert_eq!(contract.get_unclaimed_rewards(accounts(2).to_string()), U128(0));
+    }
+
+    #[test]
+    fn test_rewards_restored_on_failed_claim() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        distribute(&mut context, &mut contract, 40);
+        assert_eq!(claim(&mut context, &mut contract, accounts(1)), 40);
+        assert_eq!(contract.get_unclaimed_rewards(accounts(1).to_string()), U128(0));
+
+        testing_env_with_promise_results(context.predecessor_account_id(dao()).build(), PromiseResult::Failed);
+        assert_eq!(contract.on_rewards_claimed(accounts(1).to_string(), U128(40)), U128(0));
+        assert_eq!(contract.get_unclaimed_rewards(accounts(1).to_string()), U128(40));
+        assert_eq!(contract.locked_amount, 140);
+    }
+
+    #[test]
+    fn test_rewards_need_treasury_balance() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.distribute_rewards(U128(30));
+        let receipts = created_receipts();
+        assert!(receipts.iter().any(|r| r.contains("ft_balance_of") && r.contains(&token().to_string())));
+
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(120)).unwrap()),
+        );
+        contract.on_rewards_balance(U128(30));
+        assert_eq!(contract.get_unclaimed_rewards(accounts(1).to_string()), U128(0));
+        assert_eq!(contract.locked_amount, 100);
+    }
+
+    #[test]
+    fn test_distribute_rewards_keeps_donations() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        transfer_call(&mut context, &mut contract, accounts(2), 50, "{\"purpose\":\"OpenDonate\"}");
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.distribute_rewards(U128(30));
+        // Only the delegations and the donation are on the balance.
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(150)).unwrap()),
+        );
+        contract.on_rewards_balance(U128(30));
+        assert_eq!(contract.get_unclaimed_rewards(accounts(1).to_string()), U128(0));
+        assert_eq!(contract.locked_amount, 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_distribute_rewards_not_owner() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.distribute_rewards(U128(30));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NO_DELEGATIONS")]
+    fn test_distribute_rewards_without_delegations() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.distribute_rewards(U128(30));
+    }
+}
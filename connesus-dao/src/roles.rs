@@ -0,0 +1,191 @@
+use crate::*;
+
+// Label of the permissions of bounties, which aren't tied to a proposal kind.
+pub const BOUNTY_PERMISSION_LABEL: &str = "bounty";
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    // `owner_id` of the contract.
+    Owner,
+    // Members of the council, see `add_council_member`.
+    Council,
+}
+
+// Actions gated by roles, per label of the proposal kind. Bounty reviews use `BOUNTY_PERMISSION_LABEL`.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PermissionAction {
+    AddProposal,
+    RemoveProposal,
+    Finalize,
+    Veto,
+    // Approving and rejecting claims, which the creator and reviewers of the bounty can always do.
+    ReviewBounty,
+}
+
+impl PermissionAction {
+    fn is_valid_label(&self, label: &str) -> bool {
+        match self {
+            PermissionAction::ReviewBounty => label == BOUNTY_PERMISSION_LABEL,
+            _ => PROPOSAL_KIND_LABELS.contains(&label),
+        }
+    }
+}
+
+impl Contract {
+    pub(crate) fn has_role(&self, account_id: &AccountId, role: Role) -> bool {
+        match role {
+            Role::Owner => account_id == &self.owner_id,
+            Role::Council => self.council.contains(account_id),
+        }
+    }
+
+    // Roles allowed to take given action, only the owner unless changed with `set_permission`.
+    pub(crate) fn internal_permission_roles(&self, label: &str, action: PermissionAction) -> Vec<Role> {
+        self.permissions.get(&(label.to_string(), action)).unwrap_or_else(|| vec![Role::Owner])
+    }
+
+    pub(crate) fn has_permission(&self, account_id: &AccountId, label: &str, action: PermissionAction) -> bool {
+        self.internal_permission_roles(label, action)
+            .into_iter()
+            .any(|role| self.has_role(account_id, role))
+    }
+
+    // Panics unless the caller may take given action. While it's left to the owner the error stays `ONLY_OWNER`.
+    pub(crate) fn assert_permission(&self, label: &str, action: PermissionAction) {
+        let roles = self.internal_permission_roles(label, action);
+        let allowed = roles.iter().any(|role| self.has_role(&env::predecessor_account_id(), *role));
+        if !allowed {
+            env::panic(if roles == vec![Role::Owner] { b"ONLY_OWNER" } else { b"ERR_PERMISSION_DENIED" });
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // Council members take the actions given to `Role::Council`, see `set_permission`.
+    pub fn add_council_member(&mut self, account_id: ValidAccountId) {
+        self.assert_owner_or_dao();
+        assert!(self.council.len() < MAX_COUNCIL_MEMBERS, "ERR_TOO_MANY_COUNCIL_MEMBERS");
+        self.council.insert(account_id.as_ref());
+    }
+
+    pub fn remove_council_member(&mut self, account_id: ValidAccountId) {
+        self.assert_owner_or_dao();
+        assert!(self.council.remove(account_id.as_ref()), "ERR_NOT_COUNCIL_MEMBER");
+    }
+
+    // Sets roles allowed to take given action on proposals with given kind label, or on bounties.
+    // Empty `roles` leave it to the owner again.
+    pub fn set_permission(&mut self, label: String, action: PermissionAction, roles: Vec<Role>) {
+        self.assert_owner_or_dao();
+        assert!(action.is_valid_label(&label), "ERR_INVALID_POLICY_LABEL");
+        if roles.is_empty() {
+            self.permissions.remove(&(label, action));
+        } else {
+            self.permissions.insert(&(label, action), &roles);
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
+
+    fn setup_council() -> (VMContextBuilder, Contract) {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.add_council_member(accounts(1));
+        contract.set_permission("donate".to_string(), PermissionAction::AddProposal, vec![Role::Owner, Role::Council]);
+        contract.set_permission("donate".to_string(), PermissionAction::Veto, vec![Role::Council]);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_council_adds_proposal() {
+        let (mut context, mut contract) = setup_council();
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        let id = contract.add_proposal(proposal_input(ProposalKind::Donate));
+        assert_eq!(get_proposal(&contract, id).proposer, accounts(1).to_string());
+        // Other kinds are still left to the owner.
+        assert!(!contract.has_permission(&accounts(1).to_string(), "poll", PermissionAction::AddProposal));
+        assert!(contract.has_permission(&owner().to_string(), "poll", PermissionAction::AddProposal));
+        assert_eq!(contract.get_council(), vec![accounts(1).to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PERMISSION_DENIED")]
+    fn test_random_account_adds_proposal() {
+        let (mut context, mut contract) = setup_council();
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        contract.add_proposal(proposal_input(ProposalKind::Donate));
+    }
+
+    #[test]
+    fn test_council_vetoes_proposal() {
+        let (mut context, mut contract) = setup_council();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(0).build());
+        contract.veto_proposal(id, "spam".to_string());
+        assert_eq!(get_proposal(&contract, id).status, ProposalStatus::Vetoed);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PERMISSION_DENIED")]
+    fn test_owner_without_permission() {
+        let (mut context, mut contract) = setup_council();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(0).build());
+        contract.veto_proposal(id, "spam".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_PERMISSION_DENIED")]
+    fn test_removed_council_member() {
+        let (mut context, mut contract) = setup_council();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(0).build());
+        contract.remove_council_member(accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.veto_proposal(id, "spam".to_string());
+    }
+
+    #[test]
+    fn test_council_changed_by_dao() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(dao()).build());
+        contract.add_council_member(accounts(2));
+        contract.set_permission(BOUNTY_PERMISSION_LABEL.to_string(), PermissionAction::ReviewBounty, vec![Role::Council]);
+        assert_eq!(
+            contract.get_permission_roles(BOUNTY_PERMISSION_LABEL.to_string(), PermissionAction::ReviewBounty),
+            vec![Role::Council]
+        );
+        contract.set_permission(BOUNTY_PERMISSION_LABEL.to_string(), PermissionAction::ReviewBounty, vec![]);
+        assert_eq!(
+            contract.get_permission_roles(BOUNTY_PERMISSION_LABEL.to_string(), PermissionAction::ReviewBounty),
+            vec![Role::Owner]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_add_council_member_not_owner() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.add_council_member(accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_POLICY_LABEL")]
+    fn test_set_permission_invalid_label() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.set_permission("donate".to_string(), PermissionAction::ReviewBounty, vec![Role::Council]);
+    }
+}
@@ -0,0 +1,310 @@
+use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds, StorageManagement};
+use near_sdk::assert_one_yocto;
+
+use crate::*;
+
+impl Contract {
+    // Deposit an account needs before its delegation, donation and bounty claim records are created.
+    pub fn storage_registration_cost(&self) -> Balance {
+        ACCOUNT_STORAGE_BYTES as Balance * env::storage_byte_cost()
+    }
+
+    pub fn is_registered(&self, account_id: &AccountId) -> bool {
+        self.storage_deposits.contains_key(account_id)
+    }
+
+    fn internal_storage_balance_of(&self, account_id: &AccountId) -> Option<StorageBalance> {
+        self.storage_deposits.get(account_id).map(|deposit| StorageBalance {
+            total: U128(deposit),
+            available: U128(0),
+        })
+    }
+
+    // Returns true while given account has records its deposit pays for.
+    fn has_storage_records(&self, account_id: &AccountId) -> bool {
+        self.get_user_weight(account_id) > 0
+            || self
+                .accepted_tokens
+                .iter()
+                .any(|token_id| self.get_token_delegation(&token_id, account_id) > 0)
+            || self.delegation_funds.contains_key(account_id)
+            || self.pending_withdrawals.contains_key(account_id)
+            || self.donations.get(account_id).is_some()
+            || self.near_donations.contains_key(account_id)
+            || self
+                .donation_tokens
+                .iter()
+                .any(|token_id| self.token_open_donations.contains_key(&(token_id, account_id.clone())))
+            || self.internal_reward_account(account_id).unclaimed > 0
+            || self.bounty_claims_by_account.get(account_id).is_some_and(|ids| !ids.is_empty())
+    }
+
+    // Removes what is left of the records of an account without balances, see `has_storage_records`.
+    fn internal_remove_storage_records(&mut self, account_id: &AccountId) {
+        self.reward_accounts.remove(account_id);
+        self.delegation_history.remove(account_id);
+        self.delegation_locks.remove(account_id);
+        self.delegation_approvals.remove(account_id);
+        self.bounty_claims_by_account.remove(account_id);
+    }
+}
+
+// Registers delegators, donors and bounty claimants. Every account pays the same fixed deposit, so nothing
+// is ever available for withdrawal above it.
+#[near_bindgen]
+impl StorageManagement for Contract {
+    #[payable]
+    fn storage_deposit(&mut self, account_id: Option<ValidAccountId>, registration_only: Option<bool>) -> StorageBalance {
+        let _ = registration_only;
+        let amount = env::attached_deposit();
+        let account_id = account_id.map_or_else(env::predecessor_account_id, |account_id| account_id.into());
+        let cost = self.storage_registration_cost();
+        if self.is_registered(&account_id) {
+            if amount > 0 {
+                Promise::new(env::predecessor_account_id()).transfer(amount);
+            }
+        } else {
+            assert!(amount >= cost, "ERR_DEPOSIT_LESS_THAN_STORAGE_COST");
+            self.storage_deposits.insert(&account_id, &cost);
+            if amount > cost {
+                Promise::new(env::predecessor_account_id()).transfer(amount - cost);
+            }
+        }
+        self.internal_storage_balance_of(&account_id).unwrap()
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = self.internal_storage_balance_of(&account_id).expect("ERR_NOT_REGISTERED");
+        assert!(amount.map_or(true, |amount| amount.0 == 0), "ERR_NOT_ENOUGH_AVAILABLE_STORAGE");
+        balance
+    }
+
+    // Refunds the deposit once the account has no delegation, funds, pending withdrawals, donations,
+    // rewards or bounty claims, and removes its remaining empty records.
+    // `force` is not supported, those balances can't be burned.
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        self.assert_not_paused();
+        let _ = force;
+        let account_id = env::predecessor_account_id();
+        if !self.is_registered(&account_id) {
+            return false;
+        }
+        assert!(!self.has_storage_records(&account_id), "ERR_STORAGE_IN_USE");
+        self.internal_remove_storage_records(&account_id);
+        let deposit = self.storage_deposits.remove(&account_id).unwrap_or_default();
+        Promise::new(account_id).transfer(deposit);
+        true
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        let cost = self.storage_registration_cost();
+        StorageBalanceBounds {
+            min: U128(cost),
+            max: Some(U128(cost)),
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: ValidAccountId) -> Option<StorageBalance> {
+        self.internal_storage_balance_of(account_id.as_ref())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::MockedBlockchain;
+    use std::convert::TryInto;
+
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_storage_deposit() {
+        let (mut context, mut contract) = setup_contract();
+        let cost = contract.storage_registration_cost();
+        assert!(contract.storage_balance_of(accounts(1)).is_none());
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(cost + 5).build());
+        let balance = contract.storage_deposit(None, None);
+        assert_eq!((balance.total, balance.available), (U128(cost), U128(0)));
+        // Leftover above the cost goes back.
+        assert!(created_receipts()[0].contains("Transfer(TransferAction { deposit: 5 })"));
+
+        // Registering again, for someone else, keeps the deposit the same.
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(cost).build());
+        contract.storage_deposit(Some(accounts(1)), None);
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().total, U128(cost));
+        assert!(contract.storage_balance_of(accounts(2)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DEPOSIT_LESS_THAN_STORAGE_COST")]
+    fn test_storage_deposit_too_low() {
+        let (mut context, mut contract) = setup_contract();
+        let cost = contract.storage_registration_cost();
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(cost - 1).build());
+        contract.storage_deposit(None, None);
+    }
+
+    #[test]
+    fn test_unregistered_sender_is_refunded() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.predecessor_account_id(token()).build());
+        for msg in [
+            format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", accounts(1)),
+            "{\"purpose\":\"OpenDonate\"}".to_string(),
+            format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id),
+        ]
+        .iter()
+        {
+            match contract.ft_on_transfer(accounts(1), U128(10), msg.clone()) {
+                PromiseOrValue::Value(refund) => assert_eq!(refund, U128(10)),
+                PromiseOrValue::Promise(_) => panic!("unexpected promise"),
+            }
+        }
+        assert_eq!(get_logs(), vec!["ERR_NOT_REGISTERED: call storage_deposit first"; 3]);
+        assert_eq!(contract.delegation_total_supply(), U128(0));
+        assert_eq!(get_proposal(&contract, id).total_donations, 0);
+    }
+
+    #[test]
+    fn test_storage_unregister() {
+        let (mut context, mut contract) = setup_contract();
+        let cost = contract.storage_registration_cost();
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        assert!(!contract.storage_unregister(None));
+
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(10), None);
+        testing_env!(context.block_timestamp(DEFAULT_UNBONDING_PERIOD).build());
+        contract.withdraw_undelegated();
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        assert!(contract.storage_unregister(None));
+        assert!(created_receipts()[0].contains(&format!("Transfer(TransferAction {{ deposit: {} }})", cost)));
+        assert!(contract.storage_balance_of(accounts(1)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_STORAGE_IN_USE")]
+    fn test_storage_unregister_with_delegation() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.storage_unregister(Some(true));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_STORAGE_IN_USE")]
+    fn test_storage_unregister_with_pending_withdrawal() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 10);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(10), None);
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.storage_unregister(None);
+    }
+
+    fn add_bounty(contract: &mut Contract) -> u64 {
+        contract.create_bounty(&owner().to_string(), BountyInput {
+            description: "bounty".to_string(),
+            token: token().to_string(),
+            amount: U128(10),
+            times: 1,
+            max_deadline: U64(1_000_000_000 * 60 * 60),
+            expires_at: None,
+            reviewers: vec![],
+            milestones: vec![],
+            tags: vec![],
+            requires_application: false,
+            review_period: None,
+            claimant_whitelist: None,
+        })
+    }
+
+    #[test]
+    fn test_registration_covers_records() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_bounty(&mut contract);
+        // Longest valid account id.
+        let account: ValidAccountId = format!("{}.near", "a".repeat(59)).try_into().unwrap();
+        let cost = contract.storage_registration_cost();
+        let before = env::storage_usage();
+        // Every `testing_env!` resets the usage to the one of the builder.
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(account.clone())
+            .attached_deposit(cost)
+            .build());
+        contract.storage_deposit(None, None);
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", account);
+        testing_env!(context.storage_usage(env::storage_usage()).predecessor_account_id(token()).attached_deposit(0).build());
+        contract.ft_on_transfer(account.clone(), U128(10), msg);
+        contract.ft_on_transfer(account.clone(), U128(10), "{\"purpose\":\"OpenDonate\"}".to_string());
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(account)
+            .attached_deposit(DEFAULT_BOUNTY_BOND)
+            .build());
+        contract.bounty_claim(id, U64(1_000));
+        let used = env::storage_usage() - before;
+        assert!(used <= ACCOUNT_STORAGE_BYTES, "{} bytes used", used);
+        let bounds = contract.storage_balance_bounds();
+        assert_eq!(bounds.min, U128(ACCOUNT_STORAGE_BYTES as Balance * env::storage_byte_cost()));
+        assert_eq!(bounds.max, Some(bounds.min));
+    }
+
+    #[test]
+    fn test_storage_unregister_frees_records() {
+        let (mut context, mut contract) = setup_contract();
+        let cost = contract.storage_registration_cost();
+        let before = env::storage_usage();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(cost)
+            .build());
+        contract.storage_deposit(None, None);
+        let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", accounts(1));
+        testing_env!(context.storage_usage(env::storage_usage()).predecessor_account_id(token()).attached_deposit(0).build());
+        contract.ft_on_transfer(accounts(1), U128(10), msg);
+        testing_env!(context.storage_usage(env::storage_usage()).predecessor_account_id(accounts(1)).build());
+        contract.approve_delegation_from(accounts(2).to_string());
+        contract.undelegate(U128(10), None);
+        testing_env!(context.storage_usage(env::storage_usage()).block_timestamp(DEFAULT_UNBONDING_PERIOD).build());
+        contract.withdraw_undelegated();
+        assert!(contract.delegation_history.get(&accounts(1).to_string()).is_some());
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(1).build());
+        assert!(contract.storage_unregister(None));
+        assert_eq!(env::storage_usage(), before);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_REGISTERED")]
+    fn test_bounty_claim_not_registered() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_bounty(&mut contract);
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(DEFAULT_BOUNTY_BOND).build());
+        contract.bounty_claim(id, U64(1_000));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_STORAGE_IN_USE")]
+    fn test_storage_unregister_with_bounty_claim() {
+        let (mut context, mut contract) = setup_contract();
+        let id = add_bounty(&mut contract);
+        register(&mut contract, &accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(DEFAULT_BOUNTY_BOND).build());
+        contract.bounty_claim(id, U64(1_000));
+        testing_env!(context.attached_deposit(1).build());
+        contract.storage_unregister(None);
+    }
+}
@@ -0,0 +1,178 @@
+use std::convert::TryInto;
+
+use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+use near_sdk::MockedBlockchain;
+
+use crate::*;
+
+pub(crate) fn dao() -> ValidAccountId {
+    "dao.near".try_into().unwrap()
+}
+
+pub(crate) fn token() -> ValidAccountId {
+    "token.near".try_into().unwrap()
+}
+
+pub(crate) fn owner() -> ValidAccountId {
+    accounts(0)
+}
+
+pub(crate) fn get_context(predecessor: ValidAccountId) -> VMContextBuilder {
+    let mut builder = VMContextBuilder::new();
+    builder
+        .current_account_id(dao())
+        .signer_account_id(predecessor.clone())
+        .predecessor_account_id(predecessor);
+    builder
+}
+
+// Default policy without the minimum delegation, so tests can delegate small amounts.
+pub(crate) fn test_policy() -> Policy {
+    Policy {
+        min_delegation_amount: U128(0),
+        ..Policy::default()
+    }
+}
+
+pub(crate) fn setup_contract() -> (VMContextBuilder, Contract) {
+    setup_contract_with_policy(test_policy())
+}
+
+pub(crate) fn setup_contract_with_policy(policy: Policy) -> (VMContextBuilder, Contract) {
+    let context = get_context(owner());
+    testing_env!(context.build());
+    let contract = Contract::new(
+        DaoMetadata::test_config(),
+        token().to_string(),
+        owner().to_string(),
+        Some(policy),
+    );
+    (context, contract)
+}
+
+// Simulates `ft_transfer_call` of the DAO token, returns the refunded amount.
+pub(crate) fn transfer_call(
+    context: &mut VMContextBuilder,
+    contract: &mut Contract,
+    sender: ValidAccountId,
+    amount: Balance,
+    msg: &str,
+) -> Balance {
+    transfer_call_from(context, contract, token(), sender, amount, msg)
+}
+
+// Same as `transfer_call` but from the given token contract.
+pub(crate) fn transfer_call_from(
+    context: &mut VMContextBuilder,
+    contract: &mut Contract,
+    token_id: ValidAccountId,
+    sender: ValidAccountId,
+    amount: Balance,
+    msg: &str,
+) -> Balance {
+    register(contract, &sender);
+    testing_env!(context.predecessor_account_id(token_id).build());
+    match contract.ft_on_transfer(sender, U128(amount), msg.to_string()) {
+        PromiseOrValue::Value(refund) => refund.0,
+        PromiseOrValue::Promise(_) => panic!("unexpected promise"),
+    }
+}
+
+// Registers given account with the DAO, see `storage_deposit`.
+pub(crate) fn register(contract: &mut Contract, account: &ValidAccountId) {
+    let cost = contract.storage_registration_cost();
+    contract.storage_deposits.insert(&account.to_string(), &cost);
+}
+
+// Whitelists transfers of given token, see `add_accepted_token`.
+pub(crate) fn accept_token(context: &mut VMContextBuilder, contract: &mut Contract, token_id: ValidAccountId) {
+    testing_env!(context.predecessor_account_id(owner()).build());
+    contract.add_accepted_token(token_id);
+}
+
+// Delegates `amount` of DAO tokens to `account` from the same account.
+pub(crate) fn delegate(
+    context: &mut VMContextBuilder,
+    contract: &mut Contract,
+    account: ValidAccountId,
+    amount: Balance,
+) {
+    let msg = format!("{{\"purpose\":{{\"Delegate\":\"{}\"}}}}", account);
+    assert_eq!(transfer_call(context, contract, account, amount, &msg), 0);
+}
+
+pub(crate) fn proposal_input(kind: ProposalKind) -> ProposalInput {
+    ProposalInput {
+        description: "proposal".to_string(),
+        description_hash: None,
+        kind,
+        duration: Some(U64(1_000_000_000 * 60 * 60)),
+        options: HashMap::default(),
+        target: None,
+        deadline: None,
+        milestones: vec![],
+        beneficiary: None,
+        accepted_tokens: vec![],
+        tags: vec![],
+    }
+}
+
+// Adds proposal of given kind from the owner.
+pub(crate) fn add_proposal(
+    context: &mut VMContextBuilder,
+    contract: &mut Contract,
+    kind: ProposalKind,
+) -> u64 {
+    testing_env!(context
+        .predecessor_account_id(owner())
+        .attached_deposit(DEFAULT_PROPOSAL_BOND)
+        .build());
+    let id = contract.add_proposal(proposal_input(kind));
+    testing_env!(context.attached_deposit(0).build());
+    id
+}
+
+pub(crate) fn act(
+    context: &mut VMContextBuilder,
+    contract: &mut Contract,
+    account: ValidAccountId,
+    id: u64,
+    action: Action,
+) {
+    testing_env!(context.predecessor_account_id(account).build());
+    contract.act_proposal(id, action, None);
+}
+
+pub(crate) fn get_proposal(contract: &Contract, id: u64) -> Proposal {
+    contract.proposals.get(&id).expect("ERR_NO_PROPOSAL").into()
+}
+
+// Returns debug representation of the receipts created by the current call,
+// with method names and arguments of function calls decoded as strings.
+pub(crate) fn created_receipts() -> Vec<String> {
+    get_created_receipts()
+        .iter()
+        .map(|receipt| {
+            let mut output = format!("{:?}", receipt);
+            for field in ["method_name: [", "args: ["].iter() {
+                while let Some(start) = output.find(field) {
+                    let bytes_start = start + field.len();
+                    let bytes_end = bytes_start + output[bytes_start..].find(']').unwrap();
+                    let decoded: Vec<u8> = output[bytes_start..bytes_end]
+                        .split(", ")
+                        .filter(|byte| !byte.is_empty())
+                        .map(|byte| byte.parse().unwrap())
+                        .collect();
+                    output = format!(
+                        "{}{}{:?}{}",
+                        &output[..start],
+                        &field[..field.len() - 1],
+                        String::from_utf8(decoded).unwrap(),
+                        &output[bytes_end + 1..]
+                    );
+                }
+            }
+            output
+        })
+        .collect()
+}
@@ -0,0 +1,205 @@
+use crate::*;
+
+#[ext_contract(ext_tokens)]
+pub trait TokenCallbacks {
+    fn on_rescue_balance(&mut self, token_id: AccountId, receiver_id: AccountId, amount: U128);
+}
+
+impl Contract {
+    // Transfers are only accepted from the DAO token, the whitelisted tokens and the donation tokens.
+    pub(crate) fn is_accepted_token(&self, token_id: &AccountId) -> bool {
+        self.is_donation_token(token_id) || self.accepted_tokens.contains(token_id)
+    }
+
+    // Part of the balance of given token the contract keeps track of: delegations, donations, the bounty
+    // escrow, the matching pools and the campaigns. For the DAO token delegations are `locked_amount`.
+    // Every payout from the treasury has to leave this part of the balance alone, e.g. transfer proposals,
+    // governance bounties, rewards and rescues.
+    pub(crate) fn internal_tracked_balance(&self, token_id: &AccountId) -> Balance {
+        let delegations = if token_id == &self.token_account {
+            self.locked_amount
+        } else {
+            self.get_token_delegation_total(token_id)
+        };
+        let matching_pools: Balance = self
+            .active_matching_pools
+            .iter()
+            .filter_map(|id| self.matching_pools.get(id))
+            .filter(|pool| &pool.token == token_id)
+            .map(|pool| pool.remaining.0)
+            .sum();
+        delegations
+            + self.open_donation_totals.get(token_id).unwrap_or_default()
+            + self.proposal_donation_totals.get(token_id).unwrap_or_default()
+            + self.internal_bounty_escrow(token_id)
+            + matching_pools
+            + self.campaign_donation_totals.get(token_id).unwrap_or_default()
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // Accepts transfers of given token for bounties and proposal donations, in addition to the DAO token.
+    // Open donations also need it in the donation tokens, see `add_donation_token`.
+    pub fn add_accepted_token(&mut self, token_id: ValidAccountId) {
+        self.assert_owner_or_dao();
+        assert!(self.accepted_tokens.len() < MAX_WHITELISTED_TOKENS, "ERR_TOO_MANY_ACCEPTED_TOKENS");
+        self.accepted_tokens.insert(token_id.as_ref());
+    }
+
+    // Refunds further transfers of given token, tokens the DAO already holds can still be paid out.
+    pub fn remove_accepted_token(&mut self, token_id: ValidAccountId) {
+        self.assert_owner_or_dao();
+        self.accepted_tokens.remove(token_id.as_ref());
+    }
+
+    // Sends `amount` of given token that was transferred to the DAO without a call, and so isn't tracked,
+    // to `receiver_id`. Only the part of the balance above what the contract tracks can be rescued.
+    pub fn rescue_tokens(&mut self, token_id: ValidAccountId, receiver_id: ValidAccountId, amount: U128) -> Promise {
+        self.assert_not_paused();
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        assert!(amount.0 > 0, "ERR_INVALID_AMOUNT");
+        ext_fungible_token::ft_balance_of(env::current_account_id(), token_id.as_ref(), 0, GAS_FOR_FT_BALANCE_OF).then(
+            ext_tokens::on_rescue_balance(
+                token_id.into(),
+                receiver_id.into(),
+                amount,
+                &env::current_account_id(),
+                0,
+                GAS_FOR_RESCUE_TRANSFER,
+            ),
+        )
+    }
+
+    #[private]
+    pub fn on_rescue_balance(&mut self, token_id: AccountId, receiver_id: AccountId, amount: U128) -> Promise {
+        let balance = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<U128>(&value).map(|balance| balance.0).unwrap_or(0)
+            },
+            _ => 0,
+        };
+        assert!(
+            balance.saturating_sub(self.internal_tracked_balance(&token_id)) >= amount.0,
+            "ERR_NOT_ENOUGH_UNTRACKED_BALANCE"
+        );
+        ext_fungible_token::ft_transfer(receiver_id, amount, None, &token_id, ONE_YOCTO_NEAR, GAS_FOR_FT_TRANSFER)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, get_logs, testing_env_with_promise_results, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
+
+    fn create_bounty_msg(token_id: ValidAccountId) -> String {
+        format!(
+            "{{\"purpose\":{{\"CreateBounty\":{{\"description\":\"d\",\"token\":\"{}\",\"amount\":\"10\",\"times\":1,\"max_deadline\":\"{}\"}}}}}}",
+            token_id,
+            DEFAULT_PROPOSAL_PERIOD
+        )
+    }
+
+    #[test]
+    fn test_accepted_tokens() {
+        let (mut context, mut contract) = setup_contract();
+        let msg = create_bounty_msg(accounts(3));
+        assert_eq!(transfer_call_from(&mut context, &mut contract, accounts(3), owner(), 10, &msg), 10);
+        assert_eq!(get_logs(), vec!["ERR_TOKEN_NOT_WHITELISTED"]);
+
+        accept_token(&mut context, &mut contract, accounts(3));
+        assert_eq!(contract.get_accepted_tokens(), vec![token().to_string(), accounts(3).to_string()]);
+        assert_eq!(transfer_call_from(&mut context, &mut contract, accounts(3), owner(), 10, &msg), 0);
+        assert_eq!(contract.get_bounty_escrow(Some(accounts(3))), U128(10));
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.remove_accepted_token(accounts(3));
+        assert_eq!(transfer_call_from(&mut context, &mut contract, accounts(3), owner(), 10, &msg), 10);
+        // The DAO token can't be removed.
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.remove_accepted_token(token());
+        assert_eq!(transfer_call(&mut context, &mut contract, owner(), 10, &create_bounty_msg(token())), 0);
+    }
+
+    #[test]
+    fn test_donation_tokens_accepted() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.add_donation_token(accounts(3));
+        assert_eq!(contract.get_accepted_tokens(), vec![token().to_string(), accounts(3).to_string()]);
+        let msg = "{\"purpose\":\"OpenDonate\"}";
+        assert_eq!(transfer_call_from(&mut context, &mut contract, accounts(3), accounts(1), 10, msg), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ALLOWED")]
+    fn test_add_accepted_token_not_owner() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.add_accepted_token(accounts(3));
+    }
+
+    fn rescue_balance(context: &mut VMContextBuilder, contract: &mut Contract, balance: Balance, amount: Balance) {
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.rescue_tokens(token(), accounts(2), U128(amount));
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(near_sdk::serde_json::to_vec(&U128(balance)).unwrap()),
+        );
+        contract.on_rescue_balance(token().to_string(), accounts(2).to_string(), U128(amount));
+    }
+
+    fn setup_tracked_contract() -> (VMContextBuilder, Contract) {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 30, "{\"purpose\":\"OpenDonate\"}"), 0);
+        assert_eq!(transfer_call(&mut context, &mut contract, owner(), 10, &create_bounty_msg(token())), 0);
+        assert_eq!(contract.internal_tracked_balance(&token().to_string()), 140);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_rescue_untracked_tokens() {
+        let (mut context, mut contract) = setup_tracked_contract();
+        // 5 tokens were sent without a call.
+        rescue_balance(&mut context, &mut contract, 145, 5);
+        let receipts = created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert!(receipts[0].contains("ft_transfer") && receipts[0].contains("\\\"receiver_id\\\":\\\"charlie\\\""));
+        assert!(receipts[0].contains("\\\"amount\\\":\\\"5\\\""));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_UNTRACKED_BALANCE")]
+    fn test_rescue_tracked_tokens() {
+        let (mut context, mut contract) = setup_tracked_contract();
+        rescue_balance(&mut context, &mut contract, 145, 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_UNTRACKED_BALANCE")]
+    fn test_rescue_campaign_donations() {
+        let (mut context, mut contract) = setup_tracked_contract();
+        let msg = "{\"purpose\":{\"FundMatchingPool\":{\"ratio_bps\":5000,\"applies_to\":\"All\"}}}";
+        assert_eq!(transfer_call(&mut context, &mut contract, owner(), 10, msg), 0);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        let campaign_id = contract.create_campaign("drive".to_string(), U128(100), U64(1_000), None);
+        let msg = format!("{{\"purpose\":{{\"CampaignDonate\":{{\"campaign_id\":{}}}}}}}", campaign_id);
+        assert_eq!(transfer_call(&mut context, &mut contract, accounts(1), 20, &msg), 0);
+        // Both the donation and its match stay with the campaign.
+        assert_eq!(contract.internal_tracked_balance(&token().to_string()), 170);
+        rescue_balance(&mut context, &mut contract, 170, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ONLY_OWNER")]
+    fn test_rescue_tokens_not_owner() {
+        let (mut context, mut contract) = setup_contract();
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.rescue_tokens(token(), accounts(1), U128(1));
+    }
+}
@@ -14,7 +14,32 @@ pub const ONE_YOCTO_NEAR: Balance = 1;
 // Gas for single ft_transfer call.
 pub const GAS_FOR_FT_TRANSFER: Gas = 10_000_000_000_000;
 
-#[derive(BorshDeserialize, BorshSerialize, Clone, Deserialize, Serialize)]
+// Gas for ft_balance_of call.
+pub const GAS_FOR_FT_BALANCE_OF: Gas = 5_000_000_000_000;
+
+// Gas for the callback checking treasury balance, which then calls ft_transfer with its own callback.
+pub const GAS_FOR_TREASURY_TRANSFER: Gas = 30_000_000_000_000;
+
+// Gas for the callback checking balance of the token to rescue, which then calls ft_transfer.
+pub const GAS_FOR_RESCUE_TRANSFER: Gas = 20_000_000_000_000;
+
+// Gas for the callback recording result of proposal execution.
+pub const GAS_FOR_EXECUTION_CALLBACK: Gas = 10_000_000_000_000;
+
+// Gas for nft_mint of a supporter badge, see `Policy::badge_contract_id`.
+pub const GAS_FOR_NFT_MINT: Gas = 15_000_000_000_000;
+
+// Limits on function calls of a `FunctionCall` proposal.
+pub const MAX_ACTION_CALLS: usize = 10;
+pub const MIN_ACTION_CALL_GAS: Gas = 1_000_000_000_000;
+pub const MAX_ACTION_CALLS_GAS: Gas = 200_000_000_000_000;
+
+// Maximum lengths of metadata fields.
+pub const MAX_METADATA_NAME_LENGTH: usize = 64;
+pub const MAX_METADATA_SYMBOL_LENGTH: usize = 32;
+pub const MAX_METADATA_TEXT_LENGTH: usize = 1024;
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Deserialize, Serialize, PartialEq, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct DaoMetadata {
     // Name of the DAO.
@@ -37,13 +62,276 @@ pub struct DaoMetadata {
     pub instagram: Option<String>,
 }
 
+impl DaoMetadata {
+    // Panics if required fields are empty or any field is too long.
+    pub fn assert_valid(&self) {
+        assert_valid_text(&self.name, MAX_METADATA_NAME_LENGTH, "ERR_INVALID_NAME");
+        assert_valid_text(&self.purpose, MAX_METADATA_TEXT_LENGTH, "ERR_INVALID_PURPOSE");
+        assert_valid_text(&self.thumbnail, MAX_METADATA_TEXT_LENGTH, "ERR_INVALID_THUMBNAIL");
+        assert_valid_text(&self.symbol, MAX_METADATA_SYMBOL_LENGTH, "ERR_INVALID_SYMBOL");
+        for link in [&self.facebook, &self.twitter, &self.discord, &self.instagram].iter().copied().flatten() {
+            assert_valid_text(link, MAX_METADATA_TEXT_LENGTH, "ERR_INVALID_LINK");
+        }
+    }
+}
+
+fn assert_valid_text(text: &str, max_length: usize, error: &str) {
+    if text.is_empty() || text.len() > max_length {
+        env::panic(error.as_bytes());
+    }
+}
+
+// Default voting period of proposals: 7 days.
+pub const DEFAULT_PROPOSAL_PERIOD: u64 = 1_000_000_000 * 60 * 60 * 24 * 7;
+
+// Default bond for adding proposal: 1 NEAR.
+pub const DEFAULT_PROPOSAL_BOND: Balance = 1_000_000_000_000_000_000_000_000;
+
+pub const DEFAULT_MAX_ACTIVE_PROPOSALS: u64 = 5;
+
+// Number of times proposer can extend the voting period of a proposal.
+pub const MAX_PROPOSAL_EXTENSIONS: u32 = 2;
+
+// Default unbonding period of undelegated tokens, as long as the default voting period.
+pub const DEFAULT_UNBONDING_PERIOD: u64 = DEFAULT_PROPOSAL_PERIOD;
+
+// Default time donors can take back their open donations, a day.
+pub const DEFAULT_DONATION_REFUND_PERIOD: u64 = 1_000_000_000 * 60 * 60 * 24;
+
+// Maximum number of undelegations of an account waiting to be withdrawn.
+pub const MAX_PENDING_WITHDRAWALS: usize = 10;
+
+// Storage paid by the deposit of a delegator, donor or bounty claimant, for its delegation, funds,
+// donation and bounty claim records. Account ids appear in several of them, so it leaves room for long ones.
+pub const ACCOUNT_STORAGE_BYTES: u64 = 2_500;
+
+// One token of the DAO, in its smallest units.
+pub const DEFAULT_MIN_DELEGATION_AMOUNT: Balance = 1_000_000_000_000_000_000_000_000;
+
+// Number of delegation changes kept per account.
+pub const MAX_DELEGATION_HISTORY: usize = 50;
+
+// Longest lock of a delegation, doubling its voting weight.
+pub const MAX_LOCK_DURATION: u64 = 1_000_000_000 * 60 * 60 * 24 * 365;
+// Maximum number of locked delegations of an account.
+pub const MAX_DELEGATION_LOCKS: usize = 10;
+
+// Maximum number of tokens accepted for open donations besides the DAO token.
+pub const MAX_DONATION_TOKENS: u64 = 10;
+
+// Maximum number of donors returned by `get_top_donors`.
+pub const MAX_TOP_DONORS: u64 = 100;
+
+// Category of open donations made without one.
+pub const GENERAL_DONATION_CATEGORY: &str = "general";
+// Maximum number of donation categories besides the general one, and length of their names.
+pub const MAX_DONATION_CATEGORIES: u64 = 20;
+pub const MAX_DONATION_CATEGORY_LENGTH: usize = 32;
+// Maximum number of tokens accepted besides the DAO token, see `add_accepted_token`.
+pub const MAX_WHITELISTED_TOKENS: u64 = 50;
+// Maximum number of council members, see `add_council_member`.
+pub const MAX_COUNCIL_MEMBERS: u64 = 50;
+
+// Donor shown in public views and events instead of the account of anonymous donations.
+pub const ANONYMOUS_DONOR: &str = "anonymous";
+
+// Maximum ratio of a matching pool, ten times the donation.
+pub const MAX_MATCHING_RATIO_BPS: u32 = 100_000;
+// Maximum number of matching pools that didn't run out yet.
+pub const MAX_ACTIVE_MATCHING_POOLS: usize = 10;
+
+// Default bond for claiming a bounty: 1 NEAR.
+pub const DEFAULT_BOUNTY_BOND: Balance = 1_000_000_000_000_000_000_000_000;
+// Default time claimant can give up a bounty and get the bond back: 1 day.
+pub const DEFAULT_BOUNTY_FORGIVENESS_PERIOD: u64 = 1_000_000_000 * 60 * 60 * 24;
+// Default number of bounties a single account can have claimed at once.
+pub const DEFAULT_MAX_BOUNTY_CLAIMS_PER_ACCOUNT: u32 = 3;
+// Maximum number of claims a bounty can have at once.
+pub const MAX_BOUNTY_CLAIMS: usize = 10;
+// Maximum number of reviewers of a bounty.
+pub const MAX_BOUNTY_REVIEWERS: usize = 10;
+// Maximum number of milestones of a bounty.
+pub const MAX_BOUNTY_MILESTONES: usize = 10;
+// Maximum number of tags of a bounty, each at most `MAX_TAG_LENGTH` long.
+pub const MAX_BOUNTY_TAGS: usize = 5;
+// Limits on applications for a bounty, see `Bounty::requires_application`.
+pub const MAX_BOUNTY_APPLICATIONS: usize = 50;
+pub const MAX_BOUNTY_APPLICATION_LENGTH: usize = 1024;
+// Maximum number of accounts allowed to claim a bounty with a whitelist.
+pub const MAX_BOUNTY_WHITELIST: usize = 50;
+// Maximum number of recipients of a split bounty payout.
+pub const MAX_BOUNTY_SPLIT_RECIPIENTS: usize = 10;
+
+// Maximum number of accounts a delegate accepts delegations from.
+pub const MAX_DELEGATION_APPROVALS: usize = 100;
+
+// Ratios of the policy are expressed in basis points.
+pub const MAX_BASIS_POINTS: u32 = 10_000;
+
+// Rules of this DAO that are not part of its metadata.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Deserialize, Serialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Policy {
+    // Voting period of proposals, in nanoseconds. Used when proposal doesn't set its own duration.
+    pub proposal_period: U64,
+    // Minimum amount of $NEAR proposer must attach, refunded when proposal is settled.
+    pub proposal_bond: U128,
+    // Share of the total delegation that has to vote, in basis points.
+    pub quorum: u32,
+    // Share of the cast votes that has to approve, in basis points. Approve votes must be above it.
+    pub threshold: u32,
+    // Overrides of the voting rules per proposal kind label.
+    pub kind_overrides: HashMap<String, VotePolicyOverride>,
+    // Number of proposals a single account can have in progress at once.
+    pub max_active_proposals: u64,
+    // Total time proposer can add to the voting period of a proposal, in nanoseconds.
+    pub max_extension_period: U64,
+    // When set, proposers need at least `min_proposer_delegation` delegated, and some delegation in any case.
+    pub proposers_must_delegate: bool,
+    pub min_proposer_delegation: U128,
+    // Time undelegated tokens wait before they can be withdrawn, in nanoseconds.
+    pub unbonding_period: U64,
+    // Most an account can have delegated to it, none for no limit.
+    // Delegations above a lowered cap stay, but can't grow.
+    pub max_delegation_per_account: Option<U128>,
+    // Smallest delegation an account can have, undelegating below it has to take out everything.
+    pub min_delegation_amount: U128,
+    // Smallest donation of tokens, in the units of the donated token. Smaller transfers are refunded.
+    pub min_donation_amount: U128,
+    // Time after an open donation of the DAO token its donor can take it back, in nanoseconds. Zero disables refunds.
+    pub donation_refund_period: U64,
+    // Contract minting a supporter badge to donors of at least `badge_threshold` of the DAO token in one donation.
+    pub badge_contract_id: Option<AccountId>,
+    pub badge_threshold: U128,
+    // $NEAR claimant of a bounty must attach, locked for the time of the claim.
+    pub bounty_bond: U128,
+    // Time after claiming a bounty the claimant can give it up with the bond refunded, in nanoseconds.
+    pub bounty_forgiveness_period: U64,
+    // Number of bounties a single account can have claimed at once. A claim counts until it's paid or closed.
+    pub max_bounty_claims_per_account: u32,
+}
+
+// Voting rules for a proposal kind, fields that are not set use the default of the policy.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Deserialize, Serialize, PartialEq, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VotePolicyOverride {
+    pub proposal_period: Option<U64>,
+    pub quorum: Option<u32>,
+    pub threshold: Option<u32>,
+}
+
+// Voting rules applied to a proposal.
+#[derive(Clone, PartialEq, Debug)]
+pub struct VotePolicy {
+    pub proposal_period: U64,
+    pub quorum: u32,
+    pub threshold: u32,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            proposal_period: U64(DEFAULT_PROPOSAL_PERIOD),
+            proposal_bond: U128(DEFAULT_PROPOSAL_BOND),
+            quorum: 0,
+            threshold: MAX_BASIS_POINTS / 2,
+            kind_overrides: HashMap::default(),
+            max_active_proposals: DEFAULT_MAX_ACTIVE_PROPOSALS,
+            max_extension_period: U64(DEFAULT_PROPOSAL_PERIOD),
+            proposers_must_delegate: false,
+            min_proposer_delegation: U128(0),
+            unbonding_period: U64(DEFAULT_UNBONDING_PERIOD),
+            max_delegation_per_account: None,
+            min_delegation_amount: U128(DEFAULT_MIN_DELEGATION_AMOUNT),
+            min_donation_amount: U128(0),
+            donation_refund_period: U64(DEFAULT_DONATION_REFUND_PERIOD),
+            badge_contract_id: None,
+            badge_threshold: U128(0),
+            bounty_bond: U128(DEFAULT_BOUNTY_BOND),
+            bounty_forgiveness_period: U64(DEFAULT_BOUNTY_FORGIVENESS_PERIOD),
+            max_bounty_claims_per_account: DEFAULT_MAX_BOUNTY_CLAIMS_PER_ACCOUNT,
+        }
+    }
+}
+
+impl Policy {
+    pub fn assert_valid(&self) {
+        assert!(self.max_active_proposals > 0, "ERR_INVALID_POLICY");
+        assert!(self.max_bounty_claims_per_account > 0, "ERR_INVALID_POLICY");
+        if let Some(badge_contract_id) = &self.badge_contract_id {
+            assert!(env::is_valid_account_id(badge_contract_id.as_bytes()), "ERR_INVALID_BADGE_CONTRACT");
+        }
+        self.vote_policy(None).assert_valid();
+        for (label, _) in self.kind_overrides.iter() {
+            assert!(PROPOSAL_KIND_LABELS.contains(&label.as_str()), "ERR_INVALID_POLICY_LABEL");
+            self.vote_policy(Some(label)).assert_valid();
+        }
+    }
+
+    // Returns voting rules for the proposal kind with given label.
+    pub fn vote_policy(&self, label: Option<&str>) -> VotePolicy {
+        let kind_override = label
+            .and_then(|label| self.kind_overrides.get(label))
+            .cloned()
+            .unwrap_or_default();
+        VotePolicy {
+            proposal_period: kind_override.proposal_period.unwrap_or(self.proposal_period),
+            quorum: kind_override.quorum.unwrap_or(self.quorum),
+            threshold: kind_override.threshold.unwrap_or(self.threshold),
+        }
+    }
+}
+
+impl VotePolicy {
+    pub fn assert_valid(&self) {
+        assert!(
+            self.quorum <= MAX_BASIS_POINTS && self.threshold < MAX_BASIS_POINTS,
+            "ERR_INVALID_POLICY"
+        );
+    }
+
+    // Returns true if `weight` is at least `basis_points` share of `total`.
+    fn reaches(weight: Balance, total: Balance, basis_points: u32) -> bool {
+        weight * MAX_BASIS_POINTS as u128 >= total * basis_points as u128
+    }
+
+    // Returns true if `weight` is above `basis_points` share of `total`.
+    fn exceeds(weight: Balance, total: Balance, basis_points: u32) -> bool {
+        weight * MAX_BASIS_POINTS as u128 > total * basis_points as u128
+    }
+
+    // Returns true if enough of the total delegation voted and enough of the votes approve.
+    pub fn is_approved(&self, approve: Balance, votes_cast: Balance, total_delegation: Balance) -> bool {
+        votes_cast > 0
+            && Self::reaches(votes_cast, total_delegation, self.quorum)
+            && Self::exceeds(approve, votes_cast, self.threshold)
+    }
+
+    // Returns true if approve votes pass the policy no matter how the rest of the delegation votes.
+    pub fn is_approval_decided(&self, approve: Balance, total_delegation: Balance) -> bool {
+        approve > 0
+            && Self::reaches(approve, total_delegation, self.quorum)
+            && Self::exceeds(approve, total_delegation, self.threshold)
+    }
+
+    // Returns true if reject votes prevent approval no matter how the rest of the delegation votes.
+    pub fn is_rejection_decided(&self, reject: Balance, total_delegation: Balance) -> bool {
+        reject > 0 && Self::reaches(reject, total_delegation, MAX_BASIS_POINTS - self.threshold)
+    }
+}
+
 #[cfg(test)]
 impl DaoMetadata {
     pub fn test_config() -> Self {
         Self {
             name: "Test".to_string(),
             purpose: "to test".to_string(),
-            thumbnail: "".to_string(),
+            thumbnail: "https://example.com/logo.png".to_string(),
+            symbol: "TST".to_string(),
+            facebook: None,
+            twitter: None,
+            discord: None,
+            instagram: None,
         }
     }
 }
@@ -53,6 +341,252 @@ impl DaoMetadata {
 #[serde(crate = "near_sdk::serde")]
 pub enum Action {
     Vote {option_id: String},
+    // Vote to approve given proposal.
+    VoteApprove,
+    // Vote to reject given proposal.
+    VoteReject,
+    // Vote for the option of a poll with given index.
+    VotePoll {option_index: u32},
+    // Remove given proposal, only the owner unless `set_permission` allows other roles.
+    RemoveProposal,
     Finalize
 }
 
+// Options recorded in proposal votes for approve / reject voting.
+pub const VOTE_APPROVE: &str = "approve";
+pub const VOTE_REJECT: &str = "reject";
+
+// Maximum length of a description stored in the proposal, longer ones go off-chain.
+pub const MAX_PROPOSAL_DESCRIPTION_LENGTH: usize = 2048;
+
+// Maximum number of entries kept in the activity log of a proposal.
+pub const MAX_PROPOSAL_ACTIVITY: usize = 100;
+
+// Maximum number of proposals added in a single `add_proposals` call.
+pub const MAX_BATCH_PROPOSALS: usize = 10;
+
+// Maximum number of milestones of a `Donate` proposal.
+pub const MAX_MILESTONES: usize = 16;
+
+// Limits on tags of a proposal.
+pub const MAX_PROPOSAL_TAGS: usize = 5;
+pub const MAX_TAG_LENGTH: usize = 32;
+
+// Maximum number of tokens a `Donate` proposal accepts.
+pub const MAX_ACCEPTED_TOKENS: usize = 5;
+
+// Maximum number of options in a poll.
+pub const MAX_POLL_OPTIONS: usize = 16;
+
+// Maximum number of items returned by a paginated view.
+pub const MAX_VIEW_LIMIT: u64 = 50;
+
+fn assert_valid_account_id(account_id: &AccountId, error: &str) {
+    if !env::is_valid_account_id(account_id.as_bytes()) {
+        env::panic(error.as_bytes());
+    }
+}
+
+impl ProposalKind {
+    // Panics if payload of the kind is invalid. Deposits of function calls must fit in `available_amount`.
+    pub fn validate(&self, available_amount: Balance) {
+        match self {
+            ProposalKind::Transfer { receiver_id, amount } => {
+                assert_valid_account_id(receiver_id, "ERR_INVALID_RECEIVER");
+                assert!(amount.0 > 0, "ERR_INVALID_AMOUNT");
+            },
+            ProposalKind::FunctionCall { receiver_id, actions } => {
+                assert_valid_account_id(receiver_id, "ERR_INVALID_RECEIVER");
+                assert_valid_action_calls(actions, available_amount);
+            },
+            ProposalKind::ChangeMetadata { metadata } => metadata.assert_valid(),
+            ProposalKind::ChangeStakingToken { token_id } => assert_valid_account_id(token_id, "ERR_INVALID_TOKEN"),
+            ProposalKind::ChangePolicy { policy } => policy.assert_valid(),
+            ProposalKind::Poll { options } => {
+                assert!(
+                    !options.is_empty()
+                        && options.len() <= MAX_POLL_OPTIONS
+                        && options.iter().all(|option| !option.is_empty()),
+                    "ERR_INVALID_POLL_OPTIONS"
+                );
+            },
+            // Only `bounty_dispute` creates disputes.
+            ProposalKind::ResolveBountyDispute { .. } => env::panic(b"ERR_BOUNTY_DISPUTE_PROPOSAL"),
+            ProposalKind::AddBounty { bounty } => {
+                assert_valid_account_id(&bounty.token, "ERR_INVALID_TOKEN");
+                bounty.validate().unwrap_or_else(|err| env::panic(err.as_bytes()));
+            },
+            ProposalKind::Donate | ProposalKind::Vote { .. } => {},
+        }
+    }
+}
+
+impl ProposalInput {
+    // Panics on the first invalid field, before the proposal is added by any of the creation paths.
+    // Donations default to `dao_token` when the input doesn't list accepted tokens.
+    pub fn validate(&self, dao_token: &AccountId, available_amount: Balance) {
+        if self.description_hash.is_some() {
+            assert!(self.description.is_empty(), "ERR_DESCRIPTION_AND_HASH");
+        } else {
+            assert!(!self.description.is_empty(), "ERR_EMPTY_DESCRIPTION");
+            assert!(
+                self.description.len() <= MAX_PROPOSAL_DESCRIPTION_LENGTH,
+                "ERR_DESCRIPTION_TOO_LONG"
+            );
+        }
+        self.kind.validate(available_amount);
+        if let ProposalKind::Vote { .. } = self.kind {
+            assert!(
+                !self.options.is_empty()
+                    && self.options.len() <= MAX_POLL_OPTIONS
+                    && self.options.values().all(|option| !option.title.is_empty()),
+                "ERR_INVALID_VOTE_OPTIONS"
+            );
+        }
+        if self.kind != ProposalKind::Donate {
+            assert!(
+                self.target.is_none() && self.deadline.is_none(),
+                "ERR_TARGET_ONLY_FOR_DONATE"
+            );
+            assert!(
+                self.milestones.is_empty() && self.beneficiary.is_none(),
+                "ERR_MILESTONES_ONLY_FOR_DONATE"
+            );
+            assert!(self.accepted_tokens.is_empty(), "ERR_TOKENS_ONLY_FOR_DONATE");
+        }
+        if let Some(beneficiary) = &self.beneficiary {
+            assert_valid_account_id(beneficiary, "ERR_INVALID_BENEFICIARY");
+        }
+        assert!(
+            self.accepted_tokens.len() <= MAX_ACCEPTED_TOKENS
+                && self.accepted_tokens.iter().enumerate().all(|(index, token_id)| {
+                    env::is_valid_account_id(token_id.as_bytes())
+                        && !self.accepted_tokens[..index].contains(token_id)
+                }),
+            "ERR_INVALID_ACCEPTED_TOKENS"
+        );
+        let accepted_tokens = if self.accepted_tokens.is_empty() {
+            vec![dao_token.clone()]
+        } else {
+            self.accepted_tokens.clone()
+        };
+        assert!(
+            self.milestones.iter().all(|milestone| {
+                milestone.token.as_ref().map_or(true, |token_id| accepted_tokens.contains(token_id))
+            }),
+            "ERR_MILESTONE_TOKEN_NOT_ACCEPTED"
+        );
+        assert!(self.milestones.len() <= MAX_MILESTONES, "ERR_TOO_MANY_MILESTONES");
+        assert!(
+            self.milestones.iter().all(|milestone| milestone.amount.0 > 0 && !milestone.paid),
+            "ERR_INVALID_MILESTONE"
+        );
+        assert!(self.tags.len() <= MAX_PROPOSAL_TAGS, "ERR_TOO_MANY_TAGS");
+        assert!(
+            self.tags.iter().enumerate().all(|(index, tag)| {
+                !tag.is_empty() && tag.len() <= MAX_TAG_LENGTH && !self.tags[..index].contains(tag)
+            }),
+            "ERR_INVALID_TAG"
+        );
+        assert!(self.target.map_or(true, |target| target.0 > 0), "ERR_INVALID_TARGET");
+        assert!(
+            self.deadline.map_or(true, |deadline| deadline.0 > env::block_timestamp()),
+            "ERR_INVALID_DEADLINE"
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
+
+    // Validates given input against the DAO token and no available $NEAR.
+    fn validate(input: ProposalInput) {
+        testing_env!(VMContextBuilder::new().block_timestamp(10).build());
+        input.validate(&token().to_string(), 0);
+    }
+
+    fn transfer(receiver_id: &str, amount: u128) -> ProposalKind {
+        ProposalKind::Transfer { receiver_id: receiver_id.to_string(), amount: U128(amount) }
+    }
+
+    #[test]
+    fn test_validate() {
+        validate(proposal_input(ProposalKind::Donate));
+        validate(proposal_input(transfer("alice.near", 1)));
+        validate(ProposalInput {
+            beneficiary: Some("alice.near".to_string()),
+            deadline: Some(U64(11)),
+            ..proposal_input(ProposalKind::Donate)
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_EMPTY_DESCRIPTION")]
+    fn test_validate_empty_description() {
+        validate(ProposalInput { description: "".to_string(), ..proposal_input(ProposalKind::Donate) });
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_RECEIVER")]
+    fn test_validate_transfer_receiver() {
+        validate(proposal_input(transfer("Not Valid", 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_AMOUNT")]
+    fn test_validate_transfer_amount() {
+        validate(proposal_input(transfer("alice.near", 0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_RECEIVER")]
+    fn test_validate_function_call_receiver() {
+        validate(proposal_input(ProposalKind::FunctionCall { receiver_id: "".to_string(), actions: vec![] }));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_TOKEN")]
+    fn test_validate_staking_token() {
+        validate(proposal_input(ProposalKind::ChangeStakingToken { token_id: "a".to_string() }));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_BENEFICIARY")]
+    fn test_validate_beneficiary() {
+        validate(ProposalInput {
+            beneficiary: Some("-alice".to_string()),
+            ..proposal_input(ProposalKind::Donate)
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_VOTE_OPTIONS")]
+    fn test_validate_vote_options() {
+        validate(proposal_input(ProposalKind::Vote { vote_kind: VoteKind::VoteByDelegation }));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_TARGET")]
+    fn test_validate_target() {
+        validate(ProposalInput { target: Some(U128(0)), ..proposal_input(ProposalKind::Donate) });
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_DEADLINE")]
+    fn test_validate_deadline() {
+        validate(ProposalInput { deadline: Some(U64(10)), ..proposal_input(ProposalKind::Donate) });
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_ACCEPTED_TOKENS")]
+    fn test_validate_accepted_tokens() {
+        let accepted_tokens = (0..=MAX_ACCEPTED_TOKENS).map(|index| format!("token{}.near", index)).collect();
+        validate(ProposalInput { accepted_tokens, ..proposal_input(ProposalKind::Donate) });
+    }
+}
@@ -6,4 +6,37 @@ pub(crate) fn assert_account_id(account_id: &AccountId) {
         account_id.clone(),
         "ERR_ACCOUNT_ID_NOT_ALLOWED"
     )
-}
\ No newline at end of file
+}
+
+// Logs the reason and returns the whole amount back to the sender of `ft_transfer_call`.
+pub(crate) fn refund_transfer(amount: U128, reason: &str) -> PromiseOrValue<U128> {
+    env::log(reason.as_bytes());
+    PromiseOrValue::Value(amount)
+}
+
+impl Contract {
+    // Guard of the methods moving funds or changing proposals and bounties while the contract is paused.
+    // Views, and callbacks of calls made before the pause, stay available.
+    pub(crate) fn assert_not_paused(&self) {
+        assert!(!self.paused, "ERR_PAUSED");
+    }
+}
+
+// Longest `msg` of `ft_transfer_call` that is parsed, longer ones are refunded.
+pub(crate) const MAX_TRANSFER_MSG_LEN: usize = 4_096;
+
+// Gas `ft_on_transfer` needs to record a transfer, checked before any state changes.
+pub(crate) const GAS_FOR_TRANSFER_RECORDS: Gas = 10_000_000_000_000;
+
+// Donations may also schedule the mint of a supporter badge, see `internal_mint_badge`.
+pub(crate) const GAS_FOR_DONATION: Gas = GAS_FOR_TRANSFER_RECORDS + GAS_FOR_NFT_MINT + GAS_FOR_EXECUTION_CALLBACK;
+
+// Gas that must be left for `ft_on_transfer` with given purpose.
+pub(crate) fn min_gas_for_transfer(purpose: &TransferPurpose) -> Gas {
+    match purpose {
+        TransferPurpose::OpenDonate | TransferPurpose::ProposalDonate(_) | TransferPurpose::CampaignDonate { .. } => {
+            GAS_FOR_DONATION
+        },
+        _ => GAS_FOR_TRANSFER_RECORDS,
+    }
+}
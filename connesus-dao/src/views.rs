@@ -2,108 +2,280 @@ use std::iter::FromIterator;
 
 use crate::*;
 
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct ProposalBaseInformation {
     pub proposer: AccountId,
     pub description: String,
+    // Set when the description is stored off-chain.
+    pub description_hash: Option<Base58CryptoHash>,
     pub kind: ProposalKind,
     pub status: ProposalStatus,
     pub options: HashMap<String, VoteOption>,
     pub submission_time: U64,
     pub duration: U64,
-    pub total_donations: Balance,
-    pub total_delegation_amount: Balance,
-    pub option_delegations: HashMap<String, Balance>
+    pub total_donations: U128,
+    pub total_delegation_amount: U128,
+    pub option_delegations: HashMap<String, U128>,
+    pub total_delegation_snapshot: U128,
+    pub bond: U128,
+    pub poll_winner: Option<u32>,
+    pub target: Option<U128>,
+    pub deadline: Option<U64>,
+    pub extension_count: u32,
+    pub extended_period: U64,
+    pub milestones: Vec<Milestone>,
+    pub beneficiary: Option<AccountId>,
+    pub accepted_tokens: Vec<AccountId>,
+    pub token_donations: HashMap<AccountId, U128>,
+    pub tags: Vec<String>,
 }
 
 impl From<VersionedProposal> for ProposalBaseInformation {
     fn from(proposal: VersionedProposal) -> Self {
+        let proposal: Proposal = proposal.into();
+        let status = proposal.get_status();
         let Proposal {
             proposer,
             description,
+            description_hash,
             kind,
             options,
-            status,
+            status: _,
             submission_time,
             total_delegation_amount,
             total_donations,
             option_delegations,
             duration,
             donations: _,
-            votes: _
-        } = proposal.into();
+            votes: _,
+            total_delegation_snapshot,
+            voter_weights: _,
+            bond,
+            poll_winner,
+            target,
+            deadline,
+            extension_count,
+            extended_period,
+            milestones,
+            beneficiary,
+            activity: _,
+            tags,
+            accepted_tokens,
+            token_donations,
+        } = proposal;
 
         Self {
             proposer,
             description,
+            description_hash,
             kind,
             options,
             status,
             submission_time,
-            total_delegation_amount,
-            total_donations,
-            option_delegations,
-            duration
+            total_delegation_amount: U128(total_delegation_amount),
+            total_donations: U128(total_donations),
+            option_delegations: option_delegations
+                .into_iter()
+                .map(|(option, amount)| (option, U128(amount)))
+                .collect(),
+            duration,
+            total_delegation_snapshot: U128(total_delegation_snapshot),
+            bond: U128(bond),
+            poll_winner,
+            target: target.map(U128),
+            deadline,
+            extension_count,
+            extended_period,
+            milestones,
+            beneficiary,
+            accepted_tokens,
+            token_donations: token_donations
+                .into_iter()
+                .map(|(token_id, amount)| (token_id, U128(amount)))
+                .collect(),
+            tags,
         }
     }
 }
 
+// Pending withdrawal, as part of the withdrawals output.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingWithdrawalOutput {
+    #[serde(flatten)]
+    pub withdrawal: PendingWithdrawal,
+    // True once the withdrawal can be sent with `withdraw_undelegated`.
+    pub is_matured: bool,
+}
+
+// Vote of the given account, as part of the proposal output.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UserVote {
+    pub option: String,
+    pub delegations: U128,
+}
+
 // This is format of output via JSON for the proposal.
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct ProposalOutput {
     // Id of the proposal.
     pub id: u64,
     #[serde(flatten)]
     pub proposal: ProposalBaseInformation,
-    pub user_select: Vote,
+    // Vote of the account given to the view, if it voted.
+    pub user_select: Option<UserVote>,
+}
+
+impl ProposalOutput {
+    fn new(id: u64, versioned_proposal: VersionedProposal, account_id: Option<&AccountId>) -> Self {
+        let user_select = account_id.and_then(|account_id| {
+            Proposal::from(versioned_proposal.clone())
+                .get_user_voted(account_id)
+                .map(|vote| UserVote {
+                    option: vote.option.clone(),
+                    delegations: U128(vote.delegations),
+                })
+        });
+        Self {
+            id,
+            proposal: ProposalBaseInformation::from(versioned_proposal),
+            user_select,
+        }
+    }
 }
 
 
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+// Proposal with everything frontend shows about it, at the time of the call.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalFullOutput {
+    pub id: u64,
+    #[serde(flatten)]
+    pub proposal: ProposalBaseInformation,
+    pub user_select: Option<UserVote>,
+    // Vote weight of every option that can be voted for, including the ones without votes.
+    pub tallies: HashMap<String, U128>,
+    pub voter_count: u64,
+    // Donations, only for `Donate` proposals.
+    pub donation_total: Option<U128>,
+    pub donor_count: Option<u64>,
+    // Nanoseconds until the voting period ends.
+    pub time_remaining: U64,
+    // Voting rules applied to this proposal.
+    pub quorum: u32,
+    pub threshold: u32,
+}
+
+impl ProposalFullOutput {
+    fn new(id: u64, versioned_proposal: VersionedProposal, policy: &Policy, account_id: Option<&AccountId>) -> Self {
+        let ProposalOutput { proposal: base, user_select, .. } =
+            ProposalOutput::new(id, versioned_proposal.clone(), account_id);
+        let proposal = Proposal::from(versioned_proposal);
+        let options: Vec<String> = match &proposal.kind {
+            ProposalKind::Vote { .. } => proposal.options.keys().cloned().collect(),
+            ProposalKind::Poll { options } => (0..options.len()).map(|index| index.to_string()).collect(),
+            _ => vec![VOTE_APPROVE.to_string(), VOTE_REJECT.to_string()],
+        };
+        let tallies = options
+            .into_iter()
+            .map(|option| {
+                let weight = *proposal.option_delegations.get(&option).unwrap_or(&0);
+                (option, U128(weight))
+            })
+            .collect();
+        let is_donate = proposal.kind == ProposalKind::Donate;
+        let vote_policy = policy.vote_policy(Some(proposal.kind.to_policy_label()));
+        let end_time = proposal.submission_time.0 + proposal.duration.0;
+        Self {
+            id,
+            user_select,
+            tallies,
+            voter_count: proposal.votes.len() as u64,
+            donation_total: if is_donate { Some(U128(proposal.total_donations)) } else { None },
+            donor_count: if is_donate { Some(proposal.donations.len() as u64) } else { None },
+            time_remaining: U64(end_time.saturating_sub(env::block_timestamp())),
+            quorum: vote_policy.quorum,
+            threshold: vote_policy.threshold,
+            proposal: base,
+        }
+    }
+}
+
+// Status of a bounty as shown in the views, computed from its claims and expiration.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum BountyOutputStatus {
+    Open,
+    // Someone is working on it.
+    Claimed,
+    // Expired with no claims, waiting for the creator to take back the escrow.
+    Expired,
+    // All completions were paid.
+    Done,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct BountyBaseInformation {
     pub description: String,
-    pub token: OldAccountId,
-    pub total: Balance,
-    pub rest: Balance,
-    pub start_time: U64,
-    pub duration: U64,
+    pub creator: AccountId,
+    pub token: BountyToken,
+    pub amount: U128,
+    pub max_deadline: U64,
+    pub times: u32,
+    pub completions: u32,
+    pub expires_at: Option<U64>,
+    pub reviewers: Vec<AccountId>,
+    pub milestones: Vec<BountyMilestone>,
+    pub tags: Vec<String>,
+    pub requires_application: bool,
+    pub review_period: Option<U64>,
+    pub claimant_whitelist: Option<Vec<AccountId>>,
 }
 
-impl From<VersionedBounty> for BountyBaseInformation {
-    fn from(bounty: VersionedBounty) -> Self {
-        let Bounty {
-            description,
-            token,
-            total,
-            rest,
-            start_time,
-            duration,
-            claimer: _
-        } = bounty.into();
-
+impl From<Bounty> for BountyBaseInformation {
+    fn from(bounty: Bounty) -> Self {
         Self {
-            description,
-            token,
-            total,
-            rest,
-            start_time,
-            duration,
+            description: bounty.description,
+            creator: bounty.creator,
+            token: bounty.token,
+            amount: U128(bounty.amount),
+            max_deadline: bounty.max_deadline,
+            times: bounty.times,
+            completions: bounty.completions,
+            expires_at: bounty.expires_at,
+            reviewers: bounty.reviewers,
+            milestones: bounty.milestones,
+            tags: bounty.tags,
+            requires_application: bounty.requires_application,
+            review_period: bounty.review_period,
+            claimant_whitelist: bounty.claimant_whitelist,
         }
     }
 }
 
-// This is format of output via JSON for the proposal.
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize)]
+// This is format of output via JSON for the bounty.
+#[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct BountyOutput {
-    // Id of the proposal.
+    // Id of the bounty.
     pub id: u64,
-    pub claim_amount: Balance,
     #[serde(flatten)]
     pub bounty: BountyBaseInformation,
+    pub status: BountyOutputStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BountyClaimOutput {
+    pub bounty_id: u64,
+    #[serde(flatten)]
+    pub claim: BountyClaim,
 }
 
 // This is format of output via JSON for the proposal.
@@ -114,6 +286,70 @@ pub struct ProposalDonateAsObject {
     pub doate_balance: Balance
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalDonorOutput {
+    pub account_id: AccountId,
+    #[serde(flatten)]
+    pub record: DonorRecord,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DonationCategoryOutput {
+    pub category: String,
+    pub balance: U128,
+}
+
+// Campaign with its current status and the raised part of the goal, in basis points.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CampaignOutput {
+    #[serde(flatten)]
+    pub campaign: Campaign,
+    pub progress_bps: u32,
+}
+
+impl From<VersionedCampaign> for CampaignOutput {
+    fn from(campaign: VersionedCampaign) -> Self {
+        let mut campaign: Campaign = campaign.into();
+        campaign.status = campaign.get_status();
+        let (raised, goal) = (campaign.raised.0, campaign.goal.0);
+        // Goals too large to scale are divided instead.
+        let progress = raised.checked_mul(10_000).map_or_else(|| raised / (goal / 10_000), |raised| raised / goal);
+        let progress_bps = std::cmp::min(progress, 10_000) as u32;
+        Self { campaign, progress_bps }
+    }
+}
+
+impl Contract {
+    fn internal_donation_records(&self, from_index: u64, limit: u64) -> Vec<DonationRecord> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        (from_index..std::cmp::min(from_index.saturating_add(limit), self.donation_records.len()))
+            .filter_map(|index| self.donation_records.get(index))
+            .collect()
+    }
+
+    // Donors of given proposal, with the ones that donated anonymously shown as `ANONYMOUS_DONOR`.
+    fn internal_public_proposal_donors(
+        &self,
+        proposal_id: u64,
+        donations: HashMap<AccountId, DonorRecord>,
+    ) -> Vec<(AccountId, DonorRecord)> {
+        let anonymous_donors = self.anonymous_proposal_donors.get(&proposal_id).unwrap_or_default();
+        donations
+            .into_iter()
+            .map(|(account_id, record)| {
+                if anonymous_donors.contains(&account_id) {
+                    (ANONYMOUS_DONOR.to_string(), record)
+                } else {
+                    (account_id, record)
+                }
+            })
+            .collect()
+    }
+}
+
 #[near_bindgen]
 impl Contract {
     // Returns semver of this contract.
@@ -126,14 +362,189 @@ impl Contract {
         self.dao_metadata.clone()
     }
 
+    // Returns voting and permissions policy of this DAO.
+    pub fn get_policy(&self) -> Policy {
+        self.policy.clone()
+    }
+
+    // Returns minimum bond for adding a proposal.
+    pub fn get_proposal_bond(&self) -> U128 {
+        self.policy.proposal_bond
+    }
+
+    // Returns smallest donation of tokens accepted, see `Policy::min_donation_amount`.
+    pub fn get_min_donation_amount(&self) -> U128 {
+        self.policy.min_donation_amount
+    }
+
+    // Returns number of proposals given account has in progress.
+    pub fn get_active_proposals_count(&self, account_id: AccountId) -> u64 {
+        self.active_proposals.get(&account_id).unwrap_or(0)
+    }
+
     pub fn get_owner(&self) -> AccountId {
         self.owner_id.clone()
     }
 
+    // Account that can take over with `accept_ownership`.
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner_id.clone()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn get_council(&self) -> Vec<AccountId> {
+        self.council.to_vec()
+    }
+
+    // Roles allowed to take given action on proposals with given kind label, or on bounties with "bounty".
+    pub fn get_permission_roles(&self, label: String, action: PermissionAction) -> Vec<Role> {
+        self.internal_permission_roles(&label, action)
+    }
+
+    // Returns open donations that can be withdrawn with `withdraw_donations`.
+    // Token is the DAO token by default.
+    pub fn get_total_open_donations(&self, token_id: Option<AccountId>) -> U128 {
+        let token_id = token_id.unwrap_or_else(|| self.token_account.clone());
+        U128(self.open_donation_totals.get(&token_id).unwrap_or_default())
+    }
+
+    // Returns categories of open donations with their donations in given token, the DAO token by default.
+    // The general category comes first, the ones removed from donations aren't listed.
+    pub fn get_donation_categories(&self, token_id: Option<AccountId>) -> Vec<DonationCategoryOutput> {
+        let token_id = token_id.unwrap_or_else(|| self.token_account.clone());
+        std::iter::once(GENERAL_DONATION_CATEGORY.to_string())
+            .chain(self.donation_categories.iter())
+            .map(|category| DonationCategoryOutput {
+                balance: U128(self.internal_category_donations(&category, &token_id)),
+                category,
+            })
+            .collect()
+    }
+
+    // Returns open donations of given category in given token that can be withdrawn.
+    pub fn get_category_donations(&self, category: String, token_id: Option<AccountId>) -> U128 {
+        let token_id = token_id.unwrap_or_else(|| self.token_account.clone());
+        U128(self.internal_category_donations(&category, &token_id))
+    }
+
+    // Returns tokens the DAO accepts transfers of, the DAO token first, see `add_accepted_token`.
+    pub fn get_accepted_tokens(&self) -> Vec<AccountId> {
+        let mut tokens = vec![self.token_account.clone()];
+        for token_id in self.accepted_tokens.iter().chain(self.donation_tokens.iter()) {
+            if !tokens.contains(&token_id) {
+                tokens.push(token_id);
+            }
+        }
+        tokens
+    }
+
+    // Returns tokens accepted for open donations, besides the DAO token.
+    pub fn get_donation_tokens(&self) -> Vec<AccountId> {
+        self.donation_tokens.to_vec()
+    }
+
+    // Returns open donations of given account in given token, the DAO token by default.
+    pub fn get_open_donation_balance(&self, account_id: AccountId, token_id: Option<AccountId>) -> U128 {
+        let token_id = token_id.unwrap_or_else(|| self.token_account.clone());
+        if token_id == self.token_account {
+            U128(self.donations.get(&account_id).unwrap_or_default())
+        } else {
+            U128(self.token_open_donations.get(&(token_id, account_id)).unwrap_or_default())
+        }
+    }
+
+    // Returns donations of all accounts, oldest first. Donors of anonymous donations are hidden.
+    pub fn get_donation_records(&self, from_index: u64, limit: u64) -> Vec<DonationRecord> {
+        self.internal_donation_records(from_index, limit)
+            .into_iter()
+            .map(DonationRecord::into_public)
+            .collect()
+    }
+
+    // Same as `get_donation_records` with the donors of anonymous donations.
+    // Only for the owner, so it has to be called in a transaction.
+    pub fn get_donation_records_with_donors(&self, from_index: u64, limit: u64) -> Vec<DonationRecord> {
+        assert_eq!(self.owner_id, env::predecessor_account_id(), "ONLY_OWNER");
+        self.internal_donation_records(from_index, limit)
+    }
+
+    // Returns donations of given account, oldest first, without the anonymous ones.
+    pub fn get_donation_records_by_account(&self, account_id: AccountId, from_index: u64, limit: u64) -> Vec<DonationRecord> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        self.donation_records_by_account
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|index| self.donation_records.get(index))
+            .filter(|record| !record.anonymous)
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    // Returns $NEAR donated by given account.
+    pub fn get_near_donation_balance(&self, account_id: AccountId) -> U128 {
+        U128(self.near_donations.get(&account_id).unwrap_or_default())
+    }
+
+    // Returns $NEAR donated to given proposal.
+    pub fn get_proposal_near_donations(&self, proposal_id: u64) -> U128 {
+        U128(self.proposal_near_donations.get(&proposal_id).unwrap_or_default())
+    }
+
+    // Returns donated $NEAR that can be withdrawn with `withdraw_near_donations`.
+    pub fn get_total_near_donations(&self) -> U128 {
+        U128(self.total_near_donations)
+    }
+
     pub fn get_donation_balance(&self, account_id: AccountId) -> Option<Balance> {
         self.donations.get(&account_id)
     }
 
+    // Returns open donations of the DAO token credited to given account.
+    pub fn get_donation_of(&self, account_id: AccountId) -> U128 {
+        U128(self.donations.get(&account_id).unwrap_or_default())
+    }
+
+    // Returns open donations of the DAO token credited to all donors.
+    // Withdrawn donations stay credited, `get_total_open_donations` is what is left of them.
+    pub fn get_total_donations(&self) -> U128 {
+        U128(self.total_donations)
+    }
+
+    // Returns open donations of the DAO token given account can still take back, see `refund_open_donation`.
+    pub fn get_refundable_donation(&self, account_id: AccountId) -> U128 {
+        U128(self.internal_refundable_amount(&account_id))
+    }
+
+    // Returns number of accounts with open donations of the DAO token.
+    pub fn get_donor_count(&self) -> u64 {
+        self.donations.len()
+    }
+
+    // Returns accounts that donated most to the DAO, with their total. Ties are ordered by account.
+    pub fn get_top_donors(&self, limit: u64) -> Vec<(AccountId, U128)> {
+        // Anonymous donations don't count, donors that only donated anonymously aren't listed.
+        let mut donors: Vec<(AccountId, Balance)> = self
+            .donations
+            .iter()
+            .map(|(account_id, amount)| {
+                let anonymous_amount = self.anonymous_donations.get(&account_id).unwrap_or_default();
+                (account_id, amount.saturating_sub(anonymous_amount))
+            })
+            .filter(|(_, amount)| *amount > 0)
+            .collect();
+        donors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        donors
+            .into_iter()
+            .take(std::cmp::min(limit, MAX_TOP_DONORS) as usize)
+            .map(|(account_id, amount)| (account_id, U128(amount)))
+            .collect()
+    }
+
 
     // Returns staking contract if available. Otherwise returns empty.
     pub fn token_account(self) -> String {
@@ -146,9 +557,25 @@ impl Contract {
         U128(locked_storage_amount)
     }
 
+    // Returns amount of the DAO token kept out of the treasury: delegations, undelegated tokens not
+    // withdrawn yet, rewards not claimed yet and transfers in flight.
+    pub fn get_locked_amount(&self) -> U128 {
+        U128(self.locked_amount)
+    }
+
+    // Returns $NEAR kept out of the treasury for bonds.
+    pub fn get_locked_near(&self) -> U128 {
+        U128(self.locked_near)
+    }
+
     // Returns available amount of NEAR that can be spent (outside of amount for storage and bonds).
     pub fn get_available_amount(&self) -> U128 {
-        U128(env::account_balance() - self.get_locked_storage_amount().0 - self.locked_amount)
+        U128(env::account_balance() - self.get_locked_storage_amount().0 - self.locked_near - self.near_bounty_escrow)
+    }
+
+    // Returns rewards given account can claim, see `distribute_rewards`.
+    pub fn get_unclaimed_rewards(&self, account_id: AccountId) -> U128 {
+        U128(self.internal_reward_account(&account_id).unclaimed)
     }
 
     // Returns total delegated stake.
@@ -158,7 +585,87 @@ impl Contract {
 
     // Returns delegated stake to given account.
     pub fn delegation_balance_of(&self, account_id: AccountId) -> U128 {
-        U128(self.delegations.get(&account_id).unwrap_or_default())
+        U128(self.get_user_weight(&account_id))
+    }
+
+    // Returns delegation of given account in given token, see `undelegate_token`.
+    pub fn get_token_delegation_of(&self, token_id: AccountId, account_id: AccountId) -> U128 {
+        U128(self.get_token_delegation(&token_id, &account_id))
+    }
+
+    // Returns total delegation in given token, the DAO token by default.
+    pub fn get_delegation_total(&self, token_id: Option<AccountId>) -> U128 {
+        let token_id = token_id.unwrap_or_else(|| self.token_account.clone());
+        U128(self.get_token_delegation_total(&token_id))
+    }
+
+    // Get accounts with their delegation in paginated view, at most `MAX_VIEW_LIMIT` at once.
+    // Pages are stable as long as no delegator is added or removed in between.
+    pub fn get_delegators(&self, from_index: u64, limit: u64) -> Vec<(AccountId, U128)> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        let delegators = self.delegators.as_vector();
+        (from_index..std::cmp::min(delegators.len(), from_index.saturating_add(limit)))
+            .filter_map(|index| delegators.get(index))
+            .map(|account_id| {
+                let amount = self.get_user_weight(&account_id);
+                (account_id, U128(amount))
+            })
+            .collect()
+    }
+
+    pub fn get_delegator_count(&self) -> u64 {
+        self.delegators.len()
+    }
+
+    // Returns tokens given account delegated, per delegate.
+    pub fn get_delegation_funds(&self, account_id: AccountId) -> HashMap<AccountId, U128> {
+        self.delegation_funds
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(delegate, amount)| (delegate, U128(amount)))
+            .collect()
+    }
+
+    // Returns undelegated tokens of given account that weren't withdrawn yet, oldest first.
+    pub fn get_pending_withdrawals(&self, account_id: AccountId) -> Vec<PendingWithdrawalOutput> {
+        let now = env::block_timestamp();
+        self.pending_withdrawals
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|withdrawal| PendingWithdrawalOutput {
+                is_matured: withdrawal.available_at.0 <= now,
+                withdrawal,
+            })
+            .collect()
+    }
+
+    // Returns voting weight of given account: its delegation with the bonus of its locks.
+    pub fn delegation_voting_weight_of(&self, account_id: AccountId) -> U128 {
+        U128(self.get_voting_weight(&account_id))
+    }
+
+    // Returns locked delegations of given account.
+    pub fn get_delegation_locks(&self, account_id: AccountId) -> Vec<DelegationLock> {
+        self.delegation_locks.get(&account_id).unwrap_or_default()
+    }
+
+    // Returns last changes of the delegation of given account, oldest first.
+    pub fn get_delegation_history(&self, account_id: AccountId, from_index: u64, limit: u64) -> Vec<DelegationChange> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        self.delegation_history
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    // Returns undelegated tokens of all accounts that weren't withdrawn yet.
+    pub fn get_total_pending_withdrawals(&self) -> U128 {
+        U128(self.total_pending_withdrawals)
     }
 
     // Combines balance and total amount for calling from external contracts.
@@ -169,114 +676,764 @@ impl Contract {
         )
     }
 
-    // Last proposal's id.
+    // Id the next proposal will get, proposals are stored under ids below it.
     pub fn get_last_proposal_id(&self) -> u64 {
         self.last_proposal_id
     }
-    
+
+    // Id the next bounty will get, bounties are stored under ids below it.
     pub fn get_last_bounty_id(&self) -> u64 {
-        self.last_proposal_id
+        self.last_bounty_id
     }
 
-    // Get proposals in paginated view.
-    pub fn get_proposals(&self, from_index: u64, limit: u64, account_id: AccountId) -> Vec<ProposalOutput> {
-        (from_index..std::cmp::min(self.last_proposal_id, from_index + limit))
+    // Get proposals in paginated view, at most `MAX_VIEW_LIMIT` at once.
+    // Vote of `account_id` is included if given.
+    pub fn get_proposals(&self, from_index: u64, limit: u64, account_id: Option<AccountId>) -> Vec<ProposalOutput> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        (from_index..std::cmp::min(self.last_proposal_id, from_index.saturating_add(limit)))
             .filter_map(|id| {
-                self.proposals.get(&id).map(|versioned_proposal| {
-                    let proposal = Proposal::from(versioned_proposal.clone());
-                    let voted = if proposal.votes.get(&account_id).is_some() {
-                        proposal.votes.get(&account_id).unwrap().clone()
-                    } else {
-                        Vote {
-                            option: "_".to_string(),
-                            delegations: 0
-                        }
-                    };
-                    ProposalOutput {
-                        id,
-                        proposal: ProposalBaseInformation::from(versioned_proposal.clone()),
-                        user_select: voted,
-                    }
-                })
+                self.proposals
+                    .get(&id)
+                    .map(|versioned_proposal| ProposalOutput::new(id, versioned_proposal, account_id.as_ref()))
             })
             .collect()
     }
 
-    // Get specific proposal.
-    pub fn get_proposal(&self, id: u64, account_id: AccountId) -> Option<ProposalOutput> {
-        let versioned_proposal = self.proposals.get(&id);
-        let output = if versioned_proposal.clone().is_some() {
-            let proposal: Proposal = Proposal::from(versioned_proposal.clone().unwrap());
-            let voted = if proposal.votes.get(&account_id).is_some() {
-                proposal.votes.get(&account_id).unwrap().clone()
-            } else {
-                Vote {
-                    option: "_".to_string(),
-                    delegations: 0
-                }
-            };
-            Some(ProposalOutput {
-                id,
-                proposal: ProposalBaseInformation::from(versioned_proposal.unwrap().clone()),
-                user_select: voted,
+    // Get proposals in the same id range as `get_proposals`, keeping only the ones
+    // with given computed status and kind.
+    pub fn get_proposals_filtered(
+        &self,
+        from_index: u64,
+        limit: u64,
+        status: Option<ProposalStatus>,
+        kind: Option<ProposalKindLabel>,
+    ) -> Vec<ProposalOutput> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        (from_index..std::cmp::min(self.last_proposal_id, from_index.saturating_add(limit)))
+            .filter_map(|id| self.proposals.get(&id).map(|versioned_proposal| (id, versioned_proposal)))
+            .filter(|(_, versioned_proposal)| {
+                let proposal = Proposal::from(versioned_proposal.clone());
+                status.as_ref().map_or(true, |status| proposal.get_status() == *status)
+                    && kind.map_or(true, |kind| proposal.kind.to_label() == kind)
             })
-        } else {
-            None   
-        };
-        output
+            .map(|(id, versioned_proposal)| ProposalOutput::new(id, versioned_proposal, None))
+            .collect()
+    }
+
+    // Get proposals in the same id range as `get_proposals`, keeping only the ones created by `account_id`.
+    pub fn get_proposals_by_proposer(&self, account_id: AccountId, from_index: u64, limit: u64) -> Vec<ProposalOutput> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        (from_index..std::cmp::min(self.last_proposal_id, from_index.saturating_add(limit)))
+            .filter_map(|id| self.proposals.get(&id).map(|versioned_proposal| (id, versioned_proposal)))
+            .filter(|(_, versioned_proposal)| Proposal::from(versioned_proposal.clone()).proposer == account_id)
+            .map(|(id, versioned_proposal)| ProposalOutput::new(id, versioned_proposal, None))
+            .collect()
+    }
+
+    // Get proposals with given tag, `from_index` and `limit` page through the tagged proposals only.
+    pub fn get_proposals_by_tag(&self, tag: String, from_index: u64, limit: u64) -> Vec<ProposalOutput> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        self.proposals_by_tag
+            .get(&tag)
+            .unwrap_or_default()
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|id| {
+                self.proposals
+                    .get(&id)
+                    .map(|versioned_proposal| ProposalOutput::new(id, versioned_proposal, None))
+            })
+            .collect()
+    }
+
+    // Get specific proposal, with its tallies and donations.
+    pub fn get_proposal(&self, id: u64, account_id: Option<AccountId>) -> Option<ProposalFullOutput> {
+        self.proposals.get(&id).map(|versioned_proposal| {
+            ProposalFullOutput::new(id, versioned_proposal, &self.policy, account_id.as_ref())
+        })
+    }
+
+    // Returns activity log of given proposal, oldest first, at most `MAX_VIEW_LIMIT` at once.
+    pub fn get_proposal_activity(&self, id: u64, from_index: u64, limit: u64) -> Vec<ProposalEvent> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        let proposal: Proposal = self.proposals.get(&id).expect("ERR_NO_PROPOSAL").into();
+        proposal
+            .activity
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    // Returns total amount of tokens donated to given proposal.
+    pub fn get_proposal_total_donations(&self, id: u64) -> Option<U128> {
+        self.proposals
+            .get(&id)
+            .map(|versioned_proposal| U128(Proposal::from(versioned_proposal).total_donations))
+    }
+
+    // Returns voting weight snapshotted for each voter of given proposal.
+    pub fn get_proposal_voter_weights(&self, id: u64) -> Option<HashMap<AccountId, U128>> {
+        self.proposals.get(&id).map(|versioned_proposal| {
+            Proposal::from(versioned_proposal)
+                .voter_weights
+                .into_iter()
+                .map(|(account_id, weight)| (account_id, U128(weight)))
+                .collect()
+        })
+    }
+
+    // Returns voting weight of given user on given proposal: weight snapshotted at their first vote,
+    // otherwise their current delegation, or zero once the proposal no longer takes votes.
+    pub fn get_voting_power(&self, proposal_id: u64, account_id: AccountId) -> Option<U128> {
+        self.proposals.get(&proposal_id).map(|versioned_proposal| {
+            let proposal = Proposal::from(versioned_proposal);
+            if let Some(weight) = proposal.voter_weights.get(&account_id) {
+                U128(*weight)
+            } else if proposal.get_status() != ProposalStatus::InProgress {
+                U128(0)
+            } else {
+                U128(self.get_voting_weight(&account_id))
+            }
+        })
+    }
+
+    // Returns vote weight of each option of given poll.
+    pub fn get_poll_tallies(&self, id: u64) -> Option<Vec<U128>> {
+        self.proposals.get(&id).map(|versioned_proposal| {
+            Proposal::from(versioned_proposal)
+                .get_poll_tallies()
+                .into_iter()
+                .map(U128)
+                .collect()
+        })
     }
 
     pub fn get_proposal_donation(&self, id: u64, from_index: usize, limit: usize) -> Vec<ProposalDonateAsObject> {
         let proposal: Proposal = self.proposals.get(&id).expect("ERR_NO_PROPOSAL").into();
-        let donations = proposal.donations.clone();
-        let mut hash_vec = Vec::from_iter(donations.into_iter());
-        hash_vec.sort_by(|a, b| b.1.cmp(&a.1));
+        let donations = self.internal_public_proposal_donors(id, proposal.donations.clone());
+        let mut hash_vec = Vec::from_iter(donations);
+        hash_vec.sort_by_key(|(_, record)| std::cmp::Reverse(record.amount.0));
         let donations_slice = &hash_vec[from_index..std::cmp::min(from_index + limit, hash_vec.len())];
-        let response: Vec<ProposalDonateAsObject> = donations_slice.into_iter().map(|(account_id, balance)| {
+        let response: Vec<ProposalDonateAsObject> = donations_slice.iter().map(|(account_id, record)| {
             ProposalDonateAsObject {
                 account: account_id.clone(),
-                doate_balance: balance.clone(),
+                doate_balance: record.amount.0,
             }
         }).collect();
         response
     }
 
-    pub fn get_bounties(&self, from_index: u64, limit: u64, account_id: AccountId) -> Vec<BountyOutput> {
-        (from_index..std::cmp::min(self.last_bounty_id, from_index + limit))
-            .filter_map(|id| {
-                self.bounties.get(&id).map(|versioned_bounty| {
-                    let bounty: Bounty = versioned_bounty.clone().into();
-                    let claim_value = bounty.claimer.get(&account_id.to_string()).unwrap_or(&0u128).clone();
-                    BountyOutput {
-                        id,
-                        claim_amount: claim_value,
-                        bounty: BountyBaseInformation::from(versioned_bounty)
-                    }
-                })
-            })
+    // Returns donors of given proposal ordered by account id, with their aggregated donations.
+    pub fn get_proposal_donors(&self, proposal_id: u64, from_index: u64, limit: u64) -> Vec<ProposalDonorOutput> {
+        let proposal: Proposal = self.proposals.get(&proposal_id).expect("ERR_NO_PROPOSAL").into();
+        let mut donors = self.internal_public_proposal_donors(proposal_id, proposal.donations);
+        donors.sort_by(|a, b| a.0.cmp(&b.0));
+        donors
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|(account_id, record)| ProposalDonorOutput { account_id, record })
             .collect()
     }
 
-    pub fn get_bounty(&self, id: u64, account_id: Option<AccountId>) -> Option<BountyOutput> {
-        let versioned_bounty_option = self.bounties.get(&id);
-        let output = if versioned_bounty_option.is_some() {
-            let versioned_bounty_unwrapped = versioned_bounty_option.unwrap();
-            let claim_value = if account_id.is_some() {
-                let bounty: Bounty = versioned_bounty_unwrapped.clone().into();
-                let claim_value = bounty.claimer.get(&account_id.unwrap()).unwrap_or(&0u128).clone();
-                claim_value
-            } else {
-                0
-            }; 
-            let bounty_output = BountyOutput {
+    // Get bounties in paginated view, at most `MAX_VIEW_LIMIT` at once.
+    pub fn get_bounties(&self, from_index: u64, limit: u64) -> Vec<BountyOutput> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        (from_index..std::cmp::min(self.last_bounty_id, from_index.saturating_add(limit)))
+            .filter_map(|id| self.get_bounty(id))
+            .collect()
+    }
+
+    // Get bounties created by given account, oldest first, at most `MAX_VIEW_LIMIT` at once.
+    pub fn get_bounties_by_creator(&self, account_id: AccountId, from_index: u64, limit: u64) -> Vec<BountyOutput> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        self.bounties_by_creator
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|id| self.get_bounty(id))
+            .collect()
+    }
+
+    // Get bounties with given tag, `from_index` and `limit` page through the tagged bounties only.
+    pub fn get_bounties_by_tag(&self, tag: String, from_index: u64, limit: u64) -> Vec<BountyOutput> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        self.bounties_by_tag
+            .get(&tag)
+            .unwrap_or_default()
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|id| self.get_bounty(id))
+            .collect()
+    }
+
+    // Tags of the bounties with the number of bounties having them. Completed and cancelled bounties
+    // are counted only with `include_closed`, tags without bounties to count are left out.
+    pub fn get_bounty_tags(&self, include_closed: Option<bool>) -> Vec<(String, u32)> {
+        let include_closed = include_closed.unwrap_or(false);
+        self.bounty_tags
+            .iter()
+            .map(|(tag, count)| (tag, if include_closed { count.open + count.closed } else { count.open }))
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+
+    pub fn get_bounty(&self, id: u64) -> Option<BountyOutput> {
+        self.internal_get_bounty(id).map(|bounty| {
+            BountyOutput {
                 id,
-                claim_amount: claim_value,
-                bounty: BountyBaseInformation::from(versioned_bounty_unwrapped)
-            };
-            Some(bounty_output)
-        } else {
-            None
+                status: self.internal_bounty_output_status(id, &bounty),
+                bounty: bounty.into(),
+            }
+        })
+    }
+
+    // Claims of given account, in order they were made.
+    pub fn get_bounty_claims(&self, account_id: AccountId) -> Vec<BountyClaimOutput> {
+        self.bounty_claims_by_account
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|bounty_id| {
+                self.internal_bounty_claims(bounty_id)
+                    .into_iter()
+                    .filter(|claim| claim.claimant == account_id)
+                    .map(move |claim| BountyClaimOutput { bounty_id, claim })
+            })
+            .collect()
+    }
+
+    // Claims of given bounty, at most `MAX_BOUNTY_CLAIMS`.
+    pub fn get_bounty_claimers(&self, bounty_id: u64) -> Vec<BountyClaimOutput> {
+        self.internal_bounty_claims(bounty_id)
+            .into_iter()
+            .map(|claim| BountyClaimOutput { bounty_id, claim })
+            .collect()
+    }
+
+    // Applications for given bounty in order they were made, at most `MAX_VIEW_LIMIT` at once.
+    pub fn get_bounty_applications(&self, id: u64, from_index: u64, limit: u64) -> Vec<BountyApplication> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        self.internal_bounty_applications(id)
+            .into_iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    // Split payout of given bounty still waiting for some of its transfers, see `bounty_approve_split`.
+    pub fn get_bounty_payout(&self, id: u64) -> Option<BountySplitPayout> {
+        self.bounty_payouts.get(&id)
+    }
+
+    // Number of bounties given account can still claim, see `Policy::max_bounty_claims_per_account`.
+    pub fn get_bounty_claim_allowance(&self, account_id: AccountId) -> u32 {
+        let claims = self.bounty_claims_by_account.get(&account_id).map_or(0, |ids| ids.len() as u32);
+        self.policy.max_bounty_claims_per_account.saturating_sub(claims)
+    }
+
+    pub fn get_bounty_number_of_claims(&self, bounty_id: u64) -> u32 {
+        self.internal_bounty_claims(bounty_id).len() as u32
+    }
+
+    // Tokens reserved for the bounties in given token, the DAO token by default.
+    pub fn get_bounty_escrow(&self, token_id: Option<ValidAccountId>) -> U128 {
+        let token_id: AccountId = token_id.map_or_else(|| self.token_account.clone(), |token_id| token_id.into());
+        U128(self.internal_bounty_escrow(&token_id))
+    }
+
+    // $NEAR reserved for the bounties paid in $NEAR.
+    pub fn get_near_bounty_escrow(&self) -> U128 {
+        U128(self.near_bounty_escrow)
+    }
+
+    pub fn get_last_campaign_id(&self) -> u64 {
+        self.last_campaign_id
+    }
+
+    pub fn get_campaign(&self, id: u64) -> Option<CampaignOutput> {
+        self.campaigns.get(&id).map(CampaignOutput::from)
+    }
+
+    pub fn get_last_matching_pool_id(&self) -> u64 {
+        self.last_matching_pool_id
+    }
+
+    // Returns matching pool with what is left of it.
+    pub fn get_matching_pool(&self, id: u64) -> Option<MatchingPool> {
+        self.matching_pools.get(&id)
+    }
+
+    // Returns matching pools that didn't run out yet, oldest first.
+    pub fn get_matching_pools(&self, from_index: u64, limit: u64) -> Vec<MatchingPool> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        self.active_matching_pools
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|id| self.matching_pools.get(id))
+            .collect()
+    }
+
+    // Returns campaigns from given id, with their progress.
+    pub fn get_campaigns(&self, from_index: u64, limit: u64) -> Vec<CampaignOutput> {
+        let limit = std::cmp::min(limit, MAX_VIEW_LIMIT);
+        (from_index..std::cmp::min(self.last_campaign_id, from_index.saturating_add(limit)))
+            .filter_map(|id| self.campaigns.get(&id).map(CampaignOutput::from))
+            .collect()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use near_sdk::test_utils::{accounts, testing_env_with_promise_results, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_get_proposals_pages() {
+        let (mut context, mut contract) = setup_contract_with_policy(Policy {
+            max_active_proposals: 200,
+            ..test_policy()
+        });
+        for _ in 0..120 {
+            add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        }
+        act(&mut context, &mut contract, owner(), 7, Action::RemoveProposal);
+        assert_eq!(contract.get_last_proposal_id(), 120);
+
+        let mut ids = vec![];
+        let mut from_index = 0;
+        while from_index < contract.get_last_proposal_id() {
+            let page = contract.get_proposals(from_index, 50, None);
+            assert!(page.len() <= 50);
+            ids.extend(page.iter().map(|output| output.id));
+            from_index += 50;
+        }
+        assert_eq!(ids.len(), 119);
+        assert!(!ids.contains(&7));
+        assert_eq!(ids[..8], [0, 1, 2, 3, 4, 5, 6, 8]);
+        assert_eq!(*ids.last().unwrap(), 119);
+
+        // Larger limits are capped.
+        assert_eq!(contract.get_proposals(50, 1_000, None).len(), MAX_VIEW_LIMIT as usize);
+        assert!(contract.get_proposals(120, 10, None).is_empty());
+        assert!(contract.get_proposal(7, None).is_none());
+        assert_eq!(contract.get_proposal(8, None).unwrap().id, 8);
+    }
+
+    #[test]
+    fn test_get_proposal_output() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 40);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+
+        let output = contract.get_proposal(id, Some(accounts(1).to_string())).unwrap();
+        let user_select = output.user_select.unwrap();
+        assert_eq!(user_select.option, VOTE_APPROVE);
+        assert_eq!(user_select.delegations, U128(40));
+        assert!(contract.get_proposal(id, Some(accounts(2).to_string())).unwrap().user_select.is_none());
+
+        let json = near_sdk::serde_json::to_string(&contract.get_proposal(id, None).unwrap()).unwrap();
+        assert!(json.contains("\"id\":0"));
+        // Bond is refunded once the only delegator approved.
+        assert!(json.contains("\"status\":\"Approved\""));
+        assert!(json.contains("\"bond\":\"0\""));
+        assert!(json.contains("\"total_delegation_amount\":\"40\""));
+        assert!(json.contains("\"user_select\":null"));
+    }
+
+    #[test]
+    fn test_get_proposals_filtered() {
+        let (mut context, mut contract) = setup_contract_with_policy(Policy {
+            max_active_proposals: 200,
+            ..test_policy()
+        });
+        let poll = ProposalKind::Poll { options: vec!["yes".to_string(), "no".to_string()] };
+        for _ in 0..3 {
+            add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        }
+        add_proposal(&mut context, &mut contract, poll.clone());
+        // Expire the proposals above.
+        testing_env!(context.block_timestamp(1_000_000_000 * 60 * 60).build());
+        for _ in 0..2 {
+            add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        }
+        add_proposal(&mut context, &mut contract, poll);
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.veto_proposal(4, "spam".to_string());
+
+        let ids = |status: Option<ProposalStatus>, kind: Option<ProposalKindLabel>| -> Vec<u64> {
+            contract.get_proposals_filtered(0, 50, status, kind).iter().map(|output| output.id).collect()
+        };
+        assert_eq!(ids(None, None).len(), 7);
+        assert_eq!(ids(Some(ProposalStatus::InProgress), Some(ProposalKindLabel::Donate)), vec![5]);
+        assert_eq!(ids(Some(ProposalStatus::Expired), Some(ProposalKindLabel::Donate)), vec![0, 1, 2]);
+        assert_eq!(ids(Some(ProposalStatus::Expired), None), vec![0, 1, 2, 3]);
+        assert_eq!(ids(Some(ProposalStatus::Vetoed), None), vec![4]);
+        assert_eq!(ids(None, Some(ProposalKindLabel::Poll)), vec![3, 6]);
+        assert!(ids(None, Some(ProposalKindLabel::Transfer)).is_empty());
+        assert_eq!(contract.get_proposals_filtered(4, 2, None, Some(ProposalKindLabel::Donate)).len(), 2);
+
+        // Kind filter is given by name only.
+        let kind: ProposalKindLabel = near_sdk::serde_json::from_str("\"Poll\"").unwrap();
+        assert_eq!(kind, ProposalKindLabel::Poll);
+    }
+
+    #[test]
+    fn test_delegation_views() {
+        let (mut context, mut contract) = setup_contract();
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(0));
+        assert_eq!(contract.delegation_total_supply(), U128(0));
+        assert_eq!(contract.get_locked_amount(), U128(0));
+
+        delegate(&mut context, &mut contract, accounts(1), 100);
+        delegate(&mut context, &mut contract, accounts(2), 40);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.undelegate(U128(100), None);
+        assert_eq!(contract.delegation_balance_of(accounts(1).to_string()), U128(0));
+        assert_eq!(contract.delegation_balance_of(accounts(2).to_string()), U128(40));
+        assert_eq!(contract.delegation_total_supply(), U128(40));
+        assert_eq!(
+            contract.delegation_balance_ratio(accounts(2).to_string()),
+            (U128(40), U128(40))
+        );
+        // Undelegated tokens stay locked until withdrawn.
+        assert_eq!(contract.get_locked_amount(), U128(140));
+        testing_env!(context.block_timestamp(DEFAULT_UNBONDING_PERIOD).build());
+        contract.withdraw_undelegated();
+        assert_eq!(contract.get_locked_amount(), U128(40));
+
+        let json = near_sdk::serde_json::to_string(&contract.get_locked_amount()).unwrap();
+        assert_eq!(json, "\"40\"");
+    }
+
+    #[test]
+    fn test_get_voting_power() {
+        let (mut context, mut contract) = setup_contract();
+        assert!(contract.get_voting_power(0, accounts(1).to_string()).is_none());
+
+        delegate(&mut context, &mut contract, accounts(1), 40);
+        delegate(&mut context, &mut contract, accounts(3), 100);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        assert_eq!(contract.get_voting_power(id, accounts(1).to_string()), Some(U128(40)));
+        assert_eq!(contract.get_voting_power(id, accounts(2).to_string()), Some(U128(0)));
+
+        // Weight stays at the snapshot taken on the first vote.
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        delegate(&mut context, &mut contract, accounts(1), 60);
+        delegate(&mut context, &mut contract, accounts(2), 30);
+        assert_eq!(contract.get_voting_power(id, accounts(1).to_string()), Some(U128(40)));
+        assert_eq!(contract.get_voting_power(id, accounts(2).to_string()), Some(U128(30)));
+
+        // Closed for those who haven't voted.
+        testing_env!(context.block_timestamp(DEFAULT_PROPOSAL_PERIOD).build());
+        assert_eq!(contract.get_voting_power(id, accounts(1).to_string()), Some(U128(40)));
+        assert_eq!(contract.get_voting_power(id, accounts(2).to_string()), Some(U128(0)));
+    }
+
+    #[test]
+    fn test_get_proposals_by_proposer() {
+        let (mut context, mut contract) = setup_contract();
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        testing_env!(context.block_timestamp(100).build());
+        contract.owner_id = accounts(1).to_string();
+        for _ in 0..2 {
+            testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+            contract.add_proposal(proposal_input(ProposalKind::Donate));
+        }
+        contract.owner_id = owner().to_string();
+        add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+
+        let ids = |account_id: ValidAccountId, from_index| -> Vec<u64> {
+            contract
+                .get_proposals_by_proposer(account_id.to_string(), from_index, 50)
+                .iter()
+                .map(|output| output.id)
+                .collect()
+        };
+        assert_eq!(ids(owner(), 0), vec![0, 3]);
+        assert_eq!(ids(accounts(1), 0), vec![1, 2]);
+        assert_eq!(ids(accounts(1), 2), vec![2]);
+        assert!(ids(accounts(2), 0).is_empty());
+
+        let output = &contract.get_proposals_by_proposer(accounts(1).to_string(), 1, 1)[0];
+        assert_eq!(output.proposal.proposer, accounts(1).to_string());
+        let json = near_sdk::serde_json::to_value(output).unwrap();
+        assert_eq!(json["proposer"], accounts(1).to_string());
+        assert_eq!(json["submission_time"], "100");
+    }
+
+    fn add_tagged_proposal(context: &mut VMContextBuilder, contract: &mut Contract, tags: &[&str]) -> u64 {
+        testing_env!(context.predecessor_account_id(owner()).attached_deposit(DEFAULT_PROPOSAL_BOND).build());
+        contract.add_proposal(ProposalInput {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..proposal_input(ProposalKind::Donate)
+        })
+    }
+
+    #[test]
+    fn test_get_proposals_by_tag() {
+        let (mut context, mut contract) = setup_contract();
+        add_tagged_proposal(&mut context, &mut contract, &["dev", "grants"]);
+        add_tagged_proposal(&mut context, &mut contract, &[]);
+        add_tagged_proposal(&mut context, &mut contract, &["marketing"]);
+        add_tagged_proposal(&mut context, &mut contract, &["grants"]);
+        assert_eq!(contract.proposals_by_tag.get(&"grants".to_string()), Some(vec![0, 3]));
+
+        let ids = |contract: &Contract, tag: &str, from_index| -> Vec<u64> {
+            contract
+                .get_proposals_by_tag(tag.to_string(), from_index, 50)
+                .iter()
+                .map(|output| output.id)
+                .collect()
         };
-        output
+        assert_eq!(ids(&contract, "grants", 0), vec![0, 3]);
+        assert_eq!(ids(&contract, "grants", 1), vec![3]);
+        assert_eq!(ids(&contract, "dev", 0), vec![0]);
+        assert!(ids(&contract, "unknown", 0).is_empty());
+        assert_eq!(contract.get_proposal(0, None).unwrap().proposal.tags, vec!["dev", "grants"]);
+
+        act(&mut context, &mut contract, owner(), 0, Action::RemoveProposal);
+        assert_eq!(ids(&contract, "grants", 0), vec![3]);
+        assert_eq!(contract.proposals_by_tag.get(&"dev".to_string()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOO_MANY_TAGS")]
+    fn test_too_many_tags() {
+        let (mut context, mut contract) = setup_contract();
+        add_tagged_proposal(&mut context, &mut contract, &["a", "b", "c", "d", "e", "f"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_TAG")]
+    fn test_tag_too_long() {
+        let (mut context, mut contract) = setup_contract();
+        add_tagged_proposal(&mut context, &mut contract, &[&"a".repeat(MAX_TAG_LENGTH + 1)]);
+    }
+
+    #[test]
+    fn test_get_proposal_full_output() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 40);
+        delegate(&mut context, &mut contract, accounts(2), 30);
+        delegate(&mut context, &mut contract, accounts(3), 30);
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Donate);
+        let msg = format!("{{\"purpose\":{{\"ProposalDonate\":{}}}}}", id);
+        transfer_call(&mut context, &mut contract, accounts(4), 20, &msg);
+        transfer_call(&mut context, &mut contract, accounts(4), 5, &msg);
+        transfer_call(&mut context, &mut contract, accounts(5), 5, &msg);
+        act(&mut context, &mut contract, accounts(1), id, Action::VoteApprove);
+        act(&mut context, &mut contract, accounts(2), id, Action::VoteReject);
+
+        testing_env!(context.block_timestamp(1_000).build());
+        let output = contract.get_proposal(id, None).unwrap();
+        assert_eq!(output.tallies.get(VOTE_APPROVE), Some(&U128(40)));
+        assert_eq!(output.tallies.get(VOTE_REJECT), Some(&U128(30)));
+        assert_eq!(output.voter_count, 2);
+        assert_eq!(output.donation_total, Some(U128(30)));
+        assert_eq!(output.donor_count, Some(2));
+        assert_eq!(output.time_remaining, U64(1_000_000_000 * 60 * 60 - 1_000));
+        assert_eq!(output.threshold, contract.policy.threshold);
+
+        let value = near_sdk::serde_json::to_value(&output).unwrap();
+        let mut fields: Vec<&String> = value.as_object().unwrap().keys().collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            vec![
+                "accepted_tokens", "beneficiary", "bond", "deadline", "description", "description_hash", "donation_total", "donor_count",
+                "duration", "extended_period", "extension_count", "id", "kind", "milestones", "option_delegations",
+                "options", "poll_winner", "proposer", "quorum", "status", "submission_time", "tags", "tallies",
+                "target", "threshold", "time_remaining", "token_donations",
+                "total_delegation_amount", "total_delegation_snapshot", "total_donations", "user_select",
+                "voter_count",
+            ]
+        );
+        let json = near_sdk::serde_json::to_string(&output).unwrap();
+        let output: ProposalFullOutput = near_sdk::serde_json::from_str(&json).unwrap();
+        assert_eq!(output.id, id);
+        assert_eq!(output.proposal.status, ProposalStatus::InProgress);
+        assert_eq!(near_sdk::serde_json::to_value(&output).unwrap(), value);
+    }
+
+    #[test]
+    fn test_get_proposal_full_output_poll() {
+        let (mut context, mut contract) = setup_contract();
+        delegate(&mut context, &mut contract, accounts(1), 40);
+        let options = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let id = add_proposal(&mut context, &mut contract, ProposalKind::Poll { options });
+        act(&mut context, &mut contract, accounts(1), id, Action::VotePoll { option_index: 1 });
+        testing_env!(context.block_timestamp(1_000_000_000 * 60 * 60 * 2).build());
+
+        let output = contract.get_proposal(id, None).unwrap();
+        assert_eq!(output.tallies.len(), 3);
+        assert_eq!(output.tallies.get("0"), Some(&U128(0)));
+        assert_eq!(output.tallies.get("1"), Some(&U128(40)));
+        assert_eq!(output.donation_total, None);
+        assert_eq!(output.donor_count, None);
+        assert_eq!(output.time_remaining, U64(0));
+        assert_eq!(output.proposal.status, ProposalStatus::Expired);
+    }
+
+    fn bounty_input(expires_at: Option<U64>) -> BountyInput {
+        BountyInput {
+            description: "d".to_string(),
+            token: token().to_string(),
+            amount: U128(10),
+            times: 1,
+            max_deadline: U64(1_000_000_000 * 60 * 60),
+            expires_at,
+            reviewers: vec![],
+            milestones: vec![],
+            tags: vec![],
+            requires_application: false,
+            review_period: None,
+            claimant_whitelist: None,
+        }
+    }
+
+    #[test]
+    fn test_get_bounties_pages() {
+        let (_, mut contract) = setup_contract();
+        for i in 0..60 {
+            let creator = if i % 2 == 0 { owner() } else { accounts(1) };
+            contract.create_bounty(&creator.to_string(), bounty_input(None));
+        }
+        assert_eq!(contract.get_last_bounty_id(), 60);
+        let first = contract.get_bounties(0, 100);
+        assert_eq!(first.len(), MAX_VIEW_LIMIT as usize);
+        assert_eq!(first[0].id, 0);
+        let rest = contract.get_bounties(50, 50);
+        assert_eq!(rest.iter().map(|bounty| bounty.id).collect::<Vec<_>>(), (50..60).collect::<Vec<_>>());
+        assert!(contract.get_bounties(60, 10).is_empty());
+
+        let by_creator = contract.get_bounties_by_creator(accounts(1).to_string(), 10, 50);
+        assert_eq!(by_creator.len(), 20);
+        assert_eq!(by_creator[0].id, 21);
+        assert!(by_creator.iter().all(|bounty| bounty.bounty.creator == accounts(1).to_string()));
+        assert!(contract.get_bounties_by_creator(accounts(2).to_string(), 0, 50).is_empty());
+    }
+
+    #[test]
+    fn test_get_bounty_status() {
+        let (mut context, mut contract) = setup_contract();
+        let id = contract.create_bounty(&owner().to_string(), bounty_input(Some(U64(1_000))));
+        let output = contract.get_bounty(id).unwrap();
+        assert_eq!(output.status, BountyOutputStatus::Open);
+        assert_eq!(output.bounty.amount, U128(10));
+        testing_env!(context.block_timestamp(1_000).build());
+        assert_eq!(contract.get_bounty(id).unwrap().status, BountyOutputStatus::Expired);
+
+        register(&mut contract, &accounts(1));
+        testing_env!(context
+            .block_timestamp(0)
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(DEFAULT_BOUNTY_BOND)
+            .build());
+        contract.bounty_claim(id, U64(1_000));
+        assert_eq!(contract.get_bounty(id).unwrap().status, BountyOutputStatus::Claimed);
+        testing_env!(context.attached_deposit(0).build());
+        contract.bounty_done(id, "work".to_string());
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.bounty_approve(id, accounts(1).to_string());
+        assert_eq!(contract.get_bounty(id).unwrap().status, BountyOutputStatus::Done);
+        assert!(contract.get_bounty(id + 1).is_none());
+    }
+
+    #[test]
+    fn test_get_bounty_claims() {
+        let (mut context, mut contract) = setup_contract();
+        let first = contract.create_bounty(&owner().to_string(), bounty_input(None));
+        let second = contract.create_bounty(&owner().to_string(), bounty_input(None));
+        register(&mut contract, &accounts(1));
+        register(&mut contract, &accounts(2));
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(DEFAULT_BOUNTY_BOND).build());
+        contract.bounty_claim(first, U64(1_000));
+        contract.bounty_claim(second, U64(1_000));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.bounty_claim(first, U64(1_000));
+
+        let claims = contract.get_bounty_claims(accounts(1).to_string());
+        assert_eq!(claims.iter().map(|claim| claim.bounty_id).collect::<Vec<_>>(), vec![first, second]);
+        assert_eq!(claims[0].claim.status, BountyClaimStatus::InProgress);
+        assert_eq!(contract.get_bounty_claimers(first).len(), 2);
+        assert_eq!(contract.get_bounty_number_of_claims(first), 2);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(0).build());
+        contract.bounty_done(first, "work".to_string());
+        assert_eq!(contract.get_bounty_claims(accounts(1).to_string())[0].claim.status, BountyClaimStatus::Submitted);
+        assert_eq!(contract.get_bounty_claimers(first)[0].claim.status, BountyClaimStatus::Submitted);
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.bounty_approve(first, accounts(1).to_string());
+        assert_eq!(contract.get_bounty_claims(accounts(1).to_string())[0].claim.status, BountyClaimStatus::Approved);
+        testing_env_with_promise_results(
+            context.predecessor_account_id(dao()).build(),
+            PromiseResult::Successful(vec![]),
+        );
+        contract.on_bounty_paid(first, accounts(1).to_string());
+        let claims = contract.get_bounty_claims(accounts(1).to_string());
+        assert_eq!(claims.len(), 1);
+        assert_eq!(claims[0].bounty_id, second);
+        let claimers = contract.get_bounty_claimers(first);
+        assert_eq!(claimers.len(), 1);
+        assert_eq!(claimers[0].claim.claimant, accounts(2).to_string());
+        assert_eq!(contract.get_bounty_number_of_claims(first), 1);
+    }
+
+    fn add_tagged_bounty(contract: &mut Contract, tags: &[&str]) -> u64 {
+        let input = BountyInput { tags: tags.iter().map(|tag| tag.to_string()).collect(), ..bounty_input(None) };
+        contract.create_bounty(&owner().to_string(), input)
+    }
+
+    #[test]
+    fn test_get_bounties_by_tag() {
+        let (mut context, mut contract) = setup_contract();
+        add_tagged_bounty(&mut contract, &["rust", "design"]);
+        add_tagged_bounty(&mut contract, &[]);
+        let cancelled = add_tagged_bounty(&mut contract, &["rust"]);
+        let ids = |contract: &Contract, tag: &str, from_index| -> Vec<u64> {
+            contract.get_bounties_by_tag(tag.to_string(), from_index, 50).into_iter().map(|bounty| bounty.id).collect()
+        };
+        assert_eq!(ids(&contract, "rust", 0), vec![0, 2]);
+        assert_eq!(ids(&contract, "rust", 1), vec![2]);
+        assert!(ids(&contract, "marketing", 0).is_empty());
+        assert_eq!(contract.get_bounty(0).unwrap().bounty.tags, vec!["rust", "design"]);
+        assert_eq!(
+            contract.get_bounty_tags(None),
+            vec![("rust".to_string(), 2), ("design".to_string(), 1)]
+        );
+
+        testing_env!(context.predecessor_account_id(owner()).build());
+        contract.cancel_bounty(cancelled);
+        // Cancelled bounty stays in the index, only the listing of tags leaves it out.
+        assert_eq!(ids(&contract, "rust", 0), vec![0, 2]);
+        assert_eq!(
+            contract.get_bounty_tags(None),
+            vec![("rust".to_string(), 1), ("design".to_string(), 1)]
+        );
+        assert_eq!(contract.get_bounty_tags(Some(true))[0], ("rust".to_string(), 2));
+
+        contract.cancel_bounty(0);
+        assert!(contract.get_bounty_tags(None).is_empty());
+        assert_eq!(contract.get_bounty_tags(Some(true)).len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_TAG")]
+    fn test_bounty_duplicate_tag() {
+        let (_, mut contract) = setup_contract();
+        add_tagged_bounty(&mut contract, &["rust", "rust"]);
     }
 }